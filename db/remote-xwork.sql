@@ -200,7 +200,10 @@ CREATE TABLE `user_activity` (
   `salesrepTb` int(11) NOT NULL,
   `activity_type` varchar(100) NOT NULL,
   `duration` int(11) NOT NULL,
-  `rDateTime` datetime NOT NULL
+  `rDateTime` datetime NOT NULL,
+  `start_time` datetime DEFAULT NULL,
+  `end_time` datetime DEFAULT NULL,
+  `details` varchar(500) DEFAULT NULL
 ) ENGINE=InnoDB DEFAULT CHARSET=latin1 COLLATE=latin1_swedish_ci;
 
 -- --------------------------------------------------------
@@ -219,7 +222,11 @@ CREATE TABLE `web_images` (
   `user_id` int(11) NOT NULL,
   `date` date NOT NULL,
   `time` time NOT NULL,
-  `status` varchar(200) NOT NULL
+  `status` varchar(200) NOT NULL,
+  `checksum` char(64) DEFAULT NULL,
+  `remote_url` varchar(2000) DEFAULT NULL,
+  `active_window` varchar(500) DEFAULT NULL,
+  `ocr_text` text DEFAULT NULL
 ) ENGINE=InnoDB DEFAULT CHARSET=latin1 COLLATE=latin1_swedish_ci;
 
 --
@@ -244,7 +251,9 @@ ALTER TABLE `user_activity`
 -- Indexes for table `web_images`
 --
 ALTER TABLE `web_images`
-  ADD PRIMARY KEY (`ID`);
+  ADD PRIMARY KEY (`ID`),
+  ADD KEY `idx_checksum` (`checksum`),
+  ADD FULLTEXT KEY `idx_ocr_text` (`ocr_text`);
 
 --
 -- AUTO_INCREMENT for dumped tables
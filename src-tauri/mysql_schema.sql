@@ -24,11 +24,14 @@ CREATE TABLE screenshots (
     file_path VARCHAR(500) NOT NULL,
     filename VARCHAR(255) NOT NULL,
     file_size BIGINT,
+    checksum CHAR(64), -- SHA-256 of the file, used to detect duplicates
+    active_window VARCHAR(500), -- Title of the window focused when the screenshot was taken
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE,
     INDEX idx_user_id (user_id),
     INDEX idx_session_id (session_id),
-    INDEX idx_created_at (created_at)
+    INDEX idx_created_at (created_at),
+    INDEX idx_checksum (checksum)
 );
 
 -- Table to store recording metadata
@@ -38,6 +41,7 @@ CREATE TABLE recordings (
     session_id VARCHAR(255) NOT NULL,
     filename VARCHAR(255) NOT NULL,
     file_path VARCHAR(500),
+    remote_url VARCHAR(2000), -- Set once the recording has been uploaded
     duration_seconds INT,
     file_size BIGINT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
@@ -71,6 +75,8 @@ CREATE TABLE user_activity (
     activity_type ENUM('active', 'idle') NOT NULL,
     duration_seconds INT,
     timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    start_time DATETIME, -- Span of the activity state, written when the state changes
+    end_time DATETIME,
     FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE,
     INDEX idx_user_id (user_id),
     INDEX idx_timestamp (timestamp)
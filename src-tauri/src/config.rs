@@ -0,0 +1,418 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Version of the configuration file format; files written by newer versions are rejected
+pub const CONFIG_VERSION: u32 = 1;
+
+// Name of the configuration file inside the data directory
+const CONFIG_FILE_NAME: &str = "config.json";
+
+// Name of the file inside the data directory that keeps excluded windows edits across restarts
+const EXCLUDED_WINDOWS_FILE_NAME: &str = "excluded_windows.json";
+
+// Environment variable with the comma-separated excluded windows entries to start with, e.g. "password,re:^vault"
+const DEFAULT_EXCLUSIONS_ENV: &str = "REMOTE_WORK_DEFAULT_EXCLUSIONS";
+
+// Excluded windows entries to start with. Nothing is excluded unless REMOTE_WORK_DEFAULT_EXCLUSIONS lists entries,
+// so admins choose what is hidden rather than inheriting generic keywords like "settings".
+pub fn default_excluded_windows() -> Vec<String> {
+    let entries = std::env::var(DEFAULT_EXCLUSIONS_ENV).unwrap_or_default();
+
+    let mut excluded_windows: Vec<String> = Vec::new();
+    for entry in entries.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        // Keywords are matched against lowercase titles; "re:" regexes are kept as written
+        let entry = match entry.starts_with("re:") {
+            true => entry.to_string(),
+            false => entry.to_lowercase(),
+        };
+        if !excluded_windows.contains(&entry) {
+            excluded_windows.push(entry);
+        }
+    }
+    excluded_windows
+}
+
+// Excluded windows edits, stored relative to the defaults so defaults added in later versions still apply
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcludedWindowsChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+// Complete effective agent configuration. Secrets (database credentials, manifest signing key)
+// and the machine's user ID are intentionally not part of it so a file can be shared across machines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub version: u32,
+    pub screenshot_min_interval_secs: u64,
+    pub screenshot_max_interval_secs: u64,
+    pub recording_snapshot_min_interval_secs: u64,
+    pub recording_snapshot_max_interval_secs: u64,
+    pub excluded_windows: Vec<String>,
+    pub adaptive_to_bandwidth: bool,
+    pub startup_delay_seconds: u64,
+    pub capture_on_large_paste: bool,
+    pub large_paste_min_bytes: u64,
+    pub paste_allowlist: Vec<String>,
+    pub capture_on_session_events: bool,
+    pub camera_use_mode: String, // "off", "pause" or "blur"
+    pub capture_mode: String, // "primary", "all-separate" or "all-stitched"
+    pub screenshot_format: String, // "png", "jpeg" or "webp"
+    pub screenshot_quality: u8,
+    pub screenshot_max_dimension: u32, // Longest side in pixels, 0 keeps the captured resolution
+    pub strip_metadata: bool,
+    pub skip_duplicate_threshold: f64, // 0 uploads every screenshot
+    pub blank_capture_threshold: f64, // Brightness standard deviation, 0 uploads blank frames too
+    pub retention_days: u32, // 0 keeps local files forever
+    pub min_free_disk_mb: u64, // 0 disables the free disk space check
+    pub max_concurrent_uploads: usize,
+    pub upload_limit_policy: String, // "wait" or "drop"
+    pub recording_quality: String, // "low", "balanced" or "high"
+    pub recording_encoder: String, // "libx264", "auto" or a hardware encoder such as "h264_nvenc"
+    pub recording_output: String, // "file" or "hls"
+    pub recording_framerate: u32, // 0 uses the capture device's default
+    pub max_recording_segment_minutes: u64, // 0 never rolls over to a new segment
+    pub max_recording_session_minutes: u64, // 0 never starts a new session
+    pub skip_protected_content: bool,
+    pub mask_style: String, // "black", "blur" or "pixelate"
+    pub watermark_enabled: bool,
+    pub watermark_position: String, // "top-left", "top-right", "bottom-left" or "bottom-right"
+    pub idle_warning_seconds: u64,
+    pub idle_threshold_seconds: u64,
+    pub idle_poll_interval_seconds: u64,
+    pub log_level: String, // "off", "error", "warn", "info", "debug" or "trace"
+    pub upload_retry_attempts: u64,
+    pub upload_compression: bool, // gzip uploads that aren't already compressed
+    pub admin_shortcut: String, // Accelerator such as "Ctrl+Shift+`", empty disables it
+    pub stop_shortcut: String,
+    pub work_schedule: Option<crate::work_schedule::WorkScheduleSettings>, // None captures at any time
+    pub default_capture_region: Option<crate::screen_source::CaptureRegion>, // None captures whole screens
+    pub close_behavior: String, // "minimize" or "exit"
+    pub webcam_camera: Option<String>, // None takes webcam snapshots from the first camera found
+    pub max_upload_bytes: u64, // 0 uploads files of any size
+    pub ocr_enabled: bool,
+    pub ocr_languages: String, // Tesseract language codes joined with '+', e.g. "eng+deu"
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            version: CONFIG_VERSION,
+            screenshot_min_interval_secs: 300,
+            screenshot_max_interval_secs: 1800,
+            recording_snapshot_min_interval_secs: 300,
+            recording_snapshot_max_interval_secs: 1800,
+            excluded_windows: default_excluded_windows(),
+            adaptive_to_bandwidth: false,
+            startup_delay_seconds: 10,
+            capture_on_large_paste: false,
+            large_paste_min_bytes: 100 * 1024,
+            paste_allowlist: Vec::new(),
+            capture_on_session_events: false,
+            camera_use_mode: "off".to_string(),
+            capture_mode: "primary".to_string(),
+            screenshot_format: "png".to_string(),
+            screenshot_quality: 80,
+            screenshot_max_dimension: 0,
+            strip_metadata: false,
+            skip_duplicate_threshold: 0.0,
+            blank_capture_threshold: 2.0,
+            retention_days: 0,
+            min_free_disk_mb: 200,
+            max_concurrent_uploads: 2,
+            upload_limit_policy: "wait".to_string(),
+            recording_quality: "balanced".to_string(),
+            recording_encoder: "libx264".to_string(),
+            recording_output: "file".to_string(),
+            recording_framerate: 0,
+            max_recording_segment_minutes: 0,
+            max_recording_session_minutes: 0,
+            skip_protected_content: false,
+            mask_style: "black".to_string(),
+            watermark_enabled: false,
+            watermark_position: "bottom-right".to_string(),
+            idle_warning_seconds: 30,
+            idle_threshold_seconds: 300,
+            idle_poll_interval_seconds: 5,
+            log_level: "info".to_string(),
+            upload_retry_attempts: 3,
+            upload_compression: false,
+            admin_shortcut: "Ctrl+Shift+`".to_string(),
+            stop_shortcut: "Ctrl+Shift+F12".to_string(),
+            work_schedule: None,
+            default_capture_region: None,
+            close_behavior: "minimize".to_string(),
+            webcam_camera: None,
+            max_upload_bytes: 0,
+            ocr_enabled: false,
+            ocr_languages: "eng".to_string(),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    // Check that every value is within the range the corresponding setter command accepts
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > CONFIG_VERSION {
+            return Err(format!(
+                "Configuration version {} is newer than supported version {}",
+                self.version, CONFIG_VERSION
+            ));
+        }
+
+        if self.screenshot_min_interval_secs >= self.screenshot_max_interval_secs {
+            return Err("Minimum screenshot interval must be less than maximum interval".to_string());
+        }
+
+        if self.screenshot_min_interval_secs < 60 || self.screenshot_max_interval_secs > 120 * 60 {
+            return Err("Screenshot intervals must be between 1 and 120 minutes".to_string());
+        }
+
+        if self.recording_snapshot_min_interval_secs >= self.recording_snapshot_max_interval_secs {
+            return Err("Minimum recording snapshot interval must be less than maximum interval".to_string());
+        }
+
+        if self.recording_snapshot_min_interval_secs < 60 || self.recording_snapshot_max_interval_secs > 60 * 60 {
+            return Err("Recording snapshot intervals must be between 1 and 60 minutes".to_string());
+        }
+
+        if self.startup_delay_seconds > 600 {
+            return Err("Startup delay must be between 0 and 600 seconds".to_string());
+        }
+
+        if self.capture_on_large_paste && self.large_paste_min_bytes == 0 {
+            return Err("Minimum paste size must be greater than 0 bytes".to_string());
+        }
+
+        if !["off", "pause", "blur"].contains(&self.camera_use_mode.as_str()) {
+            return Err(format!(
+                "Invalid camera use mode '{}': expected \"pause\", \"blur\" or \"off\"",
+                self.camera_use_mode
+            ));
+        }
+
+        if !["primary", "all-separate", "all-stitched"].contains(&self.capture_mode.as_str()) {
+            return Err(format!(
+                "Invalid capture mode '{}': expected \"primary\", \"all-separate\" or \"all-stitched\"",
+                self.capture_mode
+            ));
+        }
+
+        if !["png", "jpeg", "webp"].contains(&self.screenshot_format.as_str()) {
+            return Err(format!(
+                "Invalid screenshot format '{}': expected \"png\", \"jpeg\" or \"webp\"",
+                self.screenshot_format
+            ));
+        }
+
+        if !["black", "blur", "pixelate"].contains(&self.mask_style.as_str()) {
+            return Err(format!(
+                "Invalid mask style '{}': expected \"black\", \"blur\" or \"pixelate\"",
+                self.mask_style
+            ));
+        }
+
+        if !["top-left", "top-right", "bottom-left", "bottom-right"].contains(&self.watermark_position.as_str()) {
+            return Err(format!(
+                "Invalid watermark position '{}': expected \"top-left\", \"top-right\", \"bottom-left\" or \"bottom-right\"",
+                self.watermark_position
+            ));
+        }
+
+        if self.screenshot_quality < 1 || self.screenshot_quality > 100 {
+            return Err("Screenshot quality must be between 1 and 100".to_string());
+        }
+
+        if self.screenshot_max_dimension != 0 && !(240..=16384).contains(&self.screenshot_max_dimension) {
+            return Err("Screenshot maximum dimension must be 0 or between 240 and 16384 pixels".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.skip_duplicate_threshold) {
+            return Err("Skip duplicate threshold must be between 0 and 1".to_string());
+        }
+
+        if !(0.0..=64.0).contains(&self.blank_capture_threshold) {
+            return Err("Blank capture threshold must be between 0 and 64".to_string());
+        }
+
+        if !(1..=16).contains(&self.max_concurrent_uploads) {
+            return Err("Maximum concurrent uploads must be between 1 and 16".to_string());
+        }
+
+        if !["wait", "drop"].contains(&self.upload_limit_policy.as_str()) {
+            return Err(format!(
+                "Invalid upload limit policy '{}': expected \"wait\" or \"drop\"",
+                self.upload_limit_policy
+            ));
+        }
+
+        if !["low", "balanced", "high"].contains(&self.recording_quality.as_str()) {
+            return Err(format!(
+                "Invalid recording quality '{}': expected \"low\", \"balanced\" or \"high\"",
+                self.recording_quality
+            ));
+        }
+
+        if !["libx264", "auto", "h264_nvenc", "h264_qsv", "h264_videotoolbox"].contains(&self.recording_encoder.as_str()) {
+            return Err(format!("Invalid recording encoder '{}'", self.recording_encoder));
+        }
+
+        if !["file", "hls"].contains(&self.recording_output.as_str()) {
+            return Err(format!(
+                "Invalid recording output '{}': expected \"file\" or \"hls\"",
+                self.recording_output
+            ));
+        }
+
+        if self.recording_framerate > 60 {
+            return Err("Recording frame rate must be between 1 and 60 fps".to_string());
+        }
+
+        if self.max_recording_segment_minutes > 24 * 60 || self.max_recording_session_minutes > 24 * 60 {
+            return Err("Maximum recording durations must be between 0 and 1440 minutes".to_string());
+        }
+
+        if self.max_recording_segment_minutes > 0
+            && self.max_recording_session_minutes > 0
+            && self.max_recording_session_minutes < self.max_recording_segment_minutes
+        {
+            return Err("Maximum session duration must not be shorter than the maximum segment duration".to_string());
+        }
+
+        if self.idle_warning_seconds == 0 || self.idle_warning_seconds >= self.idle_threshold_seconds {
+            return Err("Warning threshold must be greater than 0 and less than the idle threshold".to_string());
+        }
+
+        if !(1..=300).contains(&self.idle_poll_interval_seconds) {
+            return Err("Idle poll interval must be between 1 and 300 seconds".to_string());
+        }
+
+        if !(1..=10).contains(&self.upload_retry_attempts) {
+            return Err("Upload retry attempts must be between 1 and 10".to_string());
+        }
+
+        if self.log_level.parse::<log::LevelFilter>().is_err() {
+            return Err(format!(
+                "Invalid log level '{}': expected \"off\", \"error\", \"warn\", \"info\", \"debug\" or \"trace\"",
+                self.log_level
+            ));
+        }
+
+        for accelerator in [&self.admin_shortcut, &self.stop_shortcut] {
+            if !accelerator.is_empty() && accelerator.parse::<tauri_plugin_global_shortcut::Shortcut>().is_err() {
+                return Err(format!("Invalid shortcut accelerator '{}'", accelerator));
+            }
+        }
+
+        if !self.admin_shortcut.is_empty() && self.admin_shortcut == self.stop_shortcut {
+            return Err("The admin and stop shortcuts must be different".to_string());
+        }
+
+        if let Some(work_schedule) = &self.work_schedule {
+            crate::work_schedule::WorkSchedule::parse(work_schedule)?;
+        }
+
+        if let Some(region) = &self.default_capture_region {
+            if region.width == 0 || region.height == 0 {
+                return Err("Default capture region must not be empty".to_string());
+            }
+        }
+
+        if !["minimize", "exit"].contains(&self.close_behavior.as_str()) {
+            return Err(format!(
+                "Invalid close behavior '{}': expected \"minimize\" or \"exit\"",
+                self.close_behavior
+            ));
+        }
+
+        crate::ocr::validate_languages(&self.ocr_languages)?;
+
+        Ok(())
+    }
+}
+
+// Path of the configuration file the agent loads at startup
+pub fn config_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(CONFIG_FILE_NAME)
+}
+
+// Read, parse and validate a configuration file
+pub fn load_config(path: &Path) -> Result<RuntimeConfig, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read configuration file {}: {}", path.display(), e))?;
+    let mut config: RuntimeConfig = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid configuration file {}: {}", path.display(), e))?;
+
+    config.validate()?;
+
+    // Keyword lists are matched against lowercase window titles ("re:" regex entries are applied case-insensitively)
+    config.paste_allowlist = config.paste_allowlist.iter().map(|keyword| keyword.to_lowercase()).collect();
+    config.version = CONFIG_VERSION;
+
+    Ok(config)
+}
+
+// Write a configuration file, replacing any existing one atomically
+pub fn save_config(path: &Path, config: &RuntimeConfig) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize configuration: {}", e))?;
+
+    write_json_file(path, &json)
+}
+
+// Path of the file the excluded windows list is persisted in
+pub fn excluded_windows_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(EXCLUDED_WINDOWS_FILE_NAME)
+}
+
+// Read the persisted excluded windows edits and apply them to the defaults
+pub fn load_excluded_windows(path: &Path) -> Result<Vec<String>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read excluded windows file {}: {}", path.display(), e))?;
+    let changes: ExcludedWindowsChanges = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid excluded windows file {}: {}", path.display(), e))?;
+
+    let mut excluded_windows: Vec<String> = default_excluded_windows()
+        .into_iter()
+        .filter(|entry| !changes.removed.contains(entry))
+        .collect();
+    for entry in changes.added {
+        if !excluded_windows.contains(&entry) {
+            excluded_windows.push(entry);
+        }
+    }
+
+    Ok(excluded_windows)
+}
+
+// Persist the excluded windows list as its differences from the defaults
+pub fn save_excluded_windows(path: &Path, excluded_windows: &[String]) -> Result<(), String> {
+    let defaults = default_excluded_windows();
+    let changes = ExcludedWindowsChanges {
+        added: excluded_windows.iter().filter(|entry| !defaults.contains(entry)).cloned().collect(),
+        removed: defaults.iter().filter(|entry| !excluded_windows.contains(entry)).cloned().collect(),
+    };
+
+    let json = serde_json::to_string_pretty(&changes)
+        .map_err(|e| format!("Failed to serialize excluded windows: {}", e))?;
+
+    write_json_file(path, &json)
+}
+
+// Write a JSON file via a temp file so a crash never leaves a truncated one behind
+fn write_json_file(path: &Path, json: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, json)
+        .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    Ok(())
+}
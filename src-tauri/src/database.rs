@@ -1,40 +1,51 @@
 use mysql::*;
 use mysql::prelude::*;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
 
 // Global flag to track if database is available
 static DATABASE_AVAILABLE: AtomicBool = AtomicBool::new(true);
 
-// Database connection pool - using lazy_static to initialize at runtime
+// Database connection pool - using lazy_static to initialize at runtime.
+// Behind a lock so a reconnect after an outage can swap in a freshly built pool.
 lazy_static! {
-    pub static ref DB_POOL: Option<Pool> = {
-        // Try environment variables first, then use config file, then defaults
-        let db_config = DatabaseConfig::load();
+    pub static ref DB_POOL: Arc<RwLock<Option<Pool>>> = Arc::new(RwLock::new(create_pool()));
+}
 
-        let url = format!(
-            "mysql://{}:{}@{}:{}/{}",
-            db_config.user,
-            db_config.password,
-            db_config.host,
-            db_config.port,
-            db_config.database
-        );
+// Helper function to build a connection pool from the current database configuration
+fn create_pool() -> Option<Pool> {
+    // Try environment variables first, then use config file, then defaults
+    let db_config = DatabaseConfig::load();
 
-        match Pool::new(Opts::from_url(&url).expect("Invalid MySQL URL")) {
-            Ok(pool) => {
-                // Initialize database tables if they don't exist
-                initialize_database(&pool);
-                DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
-                Some(pool)
-            },
-            Err(e) => {
-                eprintln!("Failed to create MySQL pool: {}", e);
-                DATABASE_AVAILABLE.store(false, Ordering::SeqCst);
-                None
-            }
+    let url = format!(
+        "mysql://{}:{}@{}:{}/{}",
+        db_config.user,
+        db_config.password,
+        db_config.host,
+        db_config.port,
+        db_config.database
+    );
+
+    match Pool::new(Opts::from_url(&url).expect("Invalid MySQL URL")) {
+        Ok(pool) => {
+            // Initialize database tables if they don't exist
+            initialize_database(&pool);
+            DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
+            Some(pool)
+        },
+        Err(e) => {
+            error!("Failed to create MySQL pool: {}", e);
+            DATABASE_AVAILABLE.store(false, Ordering::SeqCst);
+            None
         }
-    };
+    }
+}
+
+// Helper function to get the current pool (cheap to clone, it shares the underlying connections)
+pub fn current_pool() -> Option<Pool> {
+    DB_POOL.read().ok().and_then(|pool| pool.clone())
 }
 
 use std::sync::Mutex;
@@ -49,7 +60,7 @@ pub fn is_database_available() -> bool {
 
     // If database is available according to our flag, check if connection is still valid
     if current_status {
-        if let Some(ref pool) = *DB_POOL {
+        if let Some(ref pool) = current_pool() {
             if let Ok(mut conn) = pool.get_conn() {
                 // Test the connection by executing a simple query
                 let result: Option<u8> = conn.query_first("SELECT 1").unwrap_or(None);
@@ -92,7 +103,11 @@ pub fn is_database_available() -> bool {
                         if let Ok(mut conn) = test_pool.get_conn() {
                             let result: Option<u8> = conn.query_first("SELECT 1").unwrap_or(None);
                             if result.is_some() {
-                                // The database is now available!
+                                // The database is now available, replace the dead pool with the working one
+                                drop(conn);
+                                if let Ok(mut pool_guard) = DB_POOL.write() {
+                                    *pool_guard = Some(test_pool);
+                                }
                                 DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
 
                                 // Update the last connection attempt time
@@ -100,7 +115,7 @@ pub fn is_database_available() -> bool {
                                     *last_attempt = SystemTime::now();
                                 }
 
-                                println!("Database connection restored!");
+                                info!("Database connection restored!");
                                 return true;
                             }
                         }
@@ -139,8 +154,10 @@ fn try_reconnect_database() {
             // Initialize database tables if they don't exist
             initialize_database(&pool);
 
-            // The original DB_POOL is initialized with lazy_static and cannot be changed at runtime
-            // But we can at least update the availability flag to reflect that connection is now possible
+            // Replace the dead pool so queries use the new connection
+            if let Ok(mut pool_guard) = DB_POOL.write() {
+                *pool_guard = Some(pool);
+            }
             DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
 
             // Update the last connection attempt time
@@ -148,10 +165,10 @@ fn try_reconnect_database() {
                 *last_attempt = SystemTime::now();
             }
 
-            println!("Successfully reconnected to database!");
+            info!("Successfully reconnected to database!");
         },
         Err(e) => {
-            eprintln!("Failed to reconnect to database: {}", e);
+            error!("Failed to reconnect to database: {}", e);
 
             // Update the last connection attempt time even on failure
             if let Ok(mut last_attempt) = LAST_CONNECT_ATTEMPT.lock() {
@@ -161,16 +178,40 @@ fn try_reconnect_database() {
     }
 }
 
+// Function to switch to different database settings at runtime. The new settings are only kept
+// (and the pool replaced) if a connection with them succeeds; otherwise the current pool is untouched.
+pub fn apply_config_override(config: DatabaseConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let opts = Opts::from_url(&config.url())?;
+    let pool = Pool::new(opts)?;
+
+    // Validate the settings with a real query before committing to them
+    {
+        let mut conn = pool.get_conn()?;
+        let _: Option<u8> = conn.query_first("SELECT 1")?;
+    }
+
+    if let Ok(mut config_override) = DATABASE_CONFIG_OVERRIDE.write() {
+        *config_override = Some(config);
+    }
+    if let Ok(mut pool_guard) = DB_POOL.write() {
+        *pool_guard = Some(pool);
+    }
+    DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
+
+    info!("Database settings changed, connection pool rebuilt");
+    Ok(())
+}
+
 // Function to create a new user in the database
 pub fn create_user(user_id: &str, username: Option<&str>, email: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // Log that database is not available but don't fail the operation
-        eprintln!("Database not available, skipping user creation");
+        warn!("Database not available, skipping user creation");
         return Ok(());
     }
 
     // Try to use the global pool, but if it's not available, try to create a direct connection
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         // Just update the username and email if the RepID already exists
@@ -213,7 +254,7 @@ pub fn create_user(user_id: &str, username: Option<&str>, email: Option<&str>) -
                 DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
             },
             Err(_) => {
-                eprintln!("Unable to connect to database to create user");
+                error!("Unable to connect to database to create user");
             }
         }
     }
@@ -225,11 +266,11 @@ pub fn create_user(user_id: &str, username: Option<&str>, email: Option<&str>) -
 pub fn get_user(user_id: &str) -> Result<Option<UserInfo>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return None
-        eprintln!("Database not available, returning None for user query");
+        warn!("Database not available, returning None for user query");
         return Ok(None);
     }
 
-    let pool = DB_POOL.as_ref().ok_or("Database pool not available")?;
+    let pool = current_pool().ok_or("Database pool not available")?;
     let mut conn = pool.get_conn()?;
 
     let result: Option<UserInfo> = conn
@@ -256,11 +297,11 @@ pub fn get_user(user_id: &str) -> Result<Option<UserInfo>, Box<dyn std::error::E
 pub fn user_exists(user_id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, assume user doesn't exist
-        eprintln!("Database not available, assuming user doesn't exist");
+        warn!("Database not available, assuming user doesn't exist");
         return Ok(false);
     }
 
-    let pool = DB_POOL.as_ref().ok_or("Database pool not available")?;
+    let pool = current_pool().ok_or("Database pool not available")?;
     let mut conn = pool.get_conn()?;
 
     let result: Option<u32> = conn.exec_first(
@@ -275,11 +316,11 @@ pub fn user_exists(user_id: &str) -> Result<bool, Box<dyn std::error::Error + Se
 pub fn get_all_users(limit: Option<u32>) -> Result<Vec<UserInfo>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty user list");
+        warn!("Database not available, returning empty user list");
         return Ok(Vec::new());
     }
 
-    let pool = DB_POOL.as_ref().ok_or("Database pool not available")?;
+    let pool = current_pool().ok_or("Database pool not available")?;
     let mut conn = pool.get_conn()?;
 
     if let Some(lim) = limit {
@@ -326,8 +367,20 @@ pub struct DatabaseConfig {
     pub database: String,
 }
 
+lazy_static! {
+    // Database settings set at runtime, taking precedence over the MYSQL_* environment variables
+    static ref DATABASE_CONFIG_OVERRIDE: RwLock<Option<DatabaseConfig>> = RwLock::new(None);
+}
+
 impl DatabaseConfig {
     pub fn load() -> Self {
+        // A runtime override wins over everything else
+        if let Ok(config_override) = DATABASE_CONFIG_OVERRIDE.read() {
+            if let Some(ref config) = *config_override {
+                return config.clone();
+            }
+        }
+
         // First try environment variables
         let user = std::env::var("MYSQL_USER").unwrap_or_else(|_| "root".to_string());
         let password = std::env::var("MYSQL_PASSWORD").unwrap_or_else(|_| "".to_string());
@@ -344,6 +397,17 @@ impl DatabaseConfig {
         }
     }
 
+    pub fn url(&self) -> String {
+        format!(
+            "mysql://{}:{}@{}:{}/{}",
+            self.user,
+            self.password,
+            self.host,
+            self.port,
+            self.database
+        )
+    }
+
     pub fn with_defaults() -> Self {
         DatabaseConfig {
             user: "root".to_string(),
@@ -363,8 +427,217 @@ fn initialize_database(pool: &Pool) {
     // The application will adapt to use the existing schema
 }
 
-// Function to save screenshot metadata to database
-pub fn save_screenshot_to_db(user_id: &str, session_id: &str, file_path: &str, filename: &str, file_size: Option<i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+// Function to test a fresh connection with the current configuration and report the details.
+// Doesn't use the pool so the result reflects the configuration as it is now.
+pub fn check_connection() -> ConnectionReport {
+    let db_config = DatabaseConfig::load();
+
+    let mut report = ConnectionReport {
+        connected: false,
+        host: db_config.host.clone(),
+        port: db_config.port.clone(),
+        database: db_config.database.clone(),
+        user: db_config.user.clone(),
+        latency_ms: None,
+        error: None,
+    };
+
+    let url = format!(
+        "mysql://{}:{}@{}:{}/{}",
+        db_config.user,
+        db_config.password,
+        db_config.host,
+        db_config.port,
+        db_config.database
+    );
+
+    let opts = match Opts::from_url(&url) {
+        Ok(opts) => opts,
+        Err(e) => {
+            report.error = Some(format!("Invalid connection settings: {}", e));
+            return report;
+        }
+    };
+
+    let started = std::time::Instant::now();
+    let result = Conn::new(opts).and_then(|mut conn| conn.query_first::<u8, _>("SELECT 1"));
+
+    match result {
+        Ok(_) => {
+            report.connected = true;
+            report.latency_ms = Some(started.elapsed().as_millis() as u64);
+        },
+        Err(e) => report.error = Some(e.to_string()),
+    }
+
+    report
+}
+
+// Tables the agent inserts into, with the columns its inserts rely on, the newer columns it fills in when they
+// exist (databases created before them still work, without that data) and a harmless probe row
+const WRITABLE_TABLES: &[(&str, &[&str], &[&str], &str)] = &[
+    (
+        "web_images",
+        &["br_id", "imgID", "imgName", "itmName", "type", "user_id", "date", "time", "status"],
+        &["checksum", "active_window", "remote_url", "ocr_text"],
+        "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status) VALUES (0, 0, '', '', 'write-check', 0, CURDATE(), CURTIME(), 'deleted')",
+    ),
+    (
+        "user_activity",
+        &["salesrepTb", "activity_type", "duration", "rDateTime"],
+        &["start_time", "end_time", "details"],
+        "INSERT INTO user_activity (salesrepTb, activity_type, duration, rDateTime) VALUES (0, 'write-check', 0, NOW())",
+    ),
+];
+
+// Function to verify the schema and insert permission of every table the agent writes to.
+// Each probe insert runs inside a transaction that is always rolled back, so no rows are kept.
+pub fn check_table_write_access() -> Result<Vec<TableAccessReport>, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = current_pool().ok_or("Database pool not available")?;
+    let mut conn = pool.get_conn()?;
+
+    let mut reports = Vec::new();
+
+    for (table, required_columns, optional_columns, probe_sql) in WRITABLE_TABLES {
+        let mut report = TableAccessReport {
+            table: table.to_string(),
+            exists: false,
+            missing_columns: Vec::new(),
+            missing_optional_columns: Vec::new(),
+            writable: false,
+            error: None,
+        };
+
+        // Look up the table's columns to confirm it exists and matches what the inserts expect
+        let existing_columns: Vec<String> = match conn.exec(
+            "SELECT COLUMN_NAME FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+            (*table,)
+        ) {
+            Ok(columns) => columns,
+            Err(e) => {
+                report.error = Some(e.to_string());
+                reports.push(report);
+                continue;
+            }
+        };
+
+        if existing_columns.is_empty() {
+            report.error = Some(format!("Table {} does not exist", table));
+            reports.push(report);
+            continue;
+        }
+
+        report.exists = true;
+        report.missing_columns = required_columns
+            .iter()
+            .filter(|column| !existing_columns.iter().any(|existing| existing.eq_ignore_ascii_case(column)))
+            .map(|column| column.to_string())
+            .collect();
+        report.missing_optional_columns = optional_columns
+            .iter()
+            .filter(|column| !existing_columns.iter().any(|existing| existing.eq_ignore_ascii_case(column)))
+            .map(|column| column.to_string())
+            .collect();
+        if !report.missing_optional_columns.is_empty() {
+            warn!(
+                "Table {} lacks the newer column(s) {}; that data isn't stored until they are added (see db/remote-xwork.sql)",
+                table,
+                report.missing_optional_columns.join(", ")
+            );
+        }
+
+        // Attempt the probe insert and roll it back regardless of the outcome
+        match conn.start_transaction(TxOpts::default()) {
+            Ok(mut tx) => {
+                match tx.query_drop(*probe_sql) {
+                    Ok(()) => report.writable = true,
+                    Err(e) => report.error = Some(e.to_string()),
+                }
+                if let Err(e) = tx.rollback() {
+                    error!("Failed to roll back write check on {}: {}", table, e);
+                }
+            },
+            Err(e) => {
+                report.error = Some(e.to_string());
+            }
+        }
+
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+// Helper function to insert a screenshot row into web_images, including the SHA-256 of the file and the title
+// of the window that was focused when it was captured. Databases created before the active_window or checksum
+// columns existed get the row without them.
+fn insert_screenshot_row(conn: &mut PooledConn, salesrep_id: u32, filename: &str, session_id: &str, checksum: Option<&str>, active_window: Option<&str>) -> Result<u64, mysql::Error> {
+    let result = conn.exec_drop(
+        "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status, checksum, active_window) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active', ?, ?)",
+        (
+            1, // Default br_id
+            0, // imgID - using 0 as default
+            filename,
+            session_id, // Use session_id as item name
+            "screenshot", // type
+            salesrep_id, // user_id
+            checksum,
+            active_window,
+        )
+    );
+
+    // ER_BAD_FIELD_ERROR: the active_window column hasn't been added to this database yet
+    let result = match result {
+        Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => conn.exec_drop(
+            "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status, checksum) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active', ?)",
+            (1, 0, filename, session_id, "screenshot", salesrep_id, checksum)
+        ),
+        other => other,
+    };
+
+    match result {
+        // Nor the checksum column
+        Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => conn.exec_drop(
+            "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active')",
+            (1, 0, filename, session_id, "screenshot", salesrep_id)
+        )?,
+        other => other?,
+    }
+
+    // Get the ID of the inserted screenshot (last inserted ID)
+    let id: Option<u64> = conn.exec_first("SELECT LAST_INSERT_ID()", ())?;
+    Ok(id.unwrap_or(0))
+}
+
+// Function to find an earlier screenshot of the user with identical content, returns its file name
+pub fn find_screenshot_by_checksum(user_id: &str, checksum: &str) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        return Ok(None);
+    }
+
+    if let Some(ref pool) = current_pool() {
+        let mut conn = pool.get_conn()?;
+
+        let result = conn.exec_first(
+            "SELECT w.imgName FROM web_images w JOIN salesrep s ON w.user_id = s.ID WHERE s.RepID = ? AND w.type = 'screenshot' AND w.status = 'active' AND w.checksum = ? LIMIT 1",
+            (user_id, checksum)
+        );
+
+        match result {
+            Ok(filename) => Ok(filename),
+            // Without the checksum column there is nothing to compare against
+            Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => Ok(None),
+            Err(e) => Err(Box::new(e)),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+// Function to save screenshot metadata to database, returns the ID of the inserted row (0 if none was inserted)
+pub fn save_screenshot_to_db(user_id: &str, session_id: &str, file_path: &str, filename: &str, file_size: Option<i64>, checksum: Option<&str>, active_window: Option<&str>) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let mut inserted_id = 0;
+
     if !is_database_available() {
         // If database is not available, try to connect directly
         let db_config = DatabaseConfig::load();
@@ -389,36 +662,29 @@ pub fn save_screenshot_to_db(user_id: &str, session_id: &str, file_path: &str, f
 
                 if let Some(id) = salesrep_id {
                     // Insert screenshot record into the web_images table which exists in remote-xwork
-                    if let Err(e) = conn.exec_drop(
-                        "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active')",
-                        (
-                            1, // Default br_id
-                            0, // imgID - using 0 as default
-                            filename,
-                            session_id, // Use session_id as item name
-                            "screenshot", // type
-                            id, // user_id
-                        )
-                    ) {
-                        eprintln!("Failed to insert screenshot into web_images table: {}", e);
-                        return Err(Box::new(e));
+                    match insert_screenshot_row(&mut conn, id, filename, session_id, checksum, active_window) {
+                        Ok(screenshot_id) => inserted_id = screenshot_id,
+                        Err(e) => {
+                            error!("Failed to insert screenshot into web_images table: {}", e);
+                            return Err(Box::new(e));
+                        }
                     }
                 } else {
-                    eprintln!("User with RepID {} not found in salesrep table", user_id);
+                    warn!("User with RepID {} not found in salesrep table", user_id);
                 }
 
                 // Update the global flag to indicate database is now available
                 DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
             },
             Err(_) => {
-                eprintln!("Unable to connect to database to save screenshot metadata");
+                error!("Unable to connect to database to save screenshot metadata");
                 // We're still returning Ok here to match the original behavior
                 // The data just won't be saved to database if MySQL is not accessible
             }
         }
     } else {
         // If database is available via global pool, use it
-        if let Some(ref pool) = *DB_POOL {
+        if let Some(ref pool) = current_pool() {
             let mut conn = pool.get_conn()?;
 
             // Get the salesrep ID (the primary key) from the RepID
@@ -429,29 +695,64 @@ pub fn save_screenshot_to_db(user_id: &str, session_id: &str, file_path: &str, f
 
             if let Some(id) = salesrep_id {
                 // Insert screenshot record into the web_images table which exists in remote-xwork
-                if let Err(e) = conn.exec_drop(
-                    "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active')",
-                    (
-                        1, // Default br_id
-                        0, // imgID - using 0 as default
-                        filename,
-                        session_id, // Use session_id as item name
-                        "screenshot", // type
-                        id, // user_id
-                    )
-                ) {
-                    eprintln!("Failed to insert screenshot into web_images table: {}", e);
-                    return Err(Box::new(e));
+                match insert_screenshot_row(&mut conn, id, filename, session_id, checksum, active_window) {
+                    Ok(screenshot_id) => inserted_id = screenshot_id,
+                    Err(e) => {
+                        error!("Failed to insert screenshot into web_images table: {}", e);
+                        return Err(Box::new(e));
+                    }
                 }
             } else {
-                eprintln!("User with RepID {} not found in salesrep table", user_id);
+                warn!("User with RepID {} not found in salesrep table", user_id);
             }
         } else {
-            eprintln!("Database pool is not available");
+            warn!("Database pool is not available");
             return Err("Database pool is not available".into());
         }
     }
 
+    Ok(inserted_id)
+}
+
+// Function to save a webcam snapshot to the database, as a web_images row of type 'webcam'
+pub fn save_webcam_snapshot_to_db(user_id: &str, filename: &str, checksum: Option<&str>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        warn!("Database not available, skipping webcam snapshot save");
+        return Ok(());
+    }
+
+    if let Some(ref pool) = current_pool() {
+        let mut conn = pool.get_conn()?;
+
+        // Get the salesrep ID (the primary key) from the RepID
+        let salesrep_id: Option<u32> = conn.exec_first(
+            "SELECT ID FROM salesrep WHERE RepID = ?",
+            (user_id,)
+        )?;
+
+        let Some(salesrep_id) = salesrep_id else {
+            warn!("User with RepID {} not found in salesrep table", user_id);
+            return Ok(());
+        };
+
+        // Snapshots aren't part of a session, so the item name stays empty
+        let result = conn.exec_drop(
+            "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status, checksum) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active', ?)",
+            (1, 0, filename, "", "webcam", salesrep_id, checksum)
+        );
+
+        match result {
+            // ER_BAD_FIELD_ERROR: the checksum column hasn't been added to this database yet
+            Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => conn.exec_drop(
+                "INSERT INTO web_images (br_id, imgID, imgName, itmName, type, user_id, date, time, status) VALUES (?, ?, ?, ?, ?, ?, CURDATE(), CURTIME(), 'active')",
+                (1, 0, filename, "", "webcam", salesrep_id)
+            )?,
+            other => other?,
+        }
+    } else {
+        warn!("Database pool is not available");
+    }
+
     Ok(())
 }
 
@@ -505,19 +806,19 @@ pub fn save_recording_to_db(
                     DATABASE_AVAILABLE.store(true, Ordering::SeqCst);
                     Ok(id.unwrap_or(0))
                 } else {
-                    eprintln!("User with RepID {} not found in salesrep table", user_id);
+                    warn!("User with RepID {} not found in salesrep table", user_id);
                     Ok(0) // Return 0 as a placeholder
                 }
             },
             Err(_) => {
-                eprintln!("Unable to connect to database to save recording metadata");
+                error!("Unable to connect to database to save recording metadata");
                 // Return a placeholder ID to match the original behavior
                 Ok(0)
             }
         }
     } else {
         // If database is available via global pool, use it
-        if let Some(ref pool) = *DB_POOL {
+        if let Some(ref pool) = current_pool() {
             let mut conn = pool.get_conn()?;
 
             // Get the salesrep ID (the primary key) from the RepID
@@ -543,11 +844,11 @@ pub fn save_recording_to_db(
                 let id: Option<u64> = conn.exec_first("SELECT LAST_INSERT_ID()", ())?;
                 Ok(id.unwrap_or(0))
             } else {
-                eprintln!("User with RepID {} not found in salesrep table", user_id);
+                warn!("User with RepID {} not found in salesrep table", user_id);
                 Ok(0) // Return 0 as a placeholder
             }
         } else {
-            eprintln!("Database pool is not available");
+            warn!("Database pool is not available");
             Ok(0)
         }
     }
@@ -557,11 +858,11 @@ pub fn save_recording_to_db(
 pub fn get_recording_id_by_session(session_id: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return None
-        eprintln!("Database not available, returning None for recording ID query");
+        warn!("Database not available, returning None for recording ID query");
         return Ok(None);
     }
 
-    let pool = DB_POOL.as_ref().unwrap();
+    let pool = current_pool().ok_or("Database pool not available")?;
     let mut conn = pool.get_conn()?;
 
     let result: Option<u64> = conn.exec_first(
@@ -572,6 +873,156 @@ pub fn get_recording_id_by_session(session_id: &str) -> Result<Option<u64>, Box<
     Ok(result)
 }
 
+// Function to store where a recording was uploaded to. Returns false when the user has no row for the
+// session or the database has no remote_url column yet, so the caller can report the URL wasn't saved.
+pub fn update_recording_remote_url_in_db(user_id: &str, session_id: &str, remote_url: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        warn!("Database not available, skipping recording remote URL update");
+        return Ok(false);
+    }
+
+    let pool = current_pool().ok_or("Database pool not available")?;
+    let mut conn = pool.get_conn()?;
+
+    // Get the salesrep ID (the primary key) from the RepID
+    let salesrep_id: Option<u32> = conn.exec_first(
+        "SELECT ID FROM salesrep WHERE RepID = ?",
+        (user_id,)
+    )?;
+
+    let id = match salesrep_id {
+        Some(id) => id,
+        None => {
+            warn!("User with RepID {} not found in salesrep table", user_id);
+            return Ok(false);
+        }
+    };
+
+    let result = conn.exec_drop(
+        "UPDATE web_images SET remote_url = ? WHERE user_id = ? AND itmName = ? AND type = 'recording'",
+        (remote_url, id, session_id)
+    );
+
+    match result {
+        Ok(()) => Ok(conn.affected_rows() > 0),
+        // ER_BAD_FIELD_ERROR: the remote_url column hasn't been added to this database yet
+        Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => {
+            warn!("web_images has no remote_url column, recording remote URL not saved");
+            Ok(false)
+        }
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+// Function to store the text recognized on a screenshot. Returns false when the row doesn't exist or the database
+// has no ocr_text column yet, so the caller can report the text wasn't saved.
+pub fn update_screenshot_ocr_text(screenshot_id: u64, ocr_text: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        warn!("Database not available, skipping screenshot OCR text update");
+        return Ok(false);
+    }
+
+    let pool = current_pool().ok_or("Database pool not available")?;
+    let mut conn = pool.get_conn()?;
+
+    let result = conn.exec_drop(
+        "UPDATE web_images SET ocr_text = ? WHERE ID = ? AND type = 'screenshot'",
+        (ocr_text, screenshot_id)
+    );
+
+    match result {
+        Ok(()) => Ok(conn.affected_rows() > 0),
+        // ER_BAD_FIELD_ERROR: the ocr_text column hasn't been added to this database yet
+        Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => {
+            warn!("web_images has no ocr_text column, screenshot OCR text not saved");
+            Ok(false)
+        }
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+// Function to delete a screenshot or recording of the user from web_images. A soft delete only sets the status to
+// 'deleted' so the row stays available for audits; a hard delete removes the row, including already soft-deleted ones.
+// Returns the file name and session ID of the deleted item, or None if the user has no such item.
+pub fn delete_web_image(user_id: &str, item_id: u32, item_type: &str, hard_delete: bool) -> Result<Option<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        return Err("Database not available".into());
+    }
+
+    let pool = current_pool().ok_or("Database pool not available")?;
+    let mut conn = pool.get_conn()?;
+
+    let query = match hard_delete {
+        true => "SELECT w.imgName, w.itmName FROM web_images w JOIN salesrep s ON w.user_id = s.ID WHERE w.ID = ? AND s.RepID = ? AND w.type = ?",
+        false => "SELECT w.imgName, w.itmName FROM web_images w JOIN salesrep s ON w.user_id = s.ID WHERE w.ID = ? AND s.RepID = ? AND w.type = ? AND w.status = 'active'",
+    };
+    let item: Option<(String, String)> = conn.exec_first(query, (item_id, user_id, item_type))?;
+    if item.is_none() {
+        return Ok(None);
+    }
+
+    match hard_delete {
+        true => conn.exec_drop("DELETE FROM web_images WHERE ID = ?", (item_id,))?,
+        false => conn.exec_drop("UPDATE web_images SET status = 'deleted' WHERE ID = ?", (item_id,))?,
+    }
+
+    Ok(item)
+}
+
+// Rows of a user removed by purge_user_rows
+#[derive(Debug, Default)]
+pub struct PurgedUserRows {
+    pub items: Vec<(String, String, String)>, // Type, file name and session ID of every screenshot and recording
+    pub web_images: u64,
+    pub user_activity: u64,
+}
+
+// Function to erase all screenshots, recordings and activity of a user. A soft purge sets the status of their
+// web_images rows to 'deleted' and keeps user_activity, which has no status column; a hard purge deletes the rows
+// from both tables in one transaction. An unknown user has nothing to purge.
+pub fn purge_user_rows(user_id: &str, hard_delete: bool) -> Result<PurgedUserRows, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        return Err("Database not available".into());
+    }
+
+    let pool = current_pool().ok_or("Database pool not available")?;
+    let mut conn = pool.get_conn()?;
+
+    let salesrep_id: Option<u32> = conn.exec_first(
+        "SELECT ID FROM salesrep WHERE RepID = ?",
+        (user_id,)
+    )?;
+    let id = match salesrep_id {
+        Some(id) => id,
+        None => {
+            warn!("User with RepID {} not found in salesrep table, nothing to purge", user_id);
+            return Ok(PurgedUserRows::default());
+        }
+    };
+
+    let mut purged = PurgedUserRows {
+        items: conn.exec("SELECT type, imgName, itmName FROM web_images WHERE user_id = ?", (id,))?,
+        ..Default::default()
+    };
+
+    match hard_delete {
+        true => {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            tx.exec_drop("DELETE FROM web_images WHERE user_id = ?", (id,))?;
+            purged.web_images = tx.affected_rows();
+            tx.exec_drop("DELETE FROM user_activity WHERE salesrepTb = ?", (id,))?;
+            purged.user_activity = tx.affected_rows();
+            tx.commit()?;
+        }
+        false => {
+            conn.exec_drop("UPDATE web_images SET status = 'deleted' WHERE user_id = ? AND status <> 'deleted'", (id,))?;
+            purged.web_images = conn.affected_rows();
+        }
+    }
+
+    Ok(purged)
+}
+
 // Function to save recording segment to database
 pub fn save_recording_segment_to_db(
     user_id: &str,
@@ -584,10 +1035,47 @@ pub fn save_recording_segment_to_db(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Skip saving recording segments since the recording_segments table doesn't exist in remote-xwork database
     // The remote-xwork database doesn't have a table for recording segments
-    eprintln!("Skipping recording segment save - recording_segments table not available in remote-xwork database");
+    debug!("Skipping recording segment save - recording_segments table not available in remote-xwork database");
     Ok(())
 }
 
+// Function to get the segments of a recording session from database, in segment order.
+// Returns an empty list when the recording_segments table doesn't exist (as in remote-xwork).
+pub fn get_recording_segments(user_id: &str, session_id: &str) -> Result<Vec<RecordingSegmentData>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        // If database is not available, return an empty vector
+        warn!("Database not available, returning empty recording segment list");
+        return Ok(Vec::new());
+    }
+
+    if let Some(ref pool) = current_pool() {
+        let mut conn = pool.get_conn()?;
+
+        let result = conn.exec_map(
+            "SELECT s.segment_number, s.filename, s.duration_seconds, s.file_size FROM recording_segments s JOIN recordings r ON s.recording_id = r.id WHERE r.session_id = ? AND r.user_id = ? ORDER BY s.segment_number",
+            (session_id, user_id),
+            |(segment_number, filename, duration_seconds, file_size): (i32, String, Option<i32>, Option<i64>)| {
+                RecordingSegmentData {
+                    segment_number,
+                    filename,
+                    duration_seconds,
+                    file_size,
+                }
+            }
+        );
+
+        match result {
+            Ok(segments) => Ok(segments),
+            // ER_NO_SUCH_TABLE: this database has no recordings/recording_segments tables
+            Err(mysql::Error::MySqlError(ref e)) if e.code == 1146 => Ok(Vec::new()),
+            Err(e) => Err(Box::new(e)),
+        }
+    } else {
+        warn!("Database pool is not available");
+        Ok(Vec::new())
+    }
+}
+
 // Function to update recording metadata in database after completion
 pub fn update_recording_metadata_in_db(
     session_id: &str,
@@ -598,7 +1086,7 @@ pub fn update_recording_metadata_in_db(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Skip updating recording metadata since the recordings table doesn't exist in remote-xwork database
     // The remote-xwork database doesn't have a table for recordings
-    eprintln!("Skipping recording metadata update - recordings table not available in remote-xwork database");
+    debug!("Skipping recording metadata update - recordings table not available in remote-xwork database");
     Ok(())
 }
 
@@ -606,11 +1094,11 @@ pub fn update_recording_metadata_in_db(
 pub fn save_user_activity_to_db(user_id: &str, activity_type: &str, duration_seconds: Option<i32>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, log and continue
-        eprintln!("Database not available, skipping user activity save");
+        warn!("Database not available, skipping user activity save");
         return Ok(());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         // Ensure user exists in the salesrep table
@@ -628,40 +1116,102 @@ pub fn save_user_activity_to_db(user_id: &str, activity_type: &str, duration_sec
                 (id, activity_type, duration_seconds.unwrap_or(0))
             )?;
         } else {
-            eprintln!("User with RepID {} not found in salesrep table", user_id);
+            warn!("User with RepID {} not found in salesrep table", user_id);
         }
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
     }
 
     Ok(())
 }
 
-// Function to save network usage to database
-pub fn save_network_usage_to_db(
-    user_id: &str,
-    download_speed: &str,
-    upload_speed: &str,
-    total_downloaded: &str,
-    total_uploaded: &str
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+// Function to save a span of user activity, from start to end (Unix timestamps in seconds), as a single row.
+// rDateTime is the start so the rows read as a timeline.
+pub fn save_activity_span_to_db(user_id: &str, activity_type: &str, start_unix: u64, end_unix: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
-        // If database is not available, skip saving network usage
-        eprintln!("Database not available, skipping network usage save");
+        warn!("Database not available, skipping user activity save");
         return Ok(());
     }
 
-    // Check if network_usage table exists
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
-        // Skip saving network usage since there's no corresponding table in remote-xwork database
-        // The remote-xwork database doesn't have a table for network usage tracking
+        // Ensure user exists in the salesrep table
+        create_user(user_id, None, None)?;
+
+        let salesrep_id: Option<u32> = conn.exec_first(
+            "SELECT ID FROM salesrep WHERE RepID = ?",
+            (user_id,)
+        )?;
+
+        if let Some(id) = salesrep_id {
+            let duration = end_unix.saturating_sub(start_unix).min(i32::MAX as u64) as i32;
+            let result = conn.exec_drop(
+                "INSERT INTO user_activity (salesrepTb, activity_type, duration, rDateTime, start_time, end_time) VALUES (?, ?, ?, FROM_UNIXTIME(?), FROM_UNIXTIME(?), FROM_UNIXTIME(?))",
+                (id, activity_type, duration, start_unix, start_unix, end_unix)
+            );
+
+            match result {
+                // ER_BAD_FIELD_ERROR: the start_time/end_time columns haven't been added to this database yet
+                Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => conn.exec_drop(
+                    "INSERT INTO user_activity (salesrepTb, activity_type, duration, rDateTime) VALUES (?, ?, ?, FROM_UNIXTIME(?))",
+                    (id, activity_type, duration, start_unix)
+                )?,
+                other => other?,
+            }
+        } else {
+            warn!("User with RepID {} not found in salesrep table", user_id);
+        }
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
+    }
+
+    Ok(())
+}
+
+// Function to save a user activity that comes with details, e.g. the size and target window of a large paste.
+// Databases created before the details column existed get the row without them.
+pub fn save_activity_with_details_to_db(user_id: &str, activity_type: &str, details: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        warn!("Database not available, skipping user activity save");
+        return Ok(());
+    }
+
+    if let Some(ref pool) = current_pool() {
+        let mut conn = pool.get_conn()?;
+
+        // Ensure user exists in the salesrep table
+        create_user(user_id, None, None)?;
+
+        let salesrep_id: Option<u32> = conn.exec_first(
+            "SELECT ID FROM salesrep WHERE RepID = ?",
+            (user_id,)
+        )?;
+
+        if let Some(id) = salesrep_id {
+            let result = conn.exec_drop(
+                "INSERT INTO user_activity (salesrepTb, activity_type, duration, rDateTime, details) VALUES (?, ?, 0, NOW(), ?)",
+                (id, activity_type, details)
+            );
+
+            match result {
+                // ER_BAD_FIELD_ERROR: the details column hasn't been added to this database yet
+                Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => {
+                    warn!("user_activity has no details column, saving {} without: {}", activity_type, details);
+                    conn.exec_drop(
+                        "INSERT INTO user_activity (salesrepTb, activity_type, duration, rDateTime) VALUES (?, ?, 0, NOW())",
+                        (id, activity_type)
+                    )?
+                },
+                other => other?,
+            }
+        } else {
+            warn!("User with RepID {} not found in salesrep table", user_id);
+        }
+    } else {
+        warn!("Database pool is not available");
     }
 
-    // Return Ok to maintain compatibility without actually saving
     Ok(())
 }
 
@@ -669,11 +1219,11 @@ pub fn save_network_usage_to_db(
 pub fn add_excluded_window_to_db(window_title: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, log and continue
-        eprintln!("Database not available, skipping excluded window addition");
+        warn!("Database not available, skipping excluded window addition");
         return Ok(());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         conn.exec_drop(
@@ -681,7 +1231,7 @@ pub fn add_excluded_window_to_db(window_title: &str) -> Result<(), Box<dyn std::
             (window_title,)
         )?;
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
     }
 
     Ok(())
@@ -691,11 +1241,11 @@ pub fn add_excluded_window_to_db(window_title: &str) -> Result<(), Box<dyn std::
 pub fn remove_excluded_window_from_db(window_title: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, log and continue
-        eprintln!("Database not available, skipping excluded window removal");
+        warn!("Database not available, skipping excluded window removal");
         return Ok(());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         conn.exec_drop(
@@ -703,7 +1253,7 @@ pub fn remove_excluded_window_from_db(window_title: &str) -> Result<(), Box<dyn
             (window_title,)
         )?;
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
     }
 
     Ok(())
@@ -713,11 +1263,11 @@ pub fn remove_excluded_window_from_db(window_title: &str) -> Result<(), Box<dyn
 pub fn get_excluded_windows_from_db() -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty excluded windows list");
+        warn!("Database not available, returning empty excluded windows list");
         return Ok(Vec::new());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         let result: Vec<String> = conn
@@ -727,7 +1277,7 @@ pub fn get_excluded_windows_from_db() -> Result<Vec<String>, Box<dyn std::error:
 
         Ok(result)
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
         Ok(Vec::new())
     }
 }
@@ -740,11 +1290,11 @@ pub fn update_process_status_in_db(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, log and continue
-        eprintln!("Database not available, skipping process status update");
+        warn!("Database not available, skipping process status update");
         return Ok(());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         conn.exec_drop(
@@ -752,7 +1302,7 @@ pub fn update_process_status_in_db(
             (recording_active, screenshotting_active, idle_detection_active)
         )?;
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
     }
 
     Ok(())
@@ -762,11 +1312,11 @@ pub fn update_process_status_in_db(
 pub fn get_screenshots_by_session(user_id: &str, session_id: &str) -> Result<Vec<ScreenshotData>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty screenshot list");
+        warn!("Database not available, returning empty screenshot list");
         return Ok(Vec::new());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         let result: Vec<ScreenshotData> = conn
@@ -787,7 +1337,7 @@ pub fn get_screenshots_by_session(user_id: &str, session_id: &str) -> Result<Vec
 
         Ok(result)
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
         Ok(Vec::new())
     }
 }
@@ -796,11 +1346,11 @@ pub fn get_screenshots_by_session(user_id: &str, session_id: &str) -> Result<Vec
 pub fn get_all_screenshots(user_id: &str, limit: Option<u32>) -> Result<Vec<ScreenshotData>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty screenshot list");
+        warn!("Database not available, returning empty screenshot list");
         return Ok(Vec::new());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         // Get the salesrep ID (the primary key) from the RepID
@@ -812,7 +1362,7 @@ pub fn get_all_screenshots(user_id: &str, limit: Option<u32>) -> Result<Vec<Scre
         if let Some(id) = salesrep_id {
             if let Some(lim) = limit {
                 let result = conn.exec_map(
-                    "SELECT ID, itmName, imgName, imgName, br_id, date FROM web_images WHERE user_id = ? AND type = 'screenshot' ORDER BY date DESC, time DESC LIMIT ?",
+                    "SELECT ID, itmName, imgName, imgName, br_id, date FROM web_images WHERE user_id = ? AND type = 'screenshot' AND status = 'active' ORDER BY date DESC, time DESC LIMIT ?",
                     (id, lim),
                     |(id, session_id, file_path, filename, file_size, created_at): (u32, String, String, String, i32, String)| {
                         ScreenshotData {
@@ -828,7 +1378,7 @@ pub fn get_all_screenshots(user_id: &str, limit: Option<u32>) -> Result<Vec<Scre
                 Ok(result)
             } else {
                 let result = conn.exec_map(
-                    "SELECT ID, itmName, imgName, imgName, br_id, date FROM web_images WHERE user_id = ? AND type = 'screenshot' ORDER BY date DESC, time DESC",
+                    "SELECT ID, itmName, imgName, imgName, br_id, date FROM web_images WHERE user_id = ? AND type = 'screenshot' AND status = 'active' ORDER BY date DESC, time DESC",
                     (id,),
                     |(id, session_id, file_path, filename, file_size, created_at): (u32, String, String, String, i32, String)| {
                         ScreenshotData {
@@ -844,24 +1394,65 @@ pub fn get_all_screenshots(user_id: &str, limit: Option<u32>) -> Result<Vec<Scre
                 Ok(result)
             }
         } else {
-            eprintln!("User with RepID {} not found in salesrep table", user_id);
+            warn!("User with RepID {} not found in salesrep table", user_id);
             Ok(Vec::new())
         }
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
         Ok(Vec::new())
     }
 }
 
+// Function to get a single screenshot by ID, only if it belongs to the user
+pub fn get_screenshot(user_id: &str, screenshot_id: u32) -> Result<Option<ScreenshotData>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_database_available() {
+        warn!("Database not available, screenshot {} not looked up", screenshot_id);
+        return Ok(None);
+    }
+
+    if let Some(ref pool) = current_pool() {
+        let mut conn = pool.get_conn()?;
+
+        // Get the salesrep ID (the primary key) from the RepID
+        let salesrep_id: Option<u32> = conn.exec_first(
+            "SELECT ID FROM salesrep WHERE RepID = ?",
+            (user_id,)
+        )?;
+
+        let Some(salesrep_id) = salesrep_id else {
+            warn!("User with RepID {} not found in salesrep table", user_id);
+            return Ok(None);
+        };
+
+        // Filtering on the user as well means another user's screenshot looks the same as a missing one
+        let row: Option<(u32, String, String, String, i32, String)> = conn.exec_first(
+            "SELECT ID, itmName, imgName, imgName, br_id, date FROM web_images WHERE ID = ? AND user_id = ? AND type = 'screenshot' AND status = 'active'",
+            (screenshot_id, salesrep_id)
+        )?;
+
+        Ok(row.map(|(id, session_id, file_path, filename, file_size, created_at)| ScreenshotData {
+            id,
+            session_id,
+            file_path,
+            filename,
+            file_size: Some(file_size as i64),
+            created_at,
+        }))
+    } else {
+        warn!("Database pool is not available");
+        Ok(None)
+    }
+}
+
 // Function to get recordings from database for a specific user
 pub fn get_recordings(user_id: &str, limit: Option<u32>) -> Result<Vec<RecordingData>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty recording list");
+        warn!("Database not available, returning empty recording list");
         return Ok(Vec::new());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         // Get the salesrep ID (the primary key) from the RepID
@@ -873,7 +1464,7 @@ pub fn get_recordings(user_id: &str, limit: Option<u32>) -> Result<Vec<Recording
         if let Some(id) = salesrep_id {
             if let Some(lim) = limit {
                 let result = conn.exec_map(
-                    "SELECT ID, itmName, imgName, imgName, br_id, imgID, date FROM web_images WHERE user_id = ? AND type = 'recording' ORDER BY date DESC, time DESC LIMIT ?",
+                    "SELECT ID, itmName, imgName, imgName, br_id, imgID, date FROM web_images WHERE user_id = ? AND type = 'recording' AND status = 'active' ORDER BY date DESC, time DESC LIMIT ?",
                     (id, lim),
                     |(id, session_id, filename, file_path, br_id, img_id, created_at): (u32, String, String, String, i32, i32, String)| {
                         RecordingData {
@@ -890,7 +1481,7 @@ pub fn get_recordings(user_id: &str, limit: Option<u32>) -> Result<Vec<Recording
                 Ok(result)
             } else {
                 let result = conn.exec_map(
-                    "SELECT ID, itmName, imgName, imgName, br_id, imgID, date FROM web_images WHERE user_id = ? AND type = 'recording' ORDER BY date DESC, time DESC",
+                    "SELECT ID, itmName, imgName, imgName, br_id, imgID, date FROM web_images WHERE user_id = ? AND type = 'recording' AND status = 'active' ORDER BY date DESC, time DESC",
                     (id,),
                     |(id, session_id, filename, file_path, br_id, img_id, created_at): (u32, String, String, String, i32, i32, String)| {
                         RecordingData {
@@ -907,11 +1498,11 @@ pub fn get_recordings(user_id: &str, limit: Option<u32>) -> Result<Vec<Recording
                 Ok(result)
             }
         } else {
-            eprintln!("User with RepID {} not found in salesrep table", user_id);
+            warn!("User with RepID {} not found in salesrep table", user_id);
             Ok(Vec::new())
         }
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
         Ok(Vec::new())
     }
 }
@@ -920,11 +1511,11 @@ pub fn get_recordings(user_id: &str, limit: Option<u32>) -> Result<Vec<Recording
 pub fn get_user_activity(user_id: &str, limit: Option<u32>) -> Result<Vec<UserActivityData>, Box<dyn std::error::Error + Send + Sync>> {
     if !is_database_available() {
         // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty user activity list");
+        warn!("Database not available, returning empty user activity list");
         return Ok(Vec::new());
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
         // Get the salesrep ID (the primary key) from the RepID
@@ -934,93 +1525,100 @@ pub fn get_user_activity(user_id: &str, limit: Option<u32>) -> Result<Vec<UserAc
         )?;
 
         if let Some(id) = salesrep_id {
-            if let Some(lim) = limit {
-                let result = conn.exec_map(
-                    "SELECT ID, activity_type, duration, rDateTime FROM user_activity WHERE salesrepTb = ? ORDER BY rDateTime DESC LIMIT ?",
-                    (id, lim),
-                    |(id, activity_type, duration, timestamp): (u32, String, i32, String)| {
-                        UserActivityData {
-                            id,
-                            activity_type,
-                            duration_seconds: duration,
-                            timestamp,
-                        }
+            // A u32 can't be unlimited, so "no limit" is the largest one
+            let lim = limit.unwrap_or(u32::MAX);
+            let result = conn.exec_map(
+                "SELECT ID, activity_type, duration, rDateTime, end_time FROM user_activity WHERE salesrepTb = ? ORDER BY rDateTime DESC LIMIT ?",
+                (id, lim),
+                |(id, activity_type, duration, timestamp, end_time): (u32, String, i32, String, Option<String>)| {
+                    UserActivityData {
+                        id,
+                        activity_type,
+                        duration_seconds: duration,
+                        timestamp,
+                        end_time,
                     }
-                )?;
-                Ok(result)
-            } else {
-                let result = conn.exec_map(
-                    "SELECT ID, activity_type, duration, rDateTime FROM user_activity WHERE salesrepTb = ? ORDER BY rDateTime DESC",
-                    (id,),
-                    |(id, activity_type, duration, timestamp): (u32, String, i32, String)| {
-                        UserActivityData {
-                            id,
-                            activity_type,
-                            duration_seconds: duration,
-                            timestamp,
+                }
+            );
+
+            match result {
+                // ER_BAD_FIELD_ERROR: the database predates activity spans, so there are no end times
+                Err(mysql::Error::MySqlError(ref e)) if e.code == 1054 => {
+                    let result = conn.exec_map(
+                        "SELECT ID, activity_type, duration, rDateTime FROM user_activity WHERE salesrepTb = ? ORDER BY rDateTime DESC LIMIT ?",
+                        (id, lim),
+                        |(id, activity_type, duration, timestamp): (u32, String, i32, String)| {
+                            UserActivityData {
+                                id,
+                                activity_type,
+                                duration_seconds: duration,
+                                timestamp,
+                                end_time: None,
+                            }
                         }
-                    }
-                )?;
-                Ok(result)
+                    )?;
+                    Ok(result)
+                }
+                other => Ok(other?),
             }
         } else {
-            eprintln!("User with RepID {} not found in salesrep table", user_id);
+            warn!("User with RepID {} not found in salesrep table", user_id);
             Ok(Vec::new())
         }
     } else {
-        eprintln!("Database pool is not available");
+        warn!("Database pool is not available");
         Ok(Vec::new())
     }
 }
 
-// Function to get network usage from database for a specific user
-pub fn get_network_usage(user_id: &str, limit: Option<u32>) -> Result<Vec<NetworkUsageData>, Box<dyn std::error::Error + Send + Sync>> {
+// Function to total a user's activity on one day (YYYY-MM-DD), summed by the database per activity type.
+// Spans count towards the day they started on; single events without a duration are left out.
+pub fn get_activity_summary(user_id: &str, date: &str) -> Result<ActivitySummary, Box<dyn std::error::Error + Send + Sync>> {
+    let mut summary = ActivitySummary {
+        date: date.to_string(),
+        ..ActivitySummary::default()
+    };
+
     if !is_database_available() {
-        // If database is not available, return an empty vector
-        eprintln!("Database not available, returning empty network usage list");
-        return Ok(Vec::new());
+        warn!("Database not available, returning empty activity summary");
+        return Ok(summary);
     }
 
-    if let Some(ref pool) = *DB_POOL {
+    if let Some(ref pool) = current_pool() {
         let mut conn = pool.get_conn()?;
 
-        if let Some(lim) = limit {
-            let result = conn.exec_map(
-                "SELECT id, download_speed, upload_speed, total_downloaded, total_uploaded, recorded_at FROM network_usage WHERE user_id = ? ORDER BY recorded_at DESC LIMIT ?",
-                (user_id, lim),
-                |(id, download_speed, upload_speed, total_downloaded, total_uploaded, recorded_at): (u32, String, String, String, String, String)| {
-                    NetworkUsageData {
-                        id,
-                        download_speed,
-                        upload_speed,
-                        total_downloaded,
-                        total_uploaded,
-                        recorded_at,
-                    }
-                }
+        // Get the salesrep ID (the primary key) from the RepID
+        let salesrep_id: Option<u32> = conn.exec_first(
+            "SELECT ID FROM salesrep WHERE RepID = ?",
+            (user_id,)
+        )?;
+
+        if let Some(id) = salesrep_id {
+            let totals: Vec<(String, i64, u64, i64)> = conn.exec(
+                "SELECT activity_type, CAST(SUM(duration) AS SIGNED), COUNT(*), MAX(duration) FROM user_activity WHERE salesrepTb = ? AND rDateTime >= ? AND rDateTime < DATE_ADD(?, INTERVAL 1 DAY) AND duration > 0 GROUP BY activity_type",
+                (id, date, date)
             )?;
-            Ok(result)
-        } else {
-            let result = conn.exec_map(
-                "SELECT id, download_speed, upload_speed, total_downloaded, total_uploaded, recorded_at FROM network_usage WHERE user_id = ? ORDER BY recorded_at DESC",
-                (user_id,),
-                |(id, download_speed, upload_speed, total_downloaded, total_uploaded, recorded_at): (u32, String, String, String, String, String)| {
-                    NetworkUsageData {
-                        id,
-                        download_speed,
-                        upload_speed,
-                        total_downloaded,
-                        total_uploaded,
-                        recorded_at,
+
+            for (activity_type, total_seconds, spans, longest_seconds) in totals {
+                match activity_type.as_str() {
+                    "active" => summary.active_seconds = total_seconds,
+                    "idle" => {
+                        summary.idle_seconds = total_seconds;
+                        summary.idle_episodes = spans;
+                        summary.longest_idle_seconds = longest_seconds;
                     }
+                    _ => {}
                 }
-            )?;
-            Ok(result)
+                summary.seconds_by_type.insert(activity_type, total_seconds);
+            }
+        } else {
+            warn!("User with RepID {} not found in salesrep table", user_id);
         }
     } else {
-        eprintln!("Database pool is not available");
-        Ok(Vec::new())
+        warn!("Database pool is not available");
     }
+
+    Ok(summary)
 }
 
 // Data structure for user information
@@ -1035,6 +1633,30 @@ pub struct UserInfo {
     pub is_active: bool,
 }
 
+// Result of a database connection test
+#[derive(Debug, serde::Serialize)]
+pub struct ConnectionReport {
+    pub connected: bool,
+    pub host: String,
+    pub port: String,
+    pub database: String,
+    pub user: String,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+// Result of the write access check for a single table
+#[derive(Debug, serde::Serialize)]
+pub struct TableAccessReport {
+    pub table: String,
+    pub exists: bool,
+    pub missing_columns: Vec<String>,
+    // Newer columns the table lacks; writes still work but leave that data out
+    pub missing_optional_columns: Vec<String>,
+    pub writable: bool,
+    pub error: Option<String>,
+}
+
 // Data structures for returning data from database
 #[derive(Debug, serde::Serialize)]
 pub struct ScreenshotData {
@@ -1057,20 +1679,29 @@ pub struct RecordingData {
     pub created_at: String,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct RecordingSegmentData {
+    pub segment_number: i32,
+    pub filename: String,
+    pub duration_seconds: Option<i32>,
+    pub file_size: Option<i64>,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct UserActivityData {
     pub id: u32,
     pub activity_type: String, // 'active' or 'idle'
     pub duration_seconds: i32,
-    pub timestamp: String,
+    pub timestamp: String, // Start of the span
+    pub end_time: Option<String>, // None for single events and rows written before activity spans
 }
 
-#[derive(Debug, serde::Serialize)]
-pub struct NetworkUsageData {
-    pub id: u32,
-    pub download_speed: String,
-    pub upload_speed: String,
-    pub total_downloaded: String,
-    pub total_uploaded: String,
-    pub recorded_at: String,
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ActivitySummary {
+    pub date: String, // YYYY-MM-DD
+    pub active_seconds: i64,
+    pub idle_seconds: i64,
+    pub idle_episodes: u64,
+    pub longest_idle_seconds: i64,
+    pub seconds_by_type: std::collections::BTreeMap<String, i64>, // Includes "break" and any other span types
 }
\ No newline at end of file
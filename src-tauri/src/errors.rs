@@ -0,0 +1,93 @@
+// Errors returned by commands, serialized as {"code": "...", "message": "..."} so the frontend can react to the
+// code instead of matching on the English message. Every command returns it; errors from helpers that return
+// Result<_, String> are mapped to a specific variant where they are called, and whatever is left (poisoned
+// locks, serialization, file system failures) converts to Internal through `?`.
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "code", content = "message", rename_all = "snake_case")]
+pub enum CommandError {
+    // The MySQL database can't be reached
+    DatabaseUnavailable(String),
+    // Neither the bundled nor a system FFmpeg was found
+    FfmpegMissing(String),
+    // The recording, capture or task is already running
+    AlreadyRunning(String),
+    // The recording, capture or task to act on isn't running
+    NotRunning(String),
+    // An argument was rejected
+    InvalidInput(String),
+    // The user, item or device asked for doesn't exist
+    NotFound(String),
+    // No user ID has been set yet
+    UserNotSet(String),
+    // The file couldn't be uploaded to the server
+    UploadFailed(String),
+    // The feature isn't available on this platform
+    Unsupported(String),
+    // Anything else
+    Internal(String),
+}
+
+impl CommandError {
+    pub fn message(&self) -> &str {
+        match self {
+            CommandError::DatabaseUnavailable(message)
+            | CommandError::FfmpegMissing(message)
+            | CommandError::AlreadyRunning(message)
+            | CommandError::NotRunning(message)
+            | CommandError::InvalidInput(message)
+            | CommandError::NotFound(message)
+            | CommandError::UserNotSet(message)
+            | CommandError::UploadFailed(message)
+            | CommandError::Unsupported(message)
+            | CommandError::Internal(message) => message,
+        }
+    }
+
+    // The same error with its message prefixed, e.g. by what was being done when it happened
+    pub fn context(self, context: &str) -> Self {
+        let with_context = |message: String| format!("{}: {}", context, message);
+        match self {
+            CommandError::DatabaseUnavailable(message) => CommandError::DatabaseUnavailable(with_context(message)),
+            CommandError::FfmpegMissing(message) => CommandError::FfmpegMissing(with_context(message)),
+            CommandError::AlreadyRunning(message) => CommandError::AlreadyRunning(with_context(message)),
+            CommandError::NotRunning(message) => CommandError::NotRunning(with_context(message)),
+            CommandError::InvalidInput(message) => CommandError::InvalidInput(with_context(message)),
+            CommandError::NotFound(message) => CommandError::NotFound(with_context(message)),
+            CommandError::UserNotSet(message) => CommandError::UserNotSet(with_context(message)),
+            CommandError::UploadFailed(message) => CommandError::UploadFailed(with_context(message)),
+            CommandError::Unsupported(message) => CommandError::Unsupported(with_context(message)),
+            CommandError::Internal(message) => CommandError::Internal(with_context(message)),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Internal(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Internal(message.to_string())
+    }
+}
+
+// Lets commands and converted helpers still be called from helpers returning Result<_, String>
+impl From<CommandError> for String {
+    fn from(error: CommandError) -> Self {
+        error.message().to_string()
+    }
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+// Payload of "screenshot-taken" and "screenshot-skipped" events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ScreenshotEvent {
+    // Captured by the screenshotting session; id is the screenshot's database row, if it was recorded
+    ScreenshotUploaded { id: Option<u64>, url: String },
+    // Captured alongside a recording
+    SnapshotUploaded { id: Option<u64>, url: String },
+    // Assembled timelapse clip
+    TimelapseUploaded { url: String },
+    // Not uploaded, the frame was nearly identical to the previous upload
+    ScreenshotSkipped { similarity: f64 },
+}
+
+// Payload of "recording-crashed" events, sent when FFmpeg exits during a recording without being asked to
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingCrashEvent {
+    pub exit_code: Option<i32>, // None if FFmpeg was killed by a signal
+    pub error_output: Vec<String>, // FFmpeg's last lines on stderr
+    pub restarted: bool, // Whether a new segment was started; if not the recording was stopped
+}
+
+// Payload of "display-changed" events, sent when a monitor is connected or disconnected or the screen
+// geometry changes, e.g. when docking or undocking a laptop
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayChangedEvent {
+    pub previous_count: usize,
+    pub count: usize,
+    pub resolution_changed: bool, // Same number of screens, but a different size or arrangement
+    pub screens: Vec<DisplayChangedScreen>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayChangedScreen {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_primary: bool,
+}
+
+// Payload of "recording-progress" events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    NextSnapshot { seconds_remaining: u64 },
+    StartupDelay { seconds_remaining: u64 },
+    FfmpegMissing,
+    FfmpegDownloadStarted { total_bytes: u64 },
+    FfmpegDownloadProgress { percent: f64 },
+    FfmpegDownloaded,
+}
+
+// Payload of "user-idle" and "user-active" events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ActivityEvent {
+    // Past the warning threshold but not idle yet
+    IdleWarning { idle_seconds: u64 },
+    UserIdle { idle_seconds: u64 },
+    UserActive { idle_seconds: u64 },
+    // Monitoring stopped, so the user is no longer tracked as idle
+    MonitoringStopped,
+    // The user is on a break, idle time isn't tracked until it ends
+    OnBreak,
+}
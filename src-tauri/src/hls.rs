@@ -0,0 +1,90 @@
+// HLS output for recordings: FFmpeg writes short .ts segments and a live playlist while recording, so
+// finished segments can be uploaded during the session instead of one large file at the end.
+// Every FFmpeg process of a session (a new one starts after each pause, rollover or crash) writes its own
+// playlist; at the end they are joined into one VOD playlist referencing every uploaded segment.
+
+use std::path::Path;
+
+// Target length of each .ts segment in seconds
+pub const SEGMENT_SECONDS: u64 = 10;
+// Segments kept in FFmpeg's live playlist; older ones are deleted from disk, so they must be uploaded by then
+const LIVE_PLAYLIST_SIZE: u32 = 30;
+
+// A finished .ts segment listed in a playlist
+#[derive(Debug, Clone)]
+pub struct HlsSegment {
+    pub file_name: String,
+    pub duration: f64, // Seconds
+}
+
+// FFmpeg output arguments writing HLS to the playlist path, with segments named after it next to it
+pub fn output_args(playlist_path: &str) -> Vec<String> {
+    let segment_pattern = match playlist_path.strip_suffix(".m3u8") {
+        Some(stem) => format!("{}_%05d.ts", stem),
+        None => format!("{}_%05d.ts", playlist_path),
+    };
+
+    vec![
+        // Segments can only be cut at keyframes, so force one at every segment boundary
+        "-force_key_frames".to_string(),
+        format!("expr:gte(t,n_forced*{})", SEGMENT_SECONDS),
+        "-f".to_string(),
+        "hls".to_string(),
+        "-hls_time".to_string(),
+        SEGMENT_SECONDS.to_string(),
+        "-hls_list_size".to_string(),
+        LIVE_PLAYLIST_SIZE.to_string(),
+        "-hls_flags".to_string(),
+        "delete_segments".to_string(),
+        "-hls_segment_filename".to_string(),
+        segment_pattern,
+    ]
+}
+
+// Read the segments listed in a playlist, in order. FFmpeg only lists a segment once it is completely written.
+// A playlist that doesn't exist yet lists nothing.
+pub fn read_playlist(path: &Path) -> Vec<HlsSegment> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    let mut segments = Vec::new();
+    let mut duration = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            duration = info.split(',').next().and_then(|seconds| seconds.trim().parse::<f64>().ok());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            segments.push(HlsSegment { file_name: line.to_string(), duration: duration.take().unwrap_or(0.0) });
+        }
+    }
+
+    segments
+}
+
+// Write a VOD playlist of the given runs of segments, marking a discontinuity where one run ends and the next
+// begins since they come from separate FFmpeg processes
+pub fn write_vod_playlist(path: &Path, runs: &[Vec<HlsSegment>]) -> Result<(), String> {
+    let target_duration = runs
+        .iter()
+        .flatten()
+        .map(|segment| segment.duration.ceil() as u64)
+        .max()
+        .unwrap_or(SEGMENT_SECONDS)
+        .max(1);
+
+    let mut playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-PLAYLIST-TYPE:VOD\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n",
+        target_duration
+    );
+    for (index, run) in runs.iter().filter(|run| !run.is_empty()).enumerate() {
+        if index > 0 {
+            playlist.push_str("#EXT-X-DISCONTINUITY\n");
+        }
+        for segment in run {
+            playlist.push_str(&format!("#EXTINF:{:.6},\n{}\n", segment.duration, segment.file_name));
+        }
+    }
+    playlist.push_str("#EXT-X-ENDLIST\n");
+
+    std::fs::write(path, playlist).map_err(|e| format!("Failed to write playlist {}: {}", path.display(), e))
+}
@@ -0,0 +1,145 @@
+// Removal of metadata (EXIF, XMP, ICC profiles, text and comment blocks) from encoded screenshots.
+// The image crate's encoders don't write any of these, but stripping works on the final bytes so
+// what leaves the machine is checked rather than assumed.
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Ancillary PNG chunks that are kept; tRNS is transparency, not metadata
+const PNG_KEPT_ANCILLARY_CHUNKS: [&[u8; 4]; 1] = [b"tRNS"];
+
+// WebP chunks holding metadata, and the VP8X flags announcing them (ICC, EXIF, XMP)
+const WEBP_METADATA_CHUNKS: [&[u8; 4]; 3] = [b"EXIF", b"XMP ", b"ICCP"];
+const WEBP_VP8X_METADATA_FLAGS: u8 = 0x20 | 0x08 | 0x04;
+
+// Strip metadata from a PNG, JPEG or WebP file. Returns the cleaned bytes and the names of the removed blocks.
+pub fn strip(data: &[u8]) -> Result<(Vec<u8>, Vec<String>), String> {
+    if data.starts_with(PNG_SIGNATURE) {
+        strip_png(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(data)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        strip_webp(data)
+    } else {
+        Err("Unrecognized image format, metadata can't be stripped".to_string())
+    }
+}
+
+fn strip_png(data: &[u8]) -> Result<(Vec<u8>, Vec<String>), String> {
+    let mut output = PNG_SIGNATURE.to_vec();
+    let mut removed = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+
+    while offset < data.len() {
+        let header = data.get(offset..offset + 8).ok_or("Truncated PNG chunk header")?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type: [u8; 4] = [header[4], header[5], header[6], header[7]];
+        let chunk_end = offset + 12 + length; // Length, type, data and CRC
+        let chunk = data.get(offset..chunk_end).ok_or("Truncated PNG chunk")?;
+
+        // Critical chunks have an uppercase first letter
+        let critical = chunk_type[0].is_ascii_uppercase();
+        if critical || PNG_KEPT_ANCILLARY_CHUNKS.contains(&&chunk_type) {
+            output.extend_from_slice(chunk);
+        } else {
+            removed.push(format!("PNG {}", String::from_utf8_lossy(&chunk_type)));
+        }
+
+        offset = chunk_end;
+        if &chunk_type == b"IEND" {
+            break;
+        }
+    }
+
+    Ok((output, removed))
+}
+
+fn strip_jpeg(data: &[u8]) -> Result<(Vec<u8>, Vec<String>), String> {
+    let mut output = vec![0xFF, 0xD8];
+    let mut removed = Vec::new();
+    let mut offset = 2;
+
+    while offset < data.len() {
+        if data[offset] != 0xFF {
+            return Err("Malformed JPEG segment".to_string());
+        }
+
+        // Markers may be preceded by fill bytes
+        let mut marker_offset = offset + 1;
+        while data.get(marker_offset) == Some(&0xFF) {
+            marker_offset += 1;
+        }
+        let marker = *data.get(marker_offset).ok_or("Truncated JPEG marker")?;
+        let segment_start = marker_offset + 1;
+
+        match marker {
+            // Start of scan: the entropy-coded image data and end of image follow
+            0xDA => {
+                output.extend_from_slice(&data[offset..]);
+                break;
+            }
+            0xD9 => {
+                output.extend_from_slice(&[0xFF, 0xD9]);
+                break;
+            }
+            // Restart and TEM markers carry no length
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&[0xFF, marker]);
+                offset = segment_start;
+            }
+            _ => {
+                let length_bytes = data.get(segment_start..segment_start + 2).ok_or("Truncated JPEG segment")?;
+                let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+                let segment_end = segment_start + length;
+                let segment = data.get(segment_start..segment_end).ok_or("Truncated JPEG segment")?;
+
+                // APP1-APP15 hold EXIF, XMP, ICC profiles and vendor data; COM is a free-text comment.
+                // APP0 is the JFIF header, which only describes pixel density.
+                if (0xE1..=0xEF).contains(&marker) || marker == 0xFE {
+                    removed.push(match marker {
+                        0xFE => "JPEG COM".to_string(),
+                        _ => format!("JPEG APP{}", marker - 0xE0),
+                    });
+                } else {
+                    output.extend_from_slice(&[0xFF, marker]);
+                    output.extend_from_slice(segment);
+                }
+
+                offset = segment_end;
+            }
+        }
+    }
+
+    Ok((output, removed))
+}
+
+fn strip_webp(data: &[u8]) -> Result<(Vec<u8>, Vec<String>), String> {
+    let mut output = data[0..12].to_vec();
+    let mut removed = Vec::new();
+    let mut offset = 12;
+
+    while offset < data.len() {
+        let header = data.get(offset..offset + 8).ok_or("Truncated WebP chunk header")?;
+        let fourcc: [u8; 4] = [header[0], header[1], header[2], header[3]];
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let chunk_end = (offset + 8 + size + (size & 1)).min(data.len()); // Chunks are padded to an even size
+
+        if WEBP_METADATA_CHUNKS.contains(&&fourcc) {
+            removed.push(format!("WebP {}", String::from_utf8_lossy(&fourcc).trim_end()));
+        } else {
+            let start = output.len();
+            output.extend_from_slice(&data[offset..chunk_end]);
+
+            // The extended header must no longer announce the removed chunks
+            if &fourcc == b"VP8X" && output.len() > start + 8 {
+                output[start + 8] &= !WEBP_VP8X_METADATA_FLAGS;
+            }
+        }
+
+        offset = chunk_end;
+    }
+
+    let riff_size = (output.len() - 8) as u32;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+    Ok((output, removed))
+}
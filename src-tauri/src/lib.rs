@@ -1,16 +1,32 @@
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::collections::HashMap;
 use tokio::time::{Duration, Instant};
 use std::fs;
 use std::path::PathBuf;
 use lazy_static::lazy_static;
-use screenshots::Screen;
+use log::{debug, error, info, warn};
 use tauri::{Emitter, Manager};
 use tokio::io::AsyncWriteExt;
 use std::time::SystemTime;
-use sysinfo::{Networks};
+use sysinfo::{Networks, System};
+use errors::CommandError;
 mod database;
+mod manifest;
+mod config;
+mod upload_queue;
+mod events;
+mod session_state;
+mod image_metadata;
+mod screen_source;
+mod logging;
+mod session_events;
+mod work_schedule;
+mod hls;
+mod network_history;
+mod webcam;
+mod errors;
+mod ocr;
 
 // Global flag to track if database is available
 static DATABASE_AVAILABLE: AtomicBool = AtomicBool::new(true);
@@ -95,13 +111,22 @@ use {
 #[derive(Clone, PartialEq)]
 enum TaskStatus {
     Active,
+    Paused,
     Stopping,
     Stopped,
 }
 
-// Global state to track running screenshot tasks
+// Global state to track running screenshot tasks, keyed by (session ID, monitor index).
+// Sessions without a monitor index capture the screens selected by the capture mode.
 lazy_static! {
-    static ref RUNNING_TASKS: Arc<Mutex<HashMap<String, TaskStatus>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref RUNNING_TASKS: Arc<Mutex<HashMap<(String, Option<usize>), TaskStatus>>> = Arc::new(Mutex::new(HashMap::new()));
+    // Signalled whenever a session's status changes so waiting sessions react immediately
+    static ref SCREENSHOT_TASK_SIGNAL: tokio::sync::watch::Sender<()> = tokio::sync::watch::channel(()).0;
+}
+
+// Helper function to wake every screenshotting session waiting for its next capture
+fn signal_screenshot_tasks() {
+    SCREENSHOT_TASK_SIGNAL.send_replace(());
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -110,17 +135,89 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+lazy_static! {
+    // Bounds how many uploads run at once. Replaced when the limit changes; uploads holding a permit
+    // of the previous semaphore finish normally, so the new limit applies as they complete.
+    static ref UPLOAD_SEMAPHORE: Mutex<Arc<tokio::sync::Semaphore>> = Mutex::new(Arc::new(tokio::sync::Semaphore::new(2)));
+}
+
+// Limit the current upload semaphore was created with
+static MAX_CONCURRENT_UPLOADS: AtomicU64 = AtomicU64::new(2);
+
+// Whether captures are dropped instead of waiting when every upload slot is taken
+static UPLOAD_LIMIT_DROPS_FRAMES: AtomicBool = AtomicBool::new(false);
+
+// Command to limit how many uploads run at once so slow links don't pile up uploads. policy decides what
+// captures do when the limit is reached: "wait" for a free slot (default) or "drop" the frame.
 #[tauri::command]
-async fn save_file_to_xampp_htdocs(file_data: Vec<u8>, filename: String, file_type: String) -> Result<String, String> {
-    // Get file size before moving the data
-    let file_size = Some(file_data.len() as i64);
+fn set_max_concurrent_uploads(n: usize, policy: Option<String>) -> Result<String, CommandError> {
+    if !(1..=16).contains(&n) {
+        return Err(CommandError::InvalidInput("Maximum concurrent uploads must be between 1 and 16".to_string()));
+    }
 
-    // Upload the file to a remote server using HTTP
-    let client = reqwest::Client::new();
+    let drops_frames = match policy.as_deref() {
+        None => UPLOAD_LIMIT_DROPS_FRAMES.load(Ordering::SeqCst),
+        Some("wait") => false,
+        Some("drop") => true,
+        Some(other) => return Err(CommandError::InvalidInput(format!("Invalid upload limit policy '{}': expected \"wait\" or \"drop\"", other))),
+    };
 
-    // Get the remote server URL from environment variable or use a default
-    let remote_server_url = std::env::var("REMOTE_WORK_SERVER_URL")
-        .unwrap_or_else(|_| "http://localhost/remote-work/".to_string());
+    *UPLOAD_SEMAPHORE.lock().map_err(|e| e.to_string())? = Arc::new(tokio::sync::Semaphore::new(n));
+    MAX_CONCURRENT_UPLOADS.store(n as u64, Ordering::SeqCst);
+    UPLOAD_LIMIT_DROPS_FRAMES.store(drops_frames, Ordering::SeqCst);
+
+    Ok(format!(
+        "At most {} uploads will run at once; captures {} when the limit is reached",
+        n,
+        if drops_frames { "are dropped" } else { "wait" }
+    ))
+}
+
+// Helper function to wait for a free upload slot; hold the permit until the upload is done
+async fn acquire_upload_permit() -> Result<tokio::sync::OwnedSemaphorePermit, String> {
+    let semaphore = UPLOAD_SEMAPHORE.lock().map_err(|e| e.to_string())?.clone();
+    semaphore.acquire_owned().await.map_err(|e| e.to_string())
+}
+
+// Helper function to get an upload slot for a capture, following the upload limit policy.
+// Returns None when the frame should be dropped because every slot is taken.
+async fn acquire_capture_upload_permit() -> Result<Option<tokio::sync::OwnedSemaphorePermit>, String> {
+    if !UPLOAD_LIMIT_DROPS_FRAMES.load(Ordering::SeqCst) {
+        return acquire_upload_permit().await.map(Some);
+    }
+
+    let semaphore = UPLOAD_SEMAPHORE.lock().map_err(|e| e.to_string())?.clone();
+    match semaphore.try_acquire_owned() {
+        Ok(permit) => Ok(Some(permit)),
+        Err(tokio::sync::TryAcquireError::NoPermits) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+async fn save_file_to_xampp_htdocs(file_data: Vec<u8>, filename: String, file_type: String) -> Result<String, CommandError> {
+    let (file_data, filename) = fit_upload_size(file_data, &filename, &file_type).map_err(CommandError::InvalidInput)?;
+    let _permit = acquire_upload_permit().await?;
+    upload_and_record_file(file_data, filename, file_type, None)
+        .await
+        .map_err(|e| CommandError::UploadFailed(e.into_inner()))
+}
+
+// Command to upload a file from the data directory and record it in the database, like save_file_to_xampp_htdocs
+// but streamed from disk chunk by chunk, for recordings too large to hold in memory. Failed uploads aren't
+// queued for retry since the file is still on disk.
+#[tauri::command]
+async fn save_file_stream(path: String, file_type: String, filename: String) -> Result<String, CommandError> {
+    if !UPLOAD_FILE_TYPES.contains(&file_type.as_str()) {
+        return Err(CommandError::InvalidInput(format!("Unknown file type: {}", file_type)));
+    }
+
+    // Only the agent's own captures may be uploaded
+    let path = std::fs::canonicalize(&path).map_err(|e| CommandError::NotFound(format!("Failed to find {}: {}", path, e)))?;
+    let data_dir = std::fs::canonicalize(get_data_directory()).map_err(|e| format!("Failed to find data directory: {}", e))?;
+    if !path.starts_with(&data_dir) || !path.is_file() {
+        return Err(CommandError::InvalidInput(format!("{} is not a file in the data directory", path.display())));
+    }
 
     // Get user ID for the request
     let user_id = {
@@ -128,354 +225,3820 @@ async fn save_file_to_xampp_htdocs(file_data: Vec<u8>, filename: String, file_ty
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
 
-    // Create a multipart form for the upload
-    let form = reqwest::multipart::Form::new()
-        .part("file", reqwest::multipart::Part::bytes(file_data).file_name(filename.clone()))
-        .text("user_id", user_id.clone())
-        .text("file_type", file_type.clone());
-
-    // Send the POST request to upload the file
-    let response = client
-        .post(&remote_server_url)
-        .multipart(form)
-        .send()
+    let _permit = acquire_upload_permit().await?;
+    let (remote_url, checksum, file_size) = upload_file_stream_to_server(&path, &filename, &file_type, &user_id)
         .await
-        .map_err(|e| format!("Failed to upload file to remote server: {}", e))?;
+        .map_err(CommandError::UploadFailed)?;
+    save_uploaded_file_metadata(&user_id, &filename, &file_type, &remote_url, file_size as i64, &checksum, None);
 
-    if !response.status().is_success() {
-        return Err(format!("Upload failed with status: {}", response.status()));
+    Ok(remote_url)
+}
+
+// File types the upload server accepts
+const UPLOAD_FILE_TYPES: [&str; 4] = ["screenshot", "recording", "timelapse", "webcam"];
+
+// Helper function to upload a file and record it in the database. A transient failure (connection error, 5xx) queues
+// the file for retry; a permanent one (401 or another 4xx) would fail again, so the file isn't queued. The error keeps
+// its kind. active_window is the title of the window focused when a screenshot was captured. Callers must hold an
+// upload permit.
+async fn upload_and_record_file(file_data: Vec<u8>, filename: String, file_type: String, active_window: Option<&str>) -> Result<String, RetryError<String>> {
+    if !UPLOAD_FILE_TYPES.contains(&file_type.as_str()) {
+        return Err(RetryError::Permanent(format!("Unknown file type: {}", file_type)));
     }
 
-    // Get the remote URL from the response or construct it
-    let remote_url = response.text().await.map_err(|e| format!("Failed to read response from server: {}", e))?;
+    // Get user ID for the request
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+
+    let file_size = file_data.len() as i64;
+    let checksum = sha256_hex(&file_data);
 
-    // Save file info to database based on file type
-    match file_type.as_str() {
-        "screenshot" => {
-            // Create a session ID for the screenshot
-            let session_id = uuid::Uuid::new_v4().to_string();
+    // Keep a copy of the data so a failed upload can be queued for retry
+    match upload_file_to_server(file_data.clone(), &filename, &file_type, &user_id, active_window).await {
+        Ok(remote_url) => {
+            save_uploaded_file_metadata(&user_id, &filename, &file_type, &remote_url, file_size, &checksum, active_window);
 
-            if let Err(e) = database::save_screenshot_to_db(&user_id, &session_id, &remote_url, &filename, file_size) {
-                eprintln!("Failed to save screenshot metadata to database: {}", e);
+            // The server is reachable again, so try to send anything that failed earlier
+            if !upload_queue::list(&pending_uploads_directory()).is_empty() {
+                tauri::async_runtime::spawn(async {
+                    if let Err(e) = flush_pending_uploads_internal().await {
+                        error!("Failed to flush pending uploads: {}", e);
+                    }
+                });
             }
-        },
-        "recording" => {
-            // Create a session ID for the recording
-            let session_id = uuid::Uuid::new_v4().to_string();
 
-            if let Err(e) = database::save_recording_to_db(
-                &user_id,
-                &session_id,
-                &filename,
-                Some(&remote_url),
-                None, // Duration not known yet
-                file_size
-            ) {
-                eprintln!("Failed to save recording metadata to database: {}", e);
+            // Return the URL where the file can be accessed on the remote server
+            Ok(remote_url)
+        }
+        Err(RetryError::Transient(e)) => {
+            match upload_queue::enqueue(&pending_uploads_directory(), &file_data, &filename, &file_type, &user_id, active_window) {
+                Ok(_) => Err(RetryError::Transient(format!("{} (queued for retry)", e))),
+                Err(queue_error) => Err(RetryError::Transient(format!("{} (could not queue for retry: {})", e, queue_error))),
             }
-        },
-        _ => {
-            return Err(format!("Unknown file type: {}", file_type));
+        }
+        Err(RetryError::Permanent(e)) => {
+            warn!("Upload of {} was rejected, not queueing it for retry: {}", filename, e);
+            Err(RetryError::Permanent(e))
         }
     }
+}
 
-    // Return the URL where the file can be accessed on the remote server
-    Ok(remote_url)
+lazy_static! {
+    // Bearer token sent with uploads and heartbeats; kept in memory only
+    static ref AUTH_TOKEN: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
 }
 
+// Handle of the running app, for emitting events from code paths that have no window or handle of their own
+static APP_HANDLE: once_cell::sync::OnceCell<tauri::AppHandle> = once_cell::sync::OnceCell::new();
+
+// Command to set the bearer token used to authenticate with the server; an empty token removes it
 #[tauri::command]
-async fn start_screenshotting(window: tauri::Window) -> Result<String, String> {
-    // Clean up inactive tasks by removing entries with Stopped status
-    {
-        let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-        tasks.retain(|_id, status| match status {
-            TaskStatus::Stopped => false,  // Remove stopped tasks
-            _ => true,  // Keep active and stopping tasks
-        });
+fn set_auth_token(token: String) -> Result<String, CommandError> {
+    let token = token.trim().to_string();
+    let mut token_guard = AUTH_TOKEN.lock().map_err(|e| e.to_string())?;
+
+    if token.is_empty() {
+        *token_guard = None;
+        Ok("Auth token cleared".to_string())
+    } else {
+        *token_guard = Some(token);
+        Ok("Auth token set".to_string())
     }
+}
 
-    // Check if there are still any active tasks running
-    {
-        let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-        let has_active_task = tasks.values().any(|status| match status {
-            TaskStatus::Active | TaskStatus::Stopping => true,
-            TaskStatus::Stopped => false,
-        });
+// Helper function to attach the bearer token, if one is set, to a request
+fn with_auth_token(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match AUTH_TOKEN.lock().ok().and_then(|token| token.clone()) {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
 
-        if has_active_task {
-            return Err("A screenshotting session is already running".to_string());
+// Proxy all requests go through, with optional credentials: an HTTP(S) proxy, or a SOCKS5 proxy for networks
+// that block plain HTTP uploads. With socks5h:// host names are resolved by the proxy rather than locally.
+#[derive(Clone)]
+struct ProxySettings {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+lazy_static! {
+    // Proxy set with set_proxy; None leaves reqwest's default of the HTTP(S)_PROXY environment variables.
+    // Kept in memory only since it may hold credentials.
+    static ref PROXY_SETTINGS: Arc<Mutex<Option<ProxySettings>>> = Arc::new(Mutex::new(None));
+}
+
+// Proxy URL schemes set_proxy accepts
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+// Helper function to turn proxy settings into a reqwest proxy for all schemes
+fn build_proxy(settings: &ProxySettings) -> Result<reqwest::Proxy, String> {
+    let mut url = reqwest::Url::parse(&settings.url).map_err(|e| format!("Invalid proxy URL '{}': {}", settings.url, e))?;
+    if !PROXY_SCHEMES.contains(&url.scheme()) {
+        return Err(format!(
+            "Unsupported proxy scheme '{}': expected http://, https://, socks5:// or socks5h://",
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("Invalid proxy URL '{}': no host", settings.url));
+    }
+
+    let socks = url.scheme().starts_with("socks5");
+    if socks {
+        // SOCKS5 credentials are part of the URL; basic auth only applies to HTTP proxies
+        if let Some(username) = &settings.username {
+            url.set_username(username)
+                .and_then(|_| url.set_password(settings.password.as_deref()))
+                .map_err(|_| format!("Invalid proxy URL '{}': credentials can't be set", settings.url))?;
         }
-        drop(tasks);
     }
 
-    // Create a unique session ID
-    let session_id = uuid::Uuid::new_v4().to_string();
+    let proxy = reqwest::Proxy::all(url.as_str()).map_err(|e| format!("Invalid proxy URL '{}': {}", settings.url, e))?;
 
-    // Create screenshots directory in data directory
-    let data_dir_path = get_data_directory();
-    let dir = data_dir_path.join("screenshots");
-    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(match &settings.username {
+        Some(username) if !socks => proxy.basic_auth(username, settings.password.as_deref().unwrap_or("")),
+        _ => proxy,
+    })
+}
 
-    // Store task state as active
-    {
-        let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-        tasks.insert(session_id.clone(), TaskStatus::Active);
+// Command to send uploads, heartbeats and FFmpeg downloads through an HTTP(S) or SOCKS5 proxy; an empty URL removes it
+#[tauri::command]
+fn set_proxy(url: String, username: Option<String>, password: Option<String>) -> Result<String, CommandError> {
+    let url = url.trim().to_string();
+    let mut proxy_guard = PROXY_SETTINGS.lock().map_err(|e| e.to_string())?;
+
+    if url.is_empty() {
+        *proxy_guard = None;
+        return Ok("Proxy cleared".to_string());
     }
 
-    let session_id_clone = session_id.clone();
+    let settings = ProxySettings {
+        url: url.clone(),
+        username: username.filter(|username| !username.is_empty()),
+        password,
+    };
+    // Build a client too, so a proxy reqwest can't use fails here rather than on the first upload
+    let proxy = build_proxy(&settings).map_err(CommandError::InvalidInput)?;
+    reqwest::Client::builder()
+        .proxy(proxy)
+        .build()
+        .map_err(|e| CommandError::InvalidInput(format!("Proxy {} can't be used: {}", url, e)))?;
+    *proxy_guard = Some(settings);
+
+    Ok(format!("Proxy set to {}", url))
+}
 
-    // Start scheduled screenshotting in a background task
-    tokio::spawn(async move {
-        let start_time = Instant::now();
+// Helper function to start building an HTTP client that uses the configured proxy.
+// Fails rather than connecting directly when the proxy can't be used, since direct traffic may be blocked or unwanted.
+fn http_client_builder() -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder();
 
-        loop {
-            // Check if stop was requested before taking a screenshot
-            let should_continue = {
-                let tasks = RUNNING_TASKS.lock().unwrap();
-                match tasks.get(&session_id_clone) {
-                    Some(TaskStatus::Active) => true,
-                    _ => false,
-                }
-            };
+    match PROXY_SETTINGS.lock().map_err(|e| e.to_string())?.clone() {
+        Some(settings) => Ok(builder.proxy(build_proxy(&settings)?)),
+        None => Ok(builder),
+    }
+}
 
-            if !should_continue {
-                break;
+// Failure of one attempt of an operation run by retry_with_backoff
+enum RetryError<E> {
+    // Worth another attempt, e.g. a connection error or a 5xx response
+    Transient(E),
+    // Would fail the same way again, e.g. a 4xx response
+    Permanent(E),
+}
+
+impl<E> RetryError<E> {
+    fn into_inner(self) -> E {
+        match self {
+            RetryError::Transient(e) | RetryError::Permanent(e) => e,
+        }
+    }
+}
+
+// Helper function to run an operation up to `attempts` times. After a transient failure it waits initial_delay,
+// doubling the wait after each further one (1s, 2s, 4s, ...); a permanent failure is returned right away.
+// The operation is passed the attempt number, starting at 1. The last failure is returned with its kind.
+async fn retry_with_backoff<T, E, F, Fut>(description: &str, attempts: u32, initial_delay: Duration, mut operation: F) -> Result<T, RetryError<E>>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = Result<T, RetryError<E>>>,
+    E: std::fmt::Display,
+{
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+
+    loop {
+        match operation(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(RetryError::Transient(e)) if attempt < attempts => {
+                warn!("{} attempt {}/{} failed, retrying in {:?}: {}", description, attempt, attempts, delay, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
             }
+            Err(e) => return Err(e),
+        }
+    }
+}
 
-            // Take screenshot
-            match Screen::all() {
-                Ok(screens) => {
-                    if let Some(primary_screen) = screens.first() {
-                        match primary_screen.capture_area(0, 0, primary_screen.display_info.width, primary_screen.display_info.height) {
-                            Ok(img) => {
-                                let mut img = img;
-
-                                // Apply window masking on Windows (with added safety checks to prevent all-black screenshots)
-                                #[cfg(target_os = "windows")]
-                                {
-                                    // Get excluded windows list
-                                    let excluded_windows = RUNNING_EXCLUDED_WINDOWS.lock().unwrap().clone();
-
-                                    // Get visible windows to mask
-                                    if let Ok(windows_to_mask) = crate::windows_utils::get_visible_windows() {
-                                        for window in windows_to_mask {
-                                            let window_title_lower = window.title.to_lowercase();
-
-                                            let is_excluded = excluded_windows.iter().any(|keyword| {
-                                                window_title_lower.contains(keyword)
-                                            });
-
-                                            if is_excluded {
-                                                // Convert window coordinates to image coordinates
-                                                let x1_raw = window.rect.left;
-                                                let y1_raw = window.rect.top;
-                                                let x2_raw = window.rect.right;
-                                                let y2_raw = window.rect.bottom;
-
-                                                // Safety check: skip windows with invalid coordinates
-                                                if x2_raw <= x1_raw || y2_raw <= y1_raw {
-                                                    continue;
-                                                }
-
-                                                // Convert to unsigned and clamp to image dimensions
-                                                let x1 = std::cmp::max(0, x1_raw) as u32;
-                                                let y1 = std::cmp::max(0, y1_raw) as u32;
-                                                let mut x2 = std::cmp::max(0, x2_raw) as u32;
-                                                let mut y2 = std::cmp::max(0, y2_raw) as u32;
-
-                                                // Ensure coordinates are within image bounds
-                                                x2 = std::cmp::min(x2, primary_screen.display_info.width);
-                                                y2 = std::cmp::min(y2, primary_screen.display_info.height);
-
-                                                // Additional safety: prevent overly large areas
-                                                let width = x2.saturating_sub(x1);
-                                                let height = y2.saturating_sub(y1);
-
-                                                // Make sure x1,y1 are still less than or equal to x2,y2 after clamping
-                                                if x1 >= x2 || y1 >= y2 {
-                                                    continue; // Skip if the area becomes invalid after clamping
-                                                }
-
-                                                // Skip if window exceeds reasonable size (prevent accidentally capturing entire screen)
-                                                // Only skip if the window is more than 90% of the screen size to be more permissive
-                                                if width * height > primary_screen.display_info.width * primary_screen.display_info.height * 9 / 10 {
-                                                    continue;
-                                                }
-
-                                                // Black out the window area
-                                                for y in y1..y2 {
-                                                    for x in x1..x2 {
-                                                        use image::Rgba;
-                                                        img.put_pixel(x, y, Rgba([0, 0, 0, 255])); // Black with full opacity
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+// How many times an upload is attempted before it fails (and is queued for a later retry)
+static UPLOAD_RETRY_ATTEMPTS: AtomicU64 = AtomicU64::new(3);
+// Wait after the first failed upload attempt, doubled after each further one
+const UPLOAD_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(1);
 
-                                let timestamp = start_time.elapsed().as_millis();
-                                let filename = format!("screenshot_{}_{}.png", session_id_clone, timestamp);
-
-                                // Create path to screenshots directory in data directory
-                                let mut screenshots_dir = get_data_directory().join("screenshots");
-                                if let Err(e) = std::fs::create_dir_all(&screenshots_dir) {
-                                    eprintln!("Failed to create screenshots directory in data directory: {}", e);
-                                    // Try to create in temp directory as fallback
-                                    screenshots_dir = std::env::temp_dir();
-                                    screenshots_dir.push("remote-work-screenshots");
-                                    if let Err(e) = std::fs::create_dir_all(&screenshots_dir) {
-                                        eprintln!("Failed to create screenshots directory in temp: {}", e);
-                                        return;
-                                    }
-                                }
+// Command to set how many times an upload is attempted when the connection fails or the server answers
+// with a 5xx error. Client errors (4xx) are never retried.
+#[tauri::command]
+fn set_upload_retry_attempts(attempts: u64) -> Result<String, CommandError> {
+    if !(1..=10).contains(&attempts) {
+        return Err(CommandError::InvalidInput("Upload retry attempts must be between 1 and 10".to_string()));
+    }
 
-                                // Create file path
-                                let file_path = screenshots_dir.join(&filename);
+    UPLOAD_RETRY_ATTEMPTS.store(attempts, Ordering::SeqCst);
+    Ok(format!("Uploads will be attempted up to {} times", attempts))
+}
 
-                                // Save image to a temporary file first
-                                let temp_file_path = std::env::temp_dir().join(&filename);
-                                if let Err(e) = img.save(&temp_file_path) {
-                                    eprintln!("Failed to save screenshot to temp file: {}", e);
-                                } else {
-                                    // Read the image data from the temporary file
-                                    let img_data = match std::fs::read(&temp_file_path) {
-                                        Ok(data) => data,
-                                        Err(e) => {
-                                            eprintln!("Failed to read screenshot from temp file: {}", e);
-                                            return;
-                                        }
-                                    };
-
-                                    // Upload the image data to the server
-                                    match save_file_to_xampp_htdocs(img_data, filename.clone(), "screenshot".to_string()).await {
-                                        Ok(remote_url) => {
-                                            // Get user ID before saving to database
-                                            let user_id = {
-                                                let user_id_guard = USER_ID.lock().unwrap();
-                                                user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-                                            };
-
-                                            // Get file size
-                                            let file_size = std::fs::metadata(&temp_file_path)
-                                                .map(|meta| Some(meta.len() as i64))
-                                                .unwrap_or(None);
-
-                                            // Save screenshot metadata to MySQL database with the remote URL
-                                            if let Err(e) = database::save_screenshot_to_db(&user_id, &session_id_clone, &remote_url, &filename, file_size) {
-                                                eprintln!("Failed to save screenshot metadata to database: {}", e);
-                                            } else {
-                                                // Notify that screenshot was taken
-                                                window.emit("screenshot-taken", format!("Screenshot uploaded: {}", remote_url)).unwrap();
-                                            }
-                                        }
-                                        Err(e) => {
-                                            eprintln!("Failed to upload screenshot: {}", e);
-                                        }
-                                    }
+// Whether uploads of data that isn't already compressed are sent with a gzip-compressed body
+static UPLOAD_COMPRESSION: AtomicBool = AtomicBool::new(false);
+// Set once the server has answered a compressed upload with 415, after which everything is sent uncompressed
+static UPLOAD_COMPRESSION_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
 
-                                    // Clean up the temporary file
-                                    let _ = std::fs::remove_file(&temp_file_path);
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to capture screenshot: {}", e);
-                            }
-                        }
-                    } else {
-                        eprintln!("No screens found");
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to get screens: {}", e);
-                }
-            }
+// File extensions whose contents are already compressed, so gzip would only cost CPU time
+const PRECOMPRESSED_EXTENSIONS: [&str; 9] = ["png", "jpg", "jpeg", "webp", "mkv", "mp4", "ts", "zip", "gz"];
 
-            // Wait for 15 minutes before taking the next screenshot, but check for stop signal
-            // Wait in 1-second intervals to check the stop flag
-            for _ in 0..(15 * 60) {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+// Command to send uploads with a gzip-compressed body (Content-Encoding: gzip). Only applies to files held in
+// memory that aren't already compressed, e.g. JSON exports; screenshots and videos are always sent as they are.
+#[tauri::command]
+fn set_upload_compression(enabled: bool) -> Result<String, CommandError> {
+    UPLOAD_COMPRESSION.store(enabled, Ordering::SeqCst);
+    // Give the server another chance, it may have been configured to accept compressed uploads since
+    UPLOAD_COMPRESSION_UNSUPPORTED.store(false, Ordering::SeqCst);
 
-                // Check if stop was requested
-                let should_continue = {
-                    let tasks = RUNNING_TASKS.lock().unwrap();
-                    match tasks.get(&session_id_clone) {
-                        Some(TaskStatus::Active) => true,
-                        _ => false,
-                    }
-                };
+    Ok(format!("Upload compression {}", if enabled { "enabled" } else { "disabled" }))
+}
 
-                if !should_continue {
-                    break;
-                }
-            }
-        }
+// Helper function to check whether an upload of the file should be compressed
+fn upload_compression_applies(filename: &str) -> bool {
+    if !UPLOAD_COMPRESSION.load(Ordering::SeqCst) || UPLOAD_COMPRESSION_UNSUPPORTED.load(Ordering::SeqCst) {
+        return false;
+    }
 
-        // Notify completion when stopped
-        window.emit("screenshotting-finished", format!("Screenshotting stopped for session: {}", session_id_clone)).unwrap();
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    !PRECOMPRESSED_EXTENSIONS.contains(&extension.as_str())
+}
 
-        // Update the task status to stopped
-        {
-            let mut tasks = RUNNING_TASKS.lock().unwrap();
-            tasks.insert(session_id_clone, TaskStatus::Stopped);
-        }
-    });
+// Helper function to encode an upload as a multipart/form-data body and gzip it, since reqwest can't compress
+// a multipart form itself. Returns the boundary and the compressed body.
+fn gzip_multipart_body(fields: &[(&str, String)], filename: &str, file_data: &[u8]) -> std::io::Result<(String, Vec<u8>)> {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let boundary = format!("remote-work-{}", uuid::Uuid::new_v4().simple());
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+    write!(
+        encoder,
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        boundary,
+        filename.replace(['"', '\r', '\n'], "_")
+    )?;
+    encoder.write_all(file_data)?;
+    write!(encoder, "\r\n")?;
+    for (name, value) in fields {
+        write!(encoder, "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value)?;
+    }
+    write!(encoder, "--{}--\r\n", boundary)?;
+
+    Ok((boundary, encoder.finish()?))
+}
+
+// Helper function to tell the frontend the server rejected the token so it can provide a new one
+fn emit_auth_expired(context: &str) {
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit("auth-expired", serde_json::json!({
+            "context": context,
+            "tokenSet": AUTH_TOKEN.lock().map(|token| token.is_some()).unwrap_or(false),
+        }));
+    }
+}
+
+// Helper function to compute the hex encoded SHA-256 of in-memory data
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(data))
+}
+
+// Helper function to upload a file to the remote server, returns the remote URL.
+// The SHA-256 of the data is sent along as "checksum"; if the server echoes the checksum of what it
+// stored in an X-Checksum header, a mismatch fails the upload.
+async fn upload_file_to_server(file_data: Vec<u8>, filename: &str, file_type: &str, user_id: &str, active_window: Option<&str>) -> Result<String, RetryError<String>> {
+    // Get file size and checksum before moving the data
+    let file_size = file_data.len() as u64;
+    let checksum = sha256_hex(&file_data);
+
+    // A multipart body can only be sent once, so every attempt gets its own copy of the data
+    let result = retry_with_backoff("Upload", upload_retry_attempts(), UPLOAD_RETRY_INITIAL_DELAY, |_attempt| {
+        send_upload(UploadFile::Data(file_data.clone()), filename, file_type, user_id, &checksum, file_size, active_window)
+    })
+    .await;
+
+    record_upload_outcome(result.is_ok());
+    result
+}
+
+// Helper function to get the configured number of upload attempts
+fn upload_retry_attempts() -> u32 {
+    UPLOAD_RETRY_ATTEMPTS.load(Ordering::SeqCst) as u32
+}
 
-    Ok(format!("Started screenshotting session: {} (screenshots will be taken every 15 minutes)", session_id))
+// Helper function to upload a file from disk like upload_file_to_server, streaming it chunk by chunk
+// so large recordings are never loaded into memory. Returns the remote URL, the file's checksum and its size.
+async fn upload_file_stream_to_server(path: &std::path::Path, filename: &str, file_type: &str, user_id: &str) -> Result<(String, String, u64), String> {
+    let path_to_hash = path.to_path_buf();
+    let checksum = tokio::task::spawn_blocking(move || manifest::sha256_file(&path_to_hash))
+        .await
+        .map_err(|e| e.to_string())??;
+    let checksum_ref = checksum.as_str();
+
+    // Every attempt streams the file again from the start
+    let (remote_url, file_size) = retry_with_backoff("Upload", upload_retry_attempts(), UPLOAD_RETRY_INITIAL_DELAY, |_attempt| async move {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| RetryError::Permanent(format!("Failed to open {}: {}", path.display(), e)))?;
+        let file_size = file
+            .metadata()
+            .await
+            .map_err(|e| RetryError::Permanent(format!("Failed to read size of {}: {}", path.display(), e)))?
+            .len();
+
+        let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
+        let part = reqwest::multipart::Part::stream_with_length(body, file_size);
+
+        let remote_url = send_upload(UploadFile::Part(part), filename, file_type, user_id, checksum_ref, file_size, None).await?;
+        Ok((remote_url, file_size))
+    })
+    .await
+    .map_err(|e| {
+        record_upload_outcome(false);
+        e.into_inner()
+    })?;
+
+    record_upload_outcome(true);
+    Ok((remote_url, checksum, file_size))
+}
+
+lazy_static! {
+    // When an upload last succeeded
+    static ref LAST_UPLOAD_TIME: Arc<Mutex<Option<SystemTime>>> = Arc::new(Mutex::new(None));
+}
+
+// Uploads that succeeded and that failed (after all retries) since the app started
+static UPLOADS_SUCCEEDED: AtomicU64 = AtomicU64::new(0);
+static UPLOADS_FAILED: AtomicU64 = AtomicU64::new(0);
+
+// Helper function to count a finished upload
+fn record_upload_outcome(succeeded: bool) {
+    if succeeded {
+        UPLOADS_SUCCEEDED.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut last_upload) = LAST_UPLOAD_TIME.lock() {
+            *last_upload = Some(SystemTime::now());
+        }
+    } else {
+        UPLOADS_FAILED.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
+// Command to check whether this client is actually sending data, without querying the database. Returns JSON with
+// the time of the last successful upload (ISO 8601, null if none yet), seconds since then, and this session's counts.
 #[tauri::command]
-fn stop_screenshotting() -> Result<String, String> {
-    let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-    // Mark all active tasks as stopping (this will cause them to stop on next check)
-    // We need to get the session IDs first, then update them, to avoid borrow checker issues
-    let session_ids: Vec<String> = tasks.keys().cloned().collect();
+fn get_last_upload_time() -> Result<String, CommandError> {
+    let last_upload = *LAST_UPLOAD_TIME.lock().map_err(|e| e.to_string())?;
+
+    Ok(serde_json::json!({
+        "last_upload": last_upload.map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()),
+        "seconds_since": last_upload.map(|time| time.elapsed().map(|elapsed| elapsed.as_secs()).unwrap_or(0)),
+        "succeeded": UPLOADS_SUCCEEDED.load(Ordering::SeqCst),
+        "failed": UPLOADS_FAILED.load(Ordering::SeqCst),
+    })
+    .to_string())
+}
 
-    drop(tasks); // Explicitly drop the immutable lock
+// The file sent by an upload: data held in memory, which may be compressed, or a part streamed from disk
+enum UploadFile {
+    Data(Vec<u8>),
+    Part(reqwest::multipart::Part),
+}
 
-    // Now get a mutable lock to update all entries
-    let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-    for session_id in &session_ids {
-        if let Some(status) = tasks.get_mut(session_id) {
-            if *status == TaskStatus::Active {
-                *status = TaskStatus::Stopping;
+// Helper function to build the multipart form of an upload
+fn upload_form(fields: &[(&str, String)], file_part: reqwest::multipart::Part, filename: &str) -> reqwest::multipart::Form {
+    fields.iter().fold(
+        reqwest::multipart::Form::new().part("file", file_part.file_name(filename.to_string())),
+        |form, (name, value)| form.text(name.to_string(), value.clone()),
+    )
+}
+
+// Helper function to send an upload request
+async fn post_upload(request: reqwest::RequestBuilder) -> Result<reqwest::Response, RetryError<String>> {
+    request.send().await.map_err(|e| match e.is_builder() {
+        true => RetryError::Permanent(format!("Failed to build upload request: {}", e)),
+        false => RetryError::Transient(format!("Failed to upload file to remote server: {}", e)),
+    })
+}
+
+// Helper function to send a file with its metadata to the remote server, returns the remote URL.
+// Connection errors, 5xx responses and corrupted uploads are transient failures, anything else is permanent.
+async fn send_upload(
+    file: UploadFile,
+    filename: &str,
+    file_type: &str,
+    user_id: &str,
+    checksum: &str,
+    file_size: u64,
+    active_window: Option<&str>,
+) -> Result<String, RetryError<String>> {
+    // Upload the file to a remote server using HTTP
+    let client = http_client_builder()
+        .map_err(RetryError::Permanent)?
+        .build()
+        .map_err(|e| RetryError::Permanent(format!("Failed to create HTTP client: {}", e)))?;
+
+    // Get the remote server URL from environment variable or use a default
+    let remote_server_url = std::env::var("REMOTE_WORK_SERVER_URL")
+        .unwrap_or_else(|_| "http://localhost/remote-work/".to_string());
+
+    // The metadata sent as multipart fields next to the file
+    let mut fields = vec![
+        ("user_id", user_id.to_string()),
+        ("file_type", file_type.to_string()),
+        ("checksum", checksum.to_string()),
+    ];
+    if let Some(active_window) = active_window {
+        fields.push(("active_window", active_window.to_string()));
+    }
+
+    // Send the POST request to upload the file, counting the bytes that actually go over the wire
+    let upload_started = Instant::now();
+    let (response, sent_bytes) = match file {
+        UploadFile::Data(data) if upload_compression_applies(filename) => match gzip_multipart_body(&fields, filename, &data) {
+            Ok((boundary, body)) => {
+                let compressed_size = body.len() as u64;
+                let response = post_upload(
+                    with_auth_token(client.post(&remote_server_url))
+                        .header(reqwest::header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+                        .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                        .body(body),
+                )
+                .await?;
+
+                // The server has to inflate the request body before parsing the form (with Apache e.g. through
+                // mod_deflate's DEFLATE input filter); one that can't should answer 415 Unsupported Media Type
+                if response.status() == reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE {
+                    warn!("Server does not accept compressed uploads, sending {} and later uploads uncompressed", filename);
+                    UPLOAD_COMPRESSION_UNSUPPORTED.store(true, Ordering::SeqCst);
+                    let form = upload_form(&fields, reqwest::multipart::Part::bytes(data), filename);
+                    (post_upload(with_auth_token(client.post(&remote_server_url)).multipart(form)).await?, file_size)
+                } else {
+                    debug!("Sent {} compressed, {} of {} bytes", filename, compressed_size, file_size);
+                    (response, compressed_size)
+                }
+            }
+            Err(e) => {
+                warn!("Failed to compress upload of {}, sending it uncompressed: {}", filename, e);
+                let form = upload_form(&fields, reqwest::multipart::Part::bytes(data), filename);
+                (post_upload(with_auth_token(client.post(&remote_server_url)).multipart(form)).await?, file_size)
             }
+        },
+        UploadFile::Data(data) => {
+            let form = upload_form(&fields, reqwest::multipart::Part::bytes(data), filename);
+            (post_upload(with_auth_token(client.post(&remote_server_url)).multipart(form)).await?, file_size)
+        }
+        UploadFile::Part(part) => {
+            let form = upload_form(&fields, part, filename);
+            (post_upload(with_auth_token(client.post(&remote_server_url)).multipart(form)).await?, file_size)
         }
+    };
+
+    // Don't mistake the error body of a rejected request for the file's URL
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        emit_auth_expired("upload");
+        return Err(RetryError::Permanent("Upload rejected by the server: auth token missing, invalid or expired (401)".to_string()));
     }
 
-    Ok("Stop signal sent to all screenshotting sessions".to_string())
-}
+    if response.status().is_server_error() {
+        return Err(RetryError::Transient(format!("Upload failed with status: {}", response.status())));
+    }
 
-// Global state to track combined recording status
-use std::process::{Child, Command};
-use tokio::task::JoinHandle;
-use std::collections::VecDeque;
-lazy_static! {
-    static ref COMBINED_RECORDING_PROCESS: Arc<Mutex<Option<Child>>> = Arc::new(Mutex::new(None));
-    static ref RECORDING_PAUSED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-    static ref RECORDING_SEGMENT_FILES: Arc<Mutex<VecDeque<String>>> = Arc::new(Mutex::new(VecDeque::new()));
-    static ref SCREENSHOT_TASK_HANDLE: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None));
-    static ref FFMPEG_PROCESS_ID: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None)); // Store the PID for process control
-    static ref SCREENSHOT_MIN_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(300)); // Default 5 minutes in seconds
-    static ref SCREENSHOT_MAX_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(1800)); // Default 30 minutes in seconds
-    static ref RECORDING_BASE_PATH: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None)); // Store base recording path
-    static ref RECORDING_SESSION_ID: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None)); // Store session ID
-    static ref IDLE_MONITORING_TASK: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None)); // Background idle monitoring task
-    static ref LAST_IDLE_STATUS: Arc<Mutex<String>> = Arc::new(Mutex::new("active".to_string())); // Cache last idle status
+    if !response.status().is_success() {
+        return Err(RetryError::Permanent(format!("Upload failed with status: {}", response.status())));
+    }
+
+    record_own_network_bytes(0, sent_bytes);
+
+    // Servers that don't echo a checksum are trusted; the local hash is still recorded with the metadata
+    if let Some(server_checksum) = response.headers().get("X-Checksum").and_then(|value| value.to_str().ok()) {
+        if !server_checksum.trim().eq_ignore_ascii_case(checksum) {
+            return Err(RetryError::Transient(format!(
+                "Upload of {} was corrupted: sent checksum {} but server stored {}",
+                filename, checksum, server_checksum
+            )));
+        }
+    }
+
+    // Get the remote URL from the response. The file is already stored, so a response that can't be read must not
+    // fail the upload (it would be sent again); the URL the server stores it under is recorded instead.
+    let remote_url = match response.text().await {
+        Ok(remote_url) => remote_url,
+        Err(e) => {
+            let remote_url = format!("{}/{}", remote_server_url.trim_end_matches('/'), filename);
+            warn!("Failed to read response from server after uploading {}, recording {} as its URL: {}", filename, remote_url, e);
+            remote_url
+        }
+    };
+
+    // Record the measured throughput for bandwidth-adaptive capture quality
+    record_upload_throughput(sent_bytes, upload_started.elapsed());
+    debug!("Uploaded {} ({} bytes) in {:?}", filename, file_size, upload_started.elapsed());
+
+    Ok(remote_url)
 }
 
+// Helper function to check a session ID before it is put into a file name or FFmpeg argument.
+// Sessions are always identified by UUIDs, so anything else is rejected.
+fn validate_session_id(session_id: &str) -> Result<(), String> {
+    uuid::Uuid::parse_str(session_id)
+        .map(|_| ())
+        .map_err(|_| format!("Invalid session ID '{}'", session_id))
+}
 
+// Command to upload the finished recording of a session, e.g. one whose upload failed, and store its remote URL.
+// The file is streamed from disk, so recordings of any size can be uploaded.
 #[tauri::command]
-async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, String> {
-    // Check if there's already a recording in progress
-    {
-        let process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
-        if process_guard.is_some() {
-            return Err("A recording session is already in progress".to_string());
+async fn upload_recording(session_id: String) -> Result<String, CommandError> {
+    // Session IDs become part of the recording's filename
+    validate_session_id(&session_id).map_err(CommandError::InvalidInput)?;
+
+    let filename = format!("recording_{}.mkv", session_id);
+    let path = get_data_directory().join("recordings").join(&filename);
+    if !path.is_file() {
+        return Err(CommandError::NotFound(format!("No finished recording found at {}", path.display())));
+    }
+
+    // Get user ID for the request and the database update
+    let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
+    drop(user_id_guard); // Release the lock early
+
+    let (remote_url, _, _) = {
+        let _permit = acquire_upload_permit().await?;
+        upload_file_stream_to_server(&path, &filename, "recording", &user_id)
+            .await
+            .map_err(CommandError::UploadFailed)?
+    };
+
+    match database::update_recording_remote_url_in_db(&user_id, &session_id, &remote_url) {
+        Ok(true) => {}
+        Ok(false) => warn!("Recording {} uploaded, but its database row could not be updated", session_id),
+        Err(e) => error!("Failed to save remote URL of recording {}: {}", session_id, e),
+    }
+
+    Ok(remote_url)
+}
+
+// Helper function to save file info to the database based on file type
+fn save_uploaded_file_metadata(user_id: &str, filename: &str, file_type: &str, remote_url: &str, file_size: i64, checksum: &str, active_window: Option<&str>) {
+    match file_type {
+        "screenshot" => {
+            // Create a session ID for the screenshot
+            let session_id = uuid::Uuid::new_v4().to_string();
+
+            if let Err(e) = database::save_screenshot_to_db(user_id, &session_id, remote_url, filename, Some(file_size), Some(checksum), active_window) {
+                error!("Failed to save screenshot metadata to database: {}", e);
+            }
+        },
+        "recording" | "timelapse" => {
+            // Create a session ID for the recording
+            let session_id = uuid::Uuid::new_v4().to_string();
+
+            if let Err(e) = database::save_recording_to_db(
+                user_id,
+                &session_id,
+                filename,
+                Some(remote_url),
+                None, // Duration not known yet
+                Some(file_size)
+            ) {
+                error!("Failed to save recording metadata to database: {}", e);
+            }
+        },
+        "webcam" => {
+            if let Err(e) = database::save_webcam_snapshot_to_db(user_id, filename, Some(checksum)) {
+                error!("Failed to save webcam snapshot metadata to database: {}", e);
+            }
+        },
+        _ => {}
+    }
+}
+
+// How often the background task retries queued uploads
+const PENDING_UPLOAD_RETRY_INTERVAL_SECS: u64 = 5 * 60;
+
+// Set while the pending upload queue is being flushed so two flushes never upload the same file
+static FLUSHING_PENDING_UPLOADS: AtomicBool = AtomicBool::new(false);
+
+// Helper function to get the directory failed uploads are queued in
+fn pending_uploads_directory() -> PathBuf {
+    get_data_directory().join("pending_uploads")
+}
+
+// Helper function to get the file network usage samples are kept in
+fn network_history_path() -> PathBuf {
+    get_data_directory().join("network_usage.jsonl")
+}
+
+// Helper function to retry queued uploads, oldest first. Stops at the first failure since the server
+// is most likely still unreachable. Returns (uploaded, remaining).
+async fn flush_pending_uploads_internal() -> Result<(usize, usize), CommandError> {
+    if FLUSHING_PENDING_UPLOADS.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(CommandError::AlreadyRunning("Pending uploads are already being flushed".to_string()));
+    }
+
+    let queue_dir = pending_uploads_directory();
+    let pending = upload_queue::list(&queue_dir);
+    let total = pending.len();
+    let mut uploaded = 0;
+    let mut result = Ok(());
+
+    for (id, upload) in pending {
+        let file_data = match upload_queue::read_data(&queue_dir, &id) {
+            Ok(file_data) => file_data,
+            Err(e) => {
+                // The data file is gone, so the entry can never be uploaded
+                error!("{}", e);
+                let _ = upload_queue::remove(&queue_dir, &id);
+                continue;
+            }
+        };
+        let file_size = file_data.len() as i64;
+        let checksum = sha256_hex(&file_data);
+
+        match upload_file_to_server(file_data, &upload.filename, &upload.file_type, &upload.user_id, upload.active_window.as_deref()).await {
+            Ok(remote_url) => {
+                // Dequeue before anything else so the file is never uploaded twice
+                if let Err(e) = upload_queue::remove(&queue_dir, &id) {
+                    result = Err(CommandError::Internal(e));
+                    break;
+                }
+                save_uploaded_file_metadata(&upload.user_id, &upload.filename, &upload.file_type, &remote_url, file_size, &checksum, upload.active_window.as_deref());
+                uploaded += 1;
+            }
+            Err(e) => {
+                result = Err(CommandError::UploadFailed(e.into_inner()));
+                break;
+            }
+        }
+    }
+
+    FLUSHING_PENDING_UPLOADS.store(false, Ordering::SeqCst);
+
+    let remaining = upload_queue::list(&queue_dir).len();
+    match result {
+        Ok(()) => Ok((uploaded, remaining)),
+        Err(e) if uploaded == 0 && total > 0 => Err(e),
+        Err(e) => {
+            warn!("Stopped flushing pending uploads: {}", e);
+            Ok((uploaded, remaining))
+        }
+    }
+}
+
+// Command to retry all queued uploads now
+#[tauri::command]
+async fn flush_pending_uploads() -> Result<String, CommandError> {
+    let (uploaded, remaining) = flush_pending_uploads_internal().await?;
+    Ok(format!("Uploaded {} pending file(s), {} still queued", uploaded, remaining))
+}
+
+// Helper function to start the background task that periodically retries queued uploads
+fn start_pending_upload_retry_task() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(Duration::from_secs(PENDING_UPLOAD_RETRY_INTERVAL_SECS)).await;
+
+            if upload_queue::list(&pending_uploads_directory()).is_empty() {
+                continue;
+            }
+
+            match flush_pending_uploads_internal().await {
+                Ok((uploaded, remaining)) => info!("Retried pending uploads: {} uploaded, {} still queued", uploaded, remaining),
+                Err(e) => warn!("Pending uploads still failing: {}", e),
+            }
+        }
+    });
+}
+
+// Whether capture quality should follow the measured upload bandwidth
+static ADAPTIVE_TO_BANDWIDTH: AtomicBool = AtomicBool::new(false);
+
+// Recent upload throughput samples in bytes per second (newest last)
+lazy_static! {
+    static ref UPLOAD_THROUGHPUT_SAMPLES: Arc<Mutex<VecDeque<f64>>> = Arc::new(Mutex::new(VecDeque::new()));
+}
+
+// Number of upload samples averaged when estimating bandwidth
+const UPLOAD_THROUGHPUT_SAMPLE_COUNT: usize = 5;
+// Uploads smaller than this are dominated by latency and say little about bandwidth
+const MIN_THROUGHPUT_SAMPLE_BYTES: u64 = 16 * 1024;
+// Below these rates capture quality is reduced one or two steps
+const REDUCED_QUALITY_BYTES_PER_SEC: f64 = 256.0 * 1024.0;
+const MINIMAL_QUALITY_BYTES_PER_SEC: f64 = 64.0 * 1024.0;
+// Screenshot scale factor, decrease of the JPEG/WebP quality and increase of the recording CRF for each
+// quality step (full, reduced, minimal)
+const ADAPTIVE_SCREENSHOT_SCALES: [f32; 3] = [1.0, 0.75, 0.5];
+const ADAPTIVE_SCREENSHOT_QUALITY_DECREASE: [u8; 3] = [0, 15, 30];
+const ADAPTIVE_RECORDING_CRF_INCREASE: [u32; 3] = [0, 4, 8];
+
+// Helper function to store an upload throughput sample
+fn record_upload_throughput(bytes: u64, elapsed: Duration) {
+    if bytes < MIN_THROUGHPUT_SAMPLE_BYTES || elapsed.as_secs_f64() <= 0.0 {
+        return;
+    }
+
+    if let Ok(mut samples) = UPLOAD_THROUGHPUT_SAMPLES.lock() {
+        samples.push_back(bytes as f64 / elapsed.as_secs_f64());
+        while samples.len() > UPLOAD_THROUGHPUT_SAMPLE_COUNT {
+            samples.pop_front();
+        }
+    }
+}
+
+// Helper function to get the average recent upload throughput in bytes per second
+fn measured_upload_bandwidth() -> Option<f64> {
+    let samples = UPLOAD_THROUGHPUT_SAMPLES.lock().ok()?;
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<f64>() / samples.len() as f64)
+}
+
+// Helper function to get the current quality step: 0 = full, 1 = reduced, 2 = minimal
+fn adaptive_quality_level() -> usize {
+    if !ADAPTIVE_TO_BANDWIDTH.load(Ordering::SeqCst) {
+        return 0;
+    }
+
+    match measured_upload_bandwidth() {
+        Some(bandwidth) if bandwidth < MINIMAL_QUALITY_BYTES_PER_SEC => 2,
+        Some(bandwidth) if bandwidth < REDUCED_QUALITY_BYTES_PER_SEC => 1,
+        _ => 0,
+    }
+}
+
+// Helper function to get the CRF value used for new recording segments, starting from the quality preset's CRF
+fn recording_crf(preset_crf: u32) -> u32 {
+    (preset_crf + ADAPTIVE_RECORDING_CRF_INCREASE[adaptive_quality_level()]).min(51)
+}
+
+// Helper function to get the JPEG/WebP quality used for screenshots, starting from the configured quality
+fn screenshot_encoding_quality(configured_quality: u8) -> u8 {
+    let decrease = ADAPTIVE_SCREENSHOT_QUALITY_DECREASE[adaptive_quality_level()];
+    // A slow link never pushes the quality below 10, where text becomes unreadable
+    configured_quality.saturating_sub(decrease).max(configured_quality.min(10))
+}
+
+// Helper function to downscale a screenshot according to the current quality step
+fn apply_adaptive_scaling(img: image::RgbaImage) -> image::RgbaImage {
+    let scale = ADAPTIVE_SCREENSHOT_SCALES[adaptive_quality_level()];
+    if scale >= 1.0 {
+        return img;
+    }
+
+    let new_width = ((img.width() as f32 * scale) as u32).max(1);
+    let new_height = ((img.height() as f32 * scale) as u32).max(1);
+    image::imageops::resize(&img, new_width, new_height, image::imageops::FilterType::Triangle)
+}
+
+// Longest side, in pixels, screenshots are downscaled to; 0 keeps the captured resolution
+static SCREENSHOT_MAX_DIMENSION: AtomicU64 = AtomicU64::new(0);
+
+// Command to cap the resolution of screenshots, e.g. so 4K screens aren't stored at full size.
+// Images whose larger side exceeds pixels are downscaled keeping their aspect ratio; 0 disables the cap.
+#[tauri::command]
+fn set_screenshot_max_dimension(pixels: u32) -> Result<String, CommandError> {
+    if pixels != 0 && !(240..=16384).contains(&pixels) {
+        return Err(CommandError::InvalidInput("Screenshot maximum dimension must be 0 or between 240 and 16384 pixels".to_string()));
+    }
+
+    SCREENSHOT_MAX_DIMENSION.store(pixels as u64, Ordering::SeqCst);
+
+    if pixels == 0 {
+        Ok("Screenshots will be kept at their captured resolution".to_string())
+    } else {
+        Ok(format!("Screenshots will be downscaled to at most {} pixels on their longer side", pixels))
+    }
+}
+
+// Helper function to downscale a screenshot whose larger side exceeds the configured maximum
+fn limit_screenshot_dimension(img: image::RgbaImage) -> image::RgbaImage {
+    downscale_to_max_dimension(img, SCREENSHOT_MAX_DIMENSION.load(Ordering::SeqCst) as u32)
+}
+
+// Helper function to downscale an image whose larger side exceeds max_dimension, keeping its aspect ratio;
+// 0 leaves it as is
+fn downscale_to_max_dimension(img: image::RgbaImage, max_dimension: u32) -> image::RgbaImage {
+    let larger_side = img.width().max(img.height());
+    if max_dimension == 0 || larger_side <= max_dimension {
+        return img;
+    }
+
+    let scale = max_dimension as f64 / larger_side as f64;
+    let new_width = ((img.width() as f64 * scale).round() as u32).max(1);
+    let new_height = ((img.height() as f64 * scale).round() as u32).max(1);
+    image::imageops::resize(&img, new_width, new_height, image::imageops::FilterType::Triangle)
+}
+
+// Command to enable or disable bandwidth-adaptive capture quality
+#[tauri::command]
+fn set_adaptive_to_bandwidth(enabled: bool) -> Result<String, CommandError> {
+    ADAPTIVE_TO_BANDWIDTH.store(enabled, Ordering::SeqCst);
+
+    let bandwidth = match measured_upload_bandwidth() {
+        Some(bytes_per_sec) => format!("{:.2} KB/s", bytes_per_sec / 1024.0),
+        None => "not measured yet".to_string(),
+    };
+
+    if enabled {
+        Ok(format!("Bandwidth-adaptive capture quality enabled (measured upload bandwidth: {})", bandwidth))
+    } else {
+        Ok("Bandwidth-adaptive capture quality disabled".to_string())
+    }
+}
+
+// Ed25519 key used to sign session manifests, taken from REMOTE_WORK_MANIFEST_KEY (hex) if set
+lazy_static! {
+    static ref MANIFEST_SIGNING_KEY: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(
+        std::env::var("REMOTE_WORK_MANIFEST_KEY").ok().filter(|key| !key.trim().is_empty())
+    ));
+}
+
+// Command to set (or clear) the hex encoded Ed25519 secret key used to sign manifests
+#[tauri::command]
+fn set_manifest_signing_key(secret_key_hex: Option<String>) -> Result<String, CommandError> {
+    let mut key_guard = MANIFEST_SIGNING_KEY.lock().map_err(|e| e.to_string())?;
+
+    match secret_key_hex.filter(|key| !key.trim().is_empty()) {
+        Some(key) => {
+            // Validate the key before storing it
+            let signing_key = manifest::parse_signing_key(&key).map_err(CommandError::InvalidInput)?;
+            *key_guard = Some(key);
+            Ok(format!("Manifest signing key set (public key: {})", hex::encode(signing_key.verifying_key().to_bytes())))
+        }
+        None => {
+            *key_guard = None;
+            Ok("Manifest signing key cleared, manifests will be unsigned".to_string())
+        }
+    }
+}
+
+lazy_static! {
+    // Ed25519 public key manifests are verified against, taken from REMOTE_WORK_MANIFEST_PUBLIC_KEY (hex) if set.
+    // Without one, the public key of the signing key is trusted.
+    static ref MANIFEST_PUBLIC_KEY: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(
+        std::env::var("REMOTE_WORK_MANIFEST_PUBLIC_KEY").ok().filter(|key| !key.trim().is_empty())
+    ));
+}
+
+// Command to set (or clear) the hex encoded Ed25519 public key manifest signatures are verified against, for
+// machines that verify manifests without holding the signing key
+#[tauri::command]
+fn set_manifest_public_key(public_key_hex: Option<String>) -> Result<String, CommandError> {
+    let mut key_guard = MANIFEST_PUBLIC_KEY.lock().map_err(|e| e.to_string())?;
+
+    match public_key_hex.filter(|key| !key.trim().is_empty()) {
+        Some(key) => {
+            // Validate the key before storing it
+            manifest::parse_verifying_key(&key).map_err(CommandError::InvalidInput)?;
+            *key_guard = Some(key.trim().to_string());
+            Ok("Manifest public key set, manifests must be signed with its signing key".to_string())
+        }
+        None => {
+            *key_guard = None;
+            Ok("Manifest public key cleared".to_string())
+        }
+    }
+}
+
+// Helper function to get the public key manifests are verified against: the configured public key, otherwise
+// the one of the configured signing key. None if neither is set.
+fn trusted_manifest_key() -> Result<Option<ed25519_dalek::VerifyingKey>, String> {
+    if let Some(key) = MANIFEST_PUBLIC_KEY.lock().map_err(|e| e.to_string())?.as_ref() {
+        return manifest::parse_verifying_key(key).map(Some);
+    }
+
+    match MANIFEST_SIGNING_KEY.lock().map_err(|e| e.to_string())?.as_ref() {
+        Some(key) => Ok(Some(manifest::parse_signing_key(key)?.verifying_key())),
+        None => Ok(None),
+    }
+}
+
+// Command to write a tamper-evident manifest of every local capture in a session.
+// Returns the path of the manifest file.
+#[tauri::command]
+async fn generate_session_manifest(session_id: String) -> Result<String, CommandError> {
+    // Session IDs end up in file names, so only accept well-formed UUIDs
+    validate_session_id(&session_id).map_err(CommandError::InvalidInput)?;
+
+    let signing_key = {
+        let key_guard = MANIFEST_SIGNING_KEY.lock().map_err(|e| e.to_string())?;
+        match key_guard.as_ref() {
+            Some(key) => Some(manifest::parse_signing_key(key)?),
+            None => None,
+        }
+    };
+
+    let data_dir = get_data_directory();
+    let session_manifest = manifest::build_manifest(&data_dir, &session_id, signing_key.as_ref())?;
+
+    let manifests_dir = data_dir.join("manifests");
+    fs::create_dir_all(&manifests_dir).map_err(|e| format!("Failed to create manifests directory: {}", e))?;
+
+    let manifest_path = manifests_dir.join(format!("manifest_{}.json", session_id));
+    let json = serde_json::to_string_pretty(&session_manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(&manifest_path, json).map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    Ok(manifest_path.to_string_lossy().to_string())
+}
+
+// Command to check a manifest's signature and that the files it lists are unchanged. The signature is checked
+// against public_key (hex) if given, otherwise against the configured trusted key; the key stored in the manifest
+// itself is never trusted.
+#[tauri::command]
+async fn verify_session_manifest(path: String, public_key: Option<String>) -> Result<String, CommandError> {
+    let content = fs::read_to_string(&path).map_err(|e| CommandError::NotFound(format!("Failed to read manifest: {}", e)))?;
+    let session_manifest: manifest::SessionManifest = serde_json::from_str(&content)
+        .map_err(|e| CommandError::InvalidInput(format!("Invalid manifest file: {}", e)))?;
+
+    let trusted_key = match public_key.filter(|key| !key.trim().is_empty()) {
+        Some(key) => Some(manifest::parse_verifying_key(&key).map_err(CommandError::InvalidInput)?),
+        None => trusted_manifest_key()?,
+    };
+
+    let verification = manifest::verify_manifest(&get_data_directory(), &session_manifest, trusted_key.as_ref())?;
+
+    match serde_json::to_string(&verification) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(CommandError::Internal(format!("Failed to serialize manifest verification: {}", e))),
+    }
+}
+
+lazy_static! {
+    // What to do with screenshots while the camera or microphone is in use: "off", "pause" or "blur"
+    static ref CAMERA_USE_MODE: Arc<Mutex<String>> = Arc::new(Mutex::new("off".to_string()));
+}
+
+// Command to skip or blur screenshots automatically while a call app holds the camera or microphone
+#[tauri::command]
+fn set_pause_during_camera_use(mode: String) -> Result<String, CommandError> {
+    let mode = mode.to_lowercase();
+    if !["off", "pause", "blur"].contains(&mode.as_str()) {
+        return Err(CommandError::InvalidInput(format!("Invalid mode '{}': expected \"pause\", \"blur\" or \"off\"", mode)));
+    }
+
+    if mode != "off" && !cfg!(target_os = "windows") {
+        return Err(CommandError::Unsupported("Camera/microphone use detection is only supported on Windows".to_string()));
+    }
+
+    let mut mode_guard = CAMERA_USE_MODE.lock().map_err(|e| e.to_string())?;
+    *mode_guard = mode.clone();
+
+    Ok(format!("Capture during camera/microphone use set to: {}", mode))
+}
+
+// Helper function to check whether the webcam or microphone is currently held by any app
+fn camera_or_microphone_in_use() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        windows_utils::is_camera_or_microphone_in_use()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        false
+    }
+}
+
+// Helper function to blur a whole image by shrinking it and scaling it back up
+fn blur_image(img: &image::RgbaImage) -> image::RgbaImage {
+    let (width, height) = img.dimensions();
+    let small = image::imageops::resize(
+        img,
+        std::cmp::max(1, width / 32),
+        std::cmp::max(1, height / 32),
+        image::imageops::FilterType::Triangle,
+    );
+    image::imageops::resize(&small, width, height, image::imageops::FilterType::Triangle)
+}
+
+lazy_static! {
+    // How excluded windows are hidden in screenshots: "black", "blur" or "pixelate"
+    static ref MASK_STYLE: Arc<Mutex<String>> = Arc::new(Mutex::new("black".to_string()));
+}
+
+// Size in pixels of the blocks excluded windows are pixelated into
+const PIXELATE_BLOCK_SIZE: u32 = 16;
+
+// Command to choose how excluded windows are hidden in screenshots
+#[tauri::command]
+fn set_mask_style(style: String) -> Result<String, CommandError> {
+    let style = style.to_lowercase();
+    if !["black", "blur", "pixelate"].contains(&style.as_str()) {
+        return Err(CommandError::InvalidInput(format!("Invalid mask style '{}': expected \"black\", \"blur\" or \"pixelate\"", style)));
+    }
+
+    *MASK_STYLE.lock().map_err(|e| e.to_string())? = style.clone();
+
+    Ok(format!("Mask style set to: {}", style))
+}
+
+// Helper function to hide the x1..x2 / y1..y2 area of an image in the configured mask style
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn mask_region(img: &mut image::RgbaImage, x1: u32, y1: u32, x2: u32, y2: u32) {
+    let (width, height) = (x2 - x1, y2 - y1);
+    let style = MASK_STYLE.lock().map(|style| style.clone()).unwrap_or_else(|_| "black".to_string());
+
+    let region = image::imageops::crop_imm(img, x1, y1, width, height).to_image();
+    let masked = match style.as_str() {
+        "blur" => blur_image(&region),
+        "pixelate" => {
+            let small = image::imageops::resize(
+                &region,
+                std::cmp::max(1, width / PIXELATE_BLOCK_SIZE),
+                std::cmp::max(1, height / PIXELATE_BLOCK_SIZE),
+                image::imageops::FilterType::Triangle,
+            );
+            image::imageops::resize(&small, width, height, image::imageops::FilterType::Nearest)
+        },
+        _ => image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255])), // Black with full opacity
+    };
+
+    image::imageops::replace(img, &masked, x1 as i64, y1 as i64);
+}
+
+// Helper function to hide excluded windows in a captured screen image.
+// origin_x/origin_y are the screen's position on the virtual desktop, which window rectangles are relative to.
+#[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+fn mask_excluded_windows(img: &mut image::RgbaImage, origin_x: i32, origin_y: i32) {
+    // Apply window masking on Windows (with added safety checks to prevent all-black screenshots)
+    #[cfg(target_os = "windows")]
+    {
+        let (screen_width, screen_height) = img.dimensions();
+
+        // Get excluded windows list
+        let excluded_windows = RUNNING_EXCLUDED_WINDOWS.lock().unwrap().clone();
+
+        // Get visible windows to mask
+        if let Ok(windows_to_mask) = crate::windows_utils::get_visible_windows() {
+            for window in windows_to_mask {
+                let window_title_lower = window.title.to_lowercase();
+
+                let is_excluded = window_title_matches_exclusions(&window_title_lower, &excluded_windows);
+
+                if is_excluded {
+                    // Convert virtual desktop coordinates to coordinates within this screen's image
+                    let x1_raw = window.rect.left - origin_x;
+                    let y1_raw = window.rect.top - origin_y;
+                    let x2_raw = window.rect.right - origin_x;
+                    let y2_raw = window.rect.bottom - origin_y;
+
+                    // Safety check: skip windows with invalid coordinates
+                    if x2_raw <= x1_raw || y2_raw <= y1_raw {
+                        continue;
+                    }
+
+                    // Convert to unsigned and clamp to image dimensions
+                    let x1 = std::cmp::max(0, x1_raw) as u32;
+                    let y1 = std::cmp::max(0, y1_raw) as u32;
+                    let mut x2 = std::cmp::max(0, x2_raw) as u32;
+                    let mut y2 = std::cmp::max(0, y2_raw) as u32;
+
+                    // Ensure coordinates are within image bounds
+                    x2 = std::cmp::min(x2, screen_width);
+                    y2 = std::cmp::min(y2, screen_height);
+
+                    // Additional safety: prevent overly large areas
+                    let width = x2.saturating_sub(x1);
+                    let height = y2.saturating_sub(y1);
+
+                    // Make sure x1,y1 are still less than or equal to x2,y2 after clamping
+                    if x1 >= x2 || y1 >= y2 {
+                        continue; // Skip if the area becomes invalid after clamping
+                    }
+
+                    // Skip if window exceeds reasonable size (prevent accidentally capturing entire screen)
+                    // Only skip if the window is more than 90% of the screen size to be more permissive
+                    if width * height > screen_width * screen_height * 9 / 10 {
+                        continue;
+                    }
+
+                    // Hide the window area
+                    mask_region(img, x1, y1, x2, y2);
+                }
+            }
+        }
+    }
+}
+
+// Whether captures showing protected (DRM / capture-excluded) content are skipped entirely.
+// When disabled the protected window area is filled with a neutral gray instead of being left black.
+static SKIP_PROTECTED_CONTENT: AtomicBool = AtomicBool::new(false);
+
+// Gray used to mark areas of a screenshot that the OS refused to capture
+const PROTECTED_CONTENT_FILL: [u8; 4] = [96, 96, 96, 255];
+
+// Command to choose between skipping captures over protected content and masking the protected area
+#[tauri::command]
+fn set_skip_protected_content(enabled: bool) -> Result<String, CommandError> {
+    SKIP_PROTECTED_CONTENT.store(enabled, Ordering::SeqCst);
+
+    if enabled {
+        Ok("Captures over protected content will be skipped".to_string())
+    } else {
+        Ok("Protected content will be masked in captures".to_string())
+    }
+}
+
+// Helper function to detect a black frame caused by a protected foreground window.
+// Returns an error when the capture should be skipped, otherwise masks the protected area in place.
+#[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+fn handle_protected_content(img: &mut image::RgbaImage, origin_x: i32, origin_y: i32) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        let rect = match crate::windows_utils::protected_foreground_window_rect() {
+            Some(rect) => rect,
+            None => return Ok(()),
+        };
+
+        // Translate the window rectangle into this screen's image and clamp it
+        let (screen_width, screen_height) = img.dimensions();
+        let x1 = std::cmp::min(std::cmp::max(0, rect.left - origin_x) as u32, screen_width);
+        let y1 = std::cmp::min(std::cmp::max(0, rect.top - origin_y) as u32, screen_height);
+        let x2 = std::cmp::min(std::cmp::max(0, rect.right - origin_x) as u32, screen_width);
+        let y2 = std::cmp::min(std::cmp::max(0, rect.bottom - origin_y) as u32, screen_height);
+        if x1 >= x2 || y1 >= y2 {
+            return Ok(());
+        }
+
+        // Sample the window area; the OS returns (near) black pixels for protected content
+        let step = 8;
+        let mut sampled = 0u64;
+        let mut black = 0u64;
+        for y in (y1..y2).step_by(step) {
+            for x in (x1..x2).step_by(step) {
+                let pixel = img.get_pixel(x, y);
+                sampled += 1;
+                if pixel[0] < 8 && pixel[1] < 8 && pixel[2] < 8 {
+                    black += 1;
+                }
+            }
+        }
+
+        // Leave genuinely dark content alone unless nearly every sample is black
+        if sampled == 0 || black * 100 < sampled * 98 {
+            return Ok(());
+        }
+
+        if SKIP_PROTECTED_CONTENT.load(Ordering::SeqCst) {
+            return Err("Capture skipped: protected content is on screen".to_string());
+        }
+
+        for y in y1..y2 {
+            for x in x1..x2 {
+                img.put_pixel(x, y, image::Rgba(PROTECTED_CONTENT_FILL));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+lazy_static! {
+    // Which screens are captured: "primary", "all-separate" (one file per screen) or "all-stitched" (one composite image)
+    static ref CAPTURE_MODE: Arc<Mutex<String>> = Arc::new(Mutex::new("primary".to_string()));
+}
+
+// Command to choose which monitors are captured
+#[tauri::command]
+fn set_capture_mode(mode: String) -> Result<String, CommandError> {
+    let mode = mode.to_lowercase();
+    if !["primary", "all-separate", "all-stitched"].contains(&mode.as_str()) {
+        return Err(CommandError::InvalidInput(format!("Invalid capture mode '{}': expected \"primary\", \"all-separate\" or \"all-stitched\"", mode)));
+    }
+
+    let mut mode_guard = CAPTURE_MODE.lock().map_err(|e| e.to_string())?;
+    *mode_guard = mode.clone();
+
+    Ok(format!("Capture mode set to: {}", mode))
+}
+
+// A connected screen as reported by list_screens; the index is the monitor index capture commands take
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScreenInfo {
+    index: usize,
+    width: u32,
+    height: u32,
+    x: i32, // Origin on the virtual desktop
+    y: i32,
+    scale_factor: f32,
+    is_primary: bool,
+}
+
+// Command to list the connected screens and their geometry, an empty array when there are none
+#[tauri::command]
+fn list_screens() -> Result<String, CommandError> {
+    let screens = screen_source::current().screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+
+    let screen_infos: Vec<ScreenInfo> = screens
+        .iter()
+        .enumerate()
+        .map(|(index, screen)| ScreenInfo {
+            index,
+            width: screen.display_info().width,
+            height: screen.display_info().height,
+            x: screen.display_info().x,
+            y: screen.display_info().y,
+            scale_factor: screen.display_info().scale_factor,
+            is_primary: screen.display_info().is_primary,
+        })
+        .collect();
+
+    Ok(serde_json::to_string(&screen_infos).map_err(|e| format!("Failed to serialize screens: {}", e))?)
+}
+
+// Helper function to capture a single screen with protected content handled and excluded windows masked out
+fn capture_masked_screen(screen: &dyn screen_source::SourceScreen) -> Result<image::RgbaImage, String> {
+    let mut img = screen
+        .capture_area(0, 0, screen.display_info().width, screen.display_info().height)
+        .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
+
+    // Check for protected content before masking, since masked windows are black too
+    handle_protected_content(&mut img, screen.display_info().x, screen.display_info().y)?;
+
+    mask_excluded_windows(&mut img, screen.display_info().x, screen.display_info().y);
+
+    Ok(img)
+}
+
+// Helper function to composite per-screen captures into one image laid out like the virtual desktop
+fn stitch_screen_captures(captures: &[(i32, i32, image::RgbaImage)]) -> image::RgbaImage {
+    let min_x = captures.iter().map(|(x, _, _)| *x).min().unwrap_or(0);
+    let min_y = captures.iter().map(|(_, y, _)| *y).min().unwrap_or(0);
+    let width = captures.iter().map(|(x, _, img)| (x - min_x) as u32 + img.width()).max().unwrap_or(1);
+    let height = captures.iter().map(|(_, y, img)| (y - min_y) as u32 + img.height()).max().unwrap_or(1);
+
+    // Areas of the bounding box not covered by any screen stay black
+    let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+    for (x, y, img) in captures {
+        image::imageops::replace(&mut canvas, img, (x - min_x) as i64, (y - min_y) as i64);
+    }
+
+    canvas
+}
+
+lazy_static! {
+    // Geometry of the screens when they were last queried, to notice monitors being plugged in or out
+    static ref LAST_DISPLAY_LAYOUT: Arc<Mutex<Option<Vec<screen_source::DisplayGeometry>>>> = Arc::new(Mutex::new(None));
+}
+
+// How often the screens are checked for changes while recording
+const DISPLAY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Helper function to get the geometry of every screen, in monitor index order
+fn display_layout(screens: &[Box<dyn screen_source::SourceScreen>]) -> Vec<screen_source::DisplayGeometry> {
+    screens.iter().map(|screen| screen.display_info()).collect()
+}
+
+// Helper function to remember the current screen layout, emitting "display-changed" if it differs from the
+// one seen before. Returns whether it changed; the first layout seen is not a change.
+fn note_display_layout(layout: &[screen_source::DisplayGeometry]) -> bool {
+    let previous = match LAST_DISPLAY_LAYOUT.lock() {
+        Ok(mut last_layout) => match last_layout.replace(layout.to_vec()) {
+            Some(previous) if previous.as_slice() != layout => previous,
+            _ => return false,
+        },
+        Err(_) => return false,
+    };
+
+    info!("Screens changed: {} connected before, {} now", previous.len(), layout.len());
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(
+            "display-changed",
+            events::DisplayChangedEvent {
+                previous_count: previous.len(),
+                count: layout.len(),
+                resolution_changed: previous.len() == layout.len(),
+                screens: layout
+                    .iter()
+                    .map(|geometry| events::DisplayChangedScreen {
+                        x: geometry.x,
+                        y: geometry.y,
+                        width: geometry.width,
+                        height: geometry.height,
+                        is_primary: geometry.is_primary,
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    true
+}
+
+lazy_static! {
+    // Area of the primary screen captured instead of the capture mode's screens; None captures whole screens
+    static ref DEFAULT_CAPTURE_REGION: Arc<Mutex<Option<screen_source::CaptureRegion>>> = Arc::new(Mutex::new(None));
+}
+
+// Helper function to capture a region of the primary screen, with protected content handled and excluded
+// windows masked out relative to the region's position
+fn capture_masked_region(source: &dyn screen_source::ScreenSource, region: &screen_source::CaptureRegion) -> Result<image::RgbaImage, String> {
+    let screens = source.screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+    let screen = screens
+        .iter()
+        .find(|screen| screen.display_info().is_primary)
+        .or_else(|| screens.first())
+        .ok_or_else(|| "No screens found".to_string())?;
+    let info = screen.display_info();
+    region.validate(&info)?;
+
+    let mut img = screen
+        .capture_area(region.x, region.y, region.width, region.height)
+        .map_err(|e| format!("Failed to capture region: {}", e))?;
+
+    let origin_x = info.x + region.x;
+    let origin_y = info.y + region.y;
+    handle_protected_content(&mut img, origin_x, origin_y)?;
+    mask_excluded_windows(&mut img, origin_x, origin_y);
+
+    Ok(img)
+}
+
+// Command to capture and upload a fixed rectangle of the primary screen, e.g. a POS terminal's area.
+// Coordinates are relative to the primary screen's top-left corner. Returns the remote URL.
+#[tauri::command]
+async fn capture_region(x: i32, y: i32, width: u32, height: u32) -> Result<String, CommandError> {
+    let region = screen_source::CaptureRegion { x, y, width, height };
+    validate_capture_region(screen_source::current(), &region)?;
+
+    let active_window = active_window_title();
+    let img = capture_masked_region(screen_source::current(), &region)?;
+    let img = apply_adaptive_scaling(limit_screenshot_dimension(img));
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let filename = format!("region_{}_{}.{}", session_id, timestamp, screenshot_extension());
+
+    let screenshot = store_and_upload_screenshot(&img, &filename, &session_id, &active_window).await?;
+    Ok(screenshot.remote_url)
+}
+
+// Helper function to check a region given to a command against the current primary screen
+fn validate_capture_region(source: &dyn screen_source::ScreenSource, region: &screen_source::CaptureRegion) -> Result<(), CommandError> {
+    let screens = source.screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+    let primary_screen = screens
+        .iter()
+        .find(|screen| screen.display_info().is_primary)
+        .or_else(|| screens.first())
+        .ok_or_else(|| CommandError::NotFound("No screens found".to_string()))?;
+
+    region.validate(&primary_screen.display_info()).map_err(CommandError::InvalidInput)
+}
+
+// Command to make scheduled and on-demand captures take only this rectangle of the primary screen instead of
+// the screens chosen by the capture mode. Captures of a specific monitor still take the whole monitor.
+#[tauri::command]
+fn set_default_capture_region(x: i32, y: i32, width: u32, height: u32) -> Result<String, CommandError> {
+    let region = screen_source::CaptureRegion { x, y, width, height };
+    validate_capture_region(screen_source::current(), &region)?;
+
+    *DEFAULT_CAPTURE_REGION.lock().map_err(|e| e.to_string())? = Some(region);
+    Ok(format!("Captures limited to {}x{} at ({}, {}) on the primary screen", width, height, x, y))
+}
+
+// Command to go back to capturing whole screens
+#[tauri::command]
+fn clear_default_capture_region() -> Result<String, CommandError> {
+    *DEFAULT_CAPTURE_REGION.lock().map_err(|e| e.to_string())? = None;
+    Ok("Default capture region cleared".to_string())
+}
+
+// Helper function to capture the screens selected by the capture mode (or only the given monitor), with
+// privacy settings applied. Returns (filename suffix, image) pairs; in "all-separate" mode and for a single
+// monitor the suffix carries the monitor index. With a default capture region only that region is captured,
+// unless a monitor is given.
+fn capture_masked_screens(source: &dyn screen_source::ScreenSource, monitor_index: Option<usize>) -> Result<Vec<(String, image::RgbaImage)>, String> {
+    if monitor_index.is_none() {
+        if let Some(region) = *DEFAULT_CAPTURE_REGION.lock().map_err(|e| e.to_string())? {
+            return finish_screen_captures(vec![(String::new(), capture_masked_region(source, &region)?)]);
+        }
+    }
+
+    // The screens are queried anew for every capture so monitors coming and going are picked up
+    let screens = source.screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+    note_display_layout(&display_layout(&screens));
+
+    let images = match capture_screens_in_mode(&screens, monitor_index) {
+        Ok(images) => images,
+        Err(e) => {
+            // A monitor unplugged between querying and capturing makes the capture fail; try the new layout once
+            let screens = source.screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+            if !note_display_layout(&display_layout(&screens)) {
+                return Err(e);
+            }
+            warn!("Capture failed while the screens changed, retrying: {}", e);
+            capture_screens_in_mode(&screens, monitor_index)?
+        }
+    };
+
+    finish_screen_captures(images)
+}
+
+// Helper function to capture the given screens as selected by the capture mode, or only the given monitor
+fn capture_screens_in_mode(
+    screens: &[Box<dyn screen_source::SourceScreen>],
+    monitor_index: Option<usize>,
+) -> Result<Vec<(String, image::RgbaImage)>, String> {
+    if screens.is_empty() {
+        return Err("No screens found".to_string());
+    }
+
+    let capture_mode = match monitor_index {
+        Some(_) => "monitor".to_string(),
+        None => CAPTURE_MODE.lock().map(|mode| mode.clone()).unwrap_or_else(|_| "primary".to_string()),
+    };
+    let images = match capture_mode.as_str() {
+        "monitor" => {
+            let index = monitor_index.unwrap_or(0);
+            let screen = screens
+                .get(index)
+                .ok_or_else(|| format!("Monitor {} not found ({} screens connected)", index, screens.len()))?;
+            vec![(format!("_m{}", index), capture_masked_screen(screen)?)]
+        },
+        "all-separate" => {
+            let mut images = Vec::new();
+            for (index, screen) in screens.iter().enumerate() {
+                images.push((format!("_m{}", index), capture_masked_screen(screen)?));
+            }
+            images
+        },
+        "all-stitched" => {
+            let mut captures = Vec::new();
+            for screen in screens {
+                captures.push((screen.display_info().x, screen.display_info().y, capture_masked_screen(screen)?));
+            }
+            vec![(String::new(), stitch_screen_captures(&captures))]
+        },
+        _ => {
+            let primary_screen = screens
+                .iter()
+                .find(|screen| screen.display_info().is_primary)
+                .unwrap_or(&screens[0]);
+            vec![(String::new(), capture_masked_screen(primary_screen)?)]
+        },
+    };
+
+    Ok(images)
+}
+
+// Helper function to apply the camera-use policy and size limits to fresh captures
+fn finish_screen_captures(mut images: Vec<(String, image::RgbaImage)>) -> Result<Vec<(String, image::RgbaImage)>, String> {
+    // Skip or blur the capture while a video call holds the camera or microphone
+    let camera_use_mode = CAMERA_USE_MODE.lock().map(|mode| mode.clone()).unwrap_or_else(|_| "off".to_string());
+    if camera_use_mode != "off" && camera_or_microphone_in_use() {
+        if camera_use_mode == "pause" {
+            return Err("Capture skipped: camera or microphone is in use".to_string());
+        }
+        images = images.into_iter().map(|(suffix, img)| (suffix, blur_image(&img))).collect();
+    }
+
+    // Cap the resolution and shrink the images when the upload link is slow (after masking so masked areas scale too)
+    Ok(images
+        .into_iter()
+        .map(|(suffix, img)| (suffix, apply_adaptive_scaling(limit_screenshot_dimension(img))))
+        .collect())
+}
+
+lazy_static! {
+    // Screenshot file format ("png", "jpeg" or "webp") and quality (1-100, used by the lossy formats)
+    static ref SCREENSHOT_FORMAT: Arc<Mutex<String>> = Arc::new(Mutex::new("png".to_string()));
+    static ref SCREENSHOT_QUALITY: Arc<Mutex<u8>> = Arc::new(Mutex::new(80));
+}
+
+// Command to choose the screenshot file format; lossy formats make uploads much smaller
+#[tauri::command]
+fn set_screenshot_format(format: String, quality: u8) -> Result<String, CommandError> {
+    let format = match format.to_lowercase().as_str() {
+        "png" => "png".to_string(),
+        "jpeg" | "jpg" => "jpeg".to_string(),
+        "webp" => "webp".to_string(),
+        other => return Err(CommandError::InvalidInput(format!("Invalid screenshot format '{}': expected \"png\", \"jpeg\" or \"webp\"", other))),
+    };
+    let quality = quality.clamp(1, 100);
+
+    *SCREENSHOT_FORMAT.lock().map_err(|e| e.to_string())? = format.clone();
+    *SCREENSHOT_QUALITY.lock().map_err(|e| e.to_string())? = quality;
+
+    if format == "png" {
+        Ok("Screenshot format set to png".to_string())
+    } else {
+        Ok(format!("Screenshot format set to {} (quality {})", format, quality))
+    }
+}
+
+// Helper function to get the file extension for the configured screenshot format
+fn screenshot_extension() -> String {
+    match SCREENSHOT_FORMAT.lock().map(|format| format.clone()).unwrap_or_default().as_str() {
+        "jpeg" => "jpg".to_string(),
+        "webp" => "webp".to_string(),
+        _ => "png".to_string(),
+    }
+}
+
+// Helper function to encode a captured image in the configured screenshot format
+fn encode_screenshot(img: &image::RgbaImage) -> Result<Vec<u8>, String> {
+    let format = SCREENSHOT_FORMAT.lock().map(|format| format.clone()).unwrap_or_default();
+    let quality = screenshot_encoding_quality(SCREENSHOT_QUALITY.lock().map(|quality| *quality).unwrap_or(80));
+    let mut bytes = Vec::new();
+
+    match format.as_str() {
+        "jpeg" => {
+            // JPEG has no alpha channel
+            let rgb = image::DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality)
+                .encode(&rgb, rgb.width(), rgb.height(), image::ColorType::Rgb8)
+                .map_err(|e| format!("Failed to encode screenshot as JPEG: {}", e))?;
+        },
+        "webp" => {
+            image::codecs::webp::WebPEncoder::new_with_quality(&mut bytes, image::codecs::webp::WebPQuality::lossy(quality))
+                .encode(img, img.width(), img.height(), image::ColorType::Rgba8)
+                .map_err(|e| format!("Failed to encode screenshot as WebP: {}", e))?;
+        },
+        _ => {
+            use image::ImageEncoder;
+            image::codecs::png::PngEncoder::new(&mut bytes)
+                .write_image(img, img.width(), img.height(), image::ColorType::Rgba8)
+                .map_err(|e| format!("Failed to encode screenshot as PNG: {}", e))?;
+        },
+    }
+
+    Ok(bytes)
+}
+
+// Largest file uploaded as is, in bytes; larger images are re-encoded as JPEG to fit and anything else is
+// refused. 0 means no limit.
+static MAX_UPLOAD_BYTES: AtomicU64 = AtomicU64::new(0);
+// JPEG qualities tried in turn, best first, when an image has to be shrunk to the upload limit
+const UPLOAD_LIMIT_JPEG_QUALITIES: [u8; 6] = [85, 75, 65, 50, 35, 20];
+// Upload file types that are images and so can be re-encoded
+const IMAGE_UPLOAD_FILE_TYPES: [&str; 2] = ["screenshot", "webcam"];
+
+// Command to limit the size of uploaded files, e.g. so a stitched multi-monitor PNG doesn't overwhelm the server.
+// Images over the limit are re-encoded as JPEG at decreasing quality until they fit. 0 removes the limit.
+#[tauri::command]
+fn set_max_upload_bytes(bytes: u64) -> Result<String, CommandError> {
+    MAX_UPLOAD_BYTES.store(bytes, Ordering::SeqCst);
+
+    if bytes == 0 {
+        Ok("Upload size limit removed".to_string())
+    } else {
+        Ok(format!("Uploads limited to {} bytes", bytes))
+    }
+}
+
+// Helper function to make a file fit the upload size limit. Images over it are re-encoded as JPEG at the best
+// quality that fits, which changes the file name's extension to .jpg. Returns the data and file name to upload.
+fn fit_upload_size(data: Vec<u8>, filename: &str, file_type: &str) -> Result<(Vec<u8>, String), String> {
+    let max_bytes = MAX_UPLOAD_BYTES.load(Ordering::SeqCst);
+    if max_bytes == 0 || data.len() as u64 <= max_bytes {
+        return Ok((data, filename.to_string()));
+    }
+
+    if !IMAGE_UPLOAD_FILE_TYPES.contains(&file_type) {
+        return Err(format!(
+            "{} is {} bytes, over the {} byte upload limit",
+            filename,
+            data.len(),
+            max_bytes
+        ));
+    }
+
+    // JPEG has no alpha channel
+    let rgb = image::load_from_memory(&data)
+        .map_err(|e| format!("Failed to decode {} to fit the upload limit: {}", filename, e))?
+        .to_rgb8();
+    for quality in UPLOAD_LIMIT_JPEG_QUALITIES {
+        let mut bytes = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode(&rgb, rgb.width(), rgb.height(), image::ColorType::Rgb8)
+            .map_err(|e| format!("Failed to encode {} as JPEG: {}", filename, e))?;
+
+        if bytes.len() as u64 <= max_bytes {
+            let jpeg_filename = std::path::Path::new(filename).with_extension("jpg").to_string_lossy().to_string();
+            info!(
+                "Re-encoded {} ({} bytes) as JPEG at quality {} ({} bytes) to fit the upload limit",
+                filename,
+                data.len(),
+                quality,
+                bytes.len()
+            );
+            return Ok((bytes, jpeg_filename));
+        }
+    }
+
+    Err(format!(
+        "{} is {} bytes and doesn't fit the {} byte upload limit even as a quality {} JPEG",
+        filename,
+        data.len(),
+        max_bytes,
+        UPLOAD_LIMIT_JPEG_QUALITIES[UPLOAD_LIMIT_JPEG_QUALITIES.len() - 1]
+    ))
+}
+
+// Whether metadata blocks are removed from encoded screenshots before they are saved and uploaded
+static STRIP_METADATA: AtomicBool = AtomicBool::new(false);
+
+// Command to remove all metadata (EXIF, XMP, ICC profiles, text and comments) from screenshots,
+// for clients who audit exactly which bytes are transmitted
+#[tauri::command]
+fn set_strip_metadata(enabled: bool) -> Result<String, CommandError> {
+    STRIP_METADATA.store(enabled, Ordering::SeqCst);
+
+    if enabled {
+        Ok("Screenshot metadata will be stripped".to_string())
+    } else {
+        Ok("Screenshot metadata stripping disabled".to_string())
+    }
+}
+
+// Helper function to strip the metadata from an encoded screenshot and confirm none is left
+fn strip_screenshot_metadata(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    let (stripped, removed) = image_metadata::strip(&bytes)?;
+    if !removed.is_empty() {
+        debug!("Removed metadata from screenshot: {}", removed.join(", "));
+    }
+
+    // A second pass over the result must find nothing, otherwise the screenshot isn't sent
+    let (_, remaining) = image_metadata::strip(&stripped)?;
+    if !remaining.is_empty() {
+        return Err(format!("Screenshot still contains metadata after stripping: {}", remaining.join(", ")));
+    }
+
+    Ok(stripped)
+}
+
+// Whether the text on screenshots is recognized and stored with them (off by default)
+static OCR_ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    // Tesseract language codes joined with '+', e.g. "eng+deu"
+    static ref OCR_LANGUAGES: Arc<Mutex<String>> = Arc::new(Mutex::new("eng".to_string()));
+
+    // Tesseract can take several seconds per screenshot, so one runs at a time and the others wait their turn
+    static ref OCR_SEMAPHORE: Arc<tokio::sync::Semaphore> = Arc::new(tokio::sync::Semaphore::new(1));
+}
+
+// Command to recognize the text on screenshots and store it with them, so the audit trail can be searched by what
+// was on screen. The masked image is used, so excluded windows and regions contribute no text. languages are
+// Tesseract codes joined with '+' (e.g. "eng+deu"); None keeps the current ones.
+#[tauri::command]
+async fn set_ocr_enabled(enabled: bool, languages: Option<String>) -> Result<String, CommandError> {
+    let languages = match languages {
+        Some(languages) => languages.trim().to_string(),
+        None => OCR_LANGUAGES.lock().map_err(|e| e.to_string())?.clone(),
+    };
+    ocr::validate_languages(&languages).map_err(CommandError::InvalidInput)?;
+
+    if enabled {
+        // Check Tesseract and its language data now rather than failing on every screenshot
+        let requested = languages.clone();
+        let missing = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<String>, CommandError> {
+            let tesseract_cmd = ocr::find_tesseract().ok_or_else(|| {
+                CommandError::NotFound("Tesseract was not found, install it or bundle it with the app to use OCR".to_string())
+            })?;
+            let installed = ocr::installed_languages(&tesseract_cmd)?;
+            Ok(requested
+                .split('+')
+                .filter(|code| !installed.iter().any(|language| language == code))
+                .map(str::to_string)
+                .collect())
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        if !missing.is_empty() {
+            return Err(CommandError::NotFound(format!(
+                "Tesseract has no data for OCR language(s): {}",
+                missing.join(", ")
+            )));
+        }
+    }
+
+    *OCR_LANGUAGES.lock().map_err(|e| e.to_string())? = languages.clone();
+    OCR_ENABLED.store(enabled, Ordering::SeqCst);
+
+    if enabled {
+        Ok(format!("OCR enabled ({})", languages))
+    } else {
+        Ok("OCR disabled".to_string())
+    }
+}
+
+// Helper function to recognize the text on a screenshot. Tesseract reads files, so the image is handed over as a
+// lossless PNG whatever format screenshots are uploaded in.
+fn recognize_screenshot_text(img: &image::RgbaImage, languages: &str) -> Result<String, String> {
+    let tesseract_cmd = ocr::find_tesseract().ok_or("Tesseract was not found")?;
+
+    let image_path = std::env::temp_dir().join(format!("remote-work-ocr-{}.png", uuid::Uuid::new_v4()));
+    img.save_with_format(&image_path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to save screenshot for OCR: {}", e))?;
+    let result = ocr::extract_text(&tesseract_cmd, &image_path, languages);
+    let _ = std::fs::remove_file(&image_path);
+
+    result
+}
+
+// Helper function to run OCR on a stored screenshot in the background and save the text next to the local copy
+// and in the screenshot's database row. The capture loop doesn't wait for it.
+fn spawn_screenshot_ocr(img: image::RgbaImage, text_path: PathBuf, screenshot_id: Option<u64>) {
+    let languages = match OCR_LANGUAGES.lock() {
+        Ok(languages) => languages.clone(),
+        Err(e) => {
+            error!("Failed to read OCR languages: {}", e);
+            return;
+        }
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let Ok(_permit) = OCR_SEMAPHORE.clone().acquire_owned().await else {
+            return;
+        };
+
+        let text = match tauri::async_runtime::spawn_blocking(move || recognize_screenshot_text(&img, &languages)).await {
+            Ok(Ok(text)) => text,
+            Ok(Err(e)) => {
+                error!("OCR failed for {}: {}", text_path.display(), e);
+                return;
+            }
+            Err(e) => {
+                error!("OCR task failed for {}: {}", text_path.display(), e);
+                return;
+            }
+        };
+
+        if let Err(e) = std::fs::write(&text_path, &text) {
+            error!("Failed to save OCR text to {}: {}", text_path.display(), e);
+        }
+
+        if let Some(screenshot_id) = screenshot_id {
+            match database::update_screenshot_ocr_text(screenshot_id, &text) {
+                Ok(true) => {}
+                Ok(false) => warn!("OCR text of screenshot {} was not saved to the database", screenshot_id),
+                Err(e) => error!("Failed to save OCR text of screenshot {}: {}", screenshot_id, e),
+            }
+        }
+
+        debug!("Recognized {} characters of text for {}", text.chars().count(), text_path.display());
+    });
+}
+
+// Corners the screenshot watermark can be drawn in
+const WATERMARK_POSITIONS: [&str; 4] = ["top-left", "top-right", "bottom-left", "bottom-right"];
+
+// Fonts tried for the watermark, first match wins; nothing is bundled so the system's own fonts are used
+#[cfg(target_os = "windows")]
+const WATERMARK_FONT_PATHS: &[&str] = &["C:\\Windows\\Fonts\\segoeui.ttf", "C:\\Windows\\Fonts\\arial.ttf"];
+#[cfg(target_os = "macos")]
+const WATERMARK_FONT_PATHS: &[&str] = &["/System/Library/Fonts/Supplemental/Arial.ttf", "/Library/Fonts/Arial.ttf"];
+#[cfg(target_os = "linux")]
+const WATERMARK_FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+];
+
+// Whether uploaded screenshots carry a visible user ID and capture time overlay (off by default)
+static WATERMARK_ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    // Corner the watermark is drawn in, one of WATERMARK_POSITIONS
+    static ref WATERMARK_POSITION: Arc<Mutex<String>> = Arc::new(Mutex::new("bottom-right".to_string()));
+
+    // Font for the watermark, loaded once; None if no system font could be found
+    static ref WATERMARK_FONT: Option<rusttype::Font<'static>> = WATERMARK_FONT_PATHS
+        .iter()
+        .filter_map(|path| std::fs::read(path).ok())
+        .find_map(rusttype::Font::try_from_vec);
+}
+
+// Command to turn the screenshot watermark on or off and choose its corner
+#[tauri::command]
+fn set_watermark(enabled: bool, position: String) -> Result<String, CommandError> {
+    let position = position.to_lowercase();
+    if !WATERMARK_POSITIONS.contains(&position.as_str()) {
+        return Err(CommandError::InvalidInput(format!(
+            "Invalid watermark position '{}': expected \"top-left\", \"top-right\", \"bottom-left\" or \"bottom-right\"",
+            position
+        )));
+    }
+
+    if enabled && WATERMARK_FONT.is_none() {
+        return Err(CommandError::NotFound("No font available to draw the watermark with".to_string()));
+    }
+
+    *WATERMARK_POSITION.lock().map_err(|e| e.to_string())? = position.clone();
+    WATERMARK_ENABLED.store(enabled, Ordering::SeqCst);
+
+    if enabled {
+        Ok(format!("Watermark enabled ({})", position))
+    } else {
+        Ok("Watermark disabled".to_string())
+    }
+}
+
+// Helper function to draw the user ID and the local capture time in the configured corner.
+// Called on the final image, after masking, so the watermark is never blacked out.
+fn apply_watermark(img: &mut image::RgbaImage, user_id: &str) {
+    let font = match WATERMARK_FONT.as_ref() {
+        Some(font) => font,
+        None => return,
+    };
+
+    let text = format!("{} {}", user_id, chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+
+    // Scale with the image so the text stays readable on large screens and after adaptive downscaling
+    let scale = rusttype::Scale::uniform((img.height() as f32 / 60.0).max(14.0));
+    let (text_width, text_height) = imageproc::drawing::text_size(scale, font, &text);
+    let padding = (scale.y / 3.0) as i32;
+    let box_width = text_width + padding * 2;
+    let box_height = text_height + padding * 2;
+
+    let position = WATERMARK_POSITION.lock().map(|position| position.clone()).unwrap_or_default();
+    let box_x = if position.ends_with("left") { 0 } else { img.width() as i32 - box_width };
+    let box_y = if position.starts_with("top") { 0 } else { img.height() as i32 - box_height };
+    let (box_x, box_y) = (box_x.max(0), box_y.max(0));
+
+    // White text on a black box so it stays legible on any background
+    imageproc::drawing::draw_filled_rect_mut(
+        img,
+        imageproc::rect::Rect::at(box_x, box_y).of_size(box_width.max(1) as u32, box_height.max(1) as u32),
+        image::Rgba([0, 0, 0, 255]),
+    );
+    imageproc::drawing::draw_text_mut(
+        img,
+        image::Rgba([255, 255, 255, 255]),
+        box_x + padding,
+        box_y + padding,
+        scale,
+        font,
+        &text,
+    );
+}
+
+// A screenshot that was uploaded and recorded in the database
+#[derive(Debug, Clone)]
+struct UploadedScreenshot {
+    id: Option<u64>, // Row ID in web_images; None when the database was unavailable
+    remote_url: String,
+}
+
+// Helper function to save a captured image, upload it and record it in the database along with the title of the
+// window that was focused when it was captured. The local copy is kept in the screenshots directory, so session
+// manifests can list it, until the retention sweep removes it; with retention_days at 0 (the default) screenshots
+// stay on disk for good.
+async fn store_and_upload_screenshot(img: &image::RgbaImage, filename: &str, session_id: &str, active_window: &str) -> Result<UploadedScreenshot, String> {
+    ensure_free_disk_space("screenshot")?;
+    ensure_not_blank(img, filename)?;
+
+    // Hold an upload slot from here so a dropped frame isn't even encoded
+    let _permit = acquire_capture_upload_permit().await?.ok_or_else(|| {
+        format!(
+            "Dropped screenshot {}: all {} upload slots are busy",
+            filename,
+            MAX_CONCURRENT_UPLOADS.load(Ordering::SeqCst)
+        )
+    })?;
+
+    // Create path to screenshots directory in data directory
+    let mut screenshots_dir = get_data_directory().join("screenshots");
+    if let Err(e) = std::fs::create_dir_all(&screenshots_dir) {
+        warn!("Failed to create screenshots directory in data directory: {}", e);
+        // Try to create in temp directory as fallback
+        screenshots_dir = std::env::temp_dir();
+        screenshots_dir.push("remote-work-screenshots");
+        std::fs::create_dir_all(&screenshots_dir)
+            .map_err(|e| format!("Failed to create screenshots directory in temp: {}", e))?;
+    }
+
+    // Get user ID for the duplicate check and the database metadata
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+
+    // Encode the image in the configured format; skip it if identical bytes were already uploaded
+    let img_data = if WATERMARK_ENABLED.load(Ordering::SeqCst) {
+        let mut watermarked = img.clone();
+        apply_watermark(&mut watermarked, &user_id);
+        encode_screenshot(&watermarked)?
+    } else {
+        encode_screenshot(img)?
+    };
+    let img_data = match STRIP_METADATA.load(Ordering::SeqCst) {
+        true => strip_screenshot_metadata(img_data)?,
+        false => img_data,
+    };
+    // Shrink it before saving, so the local copy and the database match what is uploaded
+    let (img_data, filename) = fit_upload_size(img_data, filename, "screenshot")?;
+    let filename = filename.as_str();
+    let checksum = sha256_hex(&img_data);
+    match database::find_screenshot_by_checksum(&user_id, &checksum) {
+        Ok(Some(existing)) => return Err(format!("Skipped duplicate screenshot {}, identical to {}", filename, existing)),
+        Ok(None) => {}
+        Err(e) => error!("Failed to check for duplicate screenshot: {}", e),
+    }
+
+    // Save it to the screenshots directory
+    let file_path = screenshots_dir.join(filename);
+    std::fs::write(&file_path, &img_data)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+    let file_size = Some(img_data.len() as i64);
+
+    // Upload the image data to the server
+    let remote_url = upload_and_record_file(img_data, filename.to_string(), "screenshot".to_string(), Some(active_window))
+        .await
+        .map_err(|e| format!("Failed to upload screenshot: {}", e.into_inner()))?;
+
+    // Save screenshot metadata to MySQL database with the remote URL
+    let id = database::save_screenshot_to_db(&user_id, session_id, &remote_url, filename, file_size, Some(&checksum), Some(active_window))
+        .map_err(|e| format!("Failed to save screenshot metadata to database: {}", e))?;
+    let id = Some(id).filter(|id| *id > 0);
+
+    // Recognize the text on the masked image, before the watermark was drawn on it
+    if OCR_ENABLED.load(Ordering::SeqCst) {
+        spawn_screenshot_ocr(img.clone(), screenshots_dir.join(format!("{}.txt", filename)), id);
+    }
+
+    Ok(UploadedScreenshot { id, remote_url })
+}
+
+// Size of the grayscale thumbnails consecutive frames are compared on, and how much a thumbnail pixel's
+// brightness may change before it counts as different (absorbs compression noise and cursor blinks)
+const FRAME_COMPARE_WIDTH: u32 = 160;
+const FRAME_COMPARE_HEIGHT: u32 = 90;
+const FRAME_PIXEL_TOLERANCE: u8 = 8;
+
+lazy_static! {
+    // Screenshots whose similarity to the previously uploaded frame reaches this ratio are skipped; 0 disables it
+    static ref SKIP_DUPLICATE_THRESHOLD: Arc<Mutex<f64>> = Arc::new(Mutex::new(0.0));
+
+    // Thumbnail of the last uploaded frame, per screenshotting monitor key and monitor suffix
+    static ref PREVIOUS_FRAMES: Arc<Mutex<HashMap<(Option<usize>, String), image::GrayImage>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+// Command to skip uploading screenshots that barely changed since the last upload, e.g. while the rep is away.
+// ratio is the similarity (0-1) at which a frame is skipped: 1 skips only unchanged frames, 0 disables skipping.
+#[tauri::command]
+fn set_skip_duplicate_threshold(ratio: f64) -> Result<String, CommandError> {
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(CommandError::InvalidInput("Skip duplicate threshold must be between 0 and 1".to_string()));
+    }
+
+    *SKIP_DUPLICATE_THRESHOLD.lock().map_err(|e| e.to_string())? = ratio;
+
+    if ratio == 0.0 {
+        Ok("Skipping unchanged screenshots disabled".to_string())
+    } else {
+        Ok(format!("Screenshots at least {:.0}% similar to the previous one will be skipped", ratio * 100.0))
+    }
+}
+
+// Helper function to reduce a frame to the thumbnail consecutive frames are compared on
+fn frame_thumbnail(img: &image::RgbaImage) -> image::GrayImage {
+    let small = image::imageops::thumbnail(img, FRAME_COMPARE_WIDTH, FRAME_COMPARE_HEIGHT);
+    image::imageops::grayscale(&small)
+}
+
+// Helper function to get the share (0-1) of thumbnail pixels that stayed the same between two frames
+fn frame_similarity(previous: &image::GrayImage, current: &image::GrayImage) -> f64 {
+    if previous.dimensions() != current.dimensions() {
+        return 0.0;
+    }
+
+    let unchanged = previous
+        .pixels()
+        .zip(current.pixels())
+        .filter(|(before, after)| before[0].abs_diff(after[0]) <= FRAME_PIXEL_TOLERANCE)
+        .count();
+
+    unchanged as f64 / (FRAME_COMPARE_WIDTH * FRAME_COMPARE_HEIGHT) as f64
+}
+
+lazy_static! {
+    // Frames whose brightness standard deviation (0-255) is below this are treated as blank; 0 disables the check
+    static ref BLANK_CAPTURE_THRESHOLD: Arc<Mutex<f64>> = Arc::new(Mutex::new(2.0));
+}
+
+// Command to set how uniform a frame must be to count as blank, e.g. the black frames captured while the
+// session is locked or the GPU switches. std_dev is the brightness standard deviation (0-255); 0 disables the check.
+#[tauri::command]
+fn set_blank_capture_threshold(std_dev: f64) -> Result<String, CommandError> {
+    if !(0.0..=64.0).contains(&std_dev) {
+        return Err(CommandError::InvalidInput("Blank capture threshold must be between 0 and 64".to_string()));
+    }
+
+    *BLANK_CAPTURE_THRESHOLD.lock().map_err(|e| e.to_string())? = std_dev;
+
+    if std_dev == 0.0 {
+        Ok("Blank capture detection disabled".to_string())
+    } else {
+        Ok(format!("Frames with a brightness deviation below {} will be skipped as blank", std_dev))
+    }
+}
+
+// Helper function to get the mean and standard deviation of a frame's brightness, sampled on its thumbnail
+fn frame_brightness(img: &image::RgbaImage) -> (f64, f64) {
+    let thumbnail = frame_thumbnail(img);
+    let pixel_count = (thumbnail.width() * thumbnail.height()).max(1) as f64;
+
+    let mean = thumbnail.pixels().map(|pixel| pixel[0] as f64).sum::<f64>() / pixel_count;
+    let variance = thumbnail
+        .pixels()
+        .map(|pixel| (pixel[0] as f64 - mean).powi(2))
+        .sum::<f64>()
+        / pixel_count;
+
+    (mean, variance.sqrt())
+}
+
+// Helper function to check that a capture isn't empty or uniformly black before it is saved and uploaded.
+// For a blank frame a "capture-blank" event is emitted and the capture should be skipped.
+fn ensure_not_blank(img: &image::RgbaImage, filename: &str) -> Result<(), String> {
+    let threshold = BLANK_CAPTURE_THRESHOLD.lock().map(|threshold| *threshold).unwrap_or(0.0);
+    if threshold == 0.0 {
+        return Ok(());
+    }
+
+    let (brightness, deviation) = match img.width() == 0 || img.height() == 0 {
+        true => (0.0, 0.0),
+        false => frame_brightness(img),
+    };
+    if deviation >= threshold {
+        return Ok(());
+    }
+
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit("capture-blank", serde_json::json!({
+            "filename": filename,
+            "brightness": brightness,
+            "deviation": deviation,
+            "threshold": threshold,
+        }));
+    }
+
+    Err(format!(
+        "Skipped blank screenshot {}: brightness deviation {:.2} is below {}",
+        filename, deviation, threshold
+    ))
+}
+
+// Uploads made by capture_and_upload_screens
+#[derive(Default)]
+struct CaptureUploads {
+    screenshots: Vec<UploadedScreenshot>,
+    // Similarity of each image that was skipped for being nearly identical to the previous upload
+    skipped_similarities: Vec<f64>,
+}
+
+// Recorded instead of the focused window's title when it can't be read
+const UNKNOWN_ACTIVE_WINDOW: &str = "unknown";
+// Recorded instead of the title when the focused window is excluded, whose title is as private as its content
+const EXCLUDED_ACTIVE_WINDOW: &str = "excluded";
+
+// Helper function to get the title of the focused window (the frontmost application's name on macOS),
+// recorded with screenshots so reviewers can see what the user was working in
+fn active_window_title() -> String {
+    #[cfg(target_os = "windows")]
+    let title = windows_utils::foreground_window_title();
+    #[cfg(target_os = "macos")]
+    let title = frontmost_application_name();
+    #[cfg(target_os = "linux")]
+    let title = x11_active_window_title();
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    let title: Option<String> = None;
+
+    let title = match title.map(|title| title.trim().to_string()) {
+        Some(title) if !title.is_empty() => title,
+        _ => return UNKNOWN_ACTIVE_WINDOW.to_string(),
+    };
+
+    redact_excluded_title(title)
+}
+
+// Helper function to replace the title of an excluded window before it is stored or sent to the UI
+fn redact_excluded_title(title: String) -> String {
+    let excluded_windows = EXCLUDED_WINDOWS.lock().map(|excluded_windows| excluded_windows.clone()).unwrap_or_default();
+    match matching_exclusion(&title.to_lowercase(), &excluded_windows) {
+        Some(_) => EXCLUDED_ACTIVE_WINDOW.to_string(),
+        None => title,
+    }
+}
+
+// Helper function to get the name of the frontmost application. lsappinfo needs no accessibility
+// or automation permission, unlike asking System Events.
+#[cfg(target_os = "macos")]
+fn frontmost_application_name() -> Option<String> {
+    let front = Command::new("lsappinfo").arg("front").output().ok()?;
+    let asn = String::from_utf8_lossy(&front.stdout).trim().to_string();
+    if asn.is_empty() {
+        return None;
+    }
+
+    // Prints "LSDisplayName"="Microsoft Excel"
+    let info = Command::new("lsappinfo").args(["info", "-only", "name", &asn]).output().ok()?;
+    let info = String::from_utf8_lossy(&info.stdout);
+    let (_, name) = info.trim().split_once('=')?;
+    Some(name.trim_matches('"').to_string())
+}
+
+// Helper function to get the focused window's title from the X server through xprop.
+// Wayland doesn't expose the focused window to clients, so there it is unknown.
+#[cfg(target_os = "linux")]
+fn x11_active_window_title() -> Option<String> {
+    // Prints "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x4600003"
+    let active = Command::new("xprop").args(["-root", "_NET_ACTIVE_WINDOW"]).output().ok()?;
+    let active = String::from_utf8_lossy(&active.stdout);
+    let window_id = active.split('#').nth(1)?.split(',').next()?.trim().to_string();
+    if window_id.is_empty() || window_id == "0x0" {
+        return None;
+    }
+
+    // Prints _NET_WM_NAME(UTF8_STRING) = "Budget.xlsx - LibreOffice Calc"
+    let name = Command::new("xprop").args(["-id", &window_id, "_NET_WM_NAME"]).output().ok()?;
+    let name = String::from_utf8_lossy(&name.stdout);
+    let (_, title) = name.split_once(" = ")?;
+    Some(title.trim().trim_matches('"').to_string())
+}
+
+// Helper function to capture the configured screens and upload each image.
+// Files are named {prefix}_{session}_{timestamp}{monitor suffix}.{format extension}. With skip_unchanged, images too
+// similar to the last one uploaded for the same monitor are skipped (see set_skip_duplicate_threshold).
+// Fails only if nothing could be captured or uploaded.
+async fn capture_and_upload_screens(file_prefix: &str, session_id: &str, timestamp: u128, monitor_index: Option<usize>, skip_unchanged: bool) -> Result<CaptureUploads, String> {
+    let active_window = active_window_title();
+    let images = capture_masked_screens(screen_source::current(), monitor_index)?;
+    let extension = screenshot_extension();
+    let skip_threshold = match skip_unchanged {
+        true => SKIP_DUPLICATE_THRESHOLD.lock().map(|threshold| *threshold).unwrap_or(0.0),
+        false => 0.0,
+    };
+
+    let mut uploads = CaptureUploads::default();
+    let mut last_error = None;
+    for (suffix, img) in images {
+        if skip_threshold > 0.0 {
+            let thumbnail = frame_thumbnail(&img);
+            let mut previous_frames = PREVIOUS_FRAMES.lock().map_err(|e| e.to_string())?;
+            let frame_key = (monitor_index, suffix.clone());
+            let similarity = previous_frames
+                .get(&frame_key)
+                .map(|previous| frame_similarity(previous, &thumbnail))
+                .unwrap_or(0.0);
+
+            if similarity >= skip_threshold {
+                debug!("Skipping screenshot{}: {:.1}% similar to the previous one", suffix, similarity * 100.0);
+                uploads.skipped_similarities.push(similarity);
+                continue;
+            }
+
+            // Compare against the last uploaded frame so slow changes still add up to an upload
+            previous_frames.insert(frame_key, thumbnail);
+        }
+
+        let filename = format!("{}_{}_{}{}.{}", file_prefix, session_id, timestamp, suffix, extension);
+        match store_and_upload_screenshot(&img, &filename, session_id, &active_window).await {
+            Ok(screenshot) => uploads.screenshots.push(screenshot),
+            Err(e) => {
+                error!("{}", e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match (uploads.screenshots.is_empty() && uploads.skipped_similarities.is_empty(), last_error) {
+        (true, Some(e)) => Err(e),
+        _ => Ok(uploads),
+    }
+}
+
+// Helper function for one-off captures outside the scheduled loops; each gets its own session ID
+async fn capture_on_demand(file_prefix: &str) -> Result<Vec<String>, String> {
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+
+    capture_and_upload_screens(file_prefix, &session_id, timestamp, None, false)
+        .await
+        .map(|uploads| uploads.screenshots.into_iter().map(|screenshot| screenshot.remote_url).collect())
+}
+
+// Command to take a single screenshot right now, independent of any running session and its timer.
+// Returns the remote URL, or a JSON array of URLs when several screens are captured separately.
+#[tauri::command]
+async fn capture_screenshot_now() -> Result<String, CommandError> {
+    let remote_urls = capture_on_demand("manual").await?;
+
+    match remote_urls.as_slice() {
+        [remote_url] => Ok(remote_url.clone()),
+        _ => Ok(serde_json::to_string(&remote_urls).map_err(|e| e.to_string())?),
+    }
+}
+
+// Command to capture and upload only the topmost visible window whose title contains the keyword.
+// The window is cut out of the screen it is mostly on, so parts on other screens are left out.
+#[tauri::command]
+#[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+async fn capture_window(title_keyword: String) -> Result<String, CommandError> {
+    #[cfg(target_os = "windows")]
+    {
+        let keyword = title_keyword.trim().to_lowercase();
+        if keyword.is_empty() {
+            return Err(CommandError::InvalidInput("Window title keyword must not be empty".to_string()));
+        }
+
+        // Windows are enumerated top to bottom and minimized ones are left out
+        let window = windows_utils::get_visible_windows()
+            .map_err(|e| format!("Failed to enumerate windows: {}", e))?
+            .into_iter()
+            .find(|window| window.title.to_lowercase().contains(&keyword))
+            .ok_or_else(|| CommandError::NotFound(format!("No visible window matches '{}' (it may be minimized)", title_keyword)))?;
+
+        // The whole image would be the window, so masking couldn't hide it
+        let excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?.clone();
+        if window_title_matches_exclusions(&window.title.to_lowercase(), &excluded_windows) {
+            return Err(CommandError::Internal(format!("Window '{}' is excluded from capture", window.title)));
+        }
+
+        if window.rect.right <= window.rect.left || window.rect.bottom <= window.rect.top {
+            return Err(CommandError::Internal(format!("Window '{}' has no visible area", window.title)));
+        }
+
+        // Pick the screen showing the largest part of the window
+        let screens = screen_source::current().screens().map_err(|e| format!("Failed to get screens: {}", e))?;
+        let overlap = |screen: &dyn screen_source::SourceScreen| {
+            let info = screen.display_info();
+            let width = window.rect.right.min(info.x + info.width as i32) - window.rect.left.max(info.x);
+            let height = window.rect.bottom.min(info.y + info.height as i32) - window.rect.top.max(info.y);
+            width.max(0) as u64 * height.max(0) as u64
+        };
+        let screen = screens
+            .iter()
+            .filter(|screen| overlap(screen) > 0)
+            .max_by_key(|screen| overlap(screen))
+            .ok_or_else(|| format!("Window '{}' is off screen", window.title))?;
+
+        // Convert virtual desktop coordinates to coordinates within the screen and clamp them, like the masking code
+        let (screen_width, screen_height) = (screen.display_info().width, screen.display_info().height);
+        let x1 = std::cmp::max(0, window.rect.left - screen.display_info().x) as u32;
+        let y1 = std::cmp::max(0, window.rect.top - screen.display_info().y) as u32;
+        let x2 = std::cmp::min(std::cmp::max(0, window.rect.right - screen.display_info().x) as u32, screen_width);
+        let y2 = std::cmp::min(std::cmp::max(0, window.rect.bottom - screen.display_info().y) as u32, screen_height);
+        if x1 >= x2 || y1 >= y2 {
+            return Err(CommandError::Internal(format!("Window '{}' is off screen", window.title)));
+        }
+
+        let active_window = active_window_title();
+        let mut img = screen
+            .capture_area(x1 as i32, y1 as i32, x2 - x1, y2 - y1)
+            .map_err(|e| format!("Failed to capture window: {}", e))?;
+
+        // Same privacy handling as full screen captures, relative to the captured area's position
+        let origin_x = screen.display_info().x + x1 as i32;
+        let origin_y = screen.display_info().y + y1 as i32;
+        handle_protected_content(&mut img, origin_x, origin_y)?;
+        mask_excluded_windows(&mut img, origin_x, origin_y);
+        let img = apply_adaptive_scaling(limit_screenshot_dimension(img));
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+        let filename = format!("window_{}_{}.{}", session_id, timestamp, screenshot_extension());
+
+        store_and_upload_screenshot(&img, &filename, &session_id, &active_window)
+            .await
+            .map(|screenshot| screenshot.remote_url)
+            .map_err(CommandError::UploadFailed)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        Err(CommandError::Unsupported("Window capture is only supported on Windows".to_string()))
+    }
+}
+
+// Timelapse bounds: a frame at most every 250 ms and at least every minute, for up to 10 minutes,
+// and never more than TIMELAPSE_MAX_FRAMES frames so the temporary frame files stay small
+const TIMELAPSE_MIN_INTERVAL_MS: u64 = 250;
+const TIMELAPSE_MAX_INTERVAL_MS: u64 = 60_000;
+const TIMELAPSE_MAX_DURATION_SECS: u64 = 600;
+const TIMELAPSE_MAX_FRAMES: u64 = 1200;
+// Playback rate of the assembled clip
+const TIMELAPSE_PLAYBACK_FPS: u32 = 10;
+
+// Set while a timelapse is capturing or being assembled
+static TIMELAPSE_RUNNING: AtomicBool = AtomicBool::new(false);
+// Set to end the current timelapse early; the frames captured so far are still assembled and uploaded
+static TIMELAPSE_STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// Helper function to find an FFmpeg executable without downloading one: the bundled copy, then the system one
+fn available_ffmpeg_command() -> Option<String> {
+    let ffmpeg_path = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or_else(|| std::env::current_dir().unwrap())
+        .join(BUNDLED_FFMPEG_NAME);
+    if ffmpeg_path.exists() {
+        return Some(ffmpeg_path.to_string_lossy().to_string());
+    }
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-version").stdout(Stdio::null()).stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    match command.status() {
+        Ok(status) if status.success() => Some("ffmpeg".to_string()),
+        _ => None,
+    }
+}
+
+// Helper function to find where a system FFmpeg command resolves to, by searching PATH like the shell does
+fn system_ffmpeg_path() -> Option<String> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(BUNDLED_FFMPEG_NAME))
+        .find(|candidate| candidate.is_file())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+}
+
+// Command to check whether recording will find FFmpeg, resolving it the same way start_combined_recording
+// does (bundled copy first, then the system one) without downloading anything
+#[tauri::command]
+async fn check_ffmpeg() -> Result<String, CommandError> {
+    let Some(ffmpeg_cmd) = available_ffmpeg_command() else {
+        return Ok(serde_json::json!({
+            "available": false,
+            "path": null,
+            "version": null,
+            "source": null,
+        })
+        .to_string());
+    };
+    let bundled = ffmpeg_cmd != "ffmpeg";
+
+    let mut command = Command::new(&ffmpeg_cmd);
+    command.arg("-version").stdin(Stdio::null()).stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let output = tokio::task::spawn_blocking(move || command.output())
+        .await
+        .map_err(|e| format!("FFmpeg version check failed: {}", e))?;
+
+    // The first line reads "ffmpeg version <version> Copyright ..."
+    let version = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("ffmpeg version "))
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(|version| version.to_string()),
+        Ok(output) => {
+            warn!("{} -version exited with {}", ffmpeg_cmd, output.status);
+            None
+        }
+        Err(e) => {
+            warn!("Failed to run {} -version: {}", ffmpeg_cmd, e);
+            None
+        }
+    };
+
+    let path = if bundled { Some(ffmpeg_cmd.clone()) } else { system_ffmpeg_path() };
+
+    Ok(serde_json::json!({
+        "available": version.is_some(),
+        "path": path.unwrap_or(ffmpeg_cmd),
+        "version": version,
+        "source": if bundled { "bundled" } else { "system" },
+    })
+    .to_string())
+}
+
+// Command to capture a short timelapse instead of a full recording: masked frames are grabbed every
+// interval_ms for duration_s seconds, then FFmpeg runs once to assemble them into an MP4 that is uploaded
+// as a "timelapse". Much lighter than a continuously running gdigrab capture on low-powered machines.
+#[tauri::command]
+async fn start_timelapse(app: tauri::AppHandle, interval_ms: u64, duration_s: u64) -> Result<String, CommandError> {
+    if !(TIMELAPSE_MIN_INTERVAL_MS..=TIMELAPSE_MAX_INTERVAL_MS).contains(&interval_ms) {
+        return Err(CommandError::InvalidInput(format!(
+            "Frame interval must be between {} and {} ms",
+            TIMELAPSE_MIN_INTERVAL_MS, TIMELAPSE_MAX_INTERVAL_MS
+        )));
+    }
+
+    if duration_s == 0 || duration_s > TIMELAPSE_MAX_DURATION_SECS {
+        return Err(CommandError::InvalidInput(format!(
+            "Timelapse duration must be between 1 and {} seconds",
+            TIMELAPSE_MAX_DURATION_SECS
+        )));
+    }
+
+    let frame_count = (duration_s * 1000).div_ceil(interval_ms);
+    if frame_count > TIMELAPSE_MAX_FRAMES {
+        return Err(CommandError::InvalidInput(format!(
+            "A timelapse can have at most {} frames ({} requested); use a longer interval or a shorter duration",
+            TIMELAPSE_MAX_FRAMES, frame_count
+        )));
+    }
+
+    // FFmpeg is only needed briefly at the end, but fail now rather than after capturing every frame
+    let ffmpeg_cmd = available_ffmpeg_command().ok_or_else(|| {
+        CommandError::FfmpegMissing("FFmpeg is required to assemble a timelapse but was not found".to_string())
+    })?;
+
+    if TIMELAPSE_RUNNING.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(CommandError::AlreadyRunning("A timelapse is already in progress".to_string()));
+    }
+    TIMELAPSE_STOP_REQUESTED.store(false, Ordering::SeqCst);
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let frames_dir = get_data_directory().join("timelapse").join(&session_id);
+    if let Err(e) = std::fs::create_dir_all(&frames_dir) {
+        TIMELAPSE_RUNNING.store(false, Ordering::SeqCst);
+        return Err(format!("Failed to create timelapse directory: {}", e).into());
+    }
+
+    tokio::spawn(async move {
+        let result = capture_timelapse(&ffmpeg_cmd, &frames_dir, &session_id, interval_ms, duration_s).await;
+
+        // The frames are only needed until the clip is assembled
+        if let Err(e) = std::fs::remove_dir_all(&frames_dir) {
+            error!("Failed to remove timelapse frames {}: {}", frames_dir.display(), e);
+        }
+        TIMELAPSE_RUNNING.store(false, Ordering::SeqCst);
+
+        match result {
+            Ok(remote_url) => {
+                info!("Timelapse uploaded: {}", remote_url);
+                let _ = app.emit("screenshot-taken", events::ScreenshotEvent::TimelapseUploaded { url: remote_url });
+            }
+            Err(e) => {
+                error!("Timelapse failed: {}", e);
+                let _ = app.emit("timelapse-failed", e);
+            }
+        }
+    });
+
+    Ok(format!("Timelapse started: {} frames, one every {} ms", frame_count, interval_ms))
+}
+
+// Command to end the current timelapse before its duration is up; it takes effect before the next frame
+#[tauri::command]
+fn stop_timelapse() -> Result<String, CommandError> {
+    if !TIMELAPSE_RUNNING.load(Ordering::SeqCst) {
+        return Err(CommandError::NotRunning("No timelapse in progress".to_string()));
+    }
+
+    TIMELAPSE_STOP_REQUESTED.store(true, Ordering::SeqCst);
+    Ok("Timelapse stopping, the frames captured so far will be uploaded".to_string())
+}
+
+// Helper function to capture the timelapse frames into frames_dir, assemble them and upload the clip.
+// Returns the remote URL of the uploaded clip.
+async fn capture_timelapse(ffmpeg_cmd: &str, frames_dir: &std::path::Path, session_id: &str, interval_ms: u64, duration_s: u64) -> Result<String, String> {
+    let deadline = Instant::now() + Duration::from_secs(duration_s);
+    let mut next_frame = Instant::now();
+    let mut frame_size: Option<(u32, u32)> = None;
+    let mut frames_captured = 0u32;
+
+    while Instant::now() < deadline && !TIMELAPSE_STOP_REQUESTED.load(Ordering::SeqCst) {
+        // Same privacy handling as screenshots; frames that can't be captured (e.g. camera in use) are skipped
+        match capture_masked_screens(screen_source::current(), None) {
+            Ok(images) => {
+                if let Some((_, img)) = images.into_iter().next() {
+                    // Adaptive scaling can change the size between frames, but every frame of a video has the same size
+                    let (width, height) = *frame_size.get_or_insert(img.dimensions());
+                    let img = if img.dimensions() != (width, height) {
+                        image::imageops::resize(&img, width, height, image::imageops::FilterType::Triangle)
+                    } else {
+                        img
+                    };
+
+                    // JPEG keeps the frames small and quick to write; the clip is lossy anyway
+                    let frame_path = frames_dir.join(format!("frame_{:05}.jpg", frames_captured));
+                    image::DynamicImage::ImageRgba8(img)
+                        .to_rgb8()
+                        .save_with_format(&frame_path, image::ImageFormat::Jpeg)
+                        .map_err(|e| format!("Failed to save timelapse frame: {}", e))?;
+                    frames_captured += 1;
+                }
+            }
+            Err(e) => warn!("Skipping timelapse frame: {}", e),
+        }
+
+        next_frame += Duration::from_millis(interval_ms);
+        tokio::time::sleep_until(next_frame.min(deadline)).await;
+    }
+
+    if frames_captured == 0 {
+        return Err("No timelapse frames were captured".to_string());
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let filename = format!("timelapse_{}_{}.mp4", session_id, timestamp);
+    let clip_path = get_data_directory().join("timelapse").join(&filename);
+
+    // x264 needs even dimensions, so odd-sized captures lose their last row or column
+    let mut command = Command::new(ffmpeg_cmd);
+    command
+        .arg("-framerate")
+        .arg(TIMELAPSE_PLAYBACK_FPS.to_string())
+        .arg("-i")
+        .arg(frames_dir.join("frame_%05d.jpg"))
+        .args(&[
+            "-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+            "-vcodec", "libx264",
+            "-crf", "28",
+            "-preset", "veryfast",
+            "-pix_fmt", "yuv420p",
+            "-movflags", "+faststart",
+            "-y",
+        ])
+        .arg(&clip_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let output = tokio::task::spawn_blocking(move || command.output())
+        .await
+        .map_err(|e| format!("Timelapse assembly task failed: {}", e))?
+        .map_err(|e| format!("Failed to run FFmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "FFmpeg failed to assemble the timelapse: {}",
+            String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or("unknown error")
+        ));
+    }
+
+    info!("Assembled timelapse {} from {} frames", clip_path.display(), frames_captured);
+
+    // The local clip stays in the timelapse directory, like screenshots and recordings do
+    let clip_data = std::fs::read(&clip_path).map_err(|e| format!("Failed to read timelapse clip: {}", e))?;
+    save_file_to_xampp_htdocs(clip_data, filename, "timelapse".to_string())
+        .await
+        .map_err(|e| format!("Failed to upload timelapse: {}", e))
+}
+
+lazy_static! {
+    // Camera webcam snapshots are taken from by default (see webcam::Camera::id); None uses the first one found
+    static ref WEBCAM_CAMERA: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+}
+
+// Command to list the cameras webcam snapshots can be taken from, as [{"id", "name"}]
+#[tauri::command]
+async fn list_cameras() -> Result<String, CommandError> {
+    let cameras = available_cameras().await?;
+    Ok(serde_json::to_string(&cameras).map_err(|e| format!("Failed to serialize cameras: {}", e))?)
+}
+
+// Helper function to list the connected cameras through FFmpeg
+async fn available_cameras() -> Result<Vec<webcam::Camera>, CommandError> {
+    let ffmpeg_cmd = available_ffmpeg_command().ok_or_else(|| {
+        CommandError::FfmpegMissing("FFmpeg is required to find cameras but was not found".to_string())
+    })?;
+
+    Ok(tokio::task::spawn_blocking(move || webcam::list(&ffmpeg_cmd))
+        .await
+        .map_err(|e| format!("Camera listing task failed: {}", e))??)
+}
+
+// Command to choose the camera webcam snapshots are taken from by default, by its ID from list_cameras.
+// None goes back to the first camera found.
+#[tauri::command]
+async fn set_webcam_camera(camera_id: Option<String>) -> Result<String, CommandError> {
+    let camera_id = camera_id.map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+    if let Some(id) = &camera_id {
+        let cameras = available_cameras().await?;
+        if !cameras.iter().any(|camera| &camera.id == id) {
+            return Err(CommandError::NotFound(format!("Camera {} not found", id)));
+        }
+    }
+
+    let message = match &camera_id {
+        Some(id) => format!("Webcam snapshots will be taken from camera {}", id),
+        None => "Webcam snapshots will be taken from the first camera found".to_string(),
+    };
+    *WEBCAM_CAMERA.lock().map_err(|e| e.to_string())? = camera_id;
+    Ok(message)
+}
+
+// Command to take a photo with the webcam, e.g. for identity verification, and upload it as a "webcam" file.
+// Uses the given camera, else the one chosen with set_webcam_camera, else the first one found.
+// Returns the remote URL.
+#[tauri::command]
+async fn capture_webcam_snapshot(camera_id: Option<String>) -> Result<String, CommandError> {
+    let ffmpeg_cmd = available_ffmpeg_command().ok_or_else(|| {
+        CommandError::FfmpegMissing("FFmpeg is required for webcam snapshots but was not found".to_string())
+    })?;
+    let requested_camera = match camera_id {
+        Some(id) => Some(id),
+        None => WEBCAM_CAMERA.lock().map_err(|e| e.to_string())?.clone(),
+    };
+
+    let dir = get_data_directory().join("webcam");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create webcam directory: {}", e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let filename = format!("webcam_{}_{}.jpg", uuid::Uuid::new_v4(), timestamp);
+    let snapshot_path = dir.join(&filename);
+
+    // Listing first gives a clear error when there is no camera, instead of an FFmpeg failure
+    let capture_path = snapshot_path.clone();
+    let camera = tokio::task::spawn_blocking(move || {
+        let cameras = webcam::list(&ffmpeg_cmd)?;
+        let camera = match requested_camera {
+            Some(id) => cameras
+                .into_iter()
+                .find(|camera| camera.id == id)
+                .ok_or_else(|| CommandError::NotFound(format!("Camera {} not found", id)))?,
+            None => cameras
+                .into_iter()
+                .next()
+                .ok_or_else(|| CommandError::NotFound("No camera found".to_string()))?,
+        };
+
+        webcam::capture_snapshot(&ffmpeg_cmd, &camera.id, &capture_path)?;
+        Ok::<_, CommandError>(camera)
+    })
+    .await
+    .map_err(|e| format!("Webcam snapshot task failed: {}", e))??;
+
+    info!("Took webcam snapshot from {} ({})", camera.name, camera.id);
+
+    // The local photo stays in the webcam directory, like screenshots and recordings do
+    let snapshot_data = std::fs::read(&snapshot_path).map_err(|e| format!("Failed to read webcam snapshot: {}", e))?;
+    save_file_to_xampp_htdocs(snapshot_data, filename, "webcam".to_string())
+        .await
+        .map_err(|e| e.context("Failed to upload webcam snapshot"))
+}
+
+// Start a screenshotting session. With a monitor index the session captures only that display and
+// sessions for different monitors can run side by side; without one there is a single default session.
+#[tauri::command]
+async fn start_screenshotting(window: tauri::Window, monitor_index: Option<usize>) -> Result<String, CommandError> {
+    if let Some(index) = monitor_index {
+        let screen_count = screen_source::current().screens().map_err(|e| format!("Failed to get screens: {}", e))?.len();
+        if index >= screen_count {
+            return Err(CommandError::NotFound(format!("Monitor {} not found ({} screens connected)", index, screen_count)));
+        }
+    }
+
+    // Clean up inactive tasks by removing entries with Stopped status
+    {
+        let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+        tasks.retain(|_id, status| match status {
+            TaskStatus::Stopped => false,  // Remove stopped tasks
+            _ => true,  // Keep active and stopping tasks
+        });
+    }
+
+    // Check if there is still an active task running for the same monitor
+    {
+        let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+        let has_active_task = tasks.iter().any(|((_session_id, task_monitor), status)| {
+            *task_monitor == monitor_index && match status {
+                TaskStatus::Active | TaskStatus::Paused | TaskStatus::Stopping => true,
+                TaskStatus::Stopped => false,
+            }
+        });
+
+        if has_active_task {
+            return Err(CommandError::AlreadyRunning(match monitor_index {
+                Some(index) => format!("A screenshotting session is already running for monitor {}", index),
+                None => "A screenshotting session is already running".to_string(),
+            }));
+        }
+        drop(tasks);
+    }
+
+    // Create a unique session ID
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let task_key = (session_id.clone(), monitor_index);
+
+    // Create screenshots directory in data directory
+    let data_dir_path = get_data_directory();
+    let dir = data_dir_path.join("screenshots");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    // Store task state as active
+    {
+        let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+        tasks.insert(task_key.clone(), TaskStatus::Active);
+    }
+
+    // Compare this session's first frames against nothing rather than an earlier session's last upload
+    {
+        let mut previous_frames = PREVIOUS_FRAMES.lock().map_err(|e| e.to_string())?;
+        previous_frames.retain(|(frame_monitor, _suffix), _| *frame_monitor != monitor_index);
+    }
+
+    let session_id_clone = session_id.clone();
+
+    // Subscribe before the task starts so no stop signal is missed
+    let mut task_signal = SCREENSHOT_TASK_SIGNAL.subscribe();
+
+    // Start scheduled screenshotting in a background task
+    tokio::spawn(async move {
+        let start_time = Instant::now();
+
+        'session: loop {
+            // Check if stop was requested before taking a screenshot
+            let status = {
+                let tasks = RUNNING_TASKS.lock().unwrap();
+                tasks.get(&task_key).cloned()
+            };
+
+            match status {
+                Some(TaskStatus::Active) => {}
+                Some(TaskStatus::Paused) => {
+                    // Don't capture while paused, just keep the session alive
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+                _ => break,
+            }
+
+            // Nor outside work hours
+            if !within_work_hours() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            // Take screenshot, mask excluded windows and upload it
+            let capture_started = Instant::now();
+            let timestamp = start_time.elapsed().as_millis();
+
+            match capture_and_upload_screens("screenshot", &session_id_clone, timestamp, monitor_index, true).await {
+                Ok(uploads) => {
+                    // Notify that screenshot was taken
+                    for screenshot in uploads.screenshots {
+                        window
+                            .emit("screenshot-taken", events::ScreenshotEvent::ScreenshotUploaded { id: screenshot.id, url: screenshot.remote_url })
+                            .unwrap();
+                    }
+                    for similarity in uploads.skipped_similarities {
+                        let _ = window.emit("screenshot-skipped", events::ScreenshotEvent::ScreenshotSkipped { similarity });
+                    }
+                }
+                Err(e) => {
+                    error!("{}", e);
+                }
+            }
+
+            // Wait until the next screenshot is due, a random interval between the configured min/max after this one
+            // started. Check at least every second so paused time can be added on, and wake up right away on stop.
+            let mut next_capture_at = next_capture_deadline(capture_started, screenshot_intervals());
+            let mut last_check = Instant::now();
+            while Instant::now() < next_capture_at {
+                note_next_capture(
+                    &session_id_clone,
+                    Some(ScheduledCapture { kind: "screenshot", monitor_index, due: next_capture_at }),
+                );
+                let wait = next_capture_at.saturating_duration_since(Instant::now()).min(Duration::from_secs(1));
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = task_signal.changed() => {}
+                }
+
+                // Check if stop was requested
+                let status = {
+                    let tasks = RUNNING_TASKS.lock().unwrap();
+                    tasks.get(&task_key).cloned()
+                };
+
+                let now = Instant::now();
+                match status {
+                    Some(TaskStatus::Active) => {}
+                    // Paused time doesn't count towards the interval
+                    Some(TaskStatus::Paused) => next_capture_at += now - last_check,
+                    _ => break 'session,
+                }
+                last_check = now;
+            }
+        }
+
+        note_next_capture(&session_id_clone, None);
+
+        // Notify completion when stopped
+        window.emit("screenshotting-finished", format!("Screenshotting stopped for session: {}", session_id_clone)).unwrap();
+
+        // Update the task status to stopped
+        {
+            let mut tasks = RUNNING_TASKS.lock().unwrap();
+            tasks.insert(task_key, TaskStatus::Stopped);
+        }
+        process_status_changed();
+    });
+    process_status_changed();
+
+    let schedule = {
+        let min_interval = SCREENSHOT_MIN_INTERVAL.lock().map_err(|e| e.to_string())?;
+        let max_interval = SCREENSHOT_MAX_INTERVAL.lock().map_err(|e| e.to_string())?;
+        format!("screenshots will be taken every {}-{} minutes", *min_interval / 60, *max_interval / 60)
+    };
+
+    match monitor_index {
+        Some(index) => Ok(format!("Started screenshotting session: {} on monitor {} ({})", session_id, index, schedule)),
+        None => Ok(format!("Started screenshotting session: {} ({})", session_id, schedule)),
+    }
+}
+
+// Stop screenshotting; with a monitor index only that monitor's session is stopped, otherwise all of them
+#[tauri::command]
+fn stop_screenshotting(monitor_index: Option<usize>) -> Result<String, CommandError> {
+    let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+    // Mark the matching active tasks as stopping (this will cause them to stop on next check)
+    // We need to get the task keys first, then update them, to avoid borrow checker issues
+    let task_keys: Vec<(String, Option<usize>)> = tasks
+        .keys()
+        .filter(|(_session_id, task_monitor)| monitor_index.is_none() || *task_monitor == monitor_index)
+        .cloned()
+        .collect();
+
+    drop(tasks); // Explicitly drop the immutable lock
+
+    // Now get a mutable lock to update the entries
+    let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+    let mut stopped = 0;
+    for task_key in &task_keys {
+        if let Some(status) = tasks.get_mut(task_key) {
+            if *status == TaskStatus::Active || *status == TaskStatus::Paused {
+                *status = TaskStatus::Stopping;
+                stopped += 1;
+            }
+        }
+    }
+    drop(tasks);
+    signal_screenshot_tasks();
+
+    match monitor_index {
+        Some(index) if stopped == 0 => Err(CommandError::NotRunning(format!("No screenshotting session is running for monitor {}", index))),
+        Some(index) => Ok(format!("Stop signal sent to the screenshotting session for monitor {}", index)),
+        None => Ok("Stop signal sent to all screenshotting sessions".to_string()),
+    }
+}
+
+// Helper function to get the configured min/max interval (seconds) of standalone screenshots
+fn screenshot_intervals() -> (u64, u64) {
+    (*SCREENSHOT_MIN_INTERVAL.lock().unwrap(), *SCREENSHOT_MAX_INTERVAL.lock().unwrap())
+}
+
+// Helper function to get the configured min/max interval (seconds) of the snapshots taken while recording
+fn recording_snapshot_intervals() -> (u64, u64) {
+    (*RECORDING_SNAPSHOT_MIN_INTERVAL.lock().unwrap(), *RECORDING_SNAPSHOT_MAX_INTERVAL.lock().unwrap())
+}
+
+// Helper function to pick the wait before the next screenshot, random between the min/max intervals
+fn random_screenshot_interval((min_interval, max_interval): (u64, u64)) -> u64 {
+    use rand::Rng;
+    rand::thread_rng().gen_range(min_interval..=max_interval.max(min_interval))
+}
+
+// A capture a loop is waiting for, as reported by get_next_snapshot_time
+#[derive(Clone)]
+struct ScheduledCapture {
+    kind: &'static str, // "screenshot" or "recording_snapshot"
+    monitor_index: Option<usize>,
+    due: Instant,
+}
+
+lazy_static! {
+    // When each capture loop takes its next capture, keyed by session ID. Entries of sessions that have ended
+    // may linger until their loop notices, so readers check the session is still running.
+    static ref NEXT_CAPTURES: Arc<Mutex<HashMap<String, ScheduledCapture>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+// Helper function to record when a session's next capture is due, or that it has none (None)
+fn note_next_capture(session_id: &str, capture: Option<ScheduledCapture>) {
+    if let Ok(mut next_captures) = NEXT_CAPTURES.lock() {
+        match capture {
+            Some(capture) => next_captures.insert(session_id.to_string(), capture),
+            None => next_captures.remove(session_id),
+        };
+    }
+}
+
+// Command to get when the next scheduled screenshot or recording snapshot will be taken, so a countdown can be
+// shown without waiting for the next progress event. Returns the soonest one across all running sessions, or
+// nulls when nothing is scheduled (e.g. before a session's first capture or while outside work hours).
+// The time moves back while a session is paused.
+#[tauri::command]
+fn get_next_snapshot_time() -> Result<String, CommandError> {
+    let recording_session = RECORDING_SESSION_ID.lock().map_err(|e| e.to_string())?.clone();
+    let running_sessions: Vec<String> = RUNNING_TASKS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter(|(_, status)| matches!(status, TaskStatus::Active | TaskStatus::Paused))
+        .map(|((session_id, _), _)| session_id.clone())
+        .collect();
+
+    let next_capture = NEXT_CAPTURES
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter(|(session_id, capture)| match capture.kind {
+            "recording_snapshot" => recording_session.as_deref() == Some(session_id.as_str()),
+            _ => running_sessions.contains(session_id),
+        })
+        .min_by_key(|(_, capture)| capture.due)
+        .map(|(session_id, capture)| (session_id.clone(), capture.clone()));
+
+    let Some((session_id, capture)) = next_capture else {
+        return Ok(serde_json::json!({
+            "seconds_remaining": null,
+            "next_snapshot_at": null,
+            "kind": null,
+            "session_id": null,
+            "monitor_index": null,
+        })
+        .to_string());
+    };
+
+    let remaining = capture.due.saturating_duration_since(Instant::now());
+    let next_snapshot_at = chrono::DateTime::<chrono::Local>::from(SystemTime::now() + remaining);
+    Ok(serde_json::json!({
+        "seconds_remaining": remaining.as_secs_f64().ceil() as u64,
+        "next_snapshot_at": next_snapshot_at.to_rfc3339(),
+        "kind": capture.kind,
+        "session_id": session_id,
+        "monitor_index": capture.monitor_index,
+    })
+    .to_string())
+}
+
+// Helper function to pick when the next scheduled capture is due. The interval counts from when the previous
+// capture started, so the time spent capturing and uploading doesn't push the schedule back. A capture that
+// overran its interval skips the missed slots rather than being followed by another one right away.
+fn next_capture_deadline(capture_started: Instant, intervals: (u64, u64)) -> Instant {
+    let now = Instant::now();
+    let mut next_capture_at = capture_started + Duration::from_secs(random_screenshot_interval(intervals).max(1));
+    while next_capture_at <= now {
+        debug!("Capture and upload overran the screenshot interval, skipping to the next slot");
+        next_capture_at += Duration::from_secs(random_screenshot_interval(intervals).max(1));
+    }
+    next_capture_at
+}
+
+lazy_static! {
+    // Hours scheduled captures are limited to; None captures at any time
+    static ref WORK_SCHEDULE: Arc<Mutex<Option<work_schedule::WorkSchedule>>> = Arc::new(Mutex::new(None));
+}
+
+// Set once the capture loops find themselves outside work hours, so that is only reported once per evening
+static OUTSIDE_WORK_HOURS: AtomicBool = AtomicBool::new(false);
+
+// Command to only capture during work hours: shifts starting on the given days (e.g. "mon") from start_hm to
+// end_hm ("HH:MM") in the IANA time zone. An end before the start runs the shift past midnight. With auto_stop
+// all monitoring is stopped once a shift ends, otherwise captures are only skipped until the next one.
+#[tauri::command]
+fn set_work_schedule(days: Vec<String>, start_hm: String, end_hm: String, timezone: String, auto_stop: Option<bool>) -> Result<String, CommandError> {
+    let schedule = work_schedule::WorkSchedule::parse(&work_schedule::WorkScheduleSettings {
+        days,
+        start: start_hm,
+        end: end_hm,
+        timezone,
+        auto_stop: auto_stop.unwrap_or(false),
+    })?;
+
+    let settings = schedule.settings().clone();
+    *WORK_SCHEDULE.lock().map_err(|e| e.to_string())? = Some(schedule);
+    OUTSIDE_WORK_HOURS.store(false, Ordering::SeqCst);
+
+    Ok(format!(
+        "Captures limited to {} from {} to {} ({})",
+        settings.days.join(", "),
+        settings.start,
+        settings.end,
+        settings.timezone
+    ))
+}
+
+// Command to remove the work schedule so captures run at any time again
+#[tauri::command]
+fn clear_work_schedule() -> Result<String, CommandError> {
+    *WORK_SCHEDULE.lock().map_err(|e| e.to_string())? = None;
+    OUTSIDE_WORK_HOURS.store(false, Ordering::SeqCst);
+    Ok("Work schedule cleared".to_string())
+}
+
+// Helper function for the capture loops to check the work schedule before capturing. The first check outside
+// work hours emits "outside-work-hours" and, if the schedule says so, stops all monitoring.
+fn within_work_hours() -> bool {
+    let schedule = match WORK_SCHEDULE.lock().map(|schedule| schedule.clone()) {
+        Ok(Some(schedule)) => schedule,
+        _ => return true,
+    };
+
+    let now = chrono::Utc::now();
+    if schedule.contains(now) {
+        OUTSIDE_WORK_HOURS.store(false, Ordering::SeqCst);
+        return true;
+    }
+
+    if !OUTSIDE_WORK_HOURS.swap(true, Ordering::SeqCst) {
+        let next_start = schedule.next_start(now).map(|start| start.to_rfc3339());
+        info!("Outside work hours, skipping captures until {}", next_start.as_deref().unwrap_or("the schedule changes"));
+
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit("outside-work-hours", serde_json::json!({
+                "next_start": next_start,
+                "auto_stop": schedule.auto_stop(),
+            }));
+
+            if schedule.auto_stop() {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    match stop_all_processes(app).await {
+                        Ok(message) => info!("Work hours ended, monitoring stopped: {}", message),
+                        Err(e) => error!("Work hours ended, but monitoring could not be stopped: {}", e),
+                    }
+                });
+            }
+        }
+    }
+
+    false
+}
+
+// Helper function to move every screenshotting session in the `from` state to the `to` state.
+// Returns how many sessions changed.
+fn set_screenshotting_status(from: TaskStatus, to: TaskStatus) -> Result<usize, String> {
+    let mut tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+    let mut changed = 0;
+    for status in tasks.values_mut() {
+        if *status == from {
+            *status = to.clone();
+            changed += 1;
+        }
+    }
+    drop(tasks);
+    signal_screenshot_tasks();
+    Ok(changed)
+}
+
+#[tauri::command]
+fn pause_screenshotting(app: tauri::AppHandle) -> Result<String, CommandError> {
+    if set_screenshotting_status(TaskStatus::Active, TaskStatus::Paused)? == 0 {
+        return Err(CommandError::NotRunning("No active screenshotting session to pause".to_string()));
+    }
+
+    // Emit to each active window
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("screenshotting-paused", "Screenshotting has been paused");
+    }
+
+    Ok("Screenshotting paused".to_string())
+}
+
+#[tauri::command]
+fn resume_screenshotting(app: tauri::AppHandle) -> Result<String, CommandError> {
+    if set_screenshotting_status(TaskStatus::Paused, TaskStatus::Active)? == 0 {
+        return Err(CommandError::NotRunning("No paused screenshotting session to resume".to_string()));
+    }
+
+    // Emit to each active window
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("screenshotting-resumed", "Screenshotting has been resumed");
+    }
+
+    Ok("Screenshotting resumed".to_string())
+}
+
+// Global state to track combined recording status
+use std::process::{Child, Command, Stdio};
+use tokio::task::JoinHandle;
+use std::collections::VecDeque;
+lazy_static! {
+    static ref COMBINED_RECORDING_PROCESS: Arc<Mutex<Option<Child>>> = Arc::new(Mutex::new(None));
+    static ref RECORDING_PAUSED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    static ref RECORDING_SEGMENT_FILES: Arc<Mutex<VecDeque<String>>> = Arc::new(Mutex::new(VecDeque::new()));
+    static ref SCREENSHOT_TASK_HANDLE: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None));
+    static ref FFMPEG_PROCESS_ID: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None)); // Store the PID for process control
+    static ref SCREENSHOT_MIN_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(300)); // Default 5 minutes in seconds
+    static ref SCREENSHOT_MAX_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(1800)); // Default 30 minutes in seconds
+    // Interval of the snapshots taken alongside a recording, separate from standalone screenshotting
+    static ref RECORDING_SNAPSHOT_MIN_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(300)); // Default 5 minutes in seconds
+    static ref RECORDING_SNAPSHOT_MAX_INTERVAL: Arc<Mutex<u64>> = Arc::new(Mutex::new(1800)); // Default 30 minutes in seconds
+    static ref RECORDING_BASE_PATH: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None)); // Store base recording path
+    static ref RECORDING_SESSION_ID: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None)); // Store session ID
+    static ref IDLE_MONITORING_TASK: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None)); // Background idle monitoring task
+    static ref LAST_IDLE_STATUS: Arc<Mutex<String>> = Arc::new(Mutex::new("active".to_string())); // Cache last idle status
+}
+
+
+// File name of the FFmpeg binary bundled next to the app executable
+#[cfg(target_os = "windows")]
+const BUNDLED_FFMPEG_NAME: &str = "ffmpeg.exe";
+#[cfg(not(target_os = "windows"))]
+const BUNDLED_FFMPEG_NAME: &str = "ffmpeg";
+
+lazy_static! {
+    // Recording quality preset ("low", "balanced" or "high") and requested encoder
+    // ("libx264", "auto" or a hardware encoder name)
+    static ref RECORDING_QUALITY_PRESET: Arc<Mutex<String>> = Arc::new(Mutex::new("balanced".to_string()));
+    static ref RECORDING_ENCODER: Arc<Mutex<String>> = Arc::new(Mutex::new("libx264".to_string()));
+    // Encoding chosen when the current recording started, reused for every later segment
+    static ref RECORDING_ENCODING: Arc<Mutex<Option<RecordingEncoding>>> = Arc::new(Mutex::new(None));
+    // Whether a hardware encoder actually works on this machine, per encoder name
+    static ref HARDWARE_ENCODER_SUPPORT: Arc<Mutex<HashMap<String, bool>>> = Arc::new(Mutex::new(HashMap::new()));
+    // How new recordings are written: "file" (MKV segments joined at the end) or "hls"
+    static ref RECORDING_OUTPUT: Arc<Mutex<String>> = Arc::new(Mutex::new("file".to_string()));
+    // HLS segments of the current recording uploaded so far, in recording order
+    static ref HLS_UPLOADED_SEGMENTS: Arc<tokio::sync::Mutex<Vec<UploadedHlsSegment>>> = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+}
+
+// Whether the current recording was started with HLS output; fixed for the whole session
+static RECORDING_SESSION_HLS: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    // When the current recording session started, reported when another recording is refused
+    static ref RECORDING_STARTED_AT: Arc<Mutex<Option<SystemTime>>> = Arc::new(Mutex::new(None));
+}
+
+// How often new HLS segments are looked for while recording
+const HLS_UPLOAD_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// An HLS segment that has been uploaded, with the playlist of the FFmpeg process that wrote it
+#[derive(Debug, Clone)]
+struct UploadedHlsSegment {
+    playlist: String,
+    segment: hls::HlsSegment,
+    size: u64,
+}
+
+// Recording frame rate; 0 leaves it to the capture device's default
+static RECORDING_FRAMERATE: AtomicU64 = AtomicU64::new(0);
+
+// Hardware H.264 encoders worth trying on this platform, in order of preference
+#[cfg(target_os = "macos")]
+const HARDWARE_ENCODERS: &[&str] = &["h264_videotoolbox"];
+#[cfg(not(target_os = "macos"))]
+const HARDWARE_ENCODERS: &[&str] = &["h264_nvenc", "h264_qsv"];
+// Every hardware encoder that can be requested; ones that don't apply to this machine fall back to libx264,
+// so configurations can be shared between platforms
+const KNOWN_HARDWARE_ENCODERS: &[&str] = &["h264_nvenc", "h264_qsv", "h264_videotoolbox"];
+
+// Encoder settings a recording is made with
+#[derive(Debug, Clone)]
+struct RecordingEncoding {
+    encoder: String,
+    crf: u32,
+    x264_preset: &'static str,
+    framerate: u64, // 0 = capture device default
+}
+
+// Helper function to map a quality preset to its CRF and libx264 speed preset
+fn recording_quality_settings(preset: &str) -> Option<(u32, &'static str)> {
+    match preset {
+        "low" => Some((32, "ultrafast")),
+        "balanced" => Some((28, "ultrafast")),
+        "high" => Some((23, "veryfast")),
+        _ => None,
+    }
+}
+
+// Command to choose the recording quality preset and, optionally, the encoder.
+// Takes effect with the next recording; segments of a running recording keep their encoding.
+#[tauri::command]
+fn set_recording_quality(preset: String, encoder: Option<String>) -> Result<String, CommandError> {
+    let preset = preset.to_lowercase();
+    if recording_quality_settings(&preset).is_none() {
+        return Err(CommandError::InvalidInput(format!("Invalid recording quality '{}': expected \"low\", \"balanced\" or \"high\"", preset)));
+    }
+
+    let encoder = encoder.map(|encoder| encoder.to_lowercase());
+    if let Some(ref encoder) = encoder {
+        if encoder != "libx264" && encoder != "auto" && !KNOWN_HARDWARE_ENCODERS.contains(&encoder.as_str()) {
+            return Err(CommandError::InvalidInput(format!(
+                "Invalid encoder '{}': expected \"libx264\", \"auto\" or one of {}",
+                encoder,
+                KNOWN_HARDWARE_ENCODERS.join(", ")
+            )));
+        }
+    }
+
+    *RECORDING_QUALITY_PRESET.lock().map_err(|e| e.to_string())? = preset.clone();
+    if let Some(encoder) = encoder {
+        *RECORDING_ENCODER.lock().map_err(|e| e.to_string())? = encoder;
+    }
+
+    let encoder = RECORDING_ENCODER.lock().map_err(|e| e.to_string())?.clone();
+    Ok(format!("Recording quality set to {} (encoder: {})", preset, encoder))
+}
+
+// Command to set the recording frame rate. Activity monitoring needs few frames per second,
+// and a low rate makes recordings much smaller. Takes effect with the next recording.
+#[tauri::command]
+fn set_recording_framerate(fps: u32) -> Result<String, CommandError> {
+    if !(1..=60).contains(&fps) {
+        return Err(CommandError::InvalidInput("Recording frame rate must be between 1 and 60 fps".to_string()));
+    }
+
+    RECORDING_FRAMERATE.store(fps as u64, Ordering::SeqCst);
+    Ok(format!("Recording frame rate set to {} fps", fps))
+}
+
+// Command to choose how recordings are written: "file" records MKV segments that are joined and uploaded
+// when the session stops, "hls" writes short .ts segments that are uploaded while recording, for near
+// real-time review. Takes effect with the next recording.
+#[tauri::command]
+fn set_recording_output(mode: String) -> Result<String, CommandError> {
+    let mode = mode.to_lowercase();
+    if mode != "file" && mode != "hls" {
+        return Err(CommandError::InvalidInput(format!("Invalid recording output '{}': expected \"file\" or \"hls\"", mode)));
+    }
+
+    *RECORDING_OUTPUT.lock().map_err(|e| e.to_string())? = mode.clone();
+    Ok(format!("Recording output set to {}", mode))
+}
+
+// Helper function to get the path of a recording segment: an MKV file, or with HLS output the playlist
+// of the segment's FFmpeg process, in a directory of its own next to the .ts files
+fn recording_segment_path(base_path: &str, session_id: &str, segment_index: usize) -> Result<String, String> {
+    let base_path = std::path::Path::new(base_path);
+    let segment_path = if RECORDING_SESSION_HLS.load(Ordering::SeqCst) {
+        let hls_dir = base_path.join(format!("hls_{}", session_id));
+        std::fs::create_dir_all(&hls_dir).map_err(|e| format!("Failed to create {}: {}", hls_dir.display(), e))?;
+        hls_dir.join(format!("recording_{}_seg_{}.m3u8", session_id, segment_index))
+    } else {
+        base_path.join(format!("recording_{}_seg_{}.mkv", session_id, segment_index))
+    };
+
+    Ok(segment_path.to_string_lossy().to_string())
+}
+
+// Helper function to start the task uploading HLS segments as FFmpeg finishes them.
+// The task ends by itself once the session is stopped or replaced; stopping uploads whatever is left.
+fn start_hls_upload_task(session_id: String) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(HLS_UPLOAD_CHECK_INTERVAL).await;
+
+            let current_session = RECORDING_SESSION_ID.lock().map(|session| session.clone()).unwrap_or(None);
+            if current_session.as_deref() != Some(session_id.as_str()) {
+                break;
+            }
+
+            match upload_new_hls_segments().await {
+                Ok(0) => {}
+                Ok(count) => debug!("Uploaded {} HLS segments of recording {}", count, session_id),
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
+}
+
+// Helper function to upload the segments listed in the current recording's playlists that weren't uploaded yet.
+// Stops at the first failure so segments go up in order; the rest are tried again on the next check.
+async fn upload_new_hls_segments() -> Result<usize, String> {
+    // Held throughout, so the upload task and the final upload on stop never send a segment twice
+    let mut uploaded = HLS_UPLOADED_SEGMENTS.lock().await;
+
+    let playlists: Vec<String> = RECORDING_SEGMENT_FILES.lock().map_err(|e| e.to_string())?.iter().cloned().collect();
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+
+    let mut count = 0;
+    for playlist in playlists {
+        let playlist_path = std::path::Path::new(&playlist);
+        let Some(hls_dir) = playlist_path.parent() else { continue };
+
+        for segment in hls::read_playlist(playlist_path) {
+            if uploaded.iter().any(|entry| entry.segment.file_name == segment.file_name) {
+                continue;
+            }
+
+            // Segments are named after the session, so they can be stored side by side on the server
+            let segment_path = hls_dir.join(&segment.file_name);
+            let (_, _, size) = {
+                let _permit = acquire_upload_permit().await?;
+                upload_file_stream_to_server(&segment_path, &segment.file_name, "recording", &user_id)
+                    .await
+                    .map_err(|e| format!("Failed to upload HLS segment {}: {}", segment.file_name, e))?
+            };
+
+            uploaded.push(UploadedHlsSegment { playlist: playlist.clone(), segment, size });
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+// Helper function to finish an HLS recording once FFmpeg has stopped: uploads the remaining segments, then
+// writes recording_<session>.m3u8 listing every uploaded segment and uploads it as the recording.
+// The server is expected to serve the playlist from the same location as the segments it references.
+async fn finalize_hls_recording(session_id: &str, base_path: &str) -> Result<String, CommandError> {
+    validate_session_id(session_id).map_err(CommandError::InvalidInput)?;
+
+    // A stopped FFmpeg has listed its last segment, so this catches everything the upload task hadn't yet
+    let upload_result = upload_new_hls_segments().await;
+
+    let uploaded = HLS_UPLOADED_SEGMENTS.lock().await.clone();
+    let mut runs: Vec<Vec<hls::HlsSegment>> = Vec::new();
+    let mut current_playlist: Option<&str> = None;
+    for entry in &uploaded {
+        if current_playlist != Some(entry.playlist.as_str()) {
+            runs.push(Vec::new());
+            current_playlist = Some(entry.playlist.as_str());
+        }
+        if let Some(run) = runs.last_mut() {
+            run.push(entry.segment.clone());
+        }
+    }
+
+    let filename = format!("recording_{}.m3u8", session_id);
+    let playlist_path = std::path::Path::new(base_path).join(format!("hls_{}", session_id)).join(&filename);
+    hls::write_vod_playlist(&playlist_path, &runs)?;
+
+    let duration_seconds = uploaded.iter().map(|entry| entry.segment.duration).sum::<f64>().round() as i32;
+    let total_size = uploaded.iter().map(|entry| entry.size).sum::<u64>() as i64;
+    if let Err(e) = database::update_recording_metadata_in_db(
+        session_id,
+        Some(&filename),
+        Some(&playlist_path.to_string_lossy()),
+        Some(duration_seconds),
+        Some(total_size),
+    ) {
+        error!("Failed to update recording metadata in database: {}", e);
+    }
+
+    // Without every segment on the server the playlist would point at missing files
+    upload_result.map_err(CommandError::UploadFailed)?;
+
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+    let (remote_url, _, _) = {
+        let _permit = acquire_upload_permit().await?;
+        upload_file_stream_to_server(&playlist_path, &filename, "recording", &user_id)
+            .await
+            .map_err(CommandError::UploadFailed)?
+    };
+
+    match database::update_recording_remote_url_in_db(&user_id, session_id, &remote_url) {
+        Ok(true) => {}
+        Ok(false) => warn!("Playlist of recording {} uploaded, but its database row could not be updated", session_id),
+        Err(e) => error!("Failed to save remote URL of recording {}: {}", session_id, e),
+    }
+
+    Ok(format!("{} HLS segments and the playlist uploaded: {}", uploaded.len(), remote_url))
+}
+
+// Helper function to check whether FFmpeg can actually encode with the given encoder on this machine.
+// Being listed by "ffmpeg -encoders" isn't enough since hardware encoders also need the GPU and driver.
+fn hardware_encoder_available(ffmpeg_cmd: &str, encoder: &str) -> bool {
+    if let Some(available) = HARDWARE_ENCODER_SUPPORT.lock().ok().and_then(|support| support.get(encoder).copied()) {
+        return available;
+    }
+
+    let mut command = Command::new(ffmpeg_cmd);
+    command
+        .args(&[
+            "-hide_banner",
+            "-loglevel", "error",
+            "-f", "lavfi",
+            "-i", "color=black:s=256x256:d=0.2",
+            "-vcodec", encoder,
+            "-f", "null",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let available = command.status().map(|status| status.success()).unwrap_or(false);
+    if let Ok(mut support) = HARDWARE_ENCODER_SUPPORT.lock() {
+        support.insert(encoder.to_string(), available);
+    }
+    available
+}
+
+// Helper function to resolve the configured preset and encoder into the encoding for a new recording,
+// falling back to libx264 when the requested hardware encoder doesn't work here
+fn select_recording_encoding(ffmpeg_cmd: &str) -> RecordingEncoding {
+    let preset = RECORDING_QUALITY_PRESET.lock().map(|preset| preset.clone()).unwrap_or_default();
+    let (crf, x264_preset) = recording_quality_settings(&preset).unwrap_or((28, "ultrafast"));
+    let requested = RECORDING_ENCODER.lock().map(|encoder| encoder.clone()).unwrap_or_default();
+
+    let candidates: Vec<&str> = match requested.as_str() {
+        "auto" => HARDWARE_ENCODERS.to_vec(),
+        "libx264" | "" => Vec::new(),
+        encoder => vec![encoder],
+    };
+
+    let encoder = candidates
+        .into_iter()
+        .find(|encoder| hardware_encoder_available(ffmpeg_cmd, encoder))
+        .unwrap_or_else(|| {
+            if requested != "libx264" {
+                warn!("Hardware encoder '{}' not available, falling back to libx264", requested);
+            }
+            "libx264"
+        })
+        .to_string();
+
+    RecordingEncoding {
+        encoder,
+        crf,
+        x264_preset,
+        framerate: RECORDING_FRAMERATE.load(Ordering::SeqCst),
+    }
+}
+
+// Helper function to build the FFmpeg output codec arguments for an encoding
+fn encoder_args(encoding: &RecordingEncoding) -> Vec<String> {
+    // Lower the quality when the upload link is slow so recordings stay uploadable
+    let crf = recording_crf(encoding.crf);
+
+    let mut args = vec!["-vcodec".to_string(), encoding.encoder.clone()];
+    match encoding.encoder.as_str() {
+        "h264_nvenc" => args.extend(["-rc".to_string(), "vbr".to_string(), "-cq".to_string(), crf.to_string()]),
+        "h264_qsv" => args.extend(["-global_quality".to_string(), crf.to_string()]),
+        // VideoToolbox takes a 1-100 quality where higher is better
+        "h264_videotoolbox" => args.extend(["-q:v".to_string(), (100 - crf.min(50) * 2).max(1).to_string()]),
+        _ => args.extend([
+            "-crf".to_string(),
+            crf.to_string(),
+            "-preset".to_string(),
+            encoding.x264_preset.to_string(),
+        ]),
+    }
+
+    // Quick Sync only accepts NV12 input
+    let pix_fmt = if encoding.encoder == "h264_qsv" { "nv12" } else { "yuv420p" };
+    args.extend(["-pix_fmt".to_string(), pix_fmt.to_string()]);
+    args
+}
+
+// Helper function to spawn FFmpeg capturing the desktop into the given file, or HLS playlist
+fn spawn_ffmpeg_recording(ffmpeg_cmd: &str, video_path_str: &str, encoding: &RecordingEncoding) -> Result<Child, String> {
+    #[cfg(target_os = "windows")]
+    let (input_format, input) = ("gdigrab", "desktop".to_string());
+    // On Linux, use x11grab for screen capture
+    #[cfg(target_os = "linux")]
+    let (input_format, input) = ("x11grab", std::env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string()));
+    // On macOS, use avfoundation for screen capture
+    #[cfg(target_os = "macos")]
+    let (input_format, input) = ("avfoundation", "default".to_string());
+
+    let mut args: Vec<String> = vec!["-f".to_string(), input_format.to_string()];
+    // The input frame rate has to come before -i to limit how often the screen is grabbed
+    if encoding.framerate > 0 {
+        args.extend(["-framerate".to_string(), encoding.framerate.to_string()]);
+    }
+    args.extend(["-i".to_string(), input]);
+
+    args.extend(encoder_args(encoding));
+    if encoding.framerate > 0 {
+        args.extend(["-r".to_string(), encoding.framerate.to_string()]);
+    }
+    // A playlist path means HLS output, anything else is written as a single file
+    if video_path_str.ends_with(".m3u8") {
+        args.extend(hls::output_args(video_path_str));
+    }
+    args.extend(["-y".to_string(), video_path_str.to_string()]);
+
+    let mut command = Command::new(ffmpeg_cmd);
+    command
+        .args(&args)
+        .stdin(Stdio::piped()) // Lets us ask FFmpeg to quit gracefully with "q"
+        .stderr(Stdio::piped()); // Kept so a crash can be explained
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let mut child = command.spawn().map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?;
+    if let Some(stderr) = child.stderr.take() {
+        collect_ffmpeg_stderr(stderr);
+    }
+
+    Ok(child)
+}
+
+// Lines of FFmpeg's error output kept for crash reports
+const FFMPEG_STDERR_TAIL_LINES: usize = 20;
+
+lazy_static! {
+    // The last lines FFmpeg wrote to stderr for the current segment
+    static ref FFMPEG_STDERR_TAIL: Arc<Mutex<VecDeque<String>>> = Arc::new(Mutex::new(VecDeque::new()));
+}
+
+// Helper function to read a recording FFmpeg's stderr on a background thread, keeping its last lines.
+// The pipe must be drained continuously or FFmpeg blocks once it is full.
+fn collect_ffmpeg_stderr(mut stderr: std::process::ChildStderr) {
+    use std::io::Read;
+
+    if let Ok(mut tail) = FFMPEG_STDERR_TAIL.lock() {
+        tail.clear();
+    }
+
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        let mut line = Vec::new();
+
+        // Progress updates end in \r rather than \n, so split on both
+        while let Ok(read) = stderr.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+
+            for &byte in &buffer[..read] {
+                if byte != b'\n' && byte != b'\r' {
+                    line.push(byte);
+                    continue;
+                }
+                if line.is_empty() {
+                    continue;
+                }
+
+                if let Ok(mut tail) = FFMPEG_STDERR_TAIL.lock() {
+                    if tail.len() == FFMPEG_STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(String::from_utf8_lossy(&line).to_string());
+                }
+                line.clear();
+            }
+        }
+    });
+}
+
+// How often the recording process is checked for having exited on its own
+const FFMPEG_CRASH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+// A segment that crashes within this time of being started counts as a crash loop, and after this many
+// of those in a row the recording is stopped instead of restarted
+const FFMPEG_QUICK_CRASH_SECS: u64 = 60;
+const FFMPEG_MAX_QUICK_CRASHES: u32 = 3;
+
+// Helper function to start the task that notices FFmpeg exiting without being asked to, e.g. after a crash.
+// It reports the crash with "recording-crashed", stops the snapshots and starts a new segment.
+// The task ends by itself once the session is stopped or replaced.
+fn start_recording_crash_monitor(app: tauri::AppHandle, session_id: String) {
+    tokio::spawn(async move {
+        let mut segment_started = Instant::now();
+        let mut quick_crashes = 0;
+
+        loop {
+            tokio::time::sleep(FFMPEG_CRASH_CHECK_INTERVAL).await;
+
+            let current_session = RECORDING_SESSION_ID.lock().map(|session| session.clone()).unwrap_or(None);
+            if current_session.as_deref() != Some(session_id.as_str()) {
+                break;
+            }
+
+            // Every intentional stop takes the process out first, so one that exited while still here crashed
+            let exit_status = {
+                let mut process_guard = match COMBINED_RECORDING_PROCESS.lock() {
+                    Ok(process_guard) => process_guard,
+                    Err(_) => break,
+                };
+                let exit_status = match process_guard.as_mut().map(|child| child.try_wait()) {
+                    Some(Ok(Some(exit_status))) => exit_status,
+                    _ => continue, // Running, or paused with no process
+                };
+                *process_guard = None;
+                exit_status
+            };
+            if let Ok(mut pid_guard) = FFMPEG_PROCESS_ID.lock() {
+                *pid_guard = None;
+            }
+
+            // Give the stderr reader a moment to collect FFmpeg's last words
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let error_output: Vec<String> = FFMPEG_STDERR_TAIL.lock().map(|tail| tail.iter().cloned().collect()).unwrap_or_default();
+            error!(
+                "FFmpeg exited unexpectedly during recording {} ({}): {}",
+                session_id,
+                exit_status,
+                error_output.last().map(String::as_str).unwrap_or("no error output")
+            );
+
+            // The snapshots are paired with the segment that just ended
+            if let Some(task) = SCREENSHOT_TASK_HANDLE.lock().ok().and_then(|mut task_guard| task_guard.take()) {
+                task.abort();
+            }
+
+            quick_crashes = match segment_started.elapsed() < Duration::from_secs(FFMPEG_QUICK_CRASH_SECS) {
+                true => quick_crashes + 1,
+                false => 1,
+            };
+
+            let restart_error = match quick_crashes >= FFMPEG_MAX_QUICK_CRASHES {
+                true => Some(format!("FFmpeg crashed {} times in a row shortly after starting", quick_crashes)),
+                false => match start_new_recording_segment().await {
+                    Ok(message) => {
+                        info!("Restarted recording after FFmpeg exited. {}", message);
+                        segment_started = Instant::now();
+                        let screenshot_task = spawn_recording_snapshot_task(app.clone(), session_id.clone());
+                        if let Ok(mut task_guard) = SCREENSHOT_TASK_HANDLE.lock() {
+                            *task_guard = Some(screenshot_task);
+                        }
+                        None
+                    }
+                    Err(e) => Some(e.to_string()),
+                },
+            };
+
+            for (_window_label, window) in app.webview_windows() {
+                let _ = window.emit(
+                    "recording-crashed",
+                    events::RecordingCrashEvent {
+                        exit_code: exit_status.code(),
+                        error_output: error_output.clone(),
+                        restarted: restart_error.is_none(),
+                    },
+                );
+            }
+
+            // Finalize what was recorded so far rather than leaving a session without a process
+            if let Some(e) = restart_error {
+                error!("Stopping recording {} after FFmpeg exited: {}", session_id, e);
+                if let Err(e) = stop_combined_recording(app.clone()).await {
+                    error!("{}", e);
+                }
+                break;
+            }
+            process_status_changed();
+        }
+    });
+}
+
+// How long FFmpeg gets to finish writing the file after being asked to quit
+const FFMPEG_GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Helper function to stop an FFmpeg recording without corrupting the output file.
+// Sends "q" on stdin so FFmpeg flushes and writes the container trailer, and only kills it on timeout.
+fn stop_ffmpeg_gracefully(child: &mut Child) {
+    use std::io::Write;
+
+    let quit_sent = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(b"q").and_then(|_| stdin.flush()).is_ok(),
+        None => false,
+    }; // stdin is closed here, which also makes FFmpeg stop reading input
+
+    if quit_sent {
+        let deadline = std::time::Instant::now() + FFMPEG_GRACEFUL_STOP_TIMEOUT;
+        while std::time::Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(Some(exit_status)) => {
+                    info!("FFmpeg exited gracefully with: {}", exit_status);
+                    return;
+                }
+                Ok(None) => std::thread::sleep(std::time::Duration::from_millis(100)),
+                Err(e) => {
+                    error!("Error waiting for FFmpeg to exit: {}", e);
+                    break;
+                }
+            }
+        }
+        warn!("FFmpeg did not exit within {:?}, killing it", FFMPEG_GRACEFUL_STOP_TIMEOUT);
+    }
+
+    match child.kill() {
+        Ok(_) => {
+            info!("Successfully sent kill signal to recording process");
+            // Wait for the process to finish
+            match child.wait() {
+                Ok(exit_status) => info!("Process exited with: {}", exit_status),
+                Err(e) => warn!("Error waiting for process: {}", e),
+            }
+        },
+        Err(e) => error!("Error killing process: {}", e),
+    }
+}
+
+// Seconds to wait after launch before auto-resumed monitoring starts capturing
+static STARTUP_DELAY_SECONDS: AtomicU64 = AtomicU64::new(10);
+
+lazy_static! {
+    // Time the application was launched, used to measure the startup delay
+    static ref APP_STARTED_AT: Instant = Instant::now();
+}
+
+// Command to configure the delay applied to monitoring that is resumed automatically at launch
+#[tauri::command]
+fn set_startup_delay(seconds: u64) -> Result<String, CommandError> {
+    if seconds > 600 {
+        return Err(CommandError::InvalidInput("Startup delay must be between 0 and 600 seconds".to_string()));
+    }
+
+    STARTUP_DELAY_SECONDS.store(seconds, Ordering::SeqCst);
+    Ok(format!("Startup delay set to {} seconds", seconds))
+}
+
+// Helper function to wait out whatever remains of the startup delay since launch
+async fn wait_for_startup_delay(app: &tauri::AppHandle) {
+    let delay = Duration::from_secs(STARTUP_DELAY_SECONDS.load(Ordering::SeqCst));
+    let remaining = delay.saturating_sub(APP_STARTED_AT.elapsed());
+
+    if remaining.is_zero() {
+        return;
+    }
+
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("recording-progress", events::ProgressEvent::StartupDelay { seconds_remaining: remaining.as_secs().max(1) });
+    }
+    tokio::time::sleep(remaining).await;
+}
+
+#[tauri::command]
+async fn start_combined_recording(app: tauri::AppHandle, auto_resumed: Option<bool>, force: Option<bool>) -> Result<String, CommandError> {
+    // Give the desktop time to settle when monitoring is resumed automatically at launch,
+    // explicit starts by the user begin immediately
+    if auto_resumed.unwrap_or(false) {
+        wait_for_startup_delay(&app).await;
+    }
+
+    // Check if there's already a recording in progress. Only one can run at a time since FFmpeg captures the
+    // whole desktop; with force the running one is stopped (and saved) to make way for the new one.
+    let in_progress = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?.is_some();
+    if in_progress {
+        let active_session = RECORDING_SESSION_ID.lock().map_err(|e| e.to_string())?.clone();
+        let started_at = *RECORDING_STARTED_AT.lock().map_err(|e| e.to_string())?;
+        let active_session = active_session.unwrap_or_else(|| "unknown".to_string());
+        let started_at = started_at
+            .map(|time| chrono::DateTime::<chrono::Local>::from(time).to_rfc3339())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        if !force.unwrap_or(false) {
+            return Err(CommandError::AlreadyRunning(format!(
+                "A recording session is already in progress (session {}, started {}); stop it first or start with force to replace it",
+                active_session, started_at
+            )));
+        }
+
+        warn!("Stopping recording session {} (started {}) to force a new one", active_session, started_at);
+        // The old session's segments stay on disk even if they couldn't be joined
+        if let Err(e) = stop_combined_recording(app.clone()).await {
+            error!("{}", e);
+        }
+        if COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?.is_some() {
+            return Err(format!("Failed to stop recording session {} to start a new one", active_session).into());
         }
-        drop(process_guard);
     }
 
+    ensure_free_disk_space("recording")?;
+
     // Create recordings directory in data directory
     let data_dir_path = get_data_directory();
     let dir = data_dir_path.join("recordings");
@@ -489,6 +4052,7 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
         let mut session_guard = RECORDING_SESSION_ID.lock().unwrap();
         *session_guard = Some(session_id.clone());
     }
+    *RECORDING_STARTED_AT.lock().map_err(|e| e.to_string())? = Some(SystemTime::now());
 
     {
         let mut path_guard = RECORDING_BASE_PATH.lock().unwrap();
@@ -501,16 +4065,20 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
         files_guard.clear(); // Clear any old segment files
     }
 
+    // The output mode can't change within a session, since segments of both kinds can't be joined
+    let hls_output = RECORDING_OUTPUT.lock().map(|output| output.as_str() == "hls").unwrap_or(false);
+    RECORDING_SESSION_HLS.store(hls_output, Ordering::SeqCst);
+    HLS_UPLOADED_SEGMENTS.lock().await.clear();
+
     // Create the first segment - we'll later concatenate all segments
-    let first_segment_path = dir.join(format!("recording_{}_seg_0.mkv", session_id));
-    let video_path_str = first_segment_path.to_string_lossy().to_string();
+    let video_path_str = recording_segment_path(&dir.to_string_lossy(), &session_id, 0)?;
 
     // Look for bundled FFmpeg first
     let ffmpeg_path = std::env::current_exe()
         .ok()
         .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
         .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("ffmpeg.exe");
+        .join(BUNDLED_FFMPEG_NAME);
 
     let ffmpeg_cmd = if ffmpeg_path.exists() {
         ffmpeg_path.to_string_lossy().to_string()
@@ -535,15 +4103,20 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
             Err(_) => {
                 // Neither bundled nor system FFmpeg found, attempt to download
                 for (_window_label, window) in app.webview_windows() {
-                    let _ = window.emit("recording-progress", "FFmpeg not found, downloading...");
+                    let _ = window.emit("recording-progress", events::ProgressEvent::FfmpegMissing);
                 }
 
                 if let Err(e) = download_ffmpeg_bundled_app(&app, &ffmpeg_path).await {
-                    eprintln!("Failed to download FFmpeg: {}", e);
-                    return Err("FFmpeg is required for recording but could not be downloaded".to_string());
+                    error!("Failed to download FFmpeg: {}", e);
+                    if e.to_string() == FFMPEG_DOWNLOAD_CANCELLED_MESSAGE {
+                        return Err(CommandError::FfmpegMissing("FFmpeg download cancelled, recording not started".to_string()));
+                    }
+                    return Err(CommandError::FfmpegMissing(
+                        "FFmpeg is required for recording but could not be downloaded".to_string(),
+                    ));
                 } else {
                     for (_window_label, window) in app.webview_windows() {
-                        let _ = window.emit("recording-progress", "FFmpeg downloaded successfully!");
+                        let _ = window.emit("recording-progress", events::ProgressEvent::FfmpegDownloaded);
                     }
                     ffmpeg_path.to_string_lossy().to_string()
                 }
@@ -551,60 +4124,16 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
         }
     };
 
+    // Pick the encoding once per recording so every segment is encoded the same way
+    let encoding = select_recording_encoding(&ffmpeg_cmd);
+    info!("Recording with {} (crf {}, framerate {})", encoding.encoder, encoding.crf, encoding.framerate);
+    {
+        let mut encoding_guard = RECORDING_ENCODING.lock().map_err(|e| e.to_string())?;
+        *encoding_guard = Some(encoding.clone());
+    }
+
     // Start the video recording process with FFmpeg
-    let child = {
-        #[cfg(target_os = "windows")]
-        {
-            Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "gdigrab",
-                    "-i", "desktop",
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW flag
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-        #[cfg(target_os = "linux")]
-        {
-            // On Linux, use x11grab for screen capture
-            Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "x11grab",
-                    "-i", &std::env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string()),
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // On macOS, use avfoundation for screen capture
-            Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "avfoundation",
-                    "-i", "default",
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-    };
+    let child = spawn_ffmpeg_recording(&ffmpeg_cmd, &video_path_str, &encoding)?;
 
     // Store the recording process
     {
@@ -626,15 +4155,16 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
     };
 
     // Save the main recording metadata to database
+    let extension = if hls_output { "m3u8" } else { "mkv" };
     if let Err(e) = database::save_recording_to_db(
         &user_id,
         &session_id,
-        &format!("recording_{}.mkv", session_id),
+        &format!("recording_{}.{}", session_id, extension),
         Some(&video_path_str),
         None, // Duration not known yet
         None  // File size not known yet
     ) {
-        eprintln!("Failed to save recording metadata to database: {}", e);
+        error!("Failed to save recording metadata to database: {}", e);
     }
 
     // Store the process ID for potential pause/resume operations
@@ -648,7 +4178,7 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
         let mut task_guard = SCREENSHOT_TASK_HANDLE.lock().unwrap();
         if let Some(old_task) = task_guard.take() {
             old_task.abort(); // Cancel any old task
-            println!("Cancelled old screenshot task if it existed");
+            debug!("Cancelled old screenshot task if it existed");
         }
     }
 
@@ -660,379 +4190,833 @@ async fn start_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
     }
 
     // Start the screenshot-taking process in parallel
-    let screenshot_session_id = session_id.clone();
-    let app_for_screenshot = app.clone(); // Clone the app handle for the async block
-    let screenshot_task = tokio::spawn(async move {
-        let start_time = Instant::now();
+    let screenshot_task = spawn_recording_snapshot_task(app.clone(), session_id.clone());
+
+    // Store the screenshot task handle in global state so we can cancel it later
+    {
+        let mut task_guard = SCREENSHOT_TASK_HANDLE.lock().unwrap();
+        *task_guard = Some(screenshot_task);
+    }
+
+    // Enforce the maximum segment and session durations
+    start_recording_rollover_task(app.clone(), session_id.clone());
+    start_recording_crash_monitor(app.clone(), session_id.clone());
+    start_recording_display_watch(session_id.clone());
+    if hls_output {
+        start_hls_upload_task(session_id.clone());
+    }
+
+    // Update user activity timestamp when recording starts (user is actively starting monitoring)
+    if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
+        *last_activity = SystemTime::now();
+    }
+
+    // Record "recording started" activity in database (user is active when starting recording)
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
+        error!("Failed to save recording start activity to database: {}", e);
+    }
+
+    process_status_changed();
+
+    Ok(format!("Remote Worker: started: (Session ID: {})", session_id))
+}
+
+// Helper function to start the task taking snapshots alongside a recording, until the recording process is gone
+fn spawn_recording_snapshot_task(app_for_screenshot: tauri::AppHandle, screenshot_session_id: String) -> JoinHandle<()> {
+    tokio::spawn(async move {
+    let start_time = Instant::now();
+
+    loop {
+        // Check if the recording process is still active
+        let is_active = {
+            let process_guard = COMBINED_RECORDING_PROCESS.lock().unwrap();
+            // Check if there's a recording process running (not None)
+            process_guard.is_some()
+        };
+
+        if !is_active {
+            info!("Screenshot task terminating: recording process no longer active");
+            break; // Stop if the recording process has been terminated
+        }
+
+        // Check if the recording is paused
+        let is_paused = RECORDING_PAUSED.load(Ordering::SeqCst);
+        if is_paused {
+            // Wait for a short period before checking again
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            continue; // Skip screenshot capture when paused
+        }
+
+        // Outside work hours the recording goes on, but no snapshots are taken
+        if !within_work_hours() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+
+        // Take a screenshot, mask excluded windows and upload it
+        let capture_started = Instant::now();
+        let timestamp = start_time.elapsed().as_millis();
+
+        match capture_and_upload_screens("snapshot", &screenshot_session_id, timestamp, None, false).await {
+            Ok(uploads) => {
+                // Emit to all windows for screenshot
+                for screenshot in uploads.screenshots {
+                    for (_window_label, window) in app_for_screenshot.webview_windows() {
+                        let _ = window.emit(
+                            "screenshot-taken",
+                            events::ScreenshotEvent::SnapshotUploaded { id: screenshot.id, url: screenshot.remote_url.clone() },
+                        );
+                    }
+                }
+                // Note: Keeping event name as screenshot-taken for compatibility
+                // Update user activity since a snapshot was just taken (user is likely active)
+                if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
+                    *last_activity = SystemTime::now();
+                }
+            }
+            Err(e) => {
+                error!("{}", e);
+            }
+        }
+
+        // Wait until the next snapshot is due, a random interval between the recording snapshot min/max
+        // after this one started. But check every second if recording is still active and not paused
+        let mut next_capture_at = next_capture_deadline(capture_started, recording_snapshot_intervals());
+        let mut last_check = Instant::now();
+        while Instant::now() < next_capture_at {
+            note_next_capture(
+                &screenshot_session_id,
+                Some(ScheduledCapture { kind: "recording_snapshot", monitor_index: None, due: next_capture_at }),
+            );
+            let wait = next_capture_at.saturating_duration_since(Instant::now()).min(Duration::from_secs(1));
+            tokio::time::sleep(wait).await;
+
+            let now = Instant::now();
+            let elapsed = now - last_check;
+            last_check = now;
+
+            // Paused time doesn't count towards the interval; a paused recording has no process to check
+            if RECORDING_PAUSED.load(Ordering::SeqCst) {
+                next_capture_at += elapsed;
+                continue;
+            }
+
+            // Emit progress update about the remaining time to all windows
+            let seconds_remaining = next_capture_at.saturating_duration_since(now).as_secs_f64().ceil() as u64;
+            for (_window_label, window) in app_for_screenshot.webview_windows() {
+                let _ = window.emit("recording-progress", events::ProgressEvent::NextSnapshot { seconds_remaining });
+            }
 
-        loop {
-            // Check if the recording process is still active
             let is_active = {
                 let process_guard = COMBINED_RECORDING_PROCESS.lock().unwrap();
-                // Check if there's a recording process running (not None)
                 process_guard.is_some()
             };
 
             if !is_active {
-                println!("Screenshot task terminating: recording process no longer active");
-                break; // Stop if the recording process has been terminated
-            }
-
-            // Check if the recording is paused
-            let is_paused = RECORDING_PAUSED.load(Ordering::SeqCst);
-            if is_paused {
-                // Wait for a short period before checking again
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                continue; // Skip screenshot capture when paused
-            }
-
-            // Take a screenshot
-            match Screen::all() {
-                Ok(screens) => {
-                    if let Some(primary_screen) = screens.first() {
-                        match primary_screen.capture_area(0, 0, primary_screen.display_info.width, primary_screen.display_info.height) {
-                            Ok(img) => {
-                                let mut img = img;
-
-                                // Apply window masking on Windows (with added safety checks to prevent all-black screenshots)
-                                #[cfg(target_os = "windows")]
-                                {
-                                    // Get excluded windows list
-                                    let excluded_windows = RUNNING_EXCLUDED_WINDOWS.lock().unwrap().clone();
-
-                                    // Get visible windows to mask
-                                    if let Ok(windows_to_mask) = crate::windows_utils::get_visible_windows() {
-                                        for window in windows_to_mask {
-                                            let window_title_lower = window.title.to_lowercase();
-
-                                            let is_excluded = excluded_windows.iter().any(|keyword| {
-                                                window_title_lower.contains(keyword)
-                                            });
-
-                                            if is_excluded {
-                                                // Convert window coordinates to image coordinates
-                                                let x1_raw = window.rect.left;
-                                                let y1_raw = window.rect.top;
-                                                let x2_raw = window.rect.right;
-                                                let y2_raw = window.rect.bottom;
-
-                                                // Safety check: skip windows with invalid coordinates
-                                                if x2_raw <= x1_raw || y2_raw <= y1_raw {
-                                                    continue;
-                                                }
-
-                                                // Convert to unsigned and clamp to image dimensions
-                                                let x1 = std::cmp::max(0, x1_raw) as u32;
-                                                let y1 = std::cmp::max(0, y1_raw) as u32;
-                                                let mut x2 = std::cmp::max(0, x2_raw) as u32;
-                                                let mut y2 = std::cmp::max(0, y2_raw) as u32;
-
-                                                // Ensure coordinates are within image bounds
-                                                x2 = std::cmp::min(x2, primary_screen.display_info.width);
-                                                y2 = std::cmp::min(y2, primary_screen.display_info.height);
-
-                                                // Additional safety: prevent overly large areas
-                                                let width = x2.saturating_sub(x1);
-                                                let height = y2.saturating_sub(y1);
-
-                                                // Make sure x1,y1 are still less than or equal to x2,y2 after clamping
-                                                if x1 >= x2 || y1 >= y2 {
-                                                    continue; // Skip if the area becomes invalid after clamping
-                                                }
-
-                                                // Skip if window exceeds reasonable size (prevent accidentally capturing entire screen)
-                                                // Only skip if the window is more than 90% of the screen size to be more permissive
-                                                if width * height > primary_screen.display_info.width * primary_screen.display_info.height * 9 / 10 {
-                                                    continue;
-                                                }
-
-                                                // Black out the window area
-                                                for y in y1..y2 {
-                                                    for x in x1..x2 {
-                                                        use image::Rgba;
-                                                        img.put_pixel(x, y, Rgba([0, 0, 0, 255])); // Black with full opacity
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+                break; // Exit the waiting loop if recording stopped
+            }
+        }
 
-                                let timestamp = start_time.elapsed().as_millis();
-                                let filename = format!("snapshot_{}_{}.png", screenshot_session_id, timestamp);
-
-                                // Create path to screenshots directory in data directory
-                                let mut screenshots_dir = get_data_directory().join("screenshots");
-                                if let Err(e) = std::fs::create_dir_all(&screenshots_dir) {
-                                    eprintln!("Failed to create screenshots directory in data directory: {}", e);
-                                    // Try to create in temp directory as fallback
-                                    screenshots_dir = std::env::temp_dir();
-                                    screenshots_dir.push("remote-work-screenshots");
-                                    if let Err(e) = std::fs::create_dir_all(&screenshots_dir) {
-                                        eprintln!("Failed to create screenshots directory in temp: {}", e);
-                                        return;
-                                    }
-                                }
+        // Check again if still active after 15-minute wait
+        let is_active = {
+            let process_guard = COMBINED_RECORDING_PROCESS.lock().unwrap();
+            process_guard.is_some()
+        };
 
-                                // Create file path
-                                let file_path = screenshots_dir.join(&filename);
+        if !is_active {
+            info!("Screenshot task terminating: recording process no longer active (end of loop)");
+            break; // Exit the main loop if recording stopped
+        }
+    }
 
-                                // Save image to a temporary file first
-                                let temp_file_path = std::env::temp_dir().join(&filename);
-                                if let Err(e) = img.save(&temp_file_path) {
-                                    eprintln!("Failed to save snapshot to temp file: {}", e);
-                                } else {
-                                    // Read the image data from the temporary file
-                                    let img_data = match std::fs::read(&temp_file_path) {
-                                        Ok(data) => data,
-                                        Err(e) => {
-                                            eprintln!("Failed to read snapshot from temp file: {}", e);
-                                            return;
-                                        }
-                                    };
-
-                                    // Upload the image data to the server
-                                    match save_file_to_xampp_htdocs(img_data, filename.clone(), "screenshot".to_string()).await {
-                                        Ok(remote_url) => {
-                                            // Get user ID before saving to database
-                                            let user_id = {
-                                                let user_id_guard = USER_ID.lock().unwrap();
-                                                user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-                                            };
-
-                                            // Get file size
-                                            let file_size = std::fs::metadata(&temp_file_path)
-                                                .map(|meta| Some(meta.len() as i64))
-                                                .unwrap_or(None);
-
-                                            // Save snapshot metadata to MySQL database with the remote URL
-                                            if let Err(e) = database::save_screenshot_to_db(&user_id, &screenshot_session_id, &remote_url, &filename, file_size) {
-                                                eprintln!("Failed to save snapshot metadata to database: {}", e);
-                                            } else {
-                                                // Emit to all windows for screenshot
-                                                for (_window_label, window) in app_for_screenshot.webview_windows() {
-                                                    let _ = window.emit("screenshot-taken", format!("Snapshot uploaded: {}", remote_url));
-                                                }
-                                                // Note: Keeping event name as screenshot-taken for compatibility
-                                                // Update user activity since a snapshot was just taken (user is likely active)
-                                                if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
-                                                    *last_activity = SystemTime::now();
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            eprintln!("Failed to upload snapshot: {}", e);
-                                        }
-                                    }
+    note_next_capture(&screenshot_session_id, None);
+    })
+}
 
-                                    // Clean up the temporary file
-                                    let _ = std::fs::remove_file(&temp_file_path);
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to capture screenshot: {}", e);
-                            }
+// Global state to track user activity
+lazy_static! {
+    static ref LAST_USER_ACTIVITY: Arc<Mutex<SystemTime>> = Arc::new(Mutex::new(SystemTime::now()));
+    static ref IDLE_DETECTION_TASK: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>> = Arc::new(Mutex::new(None));
+
+    // Global state to track excluded window titles; edits are persisted by persist_excluded_windows
+    static ref EXCLUDED_WINDOWS: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(config::default_excluded_windows()));
+
+    // Global state to track application network usage
+    static ref NETWORK_STATS: Arc<Mutex<NetworkUsage>> = Arc::new(Mutex::new(NetworkUsage {
+        total_bytes_downloaded: 0,
+        total_bytes_uploaded: 0,
+        last_bytes_downloaded: 0,
+        last_bytes_uploaded: 0,
+        last_updated: std::time::Instant::now(),
+    }));
+
+    // Global state to track system network usage
+    static ref GLOBAL_NETWORK_STATS: Arc<Mutex<GlobalNetworkUsage>> = Arc::new(Mutex::new(GlobalNetworkUsage {
+        last_total_bytes_downloaded: 0,
+        last_total_bytes_uploaded: 0,
+        last_updated: std::time::Instant::now(),
+    }));
+}
+
+// Shortest interval app network speeds are measured over; shorter ones give jumpy readings
+const NETWORK_SPEED_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+struct NetworkUsage {
+    total_bytes_downloaded: u64,
+    total_bytes_uploaded: u64,
+    // Totals and time at the start of the current measurement interval
+    last_bytes_downloaded: u64,
+    last_bytes_uploaded: u64,
+    last_updated: std::time::Instant,
+}
+
+impl NetworkUsage {
+    // Download and upload speeds (bytes per second) since the start of the current interval
+    fn speeds(&self, now: std::time::Instant) -> (f64, f64) {
+        let duration = now.duration_since(self.last_updated).as_secs_f64();
+        if duration <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        (
+            self.total_bytes_downloaded.saturating_sub(self.last_bytes_downloaded) as f64 / duration,
+            self.total_bytes_uploaded.saturating_sub(self.last_bytes_uploaded) as f64 / duration,
+        )
+    }
+
+    // Start a new measurement interval once the current one is long enough to give a stable reading
+    fn roll_interval(&mut self, now: std::time::Instant) {
+        if now.duration_since(self.last_updated) >= NETWORK_SPEED_MIN_INTERVAL {
+            self.last_bytes_downloaded = self.total_bytes_downloaded;
+            self.last_bytes_uploaded = self.total_bytes_uploaded;
+            self.last_updated = now;
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GlobalNetworkUsage {
+    last_total_bytes_downloaded: u64,
+    last_total_bytes_uploaded: u64,
+    last_updated: std::time::Instant,
+}
+
+// Bytes this process has transferred itself (uploads, FFmpeg downloads). The OSes don't offer per-process
+// byte counters without elevated privileges, so the app counts its own traffic where it generates it.
+static OWN_BYTES_DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+static OWN_BYTES_UPLOADED: AtomicU64 = AtomicU64::new(0);
+
+// Helper function to account for traffic generated by this process
+fn record_own_network_bytes(downloaded_bytes: u64, uploaded_bytes: u64) {
+    OWN_BYTES_DOWNLOADED.fetch_add(downloaded_bytes, Ordering::Relaxed);
+    OWN_BYTES_UPLOADED.fetch_add(uploaded_bytes, Ordering::Relaxed);
+}
+
+// Previous sample used to turn the per-process byte totals into rates
+#[derive(Clone)]
+struct PerProcessNetworkSample {
+    own_bytes_downloaded: u64,
+    own_bytes_uploaded: u64,
+    system_bytes_downloaded: u64,
+    system_bytes_uploaded: u64,
+    taken_at: std::time::Instant,
+}
+
+lazy_static! {
+    static ref PER_PROCESS_NETWORK_SAMPLE: Arc<Mutex<Option<PerProcessNetworkSample>>> = Arc::new(Mutex::new(None));
+}
+
+// Traffic of one process (or of everything else) as reported by get_per_process_network_stats
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessNetworkStats {
+    process: String,
+    pid: Option<u32>,
+    download_bytes_per_sec: f64,
+    upload_bytes_per_sec: f64,
+    total_downloaded: u64,
+    total_uploaded: u64,
+}
+
+// Global variable to access excluded windows during capture
+#[cfg(target_os = "windows")]
+use EXCLUDED_WINDOWS as RUNNING_EXCLUDED_WINDOWS;
+
+
+
+
+#[cfg(target_os = "windows")]
+mod windows_utils {
+    use super::*;
+    use winapi::shared::minwindef::{LRESULT, WPARAM};
+
+    pub struct WindowInfo {
+        pub title: String,
+        pub rect: RECT,
+    }
+
+    pub fn get_visible_windows() -> Result<Vec<WindowInfo>, Box<dyn std::error::Error>> {
+        let mut windows = Vec::new();
+        let windows_ptr = &mut windows as *mut Vec<WindowInfo>;
+
+        unsafe {
+            EnumWindows(Some(enum_windows_proc), windows_ptr as LPARAM);
+        }
+
+        Ok(windows)
+    }
+
+    unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows: &mut Vec<WindowInfo> = &mut *(lparam as *mut Vec<WindowInfo>);
+
+        if IsWindowVisible(hwnd) != 0 && IsIconic(hwnd) == 0 {
+            let mut buf = [0u16; 256];
+            GetWindowTextW(hwnd, buf.as_mut_ptr(), 256);
+
+            let title = OsString::from_wide(&buf[..buf.iter().position(|&x| x == 0).unwrap_or(buf.len())])
+                .to_string_lossy()
+                .to_string();
+
+            // Only include windows with non-empty titles
+            if !title.is_empty() {
+                let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+                if GetWindowRect(hwnd, &mut rect) != 0 {  // GetWindowRect returns BOOL (non-zero for success)
+                    windows.push(WindowInfo {
+                        title,
+                        rect,
+                    });
+                }
+            }
+        }
+
+        TRUE  // Continue enumeration
+    }
+
+    // Returns the title of the window that currently has keyboard focus
+    pub fn foreground_window_title() -> Option<String> {
+        unsafe {
+            let hwnd = winapi::um::winuser::GetForegroundWindow();
+            if hwnd.is_null() {
+                return None;
+            }
+
+            let mut buf = [0u16; 256];
+            let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), 256);
+            if len <= 0 {
+                return None;
+            }
+
+            Some(OsString::from_wide(&buf[..len as usize]).to_string_lossy().to_string())
+        }
+    }
+
+    // Called from the keyboard hook thread once per Ctrl+V or Shift+Insert press
+    static PASTE_HANDLER: once_cell::sync::OnceCell<Box<dyn Fn() + Send + Sync>> = once_cell::sync::OnceCell::new();
+    // Whether a paste key is held, so key repeat isn't taken for more pastes
+    static PASTE_KEY_DOWN: AtomicBool = AtomicBool::new(false);
+
+    const VK_V: u32 = 0x56;
+
+    unsafe extern "system" fn paste_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        use winapi::um::winuser::{
+            CallNextHookEx, GetAsyncKeyState, HC_ACTION, KBDLLHOOKSTRUCT, VK_CONTROL, VK_INSERT, VK_SHIFT, WM_KEYDOWN, WM_KEYUP,
+            WM_SYSKEYDOWN, WM_SYSKEYUP,
+        };
+
+        if code == HC_ACTION {
+            let key = &*(lparam as *const KBDLLHOOKSTRUCT);
+            let is_paste_key = key.vkCode == VK_V || key.vkCode == VK_INSERT as u32;
+
+            match wparam as u32 {
+                WM_KEYDOWN | WM_SYSKEYDOWN if is_paste_key => {
+                    // The high bit of the returned state is set while the modifier is down
+                    let modifier = if key.vkCode == VK_V { VK_CONTROL } else { VK_SHIFT };
+                    if GetAsyncKeyState(modifier) < 0 && !PASTE_KEY_DOWN.swap(true, Ordering::SeqCst) {
+                        if let Some(handler) = PASTE_HANDLER.get() {
+                            handler();
                         }
-                    } else {
-                        eprintln!("No screens found for snapshot");
+                    }
+                },
+                WM_KEYUP | WM_SYSKEYUP if is_paste_key => PASTE_KEY_DOWN.store(false, Ordering::SeqCst),
+                _ => {},
+            }
+        }
+
+        CallNextHookEx(std::ptr::null_mut(), code, wparam, lparam)
+    }
+
+    // Watch the paste shortcuts (Ctrl+V and Shift+Insert) in every application through a low-level keyboard
+    // hook, calling on_paste from the hook thread for each press. Pastes from a context or Edit menu don't go
+    // through the keyboard and aren't seen. The hook stays installed for the rest of the process.
+    pub fn start_paste_hook(on_paste: impl Fn() + Send + Sync + 'static) -> Result<(), String> {
+        use winapi::um::libloaderapi::GetModuleHandleW;
+        use winapi::um::winuser::{DispatchMessageW, GetMessageW, SetWindowsHookExW, TranslateMessage, MSG, WH_KEYBOARD_LL};
+
+        PASTE_HANDLER
+            .set(Box::new(on_paste))
+            .map_err(|_| "Paste shortcuts are already being watched".to_string())?;
+
+        // Low-level hooks are called on the installing thread, which must pump messages; wait for it to report back
+        let (ready_sender, ready_receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || unsafe {
+            let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(paste_hook_proc), GetModuleHandleW(std::ptr::null()), 0);
+            if hook.is_null() {
+                let _ = ready_sender.send(Err("Failed to install the keyboard hook for paste shortcuts".to_string()));
+                return;
+            }
+            let _ = ready_sender.send(Ok(()));
+
+            let mut message: MSG = std::mem::zeroed();
+            while GetMessageW(&mut message, std::ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+            error!("Paste shortcut hook stopped receiving messages");
+        });
+
+        ready_receiver
+            .recv()
+            .map_err(|_| "Paste shortcut hook thread exited unexpectedly".to_string())?
+    }
+
+    // Returns the size in bytes of the clipboard's text or bitmap payload without reading its content
+    pub fn clipboard_data_size() -> Option<usize> {
+        use winapi::um::winbase::GlobalSize;
+        use winapi::um::winuser::{CloseClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, CF_DIB, CF_UNICODETEXT};
+
+        unsafe {
+            if OpenClipboard(std::ptr::null_mut()) == 0 {
+                return None;
+            }
+
+            let mut largest = 0;
+            for format in [CF_UNICODETEXT, CF_DIB] {
+                if IsClipboardFormatAvailable(format) != 0 {
+                    let handle = GetClipboardData(format);
+                    if !handle.is_null() {
+                        largest = std::cmp::max(largest, GlobalSize(handle));
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to get screens for snapshot: {}", e);
-                }
             }
 
-            // Generate a random interval using configurable min/max values
-            let random_interval: u64 = {
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                let min_interval = SCREENSHOT_MIN_INTERVAL.lock().unwrap();
-                let max_interval = SCREENSHOT_MAX_INTERVAL.lock().unwrap();
-                rng.gen_range(*min_interval..=*max_interval)
-            };
+            CloseClipboard();
+            Some(largest)
+        }
+    }
+
+    // Returns the rectangle of the foreground window if it has a display affinity set
+    // (WDA_MONITOR or WDA_EXCLUDEFROMCAPTURE), which makes it appear black in screen captures
+    pub fn protected_foreground_window_rect() -> Option<RECT> {
+        use winapi::um::winuser::{GetForegroundWindow, GetWindowDisplayAffinity};
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_null() {
+                return None;
+            }
+
+            let mut affinity: u32 = 0;
+            if GetWindowDisplayAffinity(hwnd, &mut affinity) == 0 || affinity == 0 {
+                return None; // Call failed or WDA_NONE
+            }
+
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            if GetWindowRect(hwnd, &mut rect) == 0 {
+                return None;
+            }
+
+            Some(rect)
+        }
+    }
+
+    // Returns true while any application holds the webcam or microphone.
+    // Windows records device use per app in the capability access consent store; an app that is
+    // using the device right now has a start time but no stop time.
+    pub fn is_camera_or_microphone_in_use() -> bool {
+        use winapi::um::winreg::HKEY_CURRENT_USER;
+
+        ["webcam", "microphone"].iter().any(|device| {
+            let path = format!("Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\{}", device);
+            unsafe { consent_key_has_active_app(HKEY_CURRENT_USER, &path) }
+        })
+    }
+
+    // Check a consent store key and its subkeys (packaged apps and the NonPackaged folder) for an active app
+    unsafe fn consent_key_has_active_app(parent: winapi::shared::minwindef::HKEY, subkey: &str) -> bool {
+        use winapi::um::winnt::KEY_READ;
+        use winapi::um::winreg::{RegCloseKey, RegEnumKeyExW, RegOpenKeyExW};
+
+        let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut key: winapi::shared::minwindef::HKEY = std::ptr::null_mut();
+        if RegOpenKeyExW(parent, subkey_wide.as_ptr(), 0, KEY_READ, &mut key) != 0 {
+            return false;
+        }
+
+        let started = query_registry_qword(key, "LastUsedTimeStart").unwrap_or(0);
+        let stopped = query_registry_qword(key, "LastUsedTimeStop");
+        let mut active = started != 0 && stopped == Some(0);
+
+        let mut index = 0;
+        while !active {
+            let mut name = [0u16; 512];
+            let mut name_len = name.len() as u32;
+            let result = RegEnumKeyExW(
+                key,
+                index,
+                name.as_mut_ptr(),
+                &mut name_len,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            if result != 0 {
+                break; // No more subkeys
+            }
+
+            let child = String::from_utf16_lossy(&name[..name_len as usize]);
+            active = consent_key_has_active_app(key, &child);
+            index += 1;
+        }
+
+        RegCloseKey(key);
+        active
+    }
+
+    unsafe fn query_registry_qword(key: winapi::shared::minwindef::HKEY, value_name: &str) -> Option<u64> {
+        use winapi::um::winreg::RegQueryValueExW;
+
+        let name_wide: Vec<u16> = value_name.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut value: u64 = 0;
+        let mut size = std::mem::size_of::<u64>() as u32;
+        let result = RegQueryValueExW(
+            key,
+            name_wide.as_ptr(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut value as *mut u64 as *mut u8,
+            &mut size,
+        );
+
+        if result == 0 { Some(value) } else { None }
+    }
 
-            // Wait for the random interval before taking the next screenshot
-            // But check every second if recording is still active and not paused
-            for remaining_seconds in (1..=random_interval).rev() {
-                // Check if we should pause during the waiting period
-                let is_paused = RECORDING_PAUSED.load(Ordering::SeqCst);
-                if is_paused {
-                    // If paused, wait in smaller increments and check the pause status more frequently
-                    for _ in 0..10 { // Check every 100ms during pause instead of every second
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        // Re-check pause status - if unpaused, resume the main waiting loop
-                        if !RECORDING_PAUSED.load(Ordering::SeqCst) {
-                            break; // Break the inner loop to continue the outer waiting loop
-                        }
-                    }
-                    continue; // Continue the outer waiting loop with the same remaining_seconds count
-                }
+}
 
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+// Settings for capturing a screenshot when a large clipboard paste lands in a non-allowlisted app
+static CAPTURE_ON_LARGE_PASTE: AtomicBool = AtomicBool::new(false);
+static LARGE_PASTE_MIN_BYTES: AtomicU64 = AtomicU64::new(100 * 1024);
+// Longest target window title stored with a large paste, so the details fit their column
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+const LARGE_PASTE_TITLE_MAX_CHARS: usize = 200;
 
-                // Emit progress update about the remaining time to all windows
-                for (_window_label, window) in app_for_screenshot.webview_windows() {
-                    let _ = window.emit("recording-progress", format!("Next snapshot in: {}m {}s", remaining_seconds / 60, remaining_seconds % 60));
-                }
+// The keyboard hook can't be removed once installed, disabling only stops it from capturing
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+static PASTE_HOOK_STARTED: AtomicBool = AtomicBool::new(false);
 
-                let is_active = {
-                    let process_guard = COMBINED_RECORDING_PROCESS.lock().unwrap();
-                    process_guard.is_some()
-                };
+lazy_static! {
+    // Window title keywords of applications where large pastes are expected (lowercase)
+    static ref PASTE_ALLOWLIST: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+}
 
-                if !is_active {
-                    break; // Exit the waiting loop if recording stopped
-                }
-            }
+// Command to enable capturing a screenshot whenever a clipboard paste exceeds min_bytes.
+// The clipboard content itself is never read or logged, only its size. Pastes are noticed by their keyboard
+// shortcuts, Ctrl+V and Shift+Insert; pasting from a context or Edit menu isn't detected.
+#[tauri::command]
+fn set_capture_on_large_paste(app: tauri::AppHandle, enabled: bool, min_bytes: u64, allowlist: Option<Vec<String>>) -> Result<String, CommandError> {
+    if enabled && min_bytes == 0 {
+        return Err(CommandError::InvalidInput("Minimum paste size must be greater than 0 bytes".to_string()));
+    }
 
-            // Check again if still active after 15-minute wait
-            let is_active = {
-                let process_guard = COMBINED_RECORDING_PROCESS.lock().unwrap();
-                process_guard.is_some()
-            };
+    LARGE_PASTE_MIN_BYTES.store(min_bytes, Ordering::SeqCst);
+    CAPTURE_ON_LARGE_PASTE.store(enabled, Ordering::SeqCst);
 
-            if !is_active {
-                println!("Screenshot task terminating: recording process no longer active (end of loop)");
-                break; // Exit the main loop if recording stopped
+    if let Some(allowlist) = allowlist {
+        let mut allowlist_guard = PASTE_ALLOWLIST.lock().map_err(|e| e.to_string())?;
+        *allowlist_guard = allowlist.iter().map(|keyword| keyword.to_lowercase()).collect();
+    }
+
+    if !enabled {
+        return Ok("Capture on large paste disabled".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if !PASTE_HOOK_STARTED.swap(true, Ordering::SeqCst) {
+            let result = windows_utils::start_paste_hook(move || {
+                if CAPTURE_ON_LARGE_PASTE.load(Ordering::SeqCst) {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        handle_possible_large_paste(&app).await;
+                    });
+                }
+            });
+
+            if let Err(e) = result {
+                PASTE_HOOK_STARTED.store(false, Ordering::SeqCst);
+                CAPTURE_ON_LARGE_PASTE.store(false, Ordering::SeqCst);
+                return Err(CommandError::Internal(format!("Failed to watch paste shortcuts: {}", e)));
             }
         }
-    });
 
-    // Store the screenshot task handle in global state so we can cancel it later
+        Ok(format!("Capture on large paste enabled (threshold: {} bytes)", min_bytes))
+    }
+
+    #[cfg(not(target_os = "windows"))]
     {
-        let mut task_guard = SCREENSHOT_TASK_HANDLE.lock().unwrap();
-        *task_guard = Some(screenshot_task);
+        let _ = app;
+        CAPTURE_ON_LARGE_PASTE.store(false, Ordering::SeqCst);
+        Err(CommandError::Unsupported("Capture on large paste is only supported on Windows".to_string()))
     }
+}
 
-    // Update user activity timestamp when recording starts (user is actively starting monitoring)
-    if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
-        *last_activity = SystemTime::now();
+// Helper function to capture a screenshot if the paste just made is large and not into an allowlisted app
+#[cfg(target_os = "windows")]
+async fn handle_possible_large_paste(app: &tauri::AppHandle) {
+    let min_bytes = LARGE_PASTE_MIN_BYTES.load(Ordering::SeqCst);
+    let paste_bytes = match windows_utils::clipboard_data_size() {
+        Some(size) => size as u64,
+        None => return,
+    };
+
+    if paste_bytes < min_bytes {
+        return;
     }
 
-    // Record "recording started" activity in database (user is active when starting recording)
+    let window_title = windows_utils::foreground_window_title().unwrap_or_default();
+    let window_title_lower = window_title.to_lowercase();
+    let is_allowlisted = PASTE_ALLOWLIST
+        .lock()
+        .map(|allowlist| allowlist.iter().any(|keyword| window_title_lower.contains(keyword)))
+        .unwrap_or(false);
+
+    if is_allowlisted {
+        return;
+    }
+
+    // Record the paste as an activity (size and target only, never the content). An excluded window's title is
+    // as private as its content, so it is neither stored nor sent to the UI.
+    let window_title = redact_excluded_title(window_title);
     let user_id = {
         let user_id_guard = USER_ID.lock().unwrap();
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
-    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save recording start activity to database: {}", e);
+    let details = serde_json::json!({
+        "bytes": paste_bytes,
+        "window_title": window_title.chars().take(LARGE_PASTE_TITLE_MAX_CHARS).collect::<String>(),
+    });
+    if let Err(e) = database::save_activity_with_details_to_db(&user_id, "large_paste", &details.to_string()) {
+        error!("Failed to save large paste activity to database: {}", e);
     }
 
-    Ok(format!("Remote Worker: started: (Session ID: {})", session_id))
-}
-
-// Global state to track user activity
-lazy_static! {
-    static ref LAST_USER_ACTIVITY: Arc<Mutex<SystemTime>> = Arc::new(Mutex::new(SystemTime::now()));
-    static ref IDLE_DETECTION_TASK: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>> = Arc::new(Mutex::new(None));
-
-    // Global state to track excluded window titles
-    static ref EXCLUDED_WINDOWS: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![
-        "password".to_lowercase(),
-        "key".to_lowercase(),
-        "secret".to_lowercase(),
-        "private".to_lowercase(),
-        "personal".to_lowercase(),
-        "settings".to_lowercase(),
-        "options".to_lowercase(),
-    ]));
-
-    // Global state to track application network usage
-    static ref NETWORK_STATS: Arc<Mutex<NetworkUsage>> = Arc::new(Mutex::new(NetworkUsage {
-        total_bytes_downloaded: 0,
-        total_bytes_uploaded: 0,
-        last_bytes_downloaded: 0,
-        last_bytes_uploaded: 0,
-        last_updated: std::time::Instant::now(),
-    }));
+    let screenshot_urls = match capture_on_demand("paste").await {
+        Ok(remote_urls) => remote_urls,
+        Err(e) => {
+            error!("Failed to capture screenshot for large paste: {}", e);
+            Vec::new()
+        }
+    };
 
-    // Global state to track system network usage
-    static ref GLOBAL_NETWORK_STATS: Arc<Mutex<GlobalNetworkUsage>> = Arc::new(Mutex::new(GlobalNetworkUsage {
-        last_total_bytes_downloaded: 0,
-        last_total_bytes_uploaded: 0,
-        last_updated: std::time::Instant::now(),
+    let _ = app.emit("large-paste-detected", serde_json::json!({
+        "bytes": paste_bytes,
+        "window_title": window_title,
+        "screenshot_urls": screenshot_urls,
     }));
 }
 
-#[derive(Clone)]
-struct NetworkUsage {
-    total_bytes_downloaded: u64,
-    total_bytes_uploaded: u64,
-    last_bytes_downloaded: u64,
-    last_bytes_uploaded: u64,
-    last_updated: std::time::Instant,
-}
+// Whether a screenshot is taken when the session is locked, unlocked or logged in to
+static CAPTURE_ON_SESSION_EVENTS: AtomicBool = AtomicBool::new(false);
+// The OS watcher can't be stopped once started, disabling only stops it from capturing
+static SESSION_EVENT_WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
 
-#[derive(Clone)]
-struct GlobalNetworkUsage {
-    last_total_bytes_downloaded: u64,
-    last_total_bytes_uploaded: u64,
-    last_updated: std::time::Instant,
-}
+// The desktop takes a moment to be redrawn after unlocking or logging in, so those captures wait for it
+const SESSION_EVENT_CAPTURE_DELAY: Duration = Duration::from_secs(2);
 
-// Global variable to access excluded windows during capture
-#[cfg(target_os = "windows")]
-use EXCLUDED_WINDOWS as RUNNING_EXCLUDED_WINDOWS;
+// Command to take a screenshot whenever the session is locked, unlocked or logged in to.
+// The captures are masked like any other and their files are named session_lock_..., session_unlock_...
+// or session_login_..., so they can be told apart from scheduled screenshots.
+#[tauri::command]
+fn set_capture_on_session_events(app: tauri::AppHandle, enabled: bool) -> Result<String, CommandError> {
+    if enabled && !SESSION_EVENT_WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        let result = session_events::start(move |event| {
+            if CAPTURE_ON_SESSION_EVENTS.load(Ordering::SeqCst) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    capture_on_session_event(&app, event).await;
+                });
+            }
+        });
 
+        if let Err(e) = result {
+            SESSION_EVENT_WATCHER_STARTED.store(false, Ordering::SeqCst);
+            CAPTURE_ON_SESSION_EVENTS.store(false, Ordering::SeqCst);
+            return Err(CommandError::Internal(format!("Failed to watch session events: {}", e)));
+        }
+    }
 
+    CAPTURE_ON_SESSION_EVENTS.store(enabled, Ordering::SeqCst);
 
+    if enabled {
+        Ok("Capture on session lock, unlock and login enabled".to_string())
+    } else {
+        Ok("Capture on session events disabled".to_string())
+    }
+}
 
-#[cfg(target_os = "windows")]
-mod windows_utils {
-    use super::*;
+// Helper function to capture and upload a screenshot tagged with the session event that triggered it
+async fn capture_on_session_event(app: &tauri::AppHandle, event: session_events::SessionEvent) {
+    let event_name = event.as_str();
+    info!("Session {} detected, capturing a screenshot", event_name);
 
-    pub struct WindowInfo {
-        pub title: String,
-        pub rect: RECT,
+    if event != session_events::SessionEvent::Lock {
+        tokio::time::sleep(SESSION_EVENT_CAPTURE_DELAY).await;
     }
 
-    pub fn get_visible_windows() -> Result<Vec<WindowInfo>, Box<dyn std::error::Error>> {
-        let mut windows = Vec::new();
-        let windows_ptr = &mut windows as *mut Vec<WindowInfo>;
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+    if let Err(e) = database::save_user_activity_to_db(&user_id, &format!("session_{}", event_name), Some(0)) {
+        error!("Failed to save session {} activity to database: {}", event_name, e);
+    }
 
-        unsafe {
-            EnumWindows(Some(enum_windows_proc), windows_ptr as LPARAM);
+    let screenshot_urls = match capture_on_demand(&format!("session_{}", event_name)).await {
+        Ok(remote_urls) => remote_urls,
+        Err(e) => {
+            error!("Failed to capture screenshot for session {}: {}", event_name, e);
+            Vec::new()
         }
+    };
 
-        Ok(windows)
+    let _ = app.emit("session-event-captured", serde_json::json!({
+        "event": event_name,
+        "screenshot_urls": screenshot_urls,
+    }));
+}
+
+// Prefix marking an excluded windows entry as a regular expression instead of a substring keyword
+const EXCLUDED_WINDOW_REGEX_PREFIX: &str = "re:";
+
+lazy_static! {
+    // Compiled excluded window regexes, keyed by pattern, so each is only compiled once
+    static ref EXCLUDED_WINDOW_REGEX_CACHE: Arc<Mutex<HashMap<String, regex::Regex>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+// Helper function to compile (or fetch from the cache) the regex of a "re:" excluded windows entry.
+// Regexes are case-insensitive since they are matched against lowercase window titles.
+fn excluded_window_regex(pattern: &str) -> Result<regex::Regex, String> {
+    let mut cache = EXCLUDED_WINDOW_REGEX_CACHE.lock().map_err(|e| e.to_string())?;
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
     }
 
-    unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
-        let windows: &mut Vec<WindowInfo> = &mut *(lparam as *mut Vec<WindowInfo>);
+    let regex = regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid regular expression '{}': {}", pattern, e))?;
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
 
-        if IsWindowVisible(hwnd) != 0 && IsIconic(hwnd) == 0 {
-            let mut buf = [0u16; 256];
-            GetWindowTextW(hwnd, buf.as_mut_ptr(), 256);
+// Helper function to check a lowercase window title against the excluded windows list.
+// Entries starting with "re:" are regular expressions, all others are substring keywords.
+#[cfg(target_os = "windows")]
+fn window_title_matches_exclusions(window_title_lower: &str, excluded_windows: &[String]) -> bool {
+    matching_exclusion(window_title_lower, excluded_windows).is_some()
+}
 
-            let title = OsString::from_wide(&buf[..buf.iter().position(|&x| x == 0).unwrap_or(buf.len())])
-                .to_string_lossy()
-                .to_string();
+// Helper function to find the first excluded windows entry a lowercase window title matches
+fn matching_exclusion<'a>(window_title_lower: &str, excluded_windows: &'a [String]) -> Option<&'a String> {
+    excluded_windows.iter().find(|entry| match entry.strip_prefix(EXCLUDED_WINDOW_REGEX_PREFIX) {
+        Some(pattern) => excluded_window_regex(pattern)
+            .map(|regex| regex.is_match(window_title_lower))
+            .unwrap_or(false),
+        None => window_title_lower.contains(entry.as_str()),
+    })
+}
 
-            // Only include windows with non-empty titles
-            if !title.is_empty() {
-                let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
-                if GetWindowRect(hwnd, &mut rect) != 0 {  // GetWindowRect returns BOOL (non-zero for success)
-                    windows.push(WindowInfo {
-                        title,
-                        rect,
-                    });
+// A visible window and whether screenshots would currently mask it
+#[derive(serde::Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct MaskingPreviewEntry {
+    title: String,
+    rect: MaskingPreviewRect,
+    would_mask: bool,
+    matched_entry: Option<String>,
+}
+
+// Window rectangle on the virtual desktop
+#[derive(serde::Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct MaskingPreviewRect {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+// Command to show which visible windows the current excluded windows list would mask, without taking a screenshot
+#[tauri::command]
+fn preview_masking() -> Result<String, CommandError> {
+    #[cfg(target_os = "windows")]
+    {
+        let excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?.clone();
+        let windows = windows_utils::get_visible_windows()
+            .map_err(|e| format!("Failed to enumerate windows: {}", e))?;
+
+        let preview: Vec<MaskingPreviewEntry> = windows
+            .into_iter()
+            .map(|window| {
+                let matched_entry = matching_exclusion(&window.title.to_lowercase(), &excluded_windows).cloned();
+                MaskingPreviewEntry {
+                    title: window.title,
+                    rect: MaskingPreviewRect {
+                        left: window.rect.left,
+                        top: window.rect.top,
+                        right: window.rect.right,
+                        bottom: window.rect.bottom,
+                    },
+                    would_mask: matched_entry.is_some(),
+                    matched_entry,
                 }
-            }
-        }
+            })
+            .collect();
 
-        TRUE  // Continue enumeration
+        Ok(serde_json::to_string(&preview).map_err(|e| format!("Failed to serialize masking preview: {}", e))?)
     }
 
+    #[cfg(not(target_os = "windows"))]
+    {
+        Err(CommandError::Unsupported("Masking preview is only supported on Windows".to_string()))
+    }
+}
+
+// Helper function to normalize an excluded windows entry: keywords are lowercased,
+// "re:" entries are kept as written and must compile
+fn normalize_excluded_window_entry(entry: &str) -> Result<String, String> {
+    match entry.strip_prefix(EXCLUDED_WINDOW_REGEX_PREFIX) {
+        Some(pattern) => {
+            excluded_window_regex(pattern)?;
+            Ok(entry.to_string())
+        }
+        None => Ok(entry.to_lowercase()),
+    }
 }
 
-// Function to add excluded window keywords
+// Function to add excluded window keywords (or "re:" prefixed regular expressions)
 #[tauri::command]
-fn add_excluded_window(window_title: String) -> Result<String, String> {
+fn add_excluded_window(window_title: String) -> Result<String, CommandError> {
+    let lower_title = normalize_excluded_window_entry(&window_title).map_err(CommandError::InvalidInput)?;
     let mut excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
-    let lower_title = window_title.to_lowercase();
 
     if !excluded_windows.contains(&lower_title) {
         excluded_windows.push(lower_title);
+        persist_excluded_windows(&excluded_windows);
         Ok(format!("Added '{}' to excluded windows list", window_title))
     } else {
         Ok(format!("'{}' is already in the excluded windows list", window_title))
@@ -1041,125 +5025,463 @@ fn add_excluded_window(window_title: String) -> Result<String, String> {
 
 // Function to remove excluded window keywords
 #[tauri::command]
-fn remove_excluded_window(window_title: String) -> Result<String, String> {
+fn remove_excluded_window(window_title: String) -> Result<String, CommandError> {
     let mut excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
-    let lower_title = window_title.to_lowercase();
+    let lower_title = match window_title.strip_prefix(EXCLUDED_WINDOW_REGEX_PREFIX) {
+        Some(_) => window_title.clone(),
+        None => window_title.to_lowercase(),
+    };
 
     if excluded_windows.contains(&lower_title) {
         excluded_windows.retain(|x| *x != lower_title);
+        persist_excluded_windows(&excluded_windows);
         Ok(format!("Removed '{}' from excluded windows list", window_title))
     } else {
         Ok(format!("'{}' was not found in the excluded windows list", window_title))
     }
 }
 
+// Command to throw away all excluded windows edits: to_defaults restores the entries from
+// REMOTE_WORK_DEFAULT_EXCLUSIONS, otherwise the list is left empty
+#[tauri::command]
+fn reset_excluded_windows(to_defaults: bool) -> Result<String, CommandError> {
+    let entries = match to_defaults {
+        true => config::default_excluded_windows(),
+        false => Vec::new(),
+    };
+    let count = entries.len();
+    replace_excluded_windows(entries)?;
+
+    match to_defaults {
+        true => Ok(format!("Excluded windows reset to the {} default entries", count)),
+        false => Ok("Excluded windows list cleared".to_string()),
+    }
+}
+
+// Command to remove every entry from the excluded windows list
+#[tauri::command]
+fn clear_excluded_windows() -> Result<String, CommandError> {
+    reset_excluded_windows(false)
+}
+
+// Helper function to replace the excluded windows list and persist it.
+// The admin window stays hidden while it is open, whatever the new list is.
+fn replace_excluded_windows(entries: Vec<String>) -> Result<(), String> {
+    let entries = entries
+        .iter()
+        .map(|entry| normalize_excluded_window_entry(entry))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    let mut excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
+    let admin_window_excluded = excluded_windows.iter().any(|entry| entry == ADMIN_WINDOW_EXCLUSION);
+    *excluded_windows = entries;
+    persist_excluded_windows(&excluded_windows);
+    if admin_window_excluded && !excluded_windows.iter().any(|entry| entry == ADMIN_WINDOW_EXCLUSION) {
+        excluded_windows.push(ADMIN_WINDOW_EXCLUSION.to_string());
+    }
+
+    Ok(())
+}
+
+// Helper function to save the excluded windows list to the data directory so edits survive a restart,
+// also for clients that never reach the database
+fn persist_excluded_windows(excluded_windows: &[String]) {
+    // The admin window's entry only lasts while the window is open
+    let excluded_windows: Vec<String> = excluded_windows
+        .iter()
+        .filter(|entry| entry.as_str() != ADMIN_WINDOW_EXCLUSION)
+        .cloned()
+        .collect();
+
+    let path = config::excluded_windows_file_path(&get_data_directory());
+    if let Err(e) = config::save_excluded_windows(&path, &excluded_windows) {
+        error!("Failed to persist excluded windows: {}", e);
+    }
+}
+
+// Helper function to restore the persisted excluded windows list at startup, merged with the defaults.
+// Takes precedence over the list in the configuration file, which only changes on import.
+// Returns the entries that were ignored, each logged with the reason.
+fn load_excluded_windows_file() -> Vec<String> {
+    let path = config::excluded_windows_file_path(&get_data_directory());
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    let entries = match config::load_excluded_windows(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to load excluded windows, using defaults: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut excluded_windows = Vec::new();
+    let mut ignored = Vec::new();
+    for entry in entries {
+        match normalize_excluded_window_entry(&entry) {
+            // Older versions added the loose "admin" keyword while the admin window was open, which masked
+            // every window with "admin" anywhere in its title. It can't be told apart from one the user added.
+            Ok(normalized) if normalized == ADMIN_WINDOW_LABEL || normalized == ADMIN_WINDOW_EXCLUSION => {
+                warn!(
+                    "Ignoring excluded window entry '{}', which older versions added for the admin window; add \"re:admin\" to keep masking every window with \"admin\" in its title",
+                    entry
+                );
+                ignored.push(entry);
+            },
+            Ok(normalized) => excluded_windows.push(normalized),
+            // e.g. a hand-edited regex that doesn't compile
+            Err(e) => {
+                warn!("Ignoring excluded window entry '{}': {}", entry, e);
+                ignored.push(entry);
+            },
+        }
+    }
+
+    if let Ok(mut excluded_windows_guard) = EXCLUDED_WINDOWS.lock() {
+        *excluded_windows_guard = excluded_windows;
+    }
+    info!("Loaded excluded windows from {}", path.display());
+    ignored
+}
+
 // Function to get current excluded windows
 #[tauri::command]
-fn get_excluded_windows() -> Result<Vec<String>, String> {
+fn get_excluded_windows() -> Result<Vec<String>, CommandError> {
     let excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
     Ok(excluded_windows.clone())
 }
 
 // Function to create an admin window
 #[tauri::command]
-async fn create_admin_window(window: tauri::Window) -> Result<String, String> {
-    let app_handle = window.app_handle();
+async fn create_admin_window(window: tauri::Window) -> Result<String, CommandError> {
+    create_admin_window_internal(window.app_handle()).await.map_err(CommandError::Internal)
+}
+
+// Label and title of the admin window
+const ADMIN_WINDOW_LABEL: &str = "admin";
+const ADMIN_WINDOW_TITLE: &str = "Admin";
 
+// Excluded windows entry hiding the admin window while it is open. It matches the exact title only,
+// so unrelated windows such as "phpMyAdmin" or "Administrator: cmd" are still captured.
+const ADMIN_WINDOW_EXCLUSION: &str = "re:^admin$";
+
+// Internal function to create admin window that can be called from global shortcut
+async fn create_admin_window_internal(app_handle: &tauri::AppHandle) -> Result<String, String> {
     // Check if the window already exists
-    if app_handle.get_webview_window("admin").is_some() {
+    if app_handle.get_webview_window(ADMIN_WINDOW_LABEL).is_some() {
         return Ok("Admin window already exists".to_string());
     }
 
-    // Add "admin" to the excluded windows list to ensure it's blacked out in recordings
+    // Exclude the admin window so it's blacked out in recordings and screenshots
     {
         let mut excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
-        let admin_keyword = "admin".to_lowercase();
-        if !excluded_windows.contains(&admin_keyword) {
-            excluded_windows.push(admin_keyword);
+        if !excluded_windows.iter().any(|entry| entry == ADMIN_WINDOW_EXCLUSION) {
+            excluded_windows.push(ADMIN_WINDOW_EXCLUSION.to_string());
         }
     }
 
     // Create a new window with the title "Admin"
-    let _child_window = tauri::webview::WebviewWindowBuilder::new(
+    let admin_window = tauri::webview::WebviewWindowBuilder::new(
         app_handle,
-        "admin",
+        ADMIN_WINDOW_LABEL,
         tauri::WebviewUrl::App("src/admin.html".into())
     )
-    .title("Admin")
+    .title(ADMIN_WINDOW_TITLE)
     .inner_size(800.0, 600.0)
     .min_inner_size(600.0, 400.0)
     .resizable(true)
     .maximizable(false)  // Prevent maximization
     .center()
     .build()
-    .map_err(|e| format!("Failed to create admin window: {}", e))?;
+    .map_err(|e| {
+        remove_admin_window_exclusion();
+        format!("Failed to create admin window: {}", e)
+    })?;
+
+    // Once the admin window is gone there is nothing left to hide
+    admin_window.on_window_event(|event| {
+        if let tauri::WindowEvent::Destroyed = event {
+            remove_admin_window_exclusion();
+        }
+    });
+
+    Ok("Admin window created and added to exclusion list".to_string())
+}
+
+// Helper function to drop the admin window's entry from the excluded windows list
+fn remove_admin_window_exclusion() {
+    match EXCLUDED_WINDOWS.lock() {
+        Ok(mut excluded_windows) => excluded_windows.retain(|entry| entry != ADMIN_WINDOW_EXCLUSION),
+        Err(e) => error!("Failed to remove the admin window from the excluded windows: {}", e),
+    }
+}
+
+lazy_static! {
+    // Accelerators of the global shortcuts; an empty string disables the shortcut
+    static ref ADMIN_SHORTCUT: Arc<Mutex<String>> = Arc::new(Mutex::new("Ctrl+Shift+`".to_string()));
+    static ref STOP_SHORTCUT: Arc<Mutex<String>> = Arc::new(Mutex::new("Ctrl+Shift+F12".to_string()));
+}
+
+// Helper function to get the accelerator bound to a global shortcut action ("admin" or "stop")
+fn shortcut_accelerator(action: &str) -> Option<String> {
+    let shortcut = match action {
+        "admin" => &ADMIN_SHORTCUT,
+        "stop" => &STOP_SHORTCUT,
+        _ => return None,
+    };
+    shortcut.lock().ok().map(|accelerator| accelerator.clone()).filter(|accelerator| !accelerator.is_empty())
+}
+
+// Helper function to (re)register the global shortcuts from the current accelerators
+fn register_global_shortcuts(app: &tauri::AppHandle) -> Result<(), String> {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+    let global_shortcut = app.global_shortcut();
+    global_shortcut.unregister_all().map_err(|e| format!("Failed to unregister global shortcuts: {}", e))?;
+
+    for action in ["admin", "stop"] {
+        if let Some(accelerator) = shortcut_accelerator(action) {
+            let shortcut: Shortcut = accelerator
+                .parse()
+                .map_err(|e| format!("Invalid accelerator '{}': {}", accelerator, e))?;
+            global_shortcut
+                .register(shortcut)
+                .map_err(|e| format!("Failed to register global shortcut '{}': {}", accelerator, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Handler for all global shortcuts, dispatching on which action the pressed shortcut is bound to
+fn handle_global_shortcut(app: &tauri::AppHandle, shortcut: &tauri_plugin_global_shortcut::Shortcut) {
+    let action = ["admin", "stop"].into_iter().find(|action| {
+        shortcut_accelerator(action)
+            .and_then(|accelerator| accelerator.parse::<tauri_plugin_global_shortcut::Shortcut>().ok())
+            .map_or(false, |bound| &bound == shortcut)
+    });
+
+    let app_handle = app.clone();
+    match action {
+        Some("admin") => {
+            // Open admin window when the admin shortcut is pressed
+            tauri::async_runtime::spawn(async move {
+                let _ = create_admin_window_internal(&app_handle).await;
+            });
+        }
+        Some("stop") => {
+            // Panic stop: end every capture right away
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = stop_all_processes(app_handle).await {
+                    error!("Failed to stop all processes from global shortcut: {}", e);
+                }
+            });
+        }
+        _ => {}
+    }
+}
+
+// Command to rebind a global shortcut ("admin" opens the admin window, "stop" stops all processes).
+// An empty accelerator disables the shortcut. The choice is saved to the configuration file.
+#[tauri::command]
+fn set_global_shortcut(app: tauri::AppHandle, action: String, accelerator: String) -> Result<String, CommandError> {
+    let shortcut = match action.as_str() {
+        "admin" => &ADMIN_SHORTCUT,
+        "stop" => &STOP_SHORTCUT,
+        other => return Err(CommandError::InvalidInput(format!("Invalid shortcut action '{}': expected \"admin\" or \"stop\"", other))),
+    };
+
+    let accelerator = accelerator.trim().to_string();
+    if !accelerator.is_empty() {
+        let parsed: tauri_plugin_global_shortcut::Shortcut = accelerator
+            .parse()
+            .map_err(|e| CommandError::InvalidInput(format!("Invalid accelerator '{}': {}", accelerator, e)))?;
+
+        let other_action = if action == "admin" { "stop" } else { "admin" };
+        let conflicts = shortcut_accelerator(other_action)
+            .and_then(|other| other.parse::<tauri_plugin_global_shortcut::Shortcut>().ok())
+            .map_or(false, |other| other == parsed);
+        if conflicts {
+            return Err(CommandError::InvalidInput(format!("'{}' is already bound to the {} shortcut", accelerator, other_action)));
+        }
+    }
+
+    let previous = {
+        let mut shortcut_guard = shortcut.lock().map_err(|e| e.to_string())?;
+        std::mem::replace(&mut *shortcut_guard, accelerator.clone())
+    };
+
+    // Keep the old binding if the new one can't be registered (e.g. taken by another app)
+    if let Err(e) = register_global_shortcuts(&app) {
+        *shortcut.lock().map_err(|e| e.to_string())? = previous;
+        if let Err(restore_error) = register_global_shortcuts(&app) {
+            error!("Failed to restore global shortcuts: {}", restore_error);
+        }
+        return Err(CommandError::InvalidInput(e));
+    }
+
+    let config_path = config::config_file_path(&get_data_directory());
+    config::save_config(&config_path, &current_runtime_config())?;
+
+    if accelerator.is_empty() {
+        Ok(format!("Global {} shortcut disabled", action))
+    } else {
+        Ok(format!("Global {} shortcut set to {}", action, accelerator))
+    }
+}
+
+#[tauri::command]
+fn update_user_activity() {
+    let mut last_activity = LAST_USER_ACTIVITY.lock().unwrap();
+    *last_activity = SystemTime::now();
+}
+
+// Idle thresholds in seconds: inactivity past the warning threshold is "away", past the idle threshold "idle"
+static IDLE_WARNING_SECONDS: AtomicU64 = AtomicU64::new(30);
+static IDLE_THRESHOLD_SECONDS: AtomicU64 = AtomicU64::new(300);
+
+// Command to tune how quickly a user is reported as away and idle
+#[tauri::command]
+fn set_idle_thresholds(warning_seconds: u64, idle_seconds: u64) -> Result<String, CommandError> {
+    if warning_seconds == 0 || warning_seconds >= idle_seconds {
+        return Err(CommandError::InvalidInput("Warning threshold must be greater than 0 and less than the idle threshold".to_string()));
+    }
+
+    IDLE_WARNING_SECONDS.store(warning_seconds, Ordering::SeqCst);
+    IDLE_THRESHOLD_SECONDS.store(idle_seconds, Ordering::SeqCst);
 
-    Ok("Admin window created and added to exclusion list".to_string())
+    Ok(format!("Idle thresholds set: away after {} seconds, idle after {} seconds", warning_seconds, idle_seconds))
 }
 
-// Internal function to create admin window that can be called from global shortcut
-async fn create_admin_window_internal(app_handle: &tauri::AppHandle) -> Result<String, String> {
-    // Check if the window already exists
-    if app_handle.get_webview_window("admin").is_some() {
-        return Ok("Admin window already exists".to_string());
-    }
+// Seconds between idle checks of idle detection and system idle monitoring
+static IDLE_POLL_INTERVAL_SECONDS: AtomicU64 = AtomicU64::new(5);
 
-    // Add "admin" to the excluded windows list to ensure it's blacked out in recordings
-    {
-        let mut excluded_windows = EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?;
-        let admin_keyword = "admin".to_lowercase();
-        if !excluded_windows.contains(&admin_keyword) {
-            excluded_windows.push(admin_keyword);
-        }
+// Command to change how often idle time is checked, e.g. less often to save battery.
+// Running loops pick it up from their next check.
+#[tauri::command]
+fn set_idle_poll_interval(seconds: u64) -> Result<String, CommandError> {
+    if !(1..=300).contains(&seconds) {
+        return Err(CommandError::InvalidInput("Idle poll interval must be between 1 and 300 seconds".to_string()));
     }
 
-    // Create a new window with the title "Admin"
-    let _child_window = tauri::webview::WebviewWindowBuilder::new(
-        app_handle,
-        "admin",
-        tauri::WebviewUrl::App("src/admin.html".into())
-    )
-    .title("Admin")
-    .inner_size(800.0, 600.0)
-    .min_inner_size(600.0, 400.0)
-    .resizable(true)
-    .maximizable(false)  // Prevent maximization
-    .center()
-    .build()
-    .map_err(|e| format!("Failed to create admin window: {}", e))?;
-
-    Ok("Admin window created and added to exclusion list".to_string())
+    IDLE_POLL_INTERVAL_SECONDS.store(seconds, Ordering::SeqCst);
+    Ok(format!("Idle time will be checked every {} seconds", seconds))
 }
 
+// Command to change how much is written to the log, one of "off", "error", "warn", "info", "debug" or "trace".
+// Logs go to stderr and to a rotating file in the logs folder of the data directory.
 #[tauri::command]
-fn update_user_activity() {
-    let mut last_activity = LAST_USER_ACTIVITY.lock().unwrap();
-    *last_activity = SystemTime::now();
+fn set_log_level(level: String) -> Result<String, CommandError> {
+    let level = logging::set_level(&level).map_err(CommandError::InvalidInput)?;
+    Ok(format!(
+        "Logging at {} level to {}",
+        level.to_string().to_lowercase(),
+        logging::log_file_path(&get_data_directory().join("logs")).display()
+    ))
+}
+
+// Set while the user is on a break (see pause_all_processes). Idle detection keeps running but records
+// the break as one "break" span instead of tracking idle time.
+static ON_BREAK: AtomicBool = AtomicBool::new(false);
+
+// Helper function to wait until the next idle check
+async fn sleep_idle_poll_interval() {
+    tokio::time::sleep(Duration::from_secs(IDLE_POLL_INTERVAL_SECONDS.load(Ordering::SeqCst))).await;
+}
+
+// Helper function to map seconds of inactivity to "active", "away" or "idle"
+fn idle_status_for(inactive_seconds: u64) -> &'static str {
+    if inactive_seconds >= IDLE_THRESHOLD_SECONDS.load(Ordering::SeqCst) {
+        "idle"
+    } else if inactive_seconds >= IDLE_WARNING_SECONDS.load(Ordering::SeqCst) {
+        "away"
+    } else {
+        "active"
+    }
 }
 
 #[tauri::command]
-fn get_user_idle_status() -> Result<String, String> {
+fn get_user_idle_status() -> Result<String, CommandError> {
     let last_activity = LAST_USER_ACTIVITY.lock().map_err(|e| e.to_string())?;
 
     if let Ok(elapsed) = last_activity.elapsed() {
         let elapsed_seconds = elapsed.as_secs();
 
-        let status = if elapsed_seconds >= 300 {  // 5 minutes
-            "idle"
-        } else if elapsed_seconds >= 30 {  // 30 seconds
-            "idle"
-        } else {
-            "active"
-        };
+        let status = idle_status_for(elapsed_seconds);
 
         Ok(format!(r#"{{"status": "{}", "lastActivitySeconds": {}}}"#, status, elapsed_seconds))
     } else {
-        Err("Failed to calculate elapsed time".to_string())
+        Err(CommandError::Internal("Failed to calculate elapsed time".to_string()))
     }
 }
 
-#[tauri::command]
-fn get_system_idle_status() -> Result<String, String> {
+// Native macOS idle time via CoreGraphics, avoiding a process spawn per check
+#[cfg(all(target_os = "macos", not(feature = "ioreg-idle")))]
+mod macos_idle {
+    // kCGEventSourceStateCombinedSessionState
+    const COMBINED_SESSION_STATE: i32 = 0;
+    // kCGAnyInputEventType (~0): any keyboard, mouse or tablet event
+    const ANY_INPUT_EVENT_TYPE: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state_id: i32, event_type: u32) -> f64;
+    }
+
+    // Returns the seconds since the last user input event in the login session
+    pub fn seconds_since_last_input() -> f64 {
+        unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT_TYPE) }
+    }
+}
+
+// Wayland idle time over D-Bus. Wayland doesn't let clients see global input, so ask the compositor:
+// GNOME's Mutter idle monitor, or the freedesktop ScreenSaver interface (KDE Plasma and others)
+#[cfg(target_os = "linux")]
+mod wayland_idle {
+    use std::sync::Mutex;
+
+    lazy_static::lazy_static! {
+        // Session bus connection reused across checks; dropped after a failed call so the next check reconnects
+        static ref SESSION_BUS: Mutex<Option<zbus::blocking::Connection>> = Mutex::new(None);
+    }
+
+    // Whether the desktop session is running on Wayland
+    pub fn is_wayland_session() -> bool {
+        std::env::var("WAYLAND_DISPLAY").map(|display| !display.is_empty()).unwrap_or(false)
+    }
+
+    // Helper function to call an idle time method that returns milliseconds
+    fn idle_ms<T>(connection: &zbus::blocking::Connection, service: &str, path: &str, method: &str) -> Option<u64>
+    where
+        T: Into<u64> + for<'de> serde::Deserialize<'de> + zbus::zvariant::Type,
+    {
+        let reply = connection.call_method(Some(service), path, Some(service), method, &()).ok()?;
+        reply.body().deserialize::<T>().ok().map(Into::into)
+    }
+
+    // Returns the milliseconds since the last user input, or None if the compositor offers no idle interface
+    pub fn idle_milliseconds() -> Option<u64> {
+        let mut bus_guard = SESSION_BUS.lock().ok()?;
+        if bus_guard.is_none() {
+            *bus_guard = zbus::blocking::Connection::session().ok();
+        }
+        let connection = bus_guard.as_ref()?;
+
+        let idle = idle_ms::<u64>(connection, "org.gnome.Mutter.IdleMonitor", "/org/gnome/Mutter/IdleMonitor/Core", "GetIdletime")
+            // Implementations such as KDE's return milliseconds here despite the spec's seconds
+            .or_else(|| idle_ms::<u32>(connection, "org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver", "GetSessionIdleTime"));
+
+        if idle.is_none() {
+            *bus_guard = None;
+        }
+        idle
+    }
+}
+
+// Helper function to read how long the whole system has gone without keyboard/mouse input, in seconds.
+// Returns None where the OS idle source is unavailable.
+fn system_idle_seconds() -> Option<u64> {
     #[cfg(target_os = "windows")]
     {
         use std::mem;
@@ -1169,7 +5491,7 @@ fn get_system_idle_status() -> Result<String, String> {
             last_input_info.cbSize = mem::size_of::<LASTINPUTINFO>() as UINT;
 
             if GetLastInputInfo(&mut last_input_info) == 0 {
-                return Err("Failed to get last input info".to_string());
+                return None;
             }
 
             // Get the tick count when the last input occurred
@@ -1182,110 +5504,83 @@ fn get_system_idle_status() -> Result<String, String> {
             // GetTickCount returns a u32 that wraps around after about 49.7 days
             let idle_time_ms = (current_tick as u32).wrapping_sub(last_input_tick as u32);
 
-            let idle_time_seconds = idle_time_ms / 1000;
-
-            let status = if idle_time_seconds >= 300 {  // 5 minutes
-                "idle"
-            } else if idle_time_seconds >= 30 {  // 30 seconds
-                "idle"
-            } else {
-                "active"
-            };
-
-            Ok(format!(r#"{{"status": "{}", "idleTimeSeconds": {}}}"#, status, idle_time_seconds))
+            Some((idle_time_ms / 1000) as u64)
         }
     }
 
     #[cfg(target_os = "linux")]
     {
-        // Using x11rb to get idle time on Linux
         use std::env;
         use std::process::Command;
 
-        // Try using the X11 idle time if available
-        if let Ok(display) = env::var("DISPLAY") {
-            if !display.is_empty() {
-                // Use xprintidle to get the idle time in milliseconds
-                match Command::new("xprintidle").output() {
-                    Ok(output) => {
-                        if let Ok(idle_str) = String::from_utf8(output.stdout) {
-                            if let Ok(idle_ms) = idle_str.trim().parse::<u64>() {
-                                let idle_seconds = idle_ms / 1000;
-
-                                let status = if idle_seconds >= 300 {  // 5 minutes
-                                    "idle"
-                                } else if idle_seconds >= 30 {  // 30 seconds
-                                    "idle"
-                                } else {
-                                    "active"
-                                };
+        // xprintidle can't see input on Wayland, even through XWayland, so ask the compositor instead
+        if wayland_idle::is_wayland_session() {
+            return wayland_idle::idle_milliseconds().map(|idle_ms| idle_ms / 1000);
+        }
 
-                                return Ok(format!(r#"{{"status": "{}", "idleTimeSeconds": {}}}"#, status, idle_seconds));
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        // xprintidle command not available, fall back to other methods
-                        // For now, return active status
-                        return Ok(r#"{"status": "active", "idleTimeSeconds": 0}"#.to_string());
-                    }
-                }
-            }
+        // Try using the X11 idle time if available
+        if env::var("DISPLAY").map(|display| display.is_empty()).unwrap_or(true) {
+            return None;
         }
 
-        // If running without X11 or xprintidle failed, return active
-        Ok(r#"{"status": "active", "idleTimeSeconds": 0}"#.to_string())
+        // Use xprintidle to get the idle time in milliseconds
+        let output = Command::new("xprintidle").output().ok()?;
+        let idle_str = String::from_utf8(output.stdout).ok()?;
+        let idle_ms = idle_str.trim().parse::<u64>().ok()?;
+
+        Some(idle_ms / 1000)
     }
 
-    #[cfg(target_os = "macos")]
+    #[cfg(all(target_os = "macos", not(feature = "ioreg-idle")))]
+    {
+        Some(macos_idle::seconds_since_last_input().max(0.0) as u64)
+    }
+
+    #[cfg(all(target_os = "macos", feature = "ioreg-idle"))]
     {
         use std::process::Command;
 
         // Use 'ioreg' to get system idle time on macOS
-        match Command::new("ioreg")
+        let output = Command::new("ioreg")
             .args(&["-c", "IOHIDSystem"])
             .args(&["-r", "-k", "HIDIdleTime"])
-            .output() {
-            Ok(output) => {
-                if let Ok(ioreg_output) = String::from_utf8(output.stdout) {
-                    // Parse the idle time from ioreg output (in nanoseconds)
-                    if let Some(line) = ioreg_output.lines().find(|line| line.contains("HIDIdleTime")) {
-                        if let Some(nanoseconds_str) = line.split('=').nth(1) {
-                            if let Ok(nanoseconds) = nanoseconds_str.trim().parse::<u64>() {
-                                // Convert nanoseconds to seconds
-                                let idle_seconds = (nanoseconds / 1_000_000_000) as u64;
-
-                                let status = if idle_seconds >= 300 {  // 5 minutes
-                                    "idle"
-                                } else if idle_seconds >= 30 {  // 30 seconds
-                                    "idle"
-                                } else {
-                                    "active"
-                                };
+            .output()
+            .ok()?;
+        let ioreg_output = String::from_utf8(output.stdout).ok()?;
 
-                                return Ok(format!(r#"{{"status": "{}", "idleTimeSeconds": {}}}"#, status, idle_seconds));
-                            }
-                        }
-                    }
-                }
-            }
-            Err(_) => {
-                // ioreg command not available or failed
-            }
-        }
+        // Parse the idle time from ioreg output (in nanoseconds)
+        let line = ioreg_output.lines().find(|line| line.contains("HIDIdleTime"))?;
+        let nanoseconds = line.split('=').nth(1)?.trim().parse::<u64>().ok()?;
 
-        // Fallback for macOS if ioreg is not available
-        Ok(r#"{"status": "active", "idleTimeSeconds": 0}"#.to_string())
+        // Convert nanoseconds to seconds
+        Some(nanoseconds / 1_000_000_000)
+    }
+}
+
+#[tauri::command]
+fn get_system_idle_status() -> Result<String, CommandError> {
+    match system_idle_seconds() {
+        Some(idle_seconds) => {
+            let status = idle_status_for(idle_seconds);
+            Ok(format!(r#"{{"status": "{}", "idleTimeSeconds": {}}}"#, status, idle_seconds))
+        }
+        None if cfg!(target_os = "windows") => Err(CommandError::Internal("Failed to get last input info".to_string())),
+        // Without an OS idle source (no X11/xprintidle, no Wayland idle interface, no ioreg) report the user as active
+        None => Ok(r#"{"status": "active", "idleTimeSeconds": 0}"#.to_string()),
     }
 }
 
+// How often system-idle-status is emitted while the status stays the same, so a window that missed a
+// transition catches up. Transitions are emitted as soon as they're seen.
+const IDLE_STATUS_KEEPALIVE: Duration = Duration::from_secs(60);
+
 #[tauri::command]
-async fn start_system_idle_monitoring(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn start_system_idle_monitoring(app_handle: tauri::AppHandle) -> Result<String, CommandError> {
     // Check if idle monitoring is already running
     {
         let task_guard = IDLE_MONITORING_TASK.lock().map_err(|e| e.to_string())?;
         if task_guard.is_some() {
-            return Err("System idle monitoring is already running".to_string());
+            return Err(CommandError::AlreadyRunning("System idle monitoring is already running".to_string()));
         }
         drop(task_guard);
     }
@@ -1293,37 +5588,49 @@ async fn start_system_idle_monitoring(app_handle: tauri::AppHandle) -> Result<St
     // Start the idle monitoring task in the background
     let app_handle_clone = app_handle.clone();
     let task = tokio::spawn(async move {
+        // When the status was last emitted and whether it was an error; None emits the first status right away
+        let mut last_emitted: Option<(Instant, bool)> = None;
+
         loop {
             // Use a more reliable sleep that won't be affected by throttling
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            sleep_idle_poll_interval().await;
 
-            // Get all windows to emit the event
-            let windows = app_handle_clone.webview_windows();
+            let keepalive_due = last_emitted.map_or(true, |(emitted_at, _)| emitted_at.elapsed() >= IDLE_STATUS_KEEPALIVE);
+            let was_error = last_emitted.is_some_and(|(_, was_error)| was_error);
 
             match get_system_idle_status() {
                 Ok(status_json) => {
                     if let Ok(status) = serde_json::from_str::<serde_json::Value>(&status_json) {
                         let current_status = status["status"].as_str().unwrap_or("active");
 
-                        // Update cached status
-                        {
-                            if let Ok(mut cached_status) = LAST_IDLE_STATUS.lock() {
+                        // Update cached status, noting whether it changed
+                        let changed = match LAST_IDLE_STATUS.lock() {
+                            Ok(mut cached_status) => {
+                                let changed = *cached_status != current_status;
                                 *cached_status = current_status.to_string();
+                                changed
                             }
-                        }
+                            Err(_) => true,
+                        };
 
-                        // Emit idle status update to all windows
-                        for (_label, window) in windows {
-                            let _ = window.emit("system-idle-status", &status_json);
+                        // Emit idle status update to all windows on a transition or when the keepalive is due
+                        if changed || was_error || keepalive_due {
+                            for (_label, window) in app_handle_clone.webview_windows() {
+                                let _ = window.emit("system-idle-status", &status_json);
+                            }
+                            last_emitted = Some((Instant::now(), false));
                         }
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error getting system idle status: {}", e);
-                    // Emit error status
-                    let error_json = r#"{"status": "error", "idleTimeSeconds": 0}"#;
-                    for (_label, window) in windows {
-                        let _ = window.emit("system-idle-status", error_json);
+                    error!("Error getting system idle status: {}", e);
+                    // Emit error status once when the errors start, then with the keepalive
+                    if !was_error || keepalive_due {
+                        let error_json = r#"{"status": "error", "idleTimeSeconds": 0}"#;
+                        for (_label, window) in app_handle_clone.webview_windows() {
+                            let _ = window.emit("system-idle-status", error_json);
+                        }
+                        last_emitted = Some((Instant::now(), true));
                     }
                 }
             }
@@ -1340,13 +5647,13 @@ async fn start_system_idle_monitoring(app_handle: tauri::AppHandle) -> Result<St
 }
 
 #[tauri::command]
-fn get_cached_idle_status() -> Result<String, String> {
+fn get_cached_idle_status() -> Result<String, CommandError> {
     let cached_status = LAST_IDLE_STATUS.lock().map_err(|e| format!("Failed to acquire idle status lock: {}", e))?;
     Ok(cached_status.clone())
 }
 
 #[tauri::command]
-async fn stop_system_idle_monitoring() -> Result<String, String> {
+async fn stop_system_idle_monitoring() -> Result<String, CommandError> {
     let mut task_guard = IDLE_MONITORING_TASK.lock().map_err(|e| e.to_string())?;
 
     if let Some(task) = task_guard.take() {
@@ -1357,118 +5664,63 @@ async fn stop_system_idle_monitoring() -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn start_idle_detection(window: tauri::Window) -> Result<String, String> {
+async fn start_idle_detection(window: tauri::Window) -> Result<String, CommandError> {
     // Check if idle detection is already running
     {
         let task_guard = IDLE_DETECTION_TASK.lock().map_err(|e| e.to_string())?;
         if task_guard.is_some() {
-            return Err("Idle detection is already running".to_string());
+            return Err(CommandError::AlreadyRunning("Idle detection is already running".to_string()));
         }
         drop(task_guard);
     }
 
-    // Record "start" event in database (user is active when starting idle detection)
-    let user_id = {
-        let user_id_guard = USER_ID.lock().unwrap();
-        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-    };
-    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save idle detection start to database: {}", e);
-    }
+    // The user is active when starting idle detection; the span is recorded once the state changes
+    close_activity_span(SystemTime::now(), Some("active"));
 
     // Start the idle detection task
     let window_clone = window.clone();
-    let last_idle_save_time = Arc::new(Mutex::new(std::time::Instant::now()));
-    let last_idle_save_time_clone = last_idle_save_time.clone();
-
-    // Track the previous user state to detect transitions
-    let prev_state = Arc::new(Mutex::new("active".to_string()));
-    let prev_state_clone = prev_state.clone();
 
     let task = tokio::spawn(async move {
+        // Track the previous user state to detect transitions
+        let mut prev_state = "active";
+
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;  // Check every 5 seconds
+            sleep_idle_poll_interval().await; // Check every poll interval (5 seconds by default)
 
-            if let Ok(last_activity) = LAST_USER_ACTIVITY.lock() {
-                if let Ok(elapsed) = last_activity.elapsed() {
-                    let idle_duration_seconds = elapsed.as_secs() as i32;
+            // The break span is closed by resume_all_processes, which starts a new active span
+            if ON_BREAK.load(Ordering::SeqCst) {
+                prev_state = "active";
+                continue;
+            }
 
-                    let current_state = if elapsed.as_secs() < 30 { "active" } else { "idle" };
+            // Prefer the OS-wide idle time so input in other applications counts as activity,
+            // falling back to the app-local activity timestamp where the OS source is unavailable
+            let elapsed = match system_idle_seconds() {
+                Some(idle_seconds) => Some(std::time::Duration::from_secs(idle_seconds)),
+                None => LAST_USER_ACTIVITY.lock().ok().and_then(|last_activity| last_activity.elapsed().ok()),
+            };
 
-                    // Check if the state has changed since last check
-                    let state_changed = {
-                        let prev_state_guard = prev_state_clone.lock().unwrap();
-                        *prev_state_guard != current_state
-                    };
+            if let Some(elapsed) = elapsed {
+                let warning_seconds = IDLE_WARNING_SECONDS.load(Ordering::SeqCst);
+                let idle_seconds = IDLE_THRESHOLD_SECONDS.load(Ordering::SeqCst);
 
-                    if idle_duration_seconds >= 300 {  // If idle for 5+ minutes (300 seconds)
-                        window_clone.emit("user-idle", format!("User has been idle for {} minutes", idle_duration_seconds / 60)).unwrap();
-
-                        if state_changed {
-                            // Only log to database if state changed to idle
-                            let user_id = {
-                                let user_id_guard = USER_ID.lock().unwrap();
-                                user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-                            };
-
-                            // Only log idle activity if 30 minutes have passed since last idle recording
-                            if let Ok(last_save_guard) = last_idle_save_time_clone.lock() {
-                                if last_save_guard.elapsed().as_secs() >= 1800 { // 30 minutes = 1800 seconds
-                                    if let Err(e) = database::save_user_activity_to_db(&user_id, "idle", Some(idle_duration_seconds)) {
-                                        eprintln!("Failed to save user idle activity to database: {}", e);
-                                    }
-                                    // Update the last save time
-                                    let mut guard = last_idle_save_time_clone.lock().unwrap();
-                                    *guard = std::time::Instant::now();
-                                    drop(guard);
-                                }
-                            }
-                        }
-                    } else if elapsed.as_secs() >= 30 {  // If idle for 30+ seconds but less than 5 minutes
-                        window_clone.emit("user-idle", format!("User has been idle for {} seconds", elapsed.as_secs())).unwrap();
-
-                        if state_changed {
-                            // Only log to database if state changed to idle
-                            let user_id = {
-                                let user_id_guard = USER_ID.lock().unwrap();
-                                user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-                            };
-
-                            // Only log idle activity if 30 minutes have passed since last idle recording
-                            if let Ok(last_save_guard) = last_idle_save_time_clone.lock() {
-                                if last_save_guard.elapsed().as_secs() >= 1800 { // 30 minutes = 1800 seconds
-                                    if let Err(e) = database::save_user_activity_to_db(&user_id, "idle", Some(idle_duration_seconds)) {
-                                        eprintln!("Failed to save user idle activity to database: {}", e);
-                                    }
-                                    // Update the last save time
-                                    let mut guard = last_idle_save_time_clone.lock().unwrap();
-                                    *guard = std::time::Instant::now();
-                                    drop(guard);
-                                }
-                            }
-                        }
-                    } else {  // User is active
-                        window_clone.emit("user-active", format!("User active, last activity {} seconds ago", elapsed.as_secs())).unwrap();
-
-                        if state_changed {
-                            // User became active (state changed from idle to active)
-                            let user_id = {
-                                let user_id_guard = USER_ID.lock().unwrap();
-                                user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-                            };
-                            if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(elapsed.as_secs() as i32)) {
-                                eprintln!("Failed to save user active activity to database: {}", e);
-                            }
-                        }
-                    }
+                let current_state = if elapsed.as_secs() < warning_seconds { "active" } else { "idle" };
 
-                    // Update the previous state if it changed
-                    if state_changed {
-                        {
-                            let mut prev_state_guard = prev_state_clone.lock().unwrap();
-                            *prev_state_guard = current_state.to_string();
-                        }
-                    }
+                // Live status for the UI on every check
+                if elapsed.as_secs() >= idle_seconds {  // If idle past the idle threshold
+                    window_clone.emit("user-idle", events::ActivityEvent::UserIdle { idle_seconds: elapsed.as_secs() }).unwrap();
+                } else if elapsed.as_secs() >= warning_seconds {  // If past the warning threshold but not yet idle
+                    window_clone.emit("user-idle", events::ActivityEvent::IdleWarning { idle_seconds: elapsed.as_secs() }).unwrap();
+                } else {  // User is active
+                    window_clone.emit("user-active", events::ActivityEvent::UserActive { idle_seconds: elapsed.as_secs() }).unwrap();
+                }
+
+                // Only state changes reach the database, as one row for the span that just ended.
+                // The state actually changed at the last input, elapsed ago, not at this check.
+                if current_state != prev_state {
+                    let changed_at = SystemTime::now().checked_sub(elapsed).unwrap_or_else(SystemTime::now);
+                    close_activity_span(changed_at, Some(current_state));
+                    prev_state = current_state;
                 }
             }
         }
@@ -1479,27 +5731,56 @@ async fn start_idle_detection(window: tauri::Window) -> Result<String, String> {
         let mut task_guard = IDLE_DETECTION_TASK.lock().map_err(|e| e.to_string())?;
         *task_guard = Some(task);
     }
+    process_status_changed();
 
     Ok("Idle detection started".to_string())
 }
 
+lazy_static! {
+    // Activity state idle detection is timing ("active" or "idle") and when that state began
+    static ref ACTIVITY_SPAN: Arc<Mutex<Option<(String, SystemTime)>>> = Arc::new(Mutex::new(None));
+}
+
+// Helper function to end the current activity span at `end` and save it as one user_activity row,
+// then start a span in `next_state` from the same moment (None when idle detection stops)
+fn close_activity_span(end: SystemTime, next_state: Option<&str>) {
+    let finished = match ACTIVITY_SPAN.lock() {
+        Ok(mut span) => {
+            let finished = span.take();
+            *span = next_state.map(|state| (state.to_string(), end));
+            finished
+        }
+        Err(_) => return,
+    };
+
+    if let Some((activity_type, start)) = finished {
+        let unix_seconds = |time: SystemTime| time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let start_unix = unix_seconds(start);
+        let end_unix = unix_seconds(end).max(start_unix);
+
+        let user_id = {
+            let user_id_guard = USER_ID.lock().unwrap();
+            user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+        };
+        if let Err(e) = database::save_activity_span_to_db(&user_id, &activity_type, start_unix, end_unix) {
+            error!("Failed to save {} activity span to database: {}", activity_type, e);
+        }
+    }
+}
+
 #[tauri::command]
-async fn stop_idle_detection() -> Result<String, String> {
+async fn stop_idle_detection() -> Result<String, CommandError> {
     let mut task_guard = IDLE_DETECTION_TASK.lock().map_err(|e| e.to_string())?;
 
     if let Some(task) = task_guard.take() {
         // Cancel the task (it will stop when it tries to sleep next)
         task.abort();
     }
+    drop(task_guard);
+    process_status_changed();
 
-    // Record "stop" event in database (user is active when stopping idle detection)
-    let user_id = {
-        let user_id_guard = USER_ID.lock().unwrap();
-        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-    };
-    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save idle detection stop to database: {}", e);
-    }
+    // Record the span that was still open when idle detection stopped
+    close_activity_span(SystemTime::now(), None);
 
     Ok("Idle detection stopped".to_string())
 }
@@ -1515,7 +5796,7 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
             ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip", "ffmpeg.exe");
 
         // Create HTTP client with timeout
-        let client = reqwest::Client::builder()
+        let client = http_client_builder()?
             .timeout(std::time::Duration::from_secs(300)) // 5 minute timeout
             .build()?;
 
@@ -1527,7 +5808,7 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
         let mut downloaded_successfully = false;
 
         for attempt in 1..=3 {
-            println!("Downloading FFmpeg from: {} (attempt {}/{})", download_url, attempt, 3);
+            info!("Downloading FFmpeg from: {} (attempt {}/{})", download_url, attempt, 3);
 
             match client.get(download_url).send().await {
                 Ok(response) => {
@@ -1535,7 +5816,7 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
                     let total_size = response.content_length().unwrap_or(0);
 
                     if total_size > 0 {
-                        window.emit("recording-progress", format!("Starting FFmpeg download ({:.2} MB)...", total_size as f64 / (1024.0 * 1024.0))).unwrap();
+                        window.emit("recording-progress", events::ProgressEvent::FfmpegDownloadStarted { total_bytes: total_size }).unwrap();
                     }
 
                     // Create a temporary file to save the download
@@ -1549,10 +5830,11 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
                         let chunk = chunk_result?;
                         temp_file.write_all(&chunk).await?;
                         downloaded += chunk.len() as u64;
+                        record_own_network_bytes(chunk.len() as u64, 0);
 
                         if total_size > 0 {
                             let progress = (downloaded as f64 / total_size as f64) * 100.0;
-                            window.emit("recording-progress", format!("Downloading FFmpeg: {:.1}%...", progress)).unwrap();
+                            window.emit("recording-progress", events::ProgressEvent::FfmpegDownloadProgress { percent: progress }).unwrap();
                         }
                     }
 
@@ -1562,7 +5844,7 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
                     break; // Download successful, exit retry loop
                 }
                 Err(e) => {
-                    eprintln!("Download attempt {} failed: {}", attempt, e);
+                    warn!("Download attempt {} failed: {}", attempt, e);
                     last_error = Some(e);
                     if attempt < 3 {
                         // Wait before retrying (but not after the last attempt)
@@ -1632,144 +5914,261 @@ async fn download_ffmpeg_bundled(window: tauri::Window, ffmpeg_path: &std::path:
 }
 
 async fn download_ffmpeg_bundled_app(app: &tauri::AppHandle, ffmpeg_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    use std::fs::File;
-    use futures_util::StreamExt;
-
     // Determine the appropriate FFmpeg build based on the platform
     #[cfg(target_os = "windows")]
     {
         let (download_url, executable_name): (&str, &str) =
             ("https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip", "ffmpeg.exe");
 
-        // Create HTTP client with timeout
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(300)) // 5 minute timeout
-            .build()?;
+        let temp_zip_path = ffmpeg_path.parent().unwrap().join("ffmpeg_temp.zip");
+        download_ffmpeg_archive(app, download_url, &temp_zip_path).await?;
+        extract_ffmpeg_from_zip(&temp_zip_path, executable_name, ffmpeg_path)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // Static builds are architecture specific: pick the Apple Silicon or Intel one
+        let download_url = match std::env::consts::ARCH {
+            "aarch64" => "https://www.osxexperts.net/ffmpeg7arm.zip",
+            "x86_64" => "https://evermeet.cx/ffmpeg/getrelease/ffmpeg/zip",
+            arch => return Err(format!("No FFmpeg build available for macOS on {}", arch).into()),
+        };
 
-        // Create file paths outside the loop
         let temp_zip_path = ffmpeg_path.parent().unwrap().join("ffmpeg_temp.zip");
+        download_ffmpeg_archive(app, download_url, &temp_zip_path).await?;
+        extract_ffmpeg_from_zip(&temp_zip_path, "ffmpeg", ffmpeg_path)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // johnvansickle.com static builds, published as .tar.xz per architecture
+        let download_url = match std::env::consts::ARCH {
+            "x86_64" => "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-amd64-static.tar.xz",
+            "aarch64" => "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-arm64-static.tar.xz",
+            arch => return Err(format!("No FFmpeg build available for Linux on {}", arch).into()),
+        };
 
-        // Attempt download with retry logic
-        let mut last_error = None;
-        let mut downloaded_successfully = false;
+        let temp_archive_path = ffmpeg_path.parent().unwrap().join("ffmpeg_temp.tar.xz");
+        download_ffmpeg_archive(app, download_url, &temp_archive_path).await?;
+        extract_ffmpeg_from_tar_xz(&temp_archive_path, ffmpeg_path)
+    }
+}
 
-        for attempt in 1..=3 {
-            println!("Downloading FFmpeg from: {} (attempt {}/{})", download_url, attempt, 3);
+// Set while an FFmpeg archive is being downloaded, and to ask that download to stop
+static FFMPEG_DOWNLOAD_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static FFMPEG_DOWNLOAD_CANCELLED: AtomicBool = AtomicBool::new(false);
 
-            match client.get(download_url).send().await {
-                Ok(response) => {
-                    // Download was successful, proceed with saving
-                    let total_size = response.content_length().unwrap_or(0);
+// Error returned by the FFmpeg download when it was cancelled
+const FFMPEG_DOWNLOAD_CANCELLED_MESSAGE: &str = "FFmpeg download cancelled";
 
-                    if total_size > 0 {
-                        for (_window_label, window) in app.webview_windows() {
-                            let _ = window.emit("recording-progress", format!("Starting FFmpeg download ({:.2} MB)...", total_size as f64 / (1024.0 * 1024.0)));
-                        }
-                    }
+lazy_static! {
+    // Wakes a download waiting on a stalled connection so cancellation takes effect right away
+    static ref FFMPEG_DOWNLOAD_CANCEL_NOTIFY: tokio::sync::Notify = tokio::sync::Notify::new();
+}
 
-                    // Create a temporary file to save the download
-                    let mut temp_file = tokio::fs::File::create(&temp_zip_path).await?;
+// Command to abort a running FFmpeg download; the partial archive is deleted
+#[tauri::command]
+fn cancel_ffmpeg_download() -> Result<String, CommandError> {
+    if !FFMPEG_DOWNLOAD_IN_PROGRESS.load(Ordering::SeqCst) {
+        return Err(CommandError::NotRunning("No FFmpeg download in progress".to_string()));
+    }
 
-                    // Stream the download with progress tracking
-                    let mut downloaded: u64 = 0;
-                    let mut stream = response.bytes_stream();
+    FFMPEG_DOWNLOAD_CANCELLED.store(true, Ordering::SeqCst);
+    FFMPEG_DOWNLOAD_CANCEL_NOTIFY.notify_waiters();
+    Ok("Cancelling FFmpeg download".to_string())
+}
 
-                    while let Some(chunk_result) = stream.next().await {
-                        let chunk = chunk_result?;
-                        temp_file.write_all(&chunk).await?;
-                        downloaded += chunk.len() as u64;
+// Helper function to download an FFmpeg archive, deleting the partial file if it fails or is cancelled
+async fn download_ffmpeg_archive(app: &tauri::AppHandle, download_url: &str, archive_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    FFMPEG_DOWNLOAD_CANCELLED.store(false, Ordering::SeqCst);
+    FFMPEG_DOWNLOAD_IN_PROGRESS.store(true, Ordering::SeqCst);
 
-                        if total_size > 0 {
-                            let progress = (downloaded as f64 / total_size as f64) * 100.0;
-                            for (_window_label, window) in app.webview_windows() {
-                                let _ = window.emit("recording-progress", format!("Downloading FFmpeg: {:.1}%...", progress));
-                            }
-                        }
-                    }
+    let result = download_ffmpeg_archive_with_retries(app, download_url, archive_path).await;
 
-                    temp_file.flush().await?;
-                    drop(temp_file); // Close the file before processing
-                    downloaded_successfully = true;
-                    break; // Download successful, exit retry loop
-                }
-                Err(e) => {
-                    eprintln!("Download attempt {} failed: {}", attempt, e);
-                    last_error = Some(e);
-                    if attempt < 3 {
-                        // Wait before retrying (but not after the last attempt)
-                        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                    }
-                }
-            }
+    FFMPEG_DOWNLOAD_IN_PROGRESS.store(false, Ordering::SeqCst);
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(archive_path).await;
+    }
+
+    result
+}
+
+// FFmpeg download attempts, waiting 2s and then 4s between them
+const FFMPEG_DOWNLOAD_ATTEMPTS: u32 = 3;
+const FFMPEG_DOWNLOAD_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(2);
+
+// Helper function to download an FFmpeg archive with retries, reporting progress to all windows.
+// Only failing to connect is retried; a download that breaks off partway fails right away.
+async fn download_ffmpeg_archive_with_retries(app: &tauri::AppHandle, download_url: &str, archive_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Create HTTP client with timeout
+    let client = http_client_builder()?
+        .timeout(std::time::Duration::from_secs(300)) // 5 minute timeout
+        .build()?;
+    let client = &client;
+
+    retry_with_backoff("FFmpeg download", FFMPEG_DOWNLOAD_ATTEMPTS, FFMPEG_DOWNLOAD_RETRY_INITIAL_DELAY, |attempt| async move {
+        if FFMPEG_DOWNLOAD_CANCELLED.load(Ordering::SeqCst) {
+            return Err(RetryError::Permanent(FFMPEG_DOWNLOAD_CANCELLED_MESSAGE.into()));
         }
 
-        // If all attempts failed, return the last error
-        if !downloaded_successfully {
-            if let Some(error) = last_error {
-                return Err(error.into());
-            } else {
-                return Err("Download failed for unknown reasons".into());
-            }
+        info!("Downloading FFmpeg from: {} (attempt {}/{})", download_url, attempt, FFMPEG_DOWNLOAD_ATTEMPTS);
+
+        let response = client
+            .get(download_url)
+            .send()
+            .await
+            .map_err(|e| RetryError::Transient(e.into()))?;
+        save_ffmpeg_download(app, response, archive_path).await.map_err(RetryError::Permanent)
+    })
+    .await
+    .map_err(RetryError::into_inner)
+}
+
+// Helper function to stream a successful FFmpeg download response to the archive file, reporting progress to all windows
+async fn save_ffmpeg_download(app: &tauri::AppHandle, response: reqwest::Response, archive_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use futures_util::StreamExt;
+
+    let total_size = response.content_length().unwrap_or(0);
+
+    if total_size > 0 {
+        for (_window_label, window) in app.webview_windows() {
+            let _ = window.emit("recording-progress", events::ProgressEvent::FfmpegDownloadStarted { total_bytes: total_size });
         }
+    }
 
-        // Extract the ZIP file
-        let zip_file = std::fs::File::open(&temp_zip_path)?;
-        let mut archive = zip::ZipArchive::new(zip_file)?;
+    // Create a temporary file to save the download
+    let mut temp_file = tokio::fs::File::create(archive_path).await?;
 
-        // Look for the executable in the archive
-        let mut found_executable = false;
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            let filename = file.name().to_lowercase();
+    // Stream the download with progress tracking
+    let mut downloaded: u64 = 0;
+    let mut stream = response.bytes_stream();
 
-            // Look for the executable file
-            if filename.ends_with(executable_name) {
-                // Extract this specific file to the target location
-                let mut output_file = File::create(ffmpeg_path)?;
-                std::io::copy(&mut file, &mut output_file)?;
-                output_file.sync_all()?;
+    loop {
+        // Register for the cancel notification before checking the flag so no cancel is missed
+        let cancel_notified = FFMPEG_DOWNLOAD_CANCEL_NOTIFY.notified();
+        if FFMPEG_DOWNLOAD_CANCELLED.load(Ordering::SeqCst) {
+            return Err(FFMPEG_DOWNLOAD_CANCELLED_MESSAGE.into());
+        }
 
-                // Make it executable on Unix systems (not needed on Windows)
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    std::fs::set_permissions(ffmpeg_path, std::fs::Permissions::from_mode(0o755))?;
-                }
+        let chunk_result = tokio::select! {
+            chunk_result = stream.next() => chunk_result,
+            _ = cancel_notified => return Err(FFMPEG_DOWNLOAD_CANCELLED_MESSAGE.into()),
+        };
+        let chunk = match chunk_result {
+            Some(chunk_result) => chunk_result?,
+            None => break,
+        };
+        temp_file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        record_own_network_bytes(chunk.len() as u64, 0);
+
+        if total_size > 0 {
+            let progress = (downloaded as f64 / total_size as f64) * 100.0;
+            for (_window_label, window) in app.webview_windows() {
+                let _ = window.emit("recording-progress", events::ProgressEvent::FfmpegDownloadProgress { percent: progress });
+            }
+        }
+    }
 
-                found_executable = true;
-                break;
+    temp_file.flush().await?;
+    Ok(()) // Download successful, the file is closed when it goes out of scope
+}
+
+// Helper function to extract the FFmpeg executable from a downloaded ZIP archive and delete the archive
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn extract_ffmpeg_from_zip(zip_path: &std::path::Path, executable_name: &str, ffmpeg_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let zip_file = std::fs::File::open(zip_path)?;
+    let mut archive = zip::ZipArchive::new(zip_file)?;
+
+    // Look for the executable in the archive
+    let mut found_executable = false;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let filename = file.name().to_lowercase();
+
+        // Match the file name exactly so e.g. "ffmpeg" doesn't pick up "ffmpeg.1" or a directory
+        let is_executable = filename == executable_name || filename.ends_with(&format!("/{}", executable_name));
+        if file.is_file() && is_executable {
+            // Extract this specific file to the target location
+            let mut output_file = std::fs::File::create(ffmpeg_path)?;
+            std::io::copy(&mut file, &mut output_file)?;
+            output_file.sync_all()?;
+
+            // Make it executable on Unix systems (not needed on Windows)
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(ffmpeg_path, std::fs::Permissions::from_mode(0o755))?;
             }
+
+            found_executable = true;
+            break;
         }
+    }
+
+    // Delete the temporary ZIP file
+    std::fs::remove_file(zip_path)?;
+
+    if found_executable {
+        Ok(())
+    } else {
+        Err(format!("{} not found in the downloaded archive", executable_name).into())
+    }
+}
+
+// Helper function to extract the FFmpeg executable from a downloaded .tar.xz archive and delete the archive.
+// The static builds keep the binary in a versioned top-level directory (e.g. ffmpeg-7.0.2-amd64-static/ffmpeg).
+#[cfg(target_os = "linux")]
+fn extract_ffmpeg_from_tar_xz(archive_path: &std::path::Path, ffmpeg_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let archive_file = std::fs::File::open(archive_path)?;
+    let mut archive = tar::Archive::new(xz2::read::XzDecoder::new(archive_file));
 
-        // Delete the temporary ZIP file
-        std::fs::remove_file(&temp_zip_path)?;
+    let mut found_executable = false;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
 
-        if found_executable {
-            Ok(())
-        } else {
-            Err(format!("{} not found in the downloaded archive", executable_name).into())
+        let is_executable = entry
+            .path()?
+            .file_name()
+            .map(|name| name == "ffmpeg")
+            .unwrap_or(false);
+
+        if is_executable {
+            // Extract this specific file to the target location
+            let mut output_file = std::fs::File::create(ffmpeg_path)?;
+            std::io::copy(&mut entry, &mut output_file)?;
+            output_file.sync_all()?;
+            std::fs::set_permissions(ffmpeg_path, std::fs::Permissions::from_mode(0o755))?;
+
+            found_executable = true;
+            break;
         }
     }
-    #[cfg(target_os = "macos")]
-    {
-        // For macOS, we would need a different URL
-        return Err("macOS automatic FFmpeg download not implemented".into());
-    }
-    #[cfg(target_os = "linux")]
-    {
-        // For Linux, we would need a different URL
-        return Err("Linux automatic FFmpeg download not implemented".into());
+
+    // Delete the temporary archive
+    std::fs::remove_file(archive_path)?;
+
+    if found_executable {
+        Ok(())
+    } else {
+        Err("ffmpeg not found in the downloaded archive".into())
     }
 }
 
 
 // Helper function to concatenate video segments
-async fn concatenate_segments() -> Result<String, String> {
+async fn concatenate_segments() -> Result<String, CommandError> {
+    // A segment must not be swapped for its re-encoded version while it is being concatenated
+    cancel_segment_compression().await;
+
     let session_id = {
         let session_guard = RECORDING_SESSION_ID.lock().unwrap();
         match session_guard.as_ref() {
             Some(id) => id.clone(),
-            None => return Err("No recording session ID found".to_string()),
+            None => return Err(CommandError::NotRunning("No recording session ID found".to_string())),
         }
     };
 
@@ -1777,7 +6176,7 @@ async fn concatenate_segments() -> Result<String, String> {
         let path_guard = RECORDING_BASE_PATH.lock().unwrap();
         match path_guard.as_ref() {
             Some(path) => path.clone(),
-            None => return Err("No recording path found".to_string()),
+            None => return Err(CommandError::NotRunning("No recording path found".to_string())),
         }
     };
 
@@ -1790,8 +6189,16 @@ async fn concatenate_segments() -> Result<String, String> {
         return Ok("No segments to concatenate".to_string());
     }
 
+    concatenate_segment_files(&base_path, &session_id, &segments)
+}
+
+// Helper function to join a session's segment files into recording_<session>.mkv in base_path.
+// The segments are deleted once the final video has been written.
+fn concatenate_segment_files(base_path: &str, session_id: &str, segments: &[String]) -> Result<String, CommandError> {
+    validate_session_id(session_id).map_err(CommandError::InvalidInput)?;
+
     // Create the final output file path
-    let final_path = std::path::Path::new(&base_path).join(format!("recording_{}.mkv", session_id));
+    let final_path = std::path::Path::new(base_path).join(format!("recording_{}.mkv", session_id));
     let final_path_str = final_path.to_string_lossy().to_string();
 
     if segments.len() == 1 {
@@ -1802,11 +6209,11 @@ async fn concatenate_segments() -> Result<String, String> {
     }
 
     // Create a temporary file listing all segments
-    let concat_list_path = std::path::Path::new(&base_path).join("temp_concat_list.txt");
+    let concat_list_path = std::path::Path::new(base_path).join("temp_concat_list.txt");
     let mut concat_file_content = String::new();
 
-    for segment in &segments {
-        concat_file_content.push_str(&format!("file '{}'\n", segment.replace("'", "'\\'\"'\"\\''"))); // Properly escape for FFmpeg
+    for segment in segments {
+        concat_file_content.push_str(&concat_list_entry(segment)?);
     }
 
     std::fs::write(&concat_list_path, &concat_file_content)
@@ -1817,7 +6224,7 @@ async fn concatenate_segments() -> Result<String, String> {
         .ok()
         .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
         .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("ffmpeg.exe");
+        .join(BUNDLED_FFMPEG_NAME);
 
     let ffmpeg_cmd = if ffmpeg_path.exists() {
         ffmpeg_path.to_string_lossy().to_string()
@@ -1840,90 +6247,148 @@ async fn concatenate_segments() -> Result<String, String> {
         } {
             Ok(_) => "ffmpeg".to_string(),
             Err(_) => {
-                return Err("FFmpeg is required for concatenation but not found".to_string());
+                return Err(CommandError::FfmpegMissing("FFmpeg is required for concatenation but not found".to_string()));
             }
         }
     };
 
-    // Run FFmpeg to concatenate the segments
-    let output = {
-        #[cfg(target_os = "windows")]
-        {
-            std::process::Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "concat",
-                    "-safe", "0",
-                    "-i", &concat_list_path.to_string_lossy(),
-                    "-c", "copy",
-                    "-y", // Overwrite output file
-                    &final_path_str
-                ])
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW flag
-                .output()
-        }
-        #[cfg(not(target_os = "windows"))]
-        {
-            std::process::Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "concat",
-                    "-safe", "0",
-                    "-i", &concat_list_path.to_string_lossy(),
-                    "-c", "copy",
-                    "-y", // Overwrite output file
-                    &final_path_str
-                ])
-                .output()
+    // Try a fast stream copy first; it only works when every segment was encoded with the same parameters
+    let copy_output = run_ffmpeg_concat(&ffmpeg_cmd, &concat_list_path, &final_path_str, &["-c", "copy"]);
+
+    let result = match copy_output {
+        Ok(result) if result.status.success() => {
+            info!("Concatenated {} segments with stream copy", segments.len());
+            Ok(())
         }
+        Ok(result) if is_concat_codec_mismatch(&String::from_utf8_lossy(&result.stderr)) => {
+            // Segments differ (e.g. quality or encoder changed between them), so encode everything once more
+            warn!("Stream copy failed on mismatched segment codecs, re-encoding {} segments with libx264", segments.len());
+            match run_ffmpeg_concat(&ffmpeg_cmd, &concat_list_path, &final_path_str, &CONCAT_REENCODE_ARGS) {
+                Ok(result) if result.status.success() => {
+                    info!("Concatenated {} segments with re-encode", segments.len());
+                    Ok(())
+                }
+                Ok(result) => Err(format!("FFmpeg concatenation with re-encode failed: {}", String::from_utf8_lossy(&result.stderr))),
+                Err(e) => Err(format!("Error running FFmpeg concatenation: {}", e)),
+            }
+        }
+        Ok(result) => Err(format!("FFmpeg concatenation failed: {}", String::from_utf8_lossy(&result.stderr))),
+        Err(e) => Err(format!("Error running FFmpeg concatenation: {}", e)),
     };
 
     // Clean up the temporary list file
     let _ = std::fs::remove_file(&concat_list_path);
 
-    match output {
-        Ok(result) => {
-            if result.status.success() {
-                // Remove individual segment files after successful concatenation
-                for segment in &segments {
-                    let _ = std::fs::remove_file(segment);
-                }
-                Ok(format!("Segments concatenated successfully: {}", final_path_str))
-            } else {
-                let error_msg = String::from_utf8_lossy(&result.stderr);
-                Err(format!("FFmpeg concatenation failed: {}", error_msg))
+    match result {
+        Ok(()) => {
+            // Remove individual segment files only once the final video has been written
+            for segment in segments {
+                let _ = std::fs::remove_file(segment);
             }
+            Ok(format!("Segments concatenated successfully: {}", final_path_str))
         }
-        Err(e) => Err(format!("Error running FFmpeg concatenation: {}", e)),
+        Err(e) => {
+            // Don't leave a partial final file next to the segments it was made from
+            let _ = std::fs::remove_file(&final_path_str);
+            Err(CommandError::Internal(e))
+        }
+    }
+}
+
+// Encoder settings used when segments can't be joined with a stream copy
+const CONCAT_REENCODE_ARGS: [&str; 8] = ["-vcodec", "libx264", "-crf", "28", "-preset", "veryfast", "-pix_fmt", "yuv420p"];
+
+// FFmpeg errors that mean the segments can't be stream copied into one file
+const CONCAT_CODEC_MISMATCH_MARKERS: [&str; 6] = [
+    "codec parameters",
+    "codec mismatch",
+    "non monotonically increasing dts",
+    "non-monotonous dts",
+    "incompatible",
+    "invalid data found",
+];
+
+// Helper function to format a path as a line of an FFmpeg concat list. Inside the single quotes the demuxer
+// takes everything literally except the quote itself, which is written as '\'' (close, escaped quote, reopen).
+fn concat_list_entry(path: &str) -> Result<String, String> {
+    // A line break would end the directive early, and can't be escaped
+    if path.contains(['\n', '\r']) {
+        return Err(format!("Segment path {:?} contains a line break", path));
+    }
+
+    Ok(format!("file '{}'\n", path.replace('\'', "'\\''")))
+}
+
+// Helper function to tell whether a failed stream copy concat was caused by segments with different codecs
+fn is_concat_codec_mismatch(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    CONCAT_CODEC_MISMATCH_MARKERS.iter().any(|marker| stderr.contains(marker))
+}
+
+// Helper function to run the FFmpeg concat demuxer over a segment list with the given codec arguments
+fn run_ffmpeg_concat(ffmpeg_cmd: &str, concat_list_path: &std::path::Path, output_path: &str, codec_args: &[&str]) -> std::io::Result<std::process::Output> {
+    let mut command = Command::new(ffmpeg_cmd);
+    command
+        .args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(concat_list_path)
+        .args(codec_args)
+        .args(["-y", output_path]); // Overwrite output file
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    command.output()
+}
+
+// Helper function to read a media file's duration in whole seconds with ffprobe.
+// Returns None if ffprobe is not available (bundled next to the app or on PATH) or can't read the file.
+fn probe_duration_seconds(path: &std::path::Path) -> Option<i32> {
+    let bundled_ffprobe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .map(|dir| dir.join(if cfg!(target_os = "windows") { "ffprobe.exe" } else { "ffprobe" }))
+        .filter(|path| path.exists());
+    let ffprobe_cmd = bundled_ffprobe
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| "ffprobe".to_string());
+
+    let mut command = std::process::Command::new(&ffprobe_cmd);
+    command.args(&[
+        "-v", "error",
+        "-show_entries", "format=duration",
+        "-of", "default=noprint_wrappers=1:nokey=1",
+    ]);
+    command.arg(path);
+
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
     }
+
+    let duration: f64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(duration.round() as i32)
 }
 
 #[tauri::command]
-async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, String> {
-    println!("Stop combined recording called");
+async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, CommandError> {
+    debug!("Stop combined recording called");
 
     // Stop the current recording process if it's running
     {
         let mut process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
 
         if process_guard.is_some() {
-            // Kill the recording process
+            // Stop the recording process, letting FFmpeg finalize the file
             if let Some(child) = process_guard.as_mut() {
-                println!("Attempting to kill recording process");
-                match child.kill() {
-                    Ok(_) => {
-                        println!("Successfully sent kill signal to process");
-                        // Wait for the process to finish
-                        match child.wait() {
-                            Ok(exit_status) => println!("Process exited with: {}", exit_status),
-                            Err(e) => println!("Error waiting for process: {}", e),
-                        }
-                    },
-                    Err(e) => println!("Error killing process: {}", e),
-                }
+                debug!("Attempting to stop recording process");
+                stop_ffmpeg_gracefully(child);
             }
 
             // Clear the recording process
             *process_guard = None;
-            println!("Cleared recording process");
+            debug!("Cleared recording process");
         }
     } // process_guard is dropped here
 
@@ -1932,34 +6397,52 @@ async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, String
         let mut task_guard = SCREENSHOT_TASK_HANDLE.lock().unwrap();
         if let Some(task) = task_guard.take() {
             task.abort();
-            println!("Screenshot task cancelled");
+            debug!("Screenshot task cancelled");
         }
     }
 
-    // Get session ID before clearing it to use for database updates
+    // Get session ID and recording directory before clearing them to use for database updates
     let session_id_clone = {
         let session_guard = RECORDING_SESSION_ID.lock().unwrap();
         session_guard.clone()
     };
+    let base_path_clone = {
+        let path_guard = RECORDING_BASE_PATH.lock().unwrap();
+        path_guard.clone()
+    };
 
-    // Concatenate all segments into the final video
-    let concat_result = concatenate_segments().await;
+    // Concatenate all segments into the final video; HLS segments were uploaded as they were recorded,
+    // so only the playlist is left to finish
+    let hls_output = RECORDING_SESSION_HLS.load(Ordering::SeqCst);
+    let concat_result = match (hls_output, session_id_clone.as_deref(), base_path_clone.as_deref()) {
+        (true, Some(session_id), Some(base_path)) => finalize_hls_recording(session_id, base_path).await,
+        (true, _, _) => Err(CommandError::NotRunning("No recording session found".to_string())),
+        (false, _, _) => concatenate_segments().await,
+    };
 
     // Reset the paused state
     RECORDING_PAUSED.store(false, Ordering::SeqCst);
 
     // If concatenation was successful, update the recording entry in the database
-    // with the final file location and size
-    if concat_result.is_ok() {
-        if let Some(session_id) = session_id_clone {
+    // with the final file location, duration and size
+    if concat_result.is_ok() && !hls_output {
+        if let (Some(session_id), Some(base_path)) = (session_id_clone, base_path_clone) {
+            let final_filename = format!("recording_{}.mkv", session_id);
+            let final_path = std::path::Path::new(&base_path).join(&final_filename);
+            let file_size = std::fs::metadata(&final_path).ok().map(|metadata| metadata.len() as i64);
+            let duration_seconds = probe_duration_seconds(&final_path);
+            if duration_seconds.is_none() {
+                warn!("Could not determine recording duration (is ffprobe installed?), saving size only");
+            }
+
             if let Err(e) = database::update_recording_metadata_in_db(
                 &session_id,
-                Some(&format!("recording_{}.mkv", session_id)),
-                None, // We could pass the final file path if available
-                None, // Duration would require calculating from segments
-                None  // File size would need to be calculated after concatenation
+                Some(&final_filename),
+                Some(&final_path.to_string_lossy()),
+                duration_seconds,
+                file_size
             ) {
-                eprintln!("Failed to update recording metadata in database: {}", e);
+                error!("Failed to update recording metadata in database: {}", e);
             }
         }
     }
@@ -1969,6 +6452,9 @@ async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, String
         let mut session_guard = RECORDING_SESSION_ID.lock().unwrap();
         *session_guard = None;
     }
+    if let Ok(mut started_at) = RECORDING_STARTED_AT.lock() {
+        *started_at = None;
+    }
 
     {
         let mut path_guard = RECORDING_BASE_PATH.lock().unwrap();
@@ -1986,8 +6472,9 @@ async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, String
     // Update the UI in all windows
     // Emit to each active window
     for (_window_label, window) in app.webview_windows() {
-        let _ = window.emit("recording-finished", "Combined recording stopped. Video file is being finalized, please wait a few seconds before opening.");
+        let _ = window.emit("recording-finished", "Combined recording stopped. Video file has been saved.");
     }
+    process_status_changed();
 
     // Update user activity timestamp when recording stops (user is actively managing the system)
     if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
@@ -2000,108 +6487,670 @@ async fn stop_combined_recording(app: tauri::AppHandle) -> Result<String, String
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
     if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save recording stop activity to database: {}", e);
+        error!("Failed to save recording stop activity to database: {}", e);
+    }
+
+    match concat_result {
+        Ok(msg) => Ok(format!("Combined recording stopped and {}", msg)),
+        Err(e) => Err(e.context("Recording stopped but concatenation failed")),
+    }
+}
+
+lazy_static! {
+    // What closing the main window does: "minimize" hides it to the tray, "exit" quits the app
+    static ref CLOSE_BEHAVIOR: Arc<Mutex<String>> = Arc::new(Mutex::new("minimize".to_string()));
+}
+
+// Set once closing the window has started quitting, so repeated close clicks don't stop everything twice
+static CLOSE_EXIT_STARTED: AtomicBool = AtomicBool::new(false);
+
+// Command to choose what the main window's close button does: "minimize" keeps the app running in the tray,
+// "exit" stops all monitoring and quits
+#[tauri::command]
+fn set_close_behavior(behavior: String) -> Result<String, CommandError> {
+    let behavior = behavior.to_lowercase();
+    if behavior != "minimize" && behavior != "exit" {
+        return Err(CommandError::InvalidInput(format!("Invalid close behavior '{}': expected \"minimize\" or \"exit\"", behavior)));
+    }
+
+    *CLOSE_BEHAVIOR.lock().map_err(|e| e.to_string())? = behavior.clone();
+    Ok(format!("Close behavior set to {}", behavior))
+}
+
+// Helper function to handle a close request of the main window, whose closing has already been prevented:
+// hide it, or with the "exit" close behavior stop all processes and quit
+fn handle_main_window_close(window: &tauri::WebviewWindow) {
+    let exit = CLOSE_BEHAVIOR.lock().map(|behavior| behavior.as_str() == "exit").unwrap_or(false);
+    if !exit {
+        let _ = window.hide();
+        return;
+    }
+
+    if CLOSE_EXIT_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    info!("Main window closed, quitting");
+    let app_handle = window.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = stop_all_processes(app_handle.clone()).await {
+            error!("Failed to stop all processes before quitting: {}", e);
+        }
+        app_handle.exit(0);
+    });
+}
+
+// New command to stop all processes at once
+#[tauri::command]
+async fn stop_all_processes(app: tauri::AppHandle) -> Result<String, CommandError> {
+    info!("Stopping all processes");
+
+    // Stop screenshotting (not async)
+    let screenshot_result = stop_screenshotting(None);
+
+    // Stop idle detection (async)
+    let idle_result = stop_idle_detection().await;
+
+    // Stop combined recording (async)
+    let recording_result = stop_combined_recording(app.clone()).await;
+
+    // End a running timelapse early; it still uploads what it captured
+    if TIMELAPSE_RUNNING.load(Ordering::SeqCst) {
+        let _ = stop_timelapse();
+    }
+
+    // Stopping also ends a break, there is nothing left to resume
+    ON_BREAK.store(false, Ordering::SeqCst);
+
+    // Collect results
+    let mut results = Vec::new();
+    match screenshot_result {
+        Ok(msg) => results.push(format!("Screenshotting: {}", msg)),
+        Err(e) => results.push(format!("Screenshotting error: {}", e)),
+    }
+
+    match idle_result {
+        Ok(msg) => results.push(format!("Idle detection: {}", msg)),
+        Err(e) => results.push(format!("Idle detection error: {}", e)),
+    }
+
+    match recording_result {
+        Ok(msg) => results.push(format!("Recording: {}", msg)),
+        Err(e) => results.push(format!("Recording error: {}", e)),
+    }
+
+    // Update user activity timestamp when all processes are stopped (user is actively managing the system)
+    if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
+        *last_activity = SystemTime::now();
+    }
+
+    // Record "all processes stopped" activity in database (user is active when stopping all processes)
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+    };
+    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
+        error!("Failed to save all processes stopped activity to database: {}", e);
+    }
+
+    // Notify all windows that all processes have stopped
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("all-processes-stopped", "All processes have been stopped");
+
+        // Also emit individual stop events for compatibility with existing UI elements
+        let _ = window.emit("recording-finished", "All processes stopped");
+        let _ = window.emit("screenshotting-finished", "Screenshotting stopped");
+
+        // Additionally, if idle detection was stopped, emit an active status
+        // since the user is no longer being monitored for inactivity
+        let _ = window.emit("user-active", events::ActivityEvent::MonitoringStopped);
+    }
+    process_status_changed();
+
+    Ok(format!("Stopped all processes:\n{}", results.join("\n")))
+}
+
+// Command to pause everything for a break: the recording and screenshotting are paused and idle detection
+// records the time as a break instead of idle time. Resume with resume_all_processes.
+#[tauri::command]
+async fn pause_all_processes(app: tauri::AppHandle) -> Result<String, CommandError> {
+    if ON_BREAK.load(Ordering::SeqCst) {
+        return Err(CommandError::AlreadyRunning("Already on a break".to_string()));
+    }
+
+    let status = current_process_status()?;
+    if !status.recording && !status.screenshotting && !status.idle_detection {
+        return Err(CommandError::NotRunning("Nothing is running to pause".to_string()));
+    }
+
+    info!("Pausing all processes for a break");
+    let mut results = Vec::new();
+
+    // A recording that is already paused stays paused
+    if status.recording && !RECORDING_PAUSED.load(Ordering::SeqCst) {
+        match pause_combined_recording(app.clone(), None).await {
+            Ok(msg) => results.push(format!("Recording: {}", msg)),
+            Err(e) => results.push(format!("Recording error: {}", e)),
+        }
+    }
+
+    if status.screenshotting {
+        match pause_screenshotting(app.clone()) {
+            Ok(msg) => results.push(format!("Screenshotting: {}", msg)),
+            Err(e) => results.push(format!("Screenshotting error: {}", e)),
+        }
+    }
+
+    ON_BREAK.store(true, Ordering::SeqCst);
+    if status.idle_detection {
+        close_activity_span(SystemTime::now(), Some("break"));
+        results.push("Idle detection: on break".to_string());
+    }
+
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("all-processes-paused", "All processes have been paused");
+        let _ = window.emit("user-active", events::ActivityEvent::OnBreak);
+    }
+    process_status_changed();
+
+    Ok(format!("Paused all processes:\n{}", results.join("\n")))
+}
+
+// Command to end a break started with pause_all_processes, resuming the paused recording and screenshotting
+#[tauri::command]
+async fn resume_all_processes(app: tauri::AppHandle) -> Result<String, CommandError> {
+    let recording_paused = RECORDING_PAUSED.load(Ordering::SeqCst);
+    let screenshotting_paused = {
+        let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+        tasks.values().any(|status| *status == TaskStatus::Paused)
+    };
+    if !ON_BREAK.load(Ordering::SeqCst) && !recording_paused && !screenshotting_paused {
+        return Err(CommandError::NotRunning("Nothing is paused to resume".to_string()));
+    }
+
+    info!("Resuming all processes");
+    let mut results = Vec::new();
+
+    if recording_paused {
+        match resume_combined_recording(app.clone()).await {
+            Ok(msg) => results.push(format!("Recording: {}", msg)),
+            Err(e) => results.push(format!("Recording error: {}", e)),
+        }
+    }
+
+    if screenshotting_paused {
+        match resume_screenshotting(app.clone()) {
+            Ok(msg) => results.push(format!("Screenshotting: {}", msg)),
+            Err(e) => results.push(format!("Screenshotting error: {}", e)),
+        }
+    }
+
+    // The user is back, so idle time is counted from now rather than from before the break
+    if ON_BREAK.swap(false, Ordering::SeqCst) {
+        if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
+            *last_activity = SystemTime::now();
+        }
+        if current_process_status()?.idle_detection {
+            close_activity_span(SystemTime::now(), Some("active"));
+            results.push("Idle detection: break ended".to_string());
+        }
+    }
+
+    for (_window_label, window) in app.webview_windows() {
+        let _ = window.emit("all-processes-resumed", "All processes have been resumed");
+    }
+    process_status_changed();
+
+    Ok(format!("Resumed all processes:\n{}", results.join("\n")))
+}
+
+// Which monitoring processes are running
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct ProcessStatus {
+    recording: bool,
+    screenshotting: bool,
+    idle_detection: bool,
+    on_break: bool,
+}
+
+// Helper function to check which monitoring processes are running
+fn current_process_status() -> Result<ProcessStatus, String> {
+    // Check if recording is in progress; a paused recording has no FFmpeg process but its session goes on
+    let recording_in_progress = {
+        let process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
+        process_guard.is_some() || RECORDING_PAUSED.load(Ordering::SeqCst)
+    };
+
+    // Check if screenshotting is in progress
+    let screenshotting_in_progress = {
+        let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
+        tasks.values().any(|status| match status {
+            TaskStatus::Active | TaskStatus::Paused | TaskStatus::Stopping => true,
+            TaskStatus::Stopped => false,
+        })
+    };
+
+    // Check if idle detection is running
+    let idle_detection_running = {
+        let task_guard = IDLE_DETECTION_TASK.lock().map_err(|e| e.to_string())?;
+        task_guard.is_some()
+    };
+
+    Ok(ProcessStatus {
+        recording: recording_in_progress,
+        screenshotting: screenshotting_in_progress,
+        idle_detection: idle_detection_running,
+        on_break: ON_BREAK.load(Ordering::SeqCst),
+    })
+}
+
+// Command to get the current status of all processes
+#[tauri::command]
+async fn get_process_status() -> Result<String, CommandError> {
+    let status = current_process_status()?;
+
+    let status_msg = format!(
+        "Recording: {}, Screenshotting: {}, Idle Detection: {}",
+        if status.recording { "Active" } else { "Inactive" },
+        if status.screenshotting { "Active" } else { "Inactive" },
+        if status.idle_detection { "Active" } else { "Inactive" }
+    );
+
+    Ok(status_msg)
+}
+
+// Command to get the current status of all processes as JSON: { recording, screenshotting, idle_detection }
+#[tauri::command]
+async fn get_process_status_json() -> Result<String, CommandError> {
+    Ok(serde_json::to_string(&current_process_status()?).map_err(|e| format!("Failed to serialize process status: {}", e))?)
+}
+
+// Helper function to save what is running so an unclean shutdown can be detected at the next launch
+fn persist_monitoring_state() {
+    let status = match current_process_status() {
+        Ok(status) => status,
+        Err(e) => {
+            error!("Failed to get process status for the monitoring state: {}", e);
+            return;
+        }
+    };
+
+    let state = session_state::MonitoringState {
+        recording: status.recording,
+        screenshotting: status.screenshotting,
+        idle_detection: status.idle_detection,
+        recording_session_id: RECORDING_SESSION_ID.lock().ok().and_then(|session_id| session_id.clone()),
+        screenshot_session_ids: RUNNING_TASKS
+            .lock()
+            .map(|tasks| {
+                tasks
+                    .iter()
+                    .filter(|(_, status)| **status != TaskStatus::Stopped)
+                    .map(|((session_id, _), _)| session_id.clone())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        updated_at: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    };
+
+    if let Err(e) = session_state::save(&session_state::state_file_path(&get_data_directory()), &state) {
+        error!("Failed to save monitoring state: {}", e);
+    }
+}
+
+lazy_static! {
+    // Monitoring found still running from the previous launch, kept for get_interrupted_session
+    static ref INTERRUPTED_SESSION: Arc<Mutex<Option<serde_json::Value>>> = Arc::new(Mutex::new(None));
+}
+
+// Helper function to check at launch whether the previous run exited with monitoring still running.
+// Its orphaned recording segments are joined into the session's recording, then "session-interrupted"
+// is emitted with what was running so the frontend can resume it, passing auto_resumed to start_combined_recording
+// so the startup delay is applied.
+fn check_interrupted_session(app: &tauri::AppHandle) {
+    let state_path = session_state::state_file_path(&get_data_directory());
+    let state = match session_state::load(&state_path) {
+        Some(state) if state.is_active() => state,
+        _ => return,
+    };
+
+    // This run starts with nothing running; it saves its own state once monitoring starts
+    if let Err(e) = session_state::clear(&state_path) {
+        error!("{}", e);
+    }
+
+    warn!("Previous run exited with monitoring still running (last saved at {})", state.updated_at);
+    let app = app.clone();
+    // Concatenation can take a while, so it runs off the setup thread
+    tauri::async_runtime::spawn_blocking(move || {
+        let recovered_recording = match &state.recording_session_id {
+            Some(session_id) => match recover_interrupted_recording(session_id) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    error!("Failed to recover interrupted recording {}: {}", session_id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut payload = serde_json::to_value(&state).unwrap_or_else(|_| serde_json::json!({}));
+        if let Some(object) = payload.as_object_mut() {
+            object.insert("recoveredRecording".to_string(), serde_json::json!(recovered_recording));
+        }
+
+        if let Ok(mut interrupted) = INTERRUPTED_SESSION.lock() {
+            *interrupted = Some(payload.clone());
+        }
+        let _ = app.emit("session-interrupted", payload);
+    });
+}
+
+// Helper function to list the segment files a session has on disk, one per segment in segment order
+fn segments_on_disk(session_id: &str) -> Vec<String> {
+    // Prefer an original segment over a re-encoded copy that may have been cut off mid-write
+    let mut segments: Vec<(i32, PathBuf)> = Vec::new();
+    for (segment_number, path) in local_segment_files(session_id) {
+        match segments.last_mut() {
+            Some((last_number, last_path)) if *last_number == segment_number => {
+                if last_path.to_string_lossy().ends_with("_compressed.mkv") {
+                    *last_path = path;
+                }
+            }
+            _ => segments.push((segment_number, path)),
+        }
     }
 
-    match concat_result {
-        Ok(msg) => Ok(format!("Combined recording stopped and {} Video file is being finalized, please wait a few seconds before opening.", msg)),
-        Err(e) => Err(format!("Recording stopped but concatenation failed: {}", e)),
+    segments.into_iter().map(|(_, path)| path.to_string_lossy().to_string()).collect()
+}
+
+// Helper function to join the segments an interrupted recording left on disk into its final recording
+fn recover_interrupted_recording(session_id: &str) -> Result<String, String> {
+    validate_session_id(session_id)?;
+    let recordings_dir = get_data_directory().join("recordings");
+
+    let segments = segments_on_disk(session_id);
+    if segments.is_empty() {
+        return Ok("No orphaned segments to concatenate".to_string());
     }
+
+    concatenate_segment_files(&recordings_dir.to_string_lossy(), session_id, &segments).map_err(String::from)
 }
 
-// New command to stop all processes at once
+// Command to rebuild a session's segment list from the recording_<session>_seg_*.mkv files on disk, for when the
+// app was killed between stopping one segment and starting the next and the list references files that don't
+// exist or misses ones that do. For the recording in progress the list is replaced, so stopping it joins every
+// segment; an earlier session's segments are joined into its recording right away.
 #[tauri::command]
-async fn stop_all_processes(app: tauri::AppHandle) -> Result<String, String> {
-    println!("Stopping all processes");
+async fn repair_recording_segments(session_id: String) -> Result<String, CommandError> {
+    // Session IDs become part of the segment file names
+    validate_session_id(&session_id).map_err(CommandError::InvalidInput)?;
+
+    let is_active = RECORDING_SESSION_ID.lock().map_err(|e| e.to_string())?.as_deref() == Some(session_id.as_str());
+    if is_active && RECORDING_SESSION_HLS.load(Ordering::SeqCst) {
+        return Err(CommandError::InvalidInput(
+            "HLS recordings are uploaded as they are written and have no segment files to repair".to_string(),
+        ));
+    }
 
-    // Stop screenshotting (not async)
-    let screenshot_result = stop_screenshotting();
+    if !is_active {
+        // An empty file is a segment FFmpeg never got to write, which would make the join fail
+        let segments: Vec<String> = segments_on_disk(&session_id)
+            .into_iter()
+            .filter(|segment| std::fs::metadata(segment).map(|metadata| metadata.len() > 0).unwrap_or(false))
+            .collect();
+        if segments.is_empty() {
+            return Err(CommandError::NotFound(format!("No segment files found for session {}", session_id)));
+        }
 
-    // Stop idle detection (async)
-    let idle_result = stop_idle_detection().await;
+        let recordings_dir = get_data_directory().join("recordings").to_string_lossy().to_string();
+        let (session, to_join) = (session_id.clone(), segments.clone());
+        let result = tauri::async_runtime::spawn_blocking(move || concatenate_segment_files(&recordings_dir, &session, &to_join))
+            .await
+            .map_err(|e| e.to_string())??;
+        info!("Repaired recording session {} from {} segment(s): {}", session_id, segments.len(), result);
+
+        return Ok(serde_json::json!({
+            "session_id": session_id,
+            "active": false,
+            "segments": segments,
+            "removed": Vec::<String>::new(),
+            "added": segments,
+            "result": result,
+        })
+        .to_string());
+    }
 
-    // Stop combined recording (async)
-    let recording_result = stop_combined_recording(app.clone()).await;
+    // A segment must not be swapped for its re-encoded version while the list is rebuilt
+    cancel_segment_compression().await;
+
+    let (segments, removed, added) = {
+        let mut files_guard = RECORDING_SEGMENT_FILES.lock().map_err(|e| e.to_string())?;
+        // The last segment in the list is the one FFmpeg is writing, which may not have any data yet; any other
+        // empty file is a segment FFmpeg never got to write
+        let current_segment = files_guard.back().cloned();
+        let segments: Vec<String> = segments_on_disk(&session_id)
+            .into_iter()
+            .filter(|segment| {
+                current_segment.as_ref() == Some(segment)
+                    || std::fs::metadata(segment).map(|metadata| metadata.len() > 0).unwrap_or(false)
+            })
+            .collect();
+        let removed: Vec<String> = files_guard.iter().filter(|segment| !segments.contains(segment)).cloned().collect();
+        let added: Vec<String> = segments.iter().filter(|segment| !files_guard.contains(segment)).cloned().collect();
+        *files_guard = segments.iter().cloned().collect();
+        (segments, removed, added)
+    };
+    info!(
+        "Repaired segment list of recording session {}: {} segment(s), {} missing removed, {} found added",
+        session_id,
+        segments.len(),
+        removed.len(),
+        added.len()
+    );
 
-    // Collect results
-    let mut results = Vec::new();
-    match screenshot_result {
-        Ok(msg) => results.push(format!("Screenshotting: {}", msg)),
-        Err(e) => results.push(format!("Screenshotting error: {}", e)),
-    }
+    Ok(serde_json::json!({
+        "session_id": session_id,
+        "active": true,
+        "segments": segments,
+        "removed": removed,
+        "added": added,
+        "result": null,
+    })
+    .to_string())
+}
 
-    match idle_result {
-        Ok(msg) => results.push(format!("Idle detection: {}", msg)),
-        Err(e) => results.push(format!("Idle detection error: {}", e)),
+// Command to get the session found interrupted at launch, for a frontend that missed "session-interrupted"
+#[tauri::command]
+fn get_interrupted_session() -> Result<String, CommandError> {
+    match INTERRUPTED_SESSION.lock().map_err(|e| e.to_string())?.as_ref() {
+        Some(payload) => Ok(payload.to_string()),
+        None => Ok("null".to_string()),
     }
+}
 
-    match recording_result {
-        Ok(msg) => results.push(format!("Recording: {}", msg)),
-        Err(e) => results.push(format!("Recording error: {}", e)),
+// Status entry at the top of the tray menu, set up once the tray is built
+static TRAY_STATUS_ITEM: once_cell::sync::OnceCell<tauri::menu::MenuItem<tauri::Wry>> = once_cell::sync::OnceCell::new();
+
+// Helper function to get the tray status label for the running processes
+fn tray_status_label(status: &ProcessStatus) -> &'static str {
+    if status.on_break {
+        "❚❚ On break"
+    } else if status.recording && RECORDING_PAUSED.load(Ordering::SeqCst) {
+        "❚❚ Recording paused"
+    } else if status.recording {
+        "● Recording"
+    } else if status.screenshotting {
+        "● Taking screenshots"
+    } else if status.idle_detection {
+        "● Detecting idle time"
+    } else {
+        "○ Idle"
     }
+}
 
-    // Update user activity timestamp when all processes are stopped (user is actively managing the system)
-    if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
-        *last_activity = SystemTime::now();
+// Helper function to react to a process starting or stopping
+fn process_status_changed() {
+    refresh_tray_status();
+    persist_monitoring_state();
+}
+
+// Helper function to update the tray status entry
+fn refresh_tray_status() {
+    if let (Some(item), Ok(status)) = (TRAY_STATUS_ITEM.get(), current_process_status()) {
+        if let Err(e) = item.set_text(tray_status_label(&status)) {
+            error!("Failed to update tray status: {}", e);
+        }
     }
+}
 
-    // Record "all processes stopped" activity in database (user is active when stopping all processes)
+// Consecutive failed heartbeats after which the frontend is told via "heartbeat-failed"
+const HEARTBEAT_FAILURE_THRESHOLD: u32 = 3;
+// Longest wait between heartbeat attempts while the server is unreachable
+const HEARTBEAT_MAX_BACKOFF_SECS: u64 = 15 * 60;
+// Heartbeats kept while offline; older ones are dropped first
+const HEARTBEAT_QUEUE_LIMIT: usize = 100;
+
+lazy_static! {
+    static ref HEARTBEAT_TASK: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None));
+}
+
+// Check-in sent to the server so the dashboard knows the client is alive
+#[derive(Debug, Clone, serde::Serialize)]
+struct Heartbeat {
+    user_id: String,
+    timestamp: u64, // Unix timestamp (seconds)
+    process_status: String,
+    idle_status: String,
+    idle_seconds: Option<u64>,
+}
+
+// Helper function to get the heartbeat endpoint: REMOTE_WORK_HEARTBEAT_URL, or heartbeat.php next to the upload endpoint
+fn default_heartbeat_endpoint() -> String {
+    std::env::var("REMOTE_WORK_HEARTBEAT_URL").unwrap_or_else(|_| {
+        let server_url = std::env::var("REMOTE_WORK_SERVER_URL")
+            .unwrap_or_else(|_| "http://localhost/remote-work/".to_string());
+        format!("{}/heartbeat.php", server_url.trim_end_matches('/'))
+    })
+}
+
+// Helper function to collect the current state into a heartbeat
+async fn build_heartbeat() -> Heartbeat {
     let user_id = {
         let user_id_guard = USER_ID.lock().unwrap();
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
-    if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save all processes stopped activity to database: {}", e);
+    let idle_seconds = system_idle_seconds();
+
+    Heartbeat {
+        user_id,
+        timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        process_status: get_process_status().await.unwrap_or_else(|e| format!("Unknown: {}", e)),
+        idle_status: idle_status_for(idle_seconds.unwrap_or(0)).to_string(),
+        idle_seconds,
     }
+}
 
-    // Notify all windows that all processes have stopped
-    for (_window_label, window) in app.webview_windows() {
-        let _ = window.emit("all-processes-stopped", "All processes have been stopped");
+// Command to start checking in with the server every interval_seconds. Heartbeats that can't be delivered
+// are queued and sent in order once the server is reachable again, with the interval backing off meanwhile.
+#[tauri::command]
+async fn start_heartbeat(app: tauri::AppHandle, interval_seconds: u64, endpoint: Option<String>) -> Result<String, CommandError> {
+    if !(10..=3600).contains(&interval_seconds) {
+        return Err(CommandError::InvalidInput("Heartbeat interval must be between 10 and 3600 seconds".to_string()));
+    }
 
-        // Also emit individual stop events for compatibility with existing UI elements
-        let _ = window.emit("recording-finished", "All processes stopped");
-        let _ = window.emit("screenshotting-finished", "Screenshotting stopped");
+    let endpoint = endpoint.filter(|endpoint| !endpoint.trim().is_empty()).unwrap_or_else(default_heartbeat_endpoint);
 
-        // Additionally, if idle detection was stopped, emit an active status
-        // since the user is no longer being monitored for inactivity
-        let _ = window.emit("user-active", "All processes stopped - user considered active");
+    let mut task_guard = HEARTBEAT_TASK.lock().map_err(|e| e.to_string())?;
+    if task_guard.is_some() {
+        return Err(CommandError::AlreadyRunning("Heartbeat is already running".to_string()));
     }
 
-    Ok(format!("Stopped all processes:\n{}", results.join("\n")))
-}
+    // Built once per heartbeat task, so a proxy change applies when heartbeats are restarted
+    let client = http_client_builder()?
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
-// Command to get the current status of all processes
-#[tauri::command]
-async fn get_process_status() -> Result<String, String> {
-    // Check if recording is in progress
-    let recording_in_progress = {
-        let process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
-        process_guard.is_some()
-    };
+    let task_endpoint = endpoint.clone();
+    let task = tokio::spawn(async move {
+        let mut queue: VecDeque<Heartbeat> = VecDeque::new();
+        let mut consecutive_failures: u32 = 0;
 
-    // Check if screenshotting is in progress
-    let screenshotting_in_progress = {
-        let tasks = RUNNING_TASKS.lock().map_err(|e| e.to_string())?;
-        tasks.values().any(|status| match status {
-            TaskStatus::Active | TaskStatus::Stopping => true,
-            TaskStatus::Stopped => false,
-        })
-    };
+        loop {
+            queue.push_back(build_heartbeat().await);
+            while queue.len() > HEARTBEAT_QUEUE_LIMIT {
+                queue.pop_front();
+            }
 
-    // Check if idle detection is running
-    let idle_detection_running = {
-        let task_guard = IDLE_DETECTION_TASK.lock().map_err(|e| e.to_string())?;
-        task_guard.is_some()
-    };
+            // Send the backlog oldest first, stopping at the first failure
+            let mut last_error = None;
+            while let Some(heartbeat) = queue.front() {
+                let body = serde_json::to_vec(heartbeat).unwrap_or_default();
+                let result = with_auth_token(client.post(&task_endpoint))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body)
+                    .send()
+                    .await;
+                match result {
+                    Ok(response) if response.status().is_success() => {
+                        queue.pop_front();
+                    }
+                    Ok(response) if response.status() == reqwest::StatusCode::UNAUTHORIZED => {
+                        emit_auth_expired("heartbeat");
+                        last_error = Some("Heartbeat rejected by the server: auth token missing, invalid or expired (401)".to_string());
+                        break;
+                    }
+                    Ok(response) => {
+                        last_error = Some(format!("Server responded with status {}", response.status()));
+                        break;
+                    }
+                    Err(e) => {
+                        last_error = Some(format!("Failed to reach heartbeat endpoint: {}", e));
+                        break;
+                    }
+                }
+            }
 
-    let status_msg = format!(
-        "Recording: {}, Screenshotting: {}, Idle Detection: {}",
-        if recording_in_progress { "Active" } else { "Inactive" },
-        if screenshotting_in_progress { "Active" } else { "Inactive" },
-        if idle_detection_running { "Active" } else { "Inactive" }
-    );
+            match last_error {
+                None => consecutive_failures = 0,
+                Some(e) => {
+                    consecutive_failures += 1;
+                    warn!("Heartbeat failed ({} in a row): {}", consecutive_failures, e);
+
+                    if consecutive_failures == HEARTBEAT_FAILURE_THRESHOLD {
+                        let _ = app.emit("heartbeat-failed", serde_json::json!({
+                            "consecutiveFailures": consecutive_failures,
+                            "queued": queue.len(),
+                            "error": e,
+                        }));
+                    }
+                }
+            }
 
-    Ok(status_msg)
+            // Back off exponentially while failing, never waiting less than the normal interval
+            let backoff = interval_seconds.saturating_mul(1 << consecutive_failures.min(5));
+            let wait = backoff.min(HEARTBEAT_MAX_BACKOFF_SECS).max(interval_seconds);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+        }
+    });
+
+    *task_guard = Some(task);
+
+    Ok(format!("Heartbeat started: every {} seconds to {}", interval_seconds, endpoint))
+}
+
+#[tauri::command]
+fn stop_heartbeat() -> Result<String, CommandError> {
+    let mut task_guard = HEARTBEAT_TASK.lock().map_err(|e| e.to_string())?;
+
+    match task_guard.take() {
+        Some(task) => {
+            task.abort();
+            Ok("Heartbeat stopped".to_string())
+        }
+        None => Err(CommandError::NotRunning("Heartbeat is not running".to_string())),
+    }
 }
 
 
@@ -2110,31 +7159,40 @@ async fn stop_current_recording_segment() -> Result<(), String> {
     let mut process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
 
     if let Some(mut child) = process_guard.take() {
-        // Try to terminate the process gracefully first
-        match child.kill() {
-            Ok(_) => {
-                println!("Successfully sent kill signal to recording process");
-                // Wait for the process to finish
-                match child.wait() {
-                    Ok(exit_status) => println!("Process exited with: {}", exit_status),
-                    Err(e) => println!("Error waiting for process: {}", e),
-                }
-            },
-            Err(e) => println!("Error killing process: {}", e),
-        }
+        // Terminate the process gracefully so the segment is playable
+        stop_ffmpeg_gracefully(&mut child);
     }
 
     Ok(())
 }
 
+// Helper function to get the index of a session's next segment: one past the highest in the list, so a list
+// rebuilt by repair_recording_segments with gaps in it never reuses the index of a segment that is still on disk
+fn next_segment_index(segments: &VecDeque<String>, session_id: &str) -> usize {
+    let prefix = format!("recording_{}_seg_", session_id);
+    let highest = segments
+        .iter()
+        .filter_map(|segment| {
+            let filename = std::path::Path::new(segment).file_name()?.to_string_lossy().to_string();
+            let number = filename.strip_prefix(&prefix)?.split(['.', '_']).next()?.to_string();
+            number.parse::<usize>().ok()
+        })
+        .max();
+
+    match highest {
+        Some(highest) => (highest + 1).max(segments.len()),
+        None => segments.len(),
+    }
+}
+
 // Helper function to start a new FFmpeg segment
-async fn start_new_recording_segment() -> Result<String, String> {
+async fn start_new_recording_segment() -> Result<String, CommandError> {
     // Get the session info
     let session_id = {
         let session_guard = RECORDING_SESSION_ID.lock().unwrap();
         match session_guard.as_ref() {
             Some(id) => id.clone(),
-            None => return Err("No recording session is active".to_string()),
+            None => return Err(CommandError::NotRunning("No recording session is active".to_string())),
         }
     };
 
@@ -2142,26 +7200,27 @@ async fn start_new_recording_segment() -> Result<String, String> {
         let path_guard = RECORDING_BASE_PATH.lock().unwrap();
         match path_guard.as_ref() {
             Some(path) => path.clone(),
-            None => return Err("No recording path is set".to_string()),
+            None => return Err(CommandError::NotRunning("No recording path is set".to_string())),
         }
     };
 
     // Get the next segment index
     let segment_index = {
         let files_guard = RECORDING_SEGMENT_FILES.lock().unwrap();
-        files_guard.len()
+        next_segment_index(&files_guard, &session_id)
     };
 
+    ensure_free_disk_space("recording segment")?;
+
     // Create the path for the new segment
-    let segment_path = std::path::Path::new(&base_path).join(format!("recording_{}_seg_{}.mkv", session_id, segment_index));
-    let video_path_str = segment_path.to_string_lossy().to_string();
+    let video_path_str = recording_segment_path(&base_path, &session_id, segment_index)?;
 
     // Look for bundled FFmpeg first
     let ffmpeg_path = std::env::current_exe()
         .ok()
         .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
         .unwrap_or_else(|| std::env::current_dir().unwrap())
-        .join("ffmpeg.exe");
+        .join(BUNDLED_FFMPEG_NAME);
 
     let ffmpeg_cmd = if ffmpeg_path.exists() {
         ffmpeg_path.to_string_lossy().to_string()
@@ -2184,132 +7243,359 @@ async fn start_new_recording_segment() -> Result<String, String> {
         } {
             Ok(_) => "ffmpeg".to_string(),
             Err(_) => {
-                return Err("FFmpeg is required for recording but not found".to_string());
+                return Err(CommandError::FfmpegMissing("FFmpeg is required for recording but not found".to_string()));
+            }
+        }
+    };
+
+    // Keep the encoding the recording was started with
+    let encoding = {
+        let encoding_guard = RECORDING_ENCODING.lock().map_err(|e| e.to_string())?;
+        encoding_guard.clone()
+    }
+    .unwrap_or_else(|| select_recording_encoding(&ffmpeg_cmd));
+
+    // Start the video recording process with FFmpeg for the new segment
+    let child = spawn_ffmpeg_recording(&ffmpeg_cmd, &video_path_str, &encoding)?;
+
+    // Update the recording process. There is normally none (paused); on a rollover it is the segment being closed.
+    let previous_process = {
+        let mut process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
+        process_guard.replace(child)
+    };
+
+    // Store the process ID
+    {
+        let mut pid_guard = FFMPEG_PROCESS_ID.lock().unwrap();
+        *pid_guard = COMBINED_RECORDING_PROCESS.lock().unwrap().as_ref().map(|p| p.id());
+    }
+
+    // Add the new segment to the list
+    {
+        let mut files_guard = RECORDING_SEGMENT_FILES.lock().unwrap();
+        files_guard.push_back(video_path_str.clone());
+    }
+
+    // Get user ID before saving to database
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+        // The guard is automatically dropped at the end of this block
+    };
+
+    // Get the main recording ID from the database
+    let recording_id = match database::get_recording_id_by_session(&session_id) {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            error!("Failed to find main recording for session: {}", session_id);
+            0  // Use placeholder if not found
+        },
+        Err(e) => {
+            error!("Error getting recording ID from database: {}", e);
+            0  // Use placeholder if error
+        }
+    };
+
+    // Save recording segment metadata to database
+    if let Err(e) = database::save_recording_segment_to_db(
+        &user_id,
+        recording_id,
+        segment_index as i32,
+        &std::path::Path::new(&video_path_str).file_name().unwrap_or_default().to_string_lossy(),
+        Some(&video_path_str),
+        None, // Duration not known yet
+        None  // File size not known yet
+    ) {
+        error!("Failed to save recording segment metadata to database: {}", e);
+    }
+
+    // Close the segment this one takes over from only now, so the recording never has a gap
+    if let Some(mut previous_child) = previous_process {
+        stop_ffmpeg_gracefully(&mut previous_child);
+    }
+
+    Ok(format!("Started new recording segment: {}", video_path_str))
+}
+
+// Recording time (excluding pauses) after which the current segment is closed and a new one started,
+// and after which the whole session is finalized and a new session started; 0 disables each limit
+static MAX_RECORDING_SEGMENT_SECS: AtomicU64 = AtomicU64::new(0);
+static MAX_RECORDING_SESSION_SECS: AtomicU64 = AtomicU64::new(0);
+
+// Command to limit how long recording segments and sessions get, so no single file becomes too large to upload
+#[tauri::command]
+fn set_max_recording_duration(minutes: u64, session_minutes: Option<u64>) -> Result<String, CommandError> {
+    let session_minutes = session_minutes.unwrap_or(0);
+    if minutes > 24 * 60 || session_minutes > 24 * 60 {
+        return Err(CommandError::InvalidInput("Maximum recording durations must be between 0 and 1440 minutes".to_string()));
+    }
+
+    if minutes > 0 && session_minutes > 0 && session_minutes < minutes {
+        return Err(CommandError::InvalidInput("Maximum session duration must not be shorter than the maximum segment duration".to_string()));
+    }
+
+    MAX_RECORDING_SEGMENT_SECS.store(minutes * 60, Ordering::SeqCst);
+    MAX_RECORDING_SESSION_SECS.store(session_minutes * 60, Ordering::SeqCst);
+
+    let describe = |limit: u64| match limit {
+        0 => "unlimited".to_string(),
+        minutes => format!("{} minutes", minutes),
+    };
+    Ok(format!(
+        "Maximum recording duration set: segments {}, sessions {}",
+        describe(minutes),
+        describe(session_minutes)
+    ))
+}
+
+// Helper function to start the task that enforces the maximum segment and session durations of a recording.
+// The task ends by itself once the session is stopped or replaced.
+fn start_recording_rollover_task(app: tauri::AppHandle, session_id: String) {
+    tokio::spawn(async move {
+        let mut segment_seconds = 0u64;
+        let mut session_seconds = 0u64;
+        let mut segment_count = RECORDING_SEGMENT_FILES.lock().map(|files| files.len()).unwrap_or(0);
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            let current_session = RECORDING_SESSION_ID.lock().map(|session| session.clone()).unwrap_or(None);
+            if current_session.as_deref() != Some(session_id.as_str()) {
+                break;
+            }
+
+            // Paused time doesn't count towards either limit
+            if RECORDING_PAUSED.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            // Resuming after a pause starts a new segment, so its time starts over
+            let current_segment_count = RECORDING_SEGMENT_FILES.lock().map(|files| files.len()).unwrap_or(segment_count);
+            if current_segment_count != segment_count {
+                segment_count = current_segment_count;
+                segment_seconds = 0;
+            }
+
+            segment_seconds += 1;
+            session_seconds += 1;
+
+            let max_session_seconds = MAX_RECORDING_SESSION_SECS.load(Ordering::SeqCst);
+            if max_session_seconds > 0 && session_seconds >= max_session_seconds {
+                info!("Recording session {} reached its maximum duration, starting a new session", session_id);
+                // The new session starts its own rollover task
+                match restart_combined_recording(app.clone()).await {
+                    Ok(message) => info!("{}", message),
+                    Err(e) => error!("Failed to restart recording session: {}", e),
+                }
+                break;
+            }
+
+            let max_segment_seconds = MAX_RECORDING_SEGMENT_SECS.load(Ordering::SeqCst);
+            if max_segment_seconds > 0 && segment_seconds >= max_segment_seconds {
+                // Starting the next segment closes the current one once the new FFmpeg is running
+                match start_new_recording_segment().await {
+                    Ok(message) => info!("Segment reached its maximum duration. {}", message),
+                    Err(e) => error!("Failed to roll over recording segment: {}", e),
+                }
+                segment_count = RECORDING_SEGMENT_FILES.lock().map(|files| files.len()).unwrap_or(segment_count);
+                segment_seconds = 0;
+            }
+        }
+    });
+}
+
+// Helper function to start the task that follows screen changes during a recording. FFmpeg keeps capturing
+// the desktop at the size it started with, so when monitors are plugged in or out or change resolution the
+// recording rolls over to a new segment at the new size. The task ends by itself once the session is stopped
+// or replaced.
+fn start_recording_display_watch(session_id: String) {
+    tokio::spawn(async move {
+        let mut recorded_layout = screen_source::current().screens().map(|screens| display_layout(&screens)).unwrap_or_default();
+
+        loop {
+            tokio::time::sleep(DISPLAY_CHECK_INTERVAL).await;
+
+            let current_session = RECORDING_SESSION_ID.lock().map(|session| session.clone()).unwrap_or(None);
+            if current_session.as_deref() != Some(session_id.as_str()) {
+                break;
+            }
+
+            let layout = match screen_source::current().screens() {
+                Ok(screens) => display_layout(&screens),
+                Err(e) => {
+                    debug!("Failed to get screens: {}", e);
+                    continue;
+                }
+            };
+            note_display_layout(&layout);
+
+            // With every screen gone (e.g. the lid closed while undocking) wait for one to come back
+            if layout.is_empty() || layout == recorded_layout {
+                continue;
+            }
+
+            // A paused recording starts a new segment at the current size when it resumes
+            let recording = COMBINED_RECORDING_PROCESS.lock().map(|process| process.is_some()).unwrap_or(false);
+            if recording && !RECORDING_PAUSED.load(Ordering::SeqCst) {
+                match start_new_recording_segment().await {
+                    Ok(message) => info!("Screens changed during recording. {}", message),
+                    Err(e) => {
+                        // Tried again on the next check
+                        error!("Failed to restart recording after the screens changed: {}", e);
+                        continue;
+                    }
+                }
+            }
+            recorded_layout = layout;
+        }
+    });
+}
+
+// Helper function to finalize the current recording session and start a new one. Boxed because it is called
+// from the rollover task, which start_combined_recording itself spawns.
+fn restart_combined_recording(app: tauri::AppHandle) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<String, String>> + Send>> {
+    Box::pin(async move {
+        // Keep recording even if the finished session couldn't be concatenated; its segments stay on disk
+        if let Err(e) = stop_combined_recording(app.clone()).await {
+            error!("{}", e);
+        }
+
+        start_combined_recording(app, None, None).await.map_err(String::from)
+    })
+}
+
+// CRF and libx264 preset used when re-encoding a segment while the recording is paused
+const PAUSED_SEGMENT_CRF: &str = "30";
+const PAUSED_SEGMENT_PRESET: &str = "medium";
+
+lazy_static! {
+    // FFmpeg re-encoding the last closed segment, and the background task waiting for it
+    static ref SEGMENT_COMPRESSION_PROCESS: Arc<Mutex<Option<Child>>> = Arc::new(Mutex::new(None));
+    static ref SEGMENT_COMPRESSION_TASK: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None));
+}
+
+// Set when a running segment re-encode should be thrown away instead of replacing the segment
+static SEGMENT_COMPRESSION_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+// Helper function to re-encode a closed segment to a smaller file in the background.
+// On success the compressed file replaces the segment in RECORDING_SEGMENT_FILES and the original is deleted.
+fn start_segment_compression(segment_path: String) -> Result<(), String> {
+    let ffmpeg_path = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or_else(|| std::env::current_dir().unwrap())
+        .join(BUNDLED_FFMPEG_NAME);
+    let ffmpeg_cmd = if ffmpeg_path.exists() {
+        ffmpeg_path.to_string_lossy().to_string()
+    } else {
+        "ffmpeg".to_string()
+    };
+
+    let compressed_path = segment_path.replace(".mkv", "_compressed.mkv");
+
+    let mut command = Command::new(&ffmpeg_cmd);
+    command
+        .args(&[
+            "-i", &segment_path,
+            "-vcodec", "libx264",
+            "-crf", PAUSED_SEGMENT_CRF,
+            "-preset", PAUSED_SEGMENT_PRESET,
+            "-pix_fmt", "yuv420p",
+            "-y",
+            &compressed_path,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let child = command.spawn().map_err(|e| format!("Failed to start FFmpeg for segment compression: {}", e))?;
+
+    SEGMENT_COMPRESSION_CANCELLED.store(false, Ordering::SeqCst);
+    *SEGMENT_COMPRESSION_PROCESS.lock().map_err(|e| e.to_string())? = Some(child);
+
+    let task = tokio::spawn(async move {
+        // Poll instead of blocking on wait() so the process can be killed from cancel_segment_compression
+        let exit_status = loop {
+            let status = {
+                let mut process_guard = SEGMENT_COMPRESSION_PROCESS.lock().unwrap();
+                match process_guard.as_mut() {
+                    Some(child) => child.try_wait(),
+                    None => break None, // Cancelled
+                }
+            };
+
+            match status {
+                Ok(Some(exit_status)) => break Some(exit_status),
+                Ok(None) => tokio::time::sleep(Duration::from_millis(200)).await,
+                Err(e) => {
+                    warn!("Failed to wait for segment compression: {}", e);
+                    break None;
+                }
             }
+        };
+        SEGMENT_COMPRESSION_PROCESS.lock().unwrap().take();
+
+        let succeeded = exit_status.map_or(false, |exit_status| exit_status.success());
+        if !succeeded || SEGMENT_COMPRESSION_CANCELLED.load(Ordering::SeqCst) {
+            let _ = std::fs::remove_file(&compressed_path);
+            return;
         }
-    };
 
-    // Start the video recording process with FFmpeg for the new segment
-    let child = {
-        #[cfg(target_os = "windows")]
-        {
-            std::process::Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "gdigrab",
-                    "-i", "desktop",
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW flag
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-        #[cfg(target_os = "linux")]
-        {
-            // On Linux, use x11grab for screen capture
-            std::process::Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "x11grab",
-                    "-i", &std::env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string()),
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // On macOS, use avfoundation for screen capture
-            std::process::Command::new(&ffmpeg_cmd)
-                .args(&[
-                    "-f", "avfoundation",
-                    "-i", "default",
-                    "-vcodec", "libx264",
-                    "-crf", "28",
-                    "-preset", "ultrafast",
-                    "-pix_fmt", "yuv420p",
-                    "-y",
-                    &video_path_str
-                ])
-                .spawn()
-                .map_err(|e| format!("Failed to start FFmpeg for recording: {}", e))?
-        }
-    };
-
-    // Update the recording process
-    {
-        let mut process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
-        *process_guard = Some(child);
-    }
+        // Only swap if the segment is still part of the recording
+        let replaced = {
+            let mut files_guard = RECORDING_SEGMENT_FILES.lock().unwrap();
+            match files_guard.iter_mut().find(|file| **file == segment_path) {
+                Some(file) => {
+                    *file = compressed_path.clone();
+                    true
+                }
+                None => false,
+            }
+        };
 
-    // Store the process ID
-    {
-        let mut pid_guard = FFMPEG_PROCESS_ID.lock().unwrap();
-        *pid_guard = COMBINED_RECORDING_PROCESS.lock().unwrap().as_ref().map(|p| p.id());
-    }
+        if replaced {
+            let original_size = std::fs::metadata(&segment_path).map(|metadata| metadata.len()).unwrap_or(0);
+            let compressed_size = std::fs::metadata(&compressed_path).map(|metadata| metadata.len()).unwrap_or(0);
+            let _ = std::fs::remove_file(&segment_path);
+            info!("Compressed paused segment from {} to {} bytes", original_size, compressed_size);
+        } else {
+            let _ = std::fs::remove_file(&compressed_path);
+        }
+    });
 
-    // Add the new segment to the list
-    {
-        let mut files_guard = RECORDING_SEGMENT_FILES.lock().unwrap();
-        files_guard.push_back(video_path_str.clone());
-    }
+    *SEGMENT_COMPRESSION_TASK.lock().map_err(|e| e.to_string())? = Some(task);
+    Ok(())
+}
 
-    // Get user ID before saving to database
-    let user_id = {
-        let user_id_guard = USER_ID.lock().unwrap();
-        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-        // The guard is automatically dropped at the end of this block
-    };
+// Helper function to make sure no segment re-encode is running, killing it if one is.
+// Called before segments are concatenated so a segment is never replaced while being read.
+async fn cancel_segment_compression() {
+    SEGMENT_COMPRESSION_CANCELLED.store(true, Ordering::SeqCst);
 
-    // Get the main recording ID from the database
-    let recording_id = match database::get_recording_id_by_session(&session_id) {
-        Ok(Some(id)) => id,
-        Ok(None) => {
-            eprintln!("Failed to find main recording for session: {}", session_id);
-            0  // Use placeholder if not found
-        },
-        Err(e) => {
-            eprintln!("Error getting recording ID from database: {}", e);
-            0  // Use placeholder if error
+    if let Ok(mut process_guard) = SEGMENT_COMPRESSION_PROCESS.lock() {
+        if let Some(mut child) = process_guard.take() {
+            let _ = child.kill();
+            let _ = child.wait();
         }
-    };
-
-    // Save recording segment metadata to database
-    let segment_index = {
-        let files_guard = RECORDING_SEGMENT_FILES.lock().unwrap();
-        files_guard.len() - 1  // Current index is length - 1
-    };
-
-    if let Err(e) = database::save_recording_segment_to_db(
-        &user_id,
-        recording_id,
-        segment_index as i32,
-        &format!("recording_{}_seg_{}.mkv", session_id, segment_index),
-        Some(&video_path_str),
-        None, // Duration not known yet
-        None  // File size not known yet
-    ) {
-        eprintln!("Failed to save recording segment metadata to database: {}", e);
     }
 
-    Ok(format!("Started new recording segment: {}", video_path_str))
+    let task = SEGMENT_COMPRESSION_TASK.lock().ok().and_then(|mut task_guard| task_guard.take());
+    if let Some(task) = task {
+        let _ = task.await;
+    }
 }
 
+// Pause the recording. With compress_segment the segment that was just closed is re-encoded
+// to a smaller file in the background while the recording stays paused.
 #[tauri::command]
-async fn pause_combined_recording(app: tauri::AppHandle) -> Result<String, String> {
+async fn pause_combined_recording(app: tauri::AppHandle, compress_segment: Option<bool>) -> Result<String, CommandError> {
     // Check if there's actually a recording in progress before pausing
     {
         let process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
         if process_guard.is_none() {
-            return Err("No recording in progress to pause".to_string());
+            return Err(CommandError::NotRunning("No recording in progress to pause".to_string()));
         }
         // Don't drop the guard yet, just checking
     }
@@ -2320,6 +7606,20 @@ async fn pause_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
     // Set the paused flag
     RECORDING_PAUSED.store(true, Ordering::SeqCst);
 
+    // Shrink the just-closed segment without holding up the pause. HLS segments are uploaded as they
+    // are recorded, so there is nothing to shrink.
+    if compress_segment.unwrap_or(false) && !RECORDING_SESSION_HLS.load(Ordering::SeqCst) {
+        // Only one re-encode at a time; an unfinished one for an earlier segment is dropped
+        cancel_segment_compression().await;
+
+        let last_segment = RECORDING_SEGMENT_FILES.lock().map_err(|e| e.to_string())?.back().cloned();
+        if let Some(segment_path) = last_segment {
+            if let Err(e) = start_segment_compression(segment_path) {
+                error!("{}", e);
+            }
+        }
+    }
+
     // Update user activity timestamp when recording is paused (user is actively managing the system)
     if let Ok(mut last_activity) = LAST_USER_ACTIVITY.lock() {
         *last_activity = SystemTime::now();
@@ -2331,7 +7631,7 @@ async fn pause_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
     if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save recording paused activity to database: {}", e);
+        error!("Failed to save recording paused activity to database: {}", e);
     }
 
     // Emit event to notify all UI windows
@@ -2339,23 +7639,24 @@ async fn pause_combined_recording(app: tauri::AppHandle) -> Result<String, Strin
     for (_window_label, window) in app.webview_windows() {
         let _ = window.emit("recording-paused", "Recording has been paused");
     }
+    process_status_changed();
 
     Ok("Recording paused successfully - segment saved".to_string())
 }
 
 #[tauri::command]
-async fn resume_combined_recording(app: tauri::AppHandle) -> Result<String, String> {
+async fn resume_combined_recording(app: tauri::AppHandle) -> Result<String, CommandError> {
     // Check if there's a recording session but no active process (meaning it's paused)
     {
         let process_guard = COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?;
         if process_guard.is_some() {
             // If there's an active process, it means we're not paused
-            return Err("Recording is not paused, cannot resume".to_string());
+            return Err(CommandError::NotRunning("Recording is not paused, cannot resume".to_string()));
         }
         // Also check if we have a session ID to confirm we're in a recording session
         let session_guard = RECORDING_SESSION_ID.lock().unwrap();
         if session_guard.is_none() {
-            return Err("No recording session is active".to_string());
+            return Err(CommandError::NotRunning("No recording session is active".to_string()));
         }
     }
 
@@ -2376,7 +7677,7 @@ async fn resume_combined_recording(app: tauri::AppHandle) -> Result<String, Stri
         user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
     };
     if let Err(e) = database::save_user_activity_to_db(&user_id, "active", Some(0)) {
-        eprintln!("Failed to save recording resumed activity to database: {}", e);
+        error!("Failed to save recording resumed activity to database: {}", e);
     }
 
     // Emit event to notify all UI windows
@@ -2384,40 +7685,115 @@ async fn resume_combined_recording(app: tauri::AppHandle) -> Result<String, Stri
     for (_window_label, window) in app.webview_windows() {
         let _ = window.emit("recording-resumed", "Recording has been resumed");
     }
+    process_status_changed();
 
     Ok(format!("Recording resumed successfully - {}", result))
 }
 
+// Command to cut the running recording into a new segment now, e.g. at the end of a task.
+// Like a rollover, the new FFmpeg starts before the current one is closed, so nothing is lost at the cut
+// and screenshots carry on as scheduled. Returns the new segment's path.
+#[tauri::command]
+async fn split_recording_segment(app: tauri::AppHandle) -> Result<String, CommandError> {
+    if RECORDING_PAUSED.load(Ordering::SeqCst) {
+        return Err(CommandError::NotRunning("Recording is paused; resuming it already starts a new segment".to_string()));
+    }
+    if COMBINED_RECORDING_PROCESS.lock().map_err(|e| e.to_string())?.is_none() {
+        return Err(CommandError::NotRunning("No recording in progress to split".to_string()));
+    }
+
+    start_new_recording_segment().await?;
+
+    let (segment_index, segment_path) = {
+        let files_guard = RECORDING_SEGMENT_FILES.lock().map_err(|e| e.to_string())?;
+        let segment_path = files_guard.back().cloned().ok_or("No recording segment was started")?;
+        (files_guard.len() - 1, segment_path)
+    };
+    info!("Recording split, new segment {}: {}", segment_index, segment_path);
+
+    let _ = app.emit("segment-split", serde_json::json!({
+        "segment_index": segment_index,
+        "segment_path": segment_path,
+    }));
+
+    Ok(segment_path)
+}
+
 // Command to set user ID
 #[tauri::command]
-async fn set_user_id(user_id: String) -> Result<String, String> {
+async fn set_user_id(user_id: String) -> Result<String, CommandError> {
+    // Users can't be looked up without the database, which is different from the user not existing
+    if !database::is_database_available() {
+        return Err(CommandError::DatabaseUnavailable("The database is unavailable, the user ID can't be checked".to_string()));
+    }
+
     // Check if the user ID exists in the database
-    if database::user_exists(&user_id).unwrap_or(false) {
-        // If user exists, just set the user ID in memory
-        let mut user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-        *user_id_guard = Some(user_id.clone());
-        drop(user_id_guard); // Release the lock early
+    match database::user_exists(&user_id) {
+        Ok(true) => {
+            // If user exists, just set the user ID in memory
+            let mut user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+            *user_id_guard = Some(user_id.clone());
+            drop(user_id_guard); // Release the lock early
 
-        Ok(format!("User ID set successfully: {}", user_id))
-    } else {
+            Ok(format!("User ID set successfully: {}", user_id))
+        }
         // If user doesn't exist, return an error message
-        Err("Invalid User ID".to_string())
+        Ok(false) => Err(CommandError::NotFound("Invalid User ID".to_string())),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to look up user {}: {}", user_id, e))),
+    }
+}
+
+// Command to hand the installation over to another user, e.g. on a shared machine, without restarting.
+// Everything running for the current user is stopped first so none of their captures are attributed to the
+// new user. Nothing changes if the new user doesn't exist.
+#[tauri::command]
+async fn switch_user(app: tauri::AppHandle, new_user_id: String) -> Result<String, CommandError> {
+    let new_user_id = new_user_id.trim().to_string();
+    if !database::is_database_available() {
+        return Err(CommandError::DatabaseUnavailable("The database is unavailable, the user ID can't be checked".to_string()));
     }
+    match database::user_exists(&new_user_id) {
+        Ok(true) => {}
+        Ok(false) => return Err(CommandError::NotFound("Invalid User ID".to_string())),
+        Err(e) => return Err(CommandError::DatabaseUnavailable(format!("Failed to look up user {}: {}", new_user_id, e))),
+    }
+
+    let previous_user_id = USER_ID.lock().map_err(|e| e.to_string())?.clone();
+    if previous_user_id.as_deref() == Some(new_user_id.as_str()) {
+        return Err(CommandError::InvalidInput(format!("User {} is already the current user", new_user_id)));
+    }
+
+    let stopped = stop_all_processes(app.clone()).await?;
+
+    *USER_ID.lock().map_err(|e| e.to_string())? = Some(new_user_id.clone());
+    info!("Switched user from {} to {}", previous_user_id.as_deref().unwrap_or("none"), new_user_id);
+
+    let _ = app.emit("user-switched", serde_json::json!({
+        "previous_user_id": previous_user_id,
+        "user_id": new_user_id,
+    }));
+
+    Ok(serde_json::json!({
+        "previous_user_id": previous_user_id,
+        "user_id": new_user_id,
+        "stopped": stopped,
+    })
+    .to_string())
 }
 
 // Command to get current user ID
 #[tauri::command]
-async fn get_user_id() -> Result<String, String> {
+async fn get_user_id() -> Result<String, CommandError> {
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
     match user_id_guard.as_ref() {
         Some(id) => Ok(id.clone()),
-        None => Err("User ID not set".to_string())
+        None => Err(CommandError::UserNotSet("User ID not set".to_string()))
     }
 }
 
 // Command to check if user ID is set
 #[tauri::command]
-async fn is_user_id_set() -> Result<bool, String> {
+async fn is_user_id_set() -> Result<bool, CommandError> {
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
     Ok(user_id_guard.is_some())
 }
@@ -2434,54 +7810,54 @@ pub fn is_user_id_set_sync() -> bool {
 // Database user management commands
 
 #[tauri::command]
-async fn create_user(user_id: String, username: Option<String>, email: Option<String>) -> Result<String, String> {
+async fn create_user(user_id: String, username: Option<String>, email: Option<String>) -> Result<String, CommandError> {
     if !database::is_database_available() {
-        return Err("Database is not available. Data will be stored when database is back online.".to_string());
+        return Err(CommandError::DatabaseUnavailable("Database is not available. Data will be stored when database is back online.".to_string()));
     }
 
     match database::create_user(&user_id, username.as_deref(), email.as_deref()) {
         Ok(()) => Ok(format!("User {} created/updated successfully", user_id)),
-        Err(e) => Err(format!("Failed to create/update user: {}", e)),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to create/update user: {}", e))),
     }
 }
 
 #[tauri::command]
-async fn get_user(user_id: String) -> Result<String, String> {
+async fn get_user(user_id: String) -> Result<String, CommandError> {
     if !database::is_database_available() {
-        return Err("Database is not available. Cannot retrieve data.".to_string());
+        return Err(CommandError::DatabaseUnavailable("Database is not available. Cannot retrieve data.".to_string()));
     }
 
     match database::get_user(&user_id) {
         Ok(Some(user_info)) => {
             match serde_json::to_string(&user_info) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize user info: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize user info: {}", e))),
             }
         },
-        Ok(None) => Err("User not found".to_string()),
-        Err(e) => Err(format!("Failed to get user: {}", e)),
+        Ok(None) => Err(CommandError::NotFound("User not found".to_string())),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get user: {}", e))),
     }
 }
 
 #[tauri::command]
-async fn get_all_users(limit: Option<u32>) -> Result<String, String> {
+async fn get_all_users(limit: Option<u32>) -> Result<String, CommandError> {
     if !database::is_database_available() {
-        return Err("Database is not available. Cannot retrieve data.".to_string());
+        return Err(CommandError::DatabaseUnavailable("Database is not available. Cannot retrieve data.".to_string()));
     }
 
     match database::get_all_users(limit) {
         Ok(users) => {
             match serde_json::to_string(&users) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize users: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize users: {}", e))),
             }
         },
-        Err(e) => Err(format!("Failed to get users: {}", e)),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get users: {}", e))),
     }
 }
 
 #[tauri::command]
-async fn user_exists(user_id: String) -> Result<bool, String> {
+async fn user_exists(user_id: String) -> Result<bool, CommandError> {
     if !database::is_database_available() {
         // If database is not available, assume user doesn't exist
         return Ok(false);
@@ -2489,26 +7865,89 @@ async fn user_exists(user_id: String) -> Result<bool, String> {
 
     match database::user_exists(&user_id) {
         Ok(exists) => Ok(exists),
-        Err(e) => Err(format!("Failed to check if user exists: {}", e)),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to check if user exists: {}", e))),
     }
 }
 
+// Runs the table write check and notifies the UI about any table the agent cannot use
+fn run_db_write_access_check(app: &tauri::AppHandle) -> Result<Vec<database::TableAccessReport>, CommandError> {
+    if !database::is_database_available() {
+        return Err(CommandError::DatabaseUnavailable("Database is not available. Cannot check table access.".to_string()));
+    }
+
+    let reports = database::check_table_write_access()
+        .map_err(|e| CommandError::DatabaseUnavailable(format!("Failed to check table write access: {}", e)))?;
+
+    let problem_tables: Vec<String> = reports
+        .iter()
+        .filter(|report| !report.writable || !report.missing_columns.is_empty())
+        .map(|report| report.table.clone())
+        .collect();
+
+    if !problem_tables.is_empty() {
+        error!("Database tables not writable or missing columns: {}", problem_tables.join(", "));
+        let _ = app.emit("db-permission-problem", serde_json::json!({
+            "tables": problem_tables,
+            "details": &reports,
+        }));
+    }
+
+    Ok(reports)
+}
+
+// Command to verify write access and schema for each table the agent writes to
 #[tauri::command]
-async fn get_network_stats() -> Result<String, String> {
-    let stats = NETWORK_STATS.lock().unwrap();
-    let duration = stats.last_updated.elapsed().as_secs_f64();
+async fn check_db_write_access(app: tauri::AppHandle) -> Result<String, CommandError> {
+    let reports = run_db_write_access_check(&app)?;
 
-    // Calculate speeds (bytes per second)
-    let download_speed = if duration > 0.0 {
-        (stats.total_bytes_downloaded - stats.last_bytes_downloaded) as f64 / duration
-    } else {
-        0.0
-    };
-    let upload_speed = if duration > 0.0 {
-        (stats.total_bytes_uploaded - stats.last_bytes_uploaded) as f64 / duration
-    } else {
-        0.0
+    match serde_json::to_string(&reports) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(CommandError::Internal(format!("Failed to serialize table access report: {}", e))),
+    }
+}
+
+// Command to point the agent at a different database without restarting.
+// The settings are tested first and rejected without touching the current connection if they don't work.
+#[tauri::command]
+async fn set_database_config(host: String, port: u16, user: String, password: String, database: String) -> Result<String, CommandError> {
+    if host.trim().is_empty() || user.trim().is_empty() || database.trim().is_empty() {
+        return Err(CommandError::InvalidInput("Host, user and database are required".to_string()));
+    }
+
+    let config = database::DatabaseConfig {
+        user,
+        password,
+        host,
+        port: port.to_string(),
+        database,
     };
+    let target = format!("{}:{}/{}", config.host, config.port, config.database);
+
+    database::apply_config_override(config)
+        .map_err(|e| CommandError::DatabaseUnavailable(format!("Could not connect with the new database settings: {}", e)))?;
+
+    Ok(format!("Database switched to {}", target))
+}
+
+// Command to test the database connection and report host, latency and the MySQL error on failure
+#[tauri::command]
+async fn check_database_connection() -> Result<String, CommandError> {
+    let report = database::check_connection();
+
+    match serde_json::to_string(&report) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(CommandError::Internal(format!("Failed to serialize database connection report: {}", e))),
+    }
+}
+
+#[tauri::command]
+async fn get_network_stats() -> Result<String, CommandError> {
+    let mut stats = NETWORK_STATS.lock().unwrap();
+    let now = std::time::Instant::now();
+
+    // Calculate speeds (bytes per second) against the previous snapshot, then start the next interval
+    let (download_speed, upload_speed) = stats.speeds(now);
+    stats.roll_interval(now);
 
     // Convert to appropriate units (KB/s or MB/s)
     let download_speed_str = if download_speed > 1024.0 * 1024.0 {
@@ -2531,8 +7970,15 @@ async fn get_network_stats() -> Result<String, String> {
     ))
 }
 
+// Helper function to recognize loopback interfaces by name: "lo" on Linux, "lo0" on macOS and
+// "Loopback Pseudo-Interface 1" on Windows. Names are compared exactly, since e.g. "wlo1" is a Wi-Fi card.
+fn is_loopback_interface(interface_name: &str) -> bool {
+    let name = interface_name.to_lowercase();
+    name == "lo" || name == "lo0" || name.starts_with("loopback")
+}
+
 #[tauri::command]
-async fn get_global_network_stats() -> Result<String, String> {
+async fn get_global_network_stats() -> Result<String, CommandError> {
     // Create a new Networks instance to get current network data
     let networks = Networks::new_with_refreshed_list();
 
@@ -2542,7 +7988,7 @@ async fn get_global_network_stats() -> Result<String, String> {
 
     for (interface_name, network) in networks.iter() {
         // Skip loopback interfaces
-        if interface_name.to_lowercase().contains("lo") || interface_name.to_lowercase().contains("loopback") {
+        if is_loopback_interface(interface_name) {
             continue;
         }
         total_bytes_downloaded += network.total_received();
@@ -2552,15 +7998,15 @@ async fn get_global_network_stats() -> Result<String, String> {
     let mut global_stats = GLOBAL_NETWORK_STATS.lock().map_err(|e| format!("Failed to acquire global network stats lock: {}", e))?;
     let duration = global_stats.last_updated.elapsed().as_secs_f64();
 
-    // Calculate speeds (bytes per second)
+    // Calculate speeds (bytes per second). The totals drop when an interface goes down or its counters reset.
     let download_speed = if duration > 0.0 {
-        (total_bytes_downloaded - global_stats.last_total_bytes_downloaded) as f64 / duration
+        total_bytes_downloaded.saturating_sub(global_stats.last_total_bytes_downloaded) as f64 / duration
     } else {
         0.0
     };
 
     let upload_speed = if duration > 0.0 {
-        (total_bytes_uploaded - global_stats.last_total_bytes_uploaded) as f64 / duration
+        total_bytes_uploaded.saturating_sub(global_stats.last_total_bytes_uploaded) as f64 / duration
     } else {
         0.0
     };
@@ -2591,217 +8037,1101 @@ async fn get_global_network_stats() -> Result<String, String> {
     ))
 }
 
-// Command to update network usage (would be called from download/upload operations)
-#[tauri::command]
-async fn update_network_usage(downloaded_bytes: u64, uploaded_bytes: u64) -> Result<String, String> {
-    let mut stats = NETWORK_STATS.lock().unwrap();
+// Command to report this app's own network traffic separately from the rest of the machine's traffic.
+// Returns a JSON array with one entry for this process and one "other" entry for everything else.
+#[tauri::command]
+async fn get_per_process_network_stats() -> Result<String, CommandError> {
+    let pid = sysinfo::get_current_pid().ok();
+    let mut system = System::new();
+    let process_name = pid
+        .and_then(|pid| {
+            system.refresh_process(pid);
+            system.process(pid).map(|process| process.name().to_string())
+        })
+        .unwrap_or_else(|| "remote-work".to_string());
+
+    // Same interface totals as get_global_network_stats
+    let networks = Networks::new_with_refreshed_list();
+    let mut system_bytes_downloaded = 0;
+    let mut system_bytes_uploaded = 0;
+    for (interface_name, network) in networks.iter() {
+        if is_loopback_interface(interface_name) {
+            continue;
+        }
+        system_bytes_downloaded += network.total_received();
+        system_bytes_uploaded += network.total_transmitted();
+    }
+
+    let current = PerProcessNetworkSample {
+        own_bytes_downloaded: OWN_BYTES_DOWNLOADED.load(Ordering::Relaxed),
+        own_bytes_uploaded: OWN_BYTES_UPLOADED.load(Ordering::Relaxed),
+        system_bytes_downloaded,
+        system_bytes_uploaded,
+        taken_at: std::time::Instant::now(),
+    };
+
+    let mut last_sample = PER_PROCESS_NETWORK_SAMPLE.lock().map_err(|e| format!("Failed to acquire network sample lock: {}", e))?;
+    let previous = last_sample.clone().unwrap_or_else(|| current.clone());
+    let duration = current.taken_at.duration_since(previous.taken_at).as_secs_f64();
+
+    // Calculate speeds (bytes per second); counters can go backwards when an interface disappears
+    let rate = |now: u64, before: u64| {
+        if duration > 0.0 {
+            now.saturating_sub(before) as f64 / duration
+        } else {
+            0.0
+        }
+    };
+
+    let own_download_rate = rate(current.own_bytes_downloaded, previous.own_bytes_downloaded);
+    let own_upload_rate = rate(current.own_bytes_uploaded, previous.own_bytes_uploaded);
+    let system_download_rate = rate(current.system_bytes_downloaded, previous.system_bytes_downloaded);
+    let system_upload_rate = rate(current.system_bytes_uploaded, previous.system_bytes_uploaded);
+
+    let stats = vec![
+        ProcessNetworkStats {
+            process: process_name,
+            pid: pid.map(|pid| pid.as_u32()),
+            download_bytes_per_sec: own_download_rate,
+            upload_bytes_per_sec: own_upload_rate,
+            total_downloaded: current.own_bytes_downloaded,
+            total_uploaded: current.own_bytes_uploaded,
+        },
+        ProcessNetworkStats {
+            process: "other".to_string(),
+            pid: None,
+            download_bytes_per_sec: (system_download_rate - own_download_rate).max(0.0),
+            upload_bytes_per_sec: (system_upload_rate - own_upload_rate).max(0.0),
+            total_downloaded: current.system_bytes_downloaded.saturating_sub(current.own_bytes_downloaded),
+            total_uploaded: current.system_bytes_uploaded.saturating_sub(current.own_bytes_uploaded),
+        },
+    ];
+
+    *last_sample = Some(current);
+
+    Ok(serde_json::to_string(&stats).map_err(|e| format!("Failed to serialize network stats: {}", e))?)
+}
+
+// Command to update network usage (would be called from download/upload operations)
+#[tauri::command]
+async fn update_network_usage(downloaded_bytes: u64, uploaded_bytes: u64) -> Result<String, CommandError> {
+    let mut stats = NETWORK_STATS.lock().unwrap();
+
+    stats.total_bytes_downloaded += downloaded_bytes;
+    stats.total_bytes_uploaded += uploaded_bytes;
+
+    // Convert bytes to appropriate units for display
+    let total_downloaded_mb = format!("{:.2} MB", stats.total_bytes_downloaded as f64 / (1024.0 * 1024.0));
+    let total_uploaded_mb = format!("{:.2} MB", stats.total_bytes_uploaded as f64 / (1024.0 * 1024.0));
+
+    // Calculate speeds (bytes per second) against the snapshot taken at the start of the interval;
+    // the totals above already include the bytes just added
+    let now = std::time::Instant::now();
+    let (download_speed, upload_speed) = stats.speeds(now);
+    stats.roll_interval(now);
+
+    // Convert speeds to appropriate units
+    let download_speed_str = if download_speed > 1024.0 * 1024.0 {
+        format!("{:.2} MB/s", download_speed / (1024.0 * 1024.0))
+    } else {
+        format!("{:.2} KB/s", download_speed / 1024.0)
+    };
+
+    let upload_speed_str = if upload_speed > 1024.0 * 1024.0 {
+        format!("{:.2} MB/s", upload_speed / (1024.0 * 1024.0))
+    } else {
+        format!("{:.2} KB/s", upload_speed / 1024.0)
+    };
+
+    // Get user ID before saving the sample
+    let user_id = {
+        let user_id_guard = USER_ID.lock().unwrap();
+        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
+        // The guard is automatically dropped at the end of this block
+    };
+
+    // Keep the sample in the local history, the shared database has no table for it
+    let sample = network_history::NetworkSample {
+        user_id,
+        recorded_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        download_bytes_per_sec: download_speed,
+        upload_bytes_per_sec: upload_speed,
+        total_bytes_downloaded: stats.total_bytes_downloaded,
+        total_bytes_uploaded: stats.total_bytes_uploaded,
+        download_speed: download_speed_str,
+        upload_speed: upload_speed_str,
+        total_downloaded: total_downloaded_mb,
+        total_uploaded: total_uploaded_mb,
+    };
+    drop(stats);
+    if let Err(e) = network_history::append(&network_history_path(), &sample) {
+        error!("Failed to save network usage sample: {}", e);
+    }
+
+    Ok("Network usage updated successfully".to_string())
+}
+
+#[tauri::command]
+async fn get_screenshot_intervals() -> Result<String, CommandError> {
+    let min_interval = SCREENSHOT_MIN_INTERVAL.lock().unwrap();
+    let max_interval = SCREENSHOT_MAX_INTERVAL.lock().unwrap();
+
+    Ok(format!("{{\"min\": {}, \"max\": {}}}", *min_interval / 60, *max_interval / 60)) // Return in minutes
+}
+
+#[tauri::command]
+async fn set_screenshot_intervals(min_minutes: u64, max_minutes: u64) -> Result<String, CommandError> {
+    if min_minutes >= max_minutes {
+        return Err(CommandError::InvalidInput("Minimum interval must be less than maximum interval".to_string()));
+    }
+
+    if min_minutes < 1 || max_minutes > 120 {
+        return Err(CommandError::InvalidInput("Intervals must be between 1 and 120 minutes".to_string()));
+    }
+
+    // Convert minutes to seconds
+    let min_seconds = min_minutes * 60;
+    let max_seconds = max_minutes * 60;
+
+    {
+        let mut min_guard = SCREENSHOT_MIN_INTERVAL.lock().unwrap();
+        *min_guard = min_seconds;
+    }
+
+    {
+        let mut max_guard = SCREENSHOT_MAX_INTERVAL.lock().unwrap();
+        *max_guard = max_seconds;
+    }
+
+    Ok(format!("Screenshot intervals updated: min {} min, max {} min", min_minutes, max_minutes))
+}
+
+#[tauri::command]
+async fn get_recording_snapshot_interval() -> Result<String, CommandError> {
+    let (min_interval, max_interval) = recording_snapshot_intervals();
+
+    Ok(serde_json::json!({ "min": min_interval / 60, "max": max_interval / 60 }).to_string()) // Return in minutes
+}
+
+// Command to set how often snapshots are taken alongside a recording, independently of the standalone
+// screenshot intervals. The recording itself captures everything, so the snapshots only need to be frequent
+// enough to browse it, and at most an hour apart so a session always has some.
+#[tauri::command]
+async fn set_recording_snapshot_interval(min_minutes: u64, max_minutes: u64) -> Result<String, CommandError> {
+    if min_minutes >= max_minutes {
+        return Err(CommandError::InvalidInput("Minimum interval must be less than maximum interval".to_string()));
+    }
+
+    if min_minutes < 1 || max_minutes > 60 {
+        return Err(CommandError::InvalidInput("Recording snapshot intervals must be between 1 and 60 minutes".to_string()));
+    }
+
+    *RECORDING_SNAPSHOT_MIN_INTERVAL.lock().map_err(|e| e.to_string())? = min_minutes * 60;
+    *RECORDING_SNAPSHOT_MAX_INTERVAL.lock().map_err(|e| e.to_string())? = max_minutes * 60;
+
+    Ok(format!("Recording snapshot intervals updated: min {} min, max {} min", min_minutes, max_minutes))
+}
+
+// Number of days local screenshots and recordings are kept (0 keeps them forever)
+static RETENTION_DAYS: AtomicU64 = AtomicU64::new(0);
+
+// How often the retention sweep runs
+const RETENTION_SWEEP_INTERVAL_SECS: u64 = 60 * 60;
+
+// Segments of a session that is no longer recording are only treated as orphaned after this long,
+// so a concatenation that is still running or about to be retried is never disturbed
+const ORPHANED_SEGMENT_GRACE_SECS: u64 = 24 * 60 * 60;
+
+// Command to set how many days local captures are kept; 0 disables automatic cleanup. Screenshots are kept
+// locally after upload as well, so without a limit the screenshots directory keeps growing.
+#[tauri::command]
+fn set_retention_days(days: u32) -> Result<String, CommandError> {
+    RETENTION_DAYS.store(days as u64, Ordering::SeqCst);
+
+    if days == 0 {
+        return Ok("Automatic cleanup disabled, local files are kept forever".to_string());
+    }
+
+    // Apply the new limit right away instead of waiting for the next sweep
+    let (deleted, freed_bytes) = run_retention_sweep();
+    Ok(format!(
+        "Local files are kept for {} days ({} old file(s) deleted, {:.2} MB freed)",
+        days,
+        deleted,
+        freed_bytes as f64 / (1024.0 * 1024.0)
+    ))
+}
+
+// Helper function to delete expired captures and orphaned recording segments. Returns (files deleted, bytes freed).
+fn run_retention_sweep() -> (usize, u64) {
+    let retention_days = RETENTION_DAYS.load(Ordering::SeqCst);
+    if retention_days == 0 {
+        return (0, 0);
+    }
+
+    let max_age = std::time::Duration::from_secs(retention_days * 24 * 60 * 60);
+    let orphan_age = std::time::Duration::from_secs(ORPHANED_SEGMENT_GRACE_SECS);
+    let current_session = RECORDING_SESSION_ID.lock().map(|session| session.clone()).unwrap_or(None);
+    let data_dir = get_data_directory();
+
+    let mut deleted = 0;
+    let mut freed_bytes = 0;
+
+    for directory in ["screenshots", "recordings"] {
+        let read_dir = match std::fs::read_dir(data_dir.join(directory)) {
+            Ok(read_dir) => read_dir,
+            Err(_) => continue, // Directory not created yet
+        };
+
+        for entry in read_dir.flatten() {
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .unwrap_or_default();
+
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let is_orphaned_segment = filename.starts_with("recording_")
+                && filename.contains("_seg_")
+                && filename.ends_with(".mkv")
+                && !current_session.as_ref().map(|session| filename.contains(session.as_str())).unwrap_or(false);
+
+            if age > max_age || (is_orphaned_segment && age > orphan_age) {
+                match std::fs::remove_file(entry.path()) {
+                    Ok(_) => {
+                        deleted += 1;
+                        freed_bytes += metadata.len();
+                    }
+                    Err(e) => error!("Failed to delete old file {}: {}", filename, e),
+                }
+            }
+        }
+    }
+
+    if deleted > 0 {
+        info!("Retention sweep deleted {} file(s), freed {} bytes", deleted, freed_bytes);
+    }
 
-    stats.total_bytes_downloaded += downloaded_bytes;
-    stats.total_bytes_uploaded += uploaded_bytes;
+    (deleted, freed_bytes)
+}
 
-    // Update last values and timestamp for speed calculation
-    stats.last_bytes_downloaded = stats.total_bytes_downloaded;
-    stats.last_bytes_uploaded = stats.total_bytes_uploaded;
-    stats.last_updated = std::time::Instant::now();
+// Helper function to start the background task that periodically applies the retention limit
+fn start_retention_sweep_task() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            let _ = tauri::async_runtime::spawn_blocking(run_retention_sweep).await;
+            tokio::time::sleep(Duration::from_secs(RETENTION_SWEEP_INTERVAL_SECS)).await;
+        }
+    });
+}
 
-    // Convert bytes to appropriate units for display
-    let total_downloaded_mb = format!("{:.2} MB", stats.total_bytes_downloaded as f64 / (1024.0 * 1024.0));
-    let total_uploaded_mb = format!("{:.2} MB", stats.total_bytes_uploaded as f64 / (1024.0 * 1024.0));
+// Free space on the data directory's disk, in MB, below which captures are not saved; 0 disables the check
+static MIN_FREE_DISK_MB: AtomicU64 = AtomicU64::new(200);
 
-    // Calculate speeds (bytes per second)
-    let duration = stats.last_updated.elapsed().as_secs_f64();
-    let download_speed = if duration > 0.0 {
-        (stats.total_bytes_downloaded - stats.last_bytes_downloaded) as f64 / duration
-    } else {
-        0.0
-    };
-    let upload_speed = if duration > 0.0 {
-        (stats.total_bytes_uploaded - stats.last_bytes_uploaded) as f64 / duration
-    } else {
-        0.0
-    };
+// Command to set the free disk space captures must leave on the data directory's disk
+#[tauri::command]
+fn set_min_free_disk_mb(mb: u64) -> Result<String, CommandError> {
+    MIN_FREE_DISK_MB.store(mb, Ordering::SeqCst);
 
-    // Convert speeds to appropriate units
-    let download_speed_str = if download_speed > 1024.0 * 1024.0 {
-        format!("{:.2} MB/s", download_speed / (1024.0 * 1024.0))
+    if mb == 0 {
+        Ok("Free disk space check disabled".to_string())
     } else {
-        format!("{:.2} KB/s", download_speed / 1024.0)
-    };
+        Ok(format!("Captures are skipped when less than {} MB of disk space is free", mb))
+    }
+}
 
-    let upload_speed_str = if upload_speed > 1024.0 * 1024.0 {
-        format!("{:.2} MB/s", upload_speed / (1024.0 * 1024.0))
-    } else {
-        format!("{:.2} KB/s", upload_speed / 1024.0)
-    };
+// Helper function to get the free space on the disk holding the given path (the disk with the longest
+// matching mount point). Returns None if the disk can't be determined.
+fn free_disk_space_bytes(path: &std::path::Path) -> Option<u64> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+
+    disks
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
 
-    // Get user ID before saving to database
-    let user_id = {
-        let user_id_guard = USER_ID.lock().unwrap();
-        user_id_guard.as_ref().unwrap_or(&"unknown".to_string()).clone()
-        // The guard is automatically dropped at the end of this block
+// Helper function to check that a capture can be saved without filling the disk. Below the floor, expired files
+// are swept first; if that doesn't free enough, a "disk-low" event is emitted and the capture should be skipped.
+fn ensure_free_disk_space(context: &str) -> Result<(), String> {
+    let min_free_mb = MIN_FREE_DISK_MB.load(Ordering::SeqCst);
+    if min_free_mb == 0 {
+        return Ok(());
+    }
+
+    let data_dir = get_data_directory();
+    let min_free_bytes = min_free_mb * 1024 * 1024;
+    let free_bytes = match free_disk_space_bytes(&data_dir) {
+        Some(free_bytes) if free_bytes < min_free_bytes => {
+            // The retention limit may already allow deleting enough (does nothing when retention is off)
+            let (deleted, _) = run_retention_sweep();
+            match deleted {
+                0 => free_bytes,
+                _ => free_disk_space_bytes(&data_dir).unwrap_or(free_bytes),
+            }
+        }
+        _ => return Ok(()), // Enough space, or unknown disk
     };
 
-    // Save network usage to database
-    if let Err(e) = database::save_network_usage_to_db(
-        &user_id,
-        &download_speed_str,
-        &upload_speed_str,
-        &total_downloaded_mb,
-        &total_uploaded_mb
-    ) {
-        eprintln!("Failed to save network usage to database: {}", e);
+    if free_bytes >= min_free_bytes {
+        return Ok(());
     }
 
-    Ok("Network usage updated successfully".to_string())
+    let free_mb = free_bytes / (1024 * 1024);
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit("disk-low", serde_json::json!({
+            "context": context,
+            "freeMb": free_mb,
+            "minFreeMb": min_free_mb,
+            "path": data_dir.to_string_lossy(),
+        }));
+    }
+
+    Err(format!(
+        "Skipped {}: only {} MB free on the data directory's disk, below the {} MB minimum",
+        context, free_mb, min_free_mb
+    ))
 }
 
-#[tauri::command]
-async fn get_screenshot_intervals() -> Result<String, String> {
-    let min_interval = SCREENSHOT_MIN_INTERVAL.lock().unwrap();
-    let max_interval = SCREENSHOT_MAX_INTERVAL.lock().unwrap();
+// Helper function to sum the size of all files under a directory
+fn directory_size(path: &std::path::Path) -> u64 {
+    let read_dir = match std::fs::read_dir(path) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return 0,
+    };
 
-    Ok(format!("{{\"min\": {}, \"max\": {}}}", *min_interval / 60, *max_interval / 60)) // Return in minutes
+    read_dir
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
 }
 
+// Command to report how much disk space the data directory uses
 #[tauri::command]
-async fn set_screenshot_intervals(min_minutes: u64, max_minutes: u64) -> Result<String, String> {
-    if min_minutes >= max_minutes {
-        return Err("Minimum interval must be less than maximum interval".to_string());
+async fn get_disk_usage() -> Result<String, CommandError> {
+    let data_dir = get_data_directory();
+
+    let usage = serde_json::json!({
+        "total_bytes": directory_size(&data_dir),
+        "screenshots_bytes": directory_size(&data_dir.join("screenshots")),
+        "recordings_bytes": directory_size(&data_dir.join("recordings")),
+        "pending_uploads_bytes": directory_size(&pending_uploads_directory()),
+    });
+
+    Ok(usage.to_string())
+}
+
+// Helper function to collect the effective configuration from the live globals
+fn current_runtime_config() -> config::RuntimeConfig {
+    config::RuntimeConfig {
+        version: config::CONFIG_VERSION,
+        screenshot_min_interval_secs: *SCREENSHOT_MIN_INTERVAL.lock().unwrap(),
+        screenshot_max_interval_secs: *SCREENSHOT_MAX_INTERVAL.lock().unwrap(),
+        recording_snapshot_min_interval_secs: *RECORDING_SNAPSHOT_MIN_INTERVAL.lock().unwrap(),
+        recording_snapshot_max_interval_secs: *RECORDING_SNAPSHOT_MAX_INTERVAL.lock().unwrap(),
+        excluded_windows: EXCLUDED_WINDOWS.lock().unwrap().clone(),
+        adaptive_to_bandwidth: ADAPTIVE_TO_BANDWIDTH.load(Ordering::SeqCst),
+        startup_delay_seconds: STARTUP_DELAY_SECONDS.load(Ordering::SeqCst),
+        capture_on_large_paste: CAPTURE_ON_LARGE_PASTE.load(Ordering::SeqCst),
+        large_paste_min_bytes: LARGE_PASTE_MIN_BYTES.load(Ordering::SeqCst),
+        paste_allowlist: PASTE_ALLOWLIST.lock().unwrap().clone(),
+        capture_on_session_events: CAPTURE_ON_SESSION_EVENTS.load(Ordering::SeqCst),
+        camera_use_mode: CAMERA_USE_MODE.lock().unwrap().clone(),
+        capture_mode: CAPTURE_MODE.lock().unwrap().clone(),
+        screenshot_format: SCREENSHOT_FORMAT.lock().unwrap().clone(),
+        screenshot_quality: *SCREENSHOT_QUALITY.lock().unwrap(),
+        screenshot_max_dimension: SCREENSHOT_MAX_DIMENSION.load(Ordering::SeqCst) as u32,
+        strip_metadata: STRIP_METADATA.load(Ordering::SeqCst),
+        skip_duplicate_threshold: *SKIP_DUPLICATE_THRESHOLD.lock().unwrap(),
+        blank_capture_threshold: *BLANK_CAPTURE_THRESHOLD.lock().unwrap(),
+        max_concurrent_uploads: MAX_CONCURRENT_UPLOADS.load(Ordering::SeqCst) as usize,
+        upload_limit_policy: if UPLOAD_LIMIT_DROPS_FRAMES.load(Ordering::SeqCst) { "drop" } else { "wait" }.to_string(),
+        retention_days: RETENTION_DAYS.load(Ordering::SeqCst) as u32,
+        min_free_disk_mb: MIN_FREE_DISK_MB.load(Ordering::SeqCst),
+        recording_quality: RECORDING_QUALITY_PRESET.lock().unwrap().clone(),
+        recording_encoder: RECORDING_ENCODER.lock().unwrap().clone(),
+        recording_output: RECORDING_OUTPUT.lock().unwrap().clone(),
+        recording_framerate: RECORDING_FRAMERATE.load(Ordering::SeqCst) as u32,
+        max_recording_segment_minutes: MAX_RECORDING_SEGMENT_SECS.load(Ordering::SeqCst) / 60,
+        max_recording_session_minutes: MAX_RECORDING_SESSION_SECS.load(Ordering::SeqCst) / 60,
+        skip_protected_content: SKIP_PROTECTED_CONTENT.load(Ordering::SeqCst),
+        mask_style: MASK_STYLE.lock().unwrap().clone(),
+        watermark_enabled: WATERMARK_ENABLED.load(Ordering::SeqCst),
+        watermark_position: WATERMARK_POSITION.lock().unwrap().clone(),
+        idle_warning_seconds: IDLE_WARNING_SECONDS.load(Ordering::SeqCst),
+        idle_threshold_seconds: IDLE_THRESHOLD_SECONDS.load(Ordering::SeqCst),
+        idle_poll_interval_seconds: IDLE_POLL_INTERVAL_SECONDS.load(Ordering::SeqCst),
+        log_level: logging::current_level(),
+        upload_retry_attempts: UPLOAD_RETRY_ATTEMPTS.load(Ordering::SeqCst),
+        upload_compression: UPLOAD_COMPRESSION.load(Ordering::SeqCst),
+        admin_shortcut: ADMIN_SHORTCUT.lock().unwrap().clone(),
+        stop_shortcut: STOP_SHORTCUT.lock().unwrap().clone(),
+        work_schedule: WORK_SCHEDULE.lock().unwrap().as_ref().map(|schedule| schedule.settings().clone()),
+        default_capture_region: *DEFAULT_CAPTURE_REGION.lock().unwrap(),
+        close_behavior: CLOSE_BEHAVIOR.lock().unwrap().clone(),
+        webcam_camera: WEBCAM_CAMERA.lock().unwrap().clone(),
+        max_upload_bytes: MAX_UPLOAD_BYTES.load(Ordering::SeqCst),
+        ocr_enabled: OCR_ENABLED.load(Ordering::SeqCst),
+        ocr_languages: OCR_LANGUAGES.lock().unwrap().clone(),
     }
+}
 
-    if min_minutes < 1 || max_minutes > 120 {
-        return Err("Intervals must be between 1 and 120 minutes".to_string());
+// Helper function to apply a validated configuration to the live globals
+fn apply_runtime_config(app: &tauri::AppHandle, runtime_config: &config::RuntimeConfig) -> Result<(), String> {
+    runtime_config.validate()?;
+
+    // Check the excluded window regexes before changing anything so a bad one doesn't leave a half-applied config
+    let excluded_windows = runtime_config
+        .excluded_windows
+        .iter()
+        .map(|entry| normalize_excluded_window_entry(entry))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    *SCREENSHOT_MIN_INTERVAL.lock().map_err(|e| e.to_string())? = runtime_config.screenshot_min_interval_secs;
+    *SCREENSHOT_MAX_INTERVAL.lock().map_err(|e| e.to_string())? = runtime_config.screenshot_max_interval_secs;
+    *RECORDING_SNAPSHOT_MIN_INTERVAL.lock().map_err(|e| e.to_string())? = runtime_config.recording_snapshot_min_interval_secs;
+    *RECORDING_SNAPSHOT_MAX_INTERVAL.lock().map_err(|e| e.to_string())? = runtime_config.recording_snapshot_max_interval_secs;
+    *EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())? = excluded_windows;
+    ADAPTIVE_TO_BANDWIDTH.store(runtime_config.adaptive_to_bandwidth, Ordering::SeqCst);
+    STARTUP_DELAY_SECONDS.store(runtime_config.startup_delay_seconds, Ordering::SeqCst);
+    *CAMERA_USE_MODE.lock().map_err(|e| e.to_string())? = runtime_config.camera_use_mode.clone();
+    *CAPTURE_MODE.lock().map_err(|e| e.to_string())? = runtime_config.capture_mode.clone();
+    *SCREENSHOT_FORMAT.lock().map_err(|e| e.to_string())? = runtime_config.screenshot_format.clone();
+    *SCREENSHOT_QUALITY.lock().map_err(|e| e.to_string())? = runtime_config.screenshot_quality;
+    SCREENSHOT_MAX_DIMENSION.store(runtime_config.screenshot_max_dimension as u64, Ordering::SeqCst);
+    STRIP_METADATA.store(runtime_config.strip_metadata, Ordering::SeqCst);
+    *SKIP_DUPLICATE_THRESHOLD.lock().map_err(|e| e.to_string())? = runtime_config.skip_duplicate_threshold;
+    *BLANK_CAPTURE_THRESHOLD.lock().map_err(|e| e.to_string())? = runtime_config.blank_capture_threshold;
+    if runtime_config.max_concurrent_uploads as u64 != MAX_CONCURRENT_UPLOADS.load(Ordering::SeqCst) {
+        *UPLOAD_SEMAPHORE.lock().map_err(|e| e.to_string())? = Arc::new(tokio::sync::Semaphore::new(runtime_config.max_concurrent_uploads));
+        MAX_CONCURRENT_UPLOADS.store(runtime_config.max_concurrent_uploads as u64, Ordering::SeqCst);
+    }
+    UPLOAD_LIMIT_DROPS_FRAMES.store(runtime_config.upload_limit_policy == "drop", Ordering::SeqCst);
+    RETENTION_DAYS.store(runtime_config.retention_days as u64, Ordering::SeqCst);
+    MIN_FREE_DISK_MB.store(runtime_config.min_free_disk_mb, Ordering::SeqCst);
+    set_recording_quality(runtime_config.recording_quality.clone(), Some(runtime_config.recording_encoder.clone()))?;
+    set_recording_output(runtime_config.recording_output.clone())?;
+    RECORDING_FRAMERATE.store(runtime_config.recording_framerate as u64, Ordering::SeqCst);
+    MAX_RECORDING_SEGMENT_SECS.store(runtime_config.max_recording_segment_minutes * 60, Ordering::SeqCst);
+    MAX_RECORDING_SESSION_SECS.store(runtime_config.max_recording_session_minutes * 60, Ordering::SeqCst);
+    SKIP_PROTECTED_CONTENT.store(runtime_config.skip_protected_content, Ordering::SeqCst);
+    *MASK_STYLE.lock().map_err(|e| e.to_string())? = runtime_config.mask_style.clone();
+    *WATERMARK_POSITION.lock().map_err(|e| e.to_string())? = runtime_config.watermark_position.clone();
+    WATERMARK_ENABLED.store(runtime_config.watermark_enabled, Ordering::SeqCst);
+    IDLE_WARNING_SECONDS.store(runtime_config.idle_warning_seconds, Ordering::SeqCst);
+    IDLE_THRESHOLD_SECONDS.store(runtime_config.idle_threshold_seconds, Ordering::SeqCst);
+    IDLE_POLL_INTERVAL_SECONDS.store(runtime_config.idle_poll_interval_seconds, Ordering::SeqCst);
+    logging::set_level(&runtime_config.log_level)?;
+    UPLOAD_RETRY_ATTEMPTS.store(runtime_config.upload_retry_attempts, Ordering::SeqCst);
+    UPLOAD_COMPRESSION.store(runtime_config.upload_compression, Ordering::SeqCst);
+    *ADMIN_SHORTCUT.lock().map_err(|e| e.to_string())? = runtime_config.admin_shortcut.clone();
+    *STOP_SHORTCUT.lock().map_err(|e| e.to_string())? = runtime_config.stop_shortcut.clone();
+    *WORK_SCHEDULE.lock().map_err(|e| e.to_string())? = runtime_config
+        .work_schedule
+        .as_ref()
+        .map(work_schedule::WorkSchedule::parse)
+        .transpose()?;
+    OUTSIDE_WORK_HOURS.store(false, Ordering::SeqCst);
+    // Screens differ between machines, so the region is only checked against them when capturing
+    *DEFAULT_CAPTURE_REGION.lock().map_err(|e| e.to_string())? = runtime_config.default_capture_region;
+    set_close_behavior(runtime_config.close_behavior.clone())?;
+    // Cameras differ between machines, so the ID is only looked up when taking a snapshot
+    *WEBCAM_CAMERA.lock().map_err(|e| e.to_string())? = runtime_config.webcam_camera.clone();
+    MAX_UPLOAD_BYTES.store(runtime_config.max_upload_bytes, Ordering::SeqCst);
+    // Tesseract may be missing on this machine, which only fails the OCR pass of each screenshot
+    *OCR_LANGUAGES.lock().map_err(|e| e.to_string())? = runtime_config.ocr_languages.clone();
+    OCR_ENABLED.store(runtime_config.ocr_enabled, Ordering::SeqCst);
+    if let Err(e) = register_global_shortcuts(app) {
+        warn!("Global shortcuts not applied: {}", e);
     }
 
-    // Convert minutes to seconds
-    let min_seconds = min_minutes * 60;
-    let max_seconds = max_minutes * 60;
+    // Restart the paste watcher with the new settings (it is Windows only, so don't fail the whole import)
+    if let Err(e) = set_capture_on_large_paste(
+        app.clone(),
+        runtime_config.capture_on_large_paste,
+        runtime_config.large_paste_min_bytes,
+        Some(runtime_config.paste_allowlist.clone()),
+    ) {
+        warn!("Capture on large paste not applied: {}", e);
+    }
 
-    {
-        let mut min_guard = SCREENSHOT_MIN_INTERVAL.lock().unwrap();
-        *min_guard = min_seconds;
+    // The session event watcher isn't available everywhere, so don't fail the whole import either
+    if let Err(e) = set_capture_on_session_events(app.clone(), runtime_config.capture_on_session_events) {
+        warn!("Capture on session events not applied: {}", e);
     }
 
-    {
-        let mut max_guard = SCREENSHOT_MAX_INTERVAL.lock().unwrap();
-        *max_guard = max_seconds;
+    Ok(())
+}
+
+// Helper function to load the configuration file from the data directory at startup, if there is one
+fn load_runtime_config_file(app: &tauri::AppHandle) {
+    let path = config::config_file_path(&get_data_directory());
+    if !path.exists() {
+        return;
     }
 
-    Ok(format!("Screenshot intervals updated: min {} min, max {} min", min_minutes, max_minutes))
+    match config::load_config(&path).and_then(|runtime_config| apply_runtime_config(app, &runtime_config)) {
+        Ok(()) => info!("Loaded configuration from {}", path.display()),
+        Err(e) => warn!("Failed to load configuration, using defaults: {}", e),
+    }
+}
+
+#[tauri::command]
+async fn get_runtime_config() -> Result<String, CommandError> {
+    match serde_json::to_string(&current_runtime_config()) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(CommandError::Internal(format!("Failed to serialize configuration: {}", e))),
+    }
+}
+
+// Command to write the complete effective configuration to a file that can be imported on another machine
+#[tauri::command]
+async fn export_config(path: String) -> Result<String, CommandError> {
+    config::save_config(std::path::Path::new(&path), &current_runtime_config())?;
+    Ok(format!("Configuration exported to {}", path))
+}
+
+// Command to validate and apply a configuration file, then make it the agent's stored configuration
+#[tauri::command]
+async fn import_config(app: tauri::AppHandle, path: String) -> Result<String, CommandError> {
+    let runtime_config = config::load_config(std::path::Path::new(&path)).map_err(CommandError::InvalidInput)?;
+    apply_runtime_config(&app, &runtime_config).map_err(CommandError::InvalidInput)?;
+    persist_excluded_windows(&EXCLUDED_WINDOWS.lock().map_err(|e| e.to_string())?);
+
+    let config_path = config::config_file_path(&get_data_directory());
+    config::save_config(&config_path, &current_runtime_config())?;
+
+    Ok(format!("Configuration imported from {} and saved to {}", path, config_path.display()))
 }
 
 // Database retrieval commands for admin interface
 
 #[tauri::command]
-async fn get_screenshots_by_session(session_id: String) -> Result<String, String> {
+async fn get_screenshots_by_session(session_id: String) -> Result<String, CommandError> {
     // Get user ID before retrieving data
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-    let user_id = user_id_guard.as_ref().ok_or("User ID not set")?.clone();
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
     drop(user_id_guard); // Release the lock early
 
     match database::get_screenshots_by_session(&user_id, &session_id) {
         Ok(screenshots) => {
             match serde_json::to_string(&screenshots) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize screenshots: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize screenshots: {}", e))),
             }
         }
-        Err(e) => Err(format!("Failed to get screenshots from database: {}", e)),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get screenshots from database: {}", e))),
     }
 }
 
 #[tauri::command]
-async fn get_all_screenshots(limit: Option<u32>) -> Result<String, String> {
+async fn get_all_screenshots(limit: Option<u32>) -> Result<String, CommandError> {
     // Get user ID before retrieving data
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-    let user_id = user_id_guard.as_ref().ok_or("User ID not set")?.clone();
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
     drop(user_id_guard); // Release the lock early
 
     match database::get_all_screenshots(&user_id, limit) {
         Ok(screenshots) => {
             match serde_json::to_string(&screenshots) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize screenshots: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize screenshots: {}", e))),
+            }
+        }
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get screenshots from database: {}", e))),
+    }
+}
+
+// Command to look up one of the current user's screenshots by ID, e.g. for deep links or to confirm ownership
+// before deleting it. Returns null if there is no such screenshot or it belongs to someone else.
+#[tauri::command]
+async fn get_screenshot(id: u32) -> Result<String, CommandError> {
+    // Get user ID before retrieving data
+    let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
+    drop(user_id_guard); // Release the lock early
+
+    // Without the database every lookup would come back empty, as if the screenshot didn't exist
+    if !database::is_database_available() {
+        return Err(CommandError::DatabaseUnavailable(format!("The database is unavailable, screenshot {} can't be looked up", id)));
+    }
+
+    match database::get_screenshot(&user_id, id) {
+        Ok(screenshot) => Ok(serde_json::to_string(&screenshot).map_err(|e| format!("Failed to serialize screenshot: {}", e))?),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get screenshot {} from database: {}", id, e))),
+    }
+}
+
+// Command to list the segments of a recording session, for debugging pause/resume sessions whose
+// concatenation failed. Falls back to the segment files on disk when the database has none.
+#[tauri::command]
+async fn get_recording_segments(session_id: String) -> Result<String, CommandError> {
+    // Get user ID before retrieving data
+    let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
+    drop(user_id_guard); // Release the lock early
+
+    let segments = recording_segments_for_session(&user_id, &session_id)?;
+
+    match serde_json::to_string(&segments) {
+        Ok(json) => Ok(json),
+        Err(e) => Err(CommandError::Internal(format!("Failed to serialize recording segments: {}", e))),
+    }
+}
+
+// Helper function to get the segments of a recording session from the database, or from the segment files on disk
+fn recording_segments_for_session(user_id: &str, session_id: &str) -> Result<Vec<database::RecordingSegmentData>, CommandError> {
+    let mut segments = database::get_recording_segments(user_id, session_id)
+        .map_err(|e| CommandError::DatabaseUnavailable(format!("Failed to get recording segments from database: {}", e)))?;
+
+    if segments.is_empty() {
+        for (segment_number, path) in local_segment_files(session_id) {
+            segments.push(database::RecordingSegmentData {
+                segment_number,
+                duration_seconds: probe_duration_seconds(&path),
+                file_size: std::fs::metadata(&path).ok().map(|metadata| metadata.len() as i64),
+                filename: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            });
+        }
+    }
+
+    Ok(segments)
+}
+
+// Helper function to list a session's segment files in the recordings directory, in segment order
+fn local_segment_files(session_id: &str) -> Vec<(i32, PathBuf)> {
+    let prefix = format!("recording_{}_seg_", session_id);
+    let mut segments = Vec::new();
+
+    if let Ok(read_dir) = std::fs::read_dir(get_data_directory().join("recordings")) {
+        for entry in read_dir.flatten() {
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let segment_number = filename
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".mkv"))
+                .map(|number| number.trim_end_matches("_compressed")) // Re-encoded while paused
+                .and_then(|number| number.parse::<i32>().ok());
+
+            if let Some(segment_number) = segment_number {
+                segments.push((segment_number, entry.path()));
             }
         }
-        Err(e) => Err(format!("Failed to get screenshots from database: {}", e)),
     }
+
+    segments.sort();
+    segments
+}
+
+// Command to bundle everything recorded for a session into one JSON report in the data directory, for offline audits.
+// Only reads the local database and data directory, so it works while the upload server is unreachable.
+#[tauri::command]
+async fn export_session(session_id: String) -> Result<String, CommandError> {
+    // Session IDs become part of the report's filename
+    validate_session_id(&session_id).map_err(CommandError::InvalidInput)?;
+
+    // Get user ID before retrieving data
+    let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
+    drop(user_id_guard); // Release the lock early
+
+    let data_dir = get_data_directory();
+    let screenshots_dir = data_dir.join("screenshots");
+    let recordings_dir = data_dir.join("recordings");
+
+    // Missing data is left out of the report rather than failing the whole export
+    let screenshots = database::get_screenshots_by_session(&user_id, &session_id).unwrap_or_else(|e| {
+        warn!("Failed to get screenshots for session export: {}", e);
+        Vec::new()
+    });
+    let recordings: Vec<database::RecordingData> = database::get_recordings(&user_id, None)
+        .unwrap_or_else(|e| {
+            warn!("Failed to get recordings for session export: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .filter(|recording| recording.session_id == session_id)
+        .collect();
+    let segments = recording_segments_for_session(&user_id, &session_id).unwrap_or_else(|e| {
+        error!("{}", e);
+        Vec::new()
+    });
+
+    // The session spans from its first to its last capture; activity rows are matched against that range
+    let session_start = screenshots
+        .iter()
+        .map(|screenshot| &screenshot.created_at)
+        .chain(recordings.iter().map(|recording| &recording.created_at))
+        .min()
+        .cloned();
+    let session_end = screenshots
+        .iter()
+        .map(|screenshot| &screenshot.created_at)
+        .chain(recordings.iter().map(|recording| &recording.created_at))
+        .max()
+        .cloned();
+    let activity: Vec<database::UserActivityData> = match (&session_start, &session_end) {
+        (Some(start), Some(end)) => database::get_user_activity(&user_id, None)
+            .unwrap_or_else(|e| {
+                warn!("Failed to get user activity for session export: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .filter(|row| within_session_range(&row.timestamp, start, end))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let report = serde_json::json!({
+        "session_id": session_id,
+        "user_id": user_id,
+        "exported_at": SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        "session_start": session_start,
+        "session_end": session_end,
+        "screenshots": screenshots
+            .iter()
+            .map(|screenshot| with_local_file_flag(screenshot, &screenshots_dir.join(&screenshot.filename)))
+            .collect::<Vec<_>>(),
+        "recordings": recordings
+            .iter()
+            .map(|recording| with_local_file_flag(recording, &recordings_dir.join(&recording.filename)))
+            .collect::<Vec<_>>(),
+        "recording_segments": segments
+            .iter()
+            .map(|segment| with_local_file_flag(segment, &recordings_dir.join(&segment.filename)))
+            .collect::<Vec<_>>(),
+        "user_activity": activity,
+    });
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize session report: {}", e))?;
+    let report_path = data_dir.join(format!("session_{}_report.json", session_id));
+    std::fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    std::fs::write(&report_path, json).map_err(|e| format!("Failed to write session report: {}", e))?;
+
+    Ok(report_path.to_string_lossy().to_string())
+}
+
+// Helper function to add whether a report entry's media file is still on disk
+fn with_local_file_flag<T: serde::Serialize>(entry: &T, path: &std::path::Path) -> serde_json::Value {
+    let mut value = serde_json::to_value(entry).unwrap_or(serde_json::Value::Null);
+    if let Some(object) = value.as_object_mut() {
+        object.insert("local_file_exists".to_string(), serde_json::Value::Bool(path.is_file()));
+    }
+    value
+}
+
+// Helper function to tell whether a database timestamp falls in a session's range. Bounds may be dates
+// without a time (web_images only stores the date), so the end is compared at the bound's precision.
+fn within_session_range(timestamp: &str, start: &str, end: &str) -> bool {
+    let end_precision = timestamp.get(..end.len()).unwrap_or(timestamp);
+    timestamp >= start && end_precision <= end
 }
 
 #[tauri::command]
-async fn get_recordings(limit: Option<u32>) -> Result<String, String> {
+async fn get_recordings(limit: Option<u32>) -> Result<String, CommandError> {
     // Get user ID before retrieving data
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-    let user_id = user_id_guard.as_ref().ok_or("User ID not set")?.clone();
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
     drop(user_id_guard); // Release the lock early
 
     match database::get_recordings(&user_id, limit) {
         Ok(recordings) => {
             match serde_json::to_string(&recordings) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize recordings: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize recordings: {}", e))),
+            }
+        }
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get recordings from database: {}", e))),
+    }
+}
+
+// Command to delete a screenshot, e.g. an accidental capture of a sensitive screen. By default the database row is
+// only marked as deleted; hard_delete removes it and the local file, remove_local_file removes just the local file.
+#[tauri::command]
+async fn delete_screenshot(id: u32, hard_delete: Option<bool>, remove_local_file: Option<bool>) -> Result<String, CommandError> {
+    delete_captured_item(id, "screenshot", hard_delete.unwrap_or(false), remove_local_file.unwrap_or(false))
+}
+
+// Command to delete a recording, with the same options as delete_screenshot. Removing the local files also removes
+// the recording's segment files.
+#[tauri::command]
+async fn delete_recording(id: u32, hard_delete: Option<bool>, remove_local_file: Option<bool>) -> Result<String, CommandError> {
+    delete_captured_item(id, "recording", hard_delete.unwrap_or(false), remove_local_file.unwrap_or(false))
+}
+
+// Helper function to delete a screenshot or recording of the current user, returns a JSON summary
+fn delete_captured_item(id: u32, item_type: &str, hard_delete: bool, remove_local_file: bool) -> Result<String, CommandError> {
+    // Get user ID before deleting, only the user's own items can be deleted
+    let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
+    drop(user_id_guard); // Release the lock early
+
+    let (filename, session_id) = database::delete_web_image(&user_id, id, item_type, hard_delete)
+        .map_err(|e| CommandError::DatabaseUnavailable(format!("Failed to delete {} {}: {}", item_type, id, e)))?
+        .ok_or_else(|| CommandError::NotFound(format!("No {} with ID {} found", item_type, id)))?;
+
+    let mut removed_files = Vec::new();
+    if hard_delete || remove_local_file {
+        let directory = match item_type {
+            "screenshot" => "screenshots",
+            _ => "recordings",
+        };
+        let mut paths = Vec::new();
+        // The file name comes from the database, never follow it outside the data directory
+        if !filename.is_empty() && !filename.contains(['/', '\\']) && filename != ".." {
+            paths.push(get_data_directory().join(directory).join(&filename));
+        }
+        if item_type == "recording" {
+            paths.extend(local_segment_files(&session_id).into_iter().map(|(_, path)| path));
+        }
+
+        for path in paths.into_iter().filter(|path| path.is_file()) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed_files.push(path.to_string_lossy().to_string()),
+                Err(e) => error!("Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    info!("Deleted {} {} ({}), removed {} local file(s)", item_type, id, if hard_delete { "hard" } else { "soft" }, removed_files.len());
+
+    Ok(serde_json::json!({
+        "id": id,
+        "type": item_type,
+        "hard_delete": hard_delete,
+        "removed_files": removed_files,
+    })
+    .to_string())
+}
+
+// Command to erase everything stored about a user, e.g. when an employee leaves: their screenshot and recording
+// files in the data directory, their queued uploads and network usage samples and their web_images and user_activity rows (a soft purge
+// only marks the web_images rows deleted, see database::purge_user_rows). Unlike retention cleanup it removes
+// files of any age. Monitoring is stopped first if the user is the current one, who is then unset.
+// Returns how many files and rows were removed.
+#[tauri::command]
+async fn purge_user_data(app: tauri::AppHandle, user_id: String, hard_delete: Option<bool>) -> Result<String, CommandError> {
+    let hard_delete = hard_delete.unwrap_or(false);
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err(CommandError::InvalidInput("User ID must not be empty".to_string()));
+    }
+
+    // Nothing may be captured for the user while their data is being erased
+    let is_current_user = USER_ID.lock().map_err(|e| e.to_string())?.as_deref() == Some(user_id.as_str());
+    if is_current_user {
+        stop_all_processes(app).await?;
+    }
+
+    // The rows tell which local files are the user's, so nothing is removed unless they could be read
+    let purged = database::purge_user_rows(&user_id, hard_delete)
+        .map_err(|e| CommandError::DatabaseUnavailable(format!("Failed to purge data of user {}: {}", user_id, e)))?;
+
+    let data_dir = get_data_directory();
+    let mut paths = Vec::new();
+    for (item_type, filename, session_id) in &purged.items {
+        let directory = match item_type.as_str() {
+            "screenshot" => "screenshots",
+            "webcam" => "webcam",
+            _ => "recordings",
+        };
+        // The file name comes from the database, never follow it outside the data directory
+        if !filename.is_empty() && !filename.contains(['/', '\\']) && filename != ".." {
+            paths.push(data_dir.join(directory).join(filename));
+            // Text recognized on a screenshot is kept next to it
+            if item_type == "screenshot" {
+                paths.push(data_dir.join(directory).join(format!("{}.txt", filename)));
             }
         }
-        Err(e) => Err(format!("Failed to get recordings from database: {}", e)),
+        if item_type == "recording" && validate_session_id(session_id).is_ok() {
+            paths.extend(local_segment_files(session_id).into_iter().map(|(_, path)| path));
+        }
+    }
+
+    let mut removed_files = 0;
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed_files += 1,
+            Err(e) => error!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+
+    // Captures that never reached the server are the user's too
+    let queue_dir = pending_uploads_directory();
+    let mut removed_pending_uploads = 0;
+    for (id, upload) in upload_queue::list(&queue_dir) {
+        if upload.user_id != user_id {
+            continue;
+        }
+        match upload_queue::remove(&queue_dir, &id) {
+            Ok(()) => removed_pending_uploads += 1,
+            Err(e) => error!("Failed to remove queued upload {}: {}", id, e),
+        }
+    }
+
+    let removed_network_samples = network_history::remove_user(&network_history_path(), &user_id).unwrap_or_else(|e| {
+        error!("Failed to remove network usage samples: {}", e);
+        0
+    });
+
+    if is_current_user {
+        *USER_ID.lock().map_err(|e| e.to_string())? = None;
     }
+
+    info!(
+        "Purged data of user {} ({}): {} files, {} queued uploads, {} network samples, {} web_images rows, {} user_activity rows",
+        user_id,
+        if hard_delete { "hard" } else { "soft" },
+        removed_files,
+        removed_pending_uploads,
+        removed_network_samples,
+        purged.web_images,
+        purged.user_activity
+    );
+
+    Ok(serde_json::json!({
+        "user_id": user_id,
+        "hard_delete": hard_delete,
+        "removed_files": removed_files,
+        "removed_pending_uploads": removed_pending_uploads,
+        "removed_network_samples": removed_network_samples,
+        "web_images_rows": purged.web_images,
+        "user_activity_rows": purged.user_activity,
+        "current_user_cleared": is_current_user,
+    })
+    .to_string())
 }
 
 #[tauri::command]
-async fn get_user_activity(limit: Option<u32>) -> Result<String, String> {
+async fn get_user_activity(limit: Option<u32>) -> Result<String, CommandError> {
     // Get user ID before retrieving data
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-    let user_id = user_id_guard.as_ref().ok_or("User ID not set")?.clone();
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
     drop(user_id_guard); // Release the lock early
 
     match database::get_user_activity(&user_id, limit) {
         Ok(activity) => {
             match serde_json::to_string(&activity) {
                 Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize user activity: {}", e)),
+                Err(e) => Err(CommandError::Internal(format!("Failed to serialize user activity: {}", e))),
             }
         }
-        Err(e) => Err(format!("Failed to get user activity from database: {}", e)),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get user activity from database: {}", e))),
     }
 }
 
+// Command to get one user's activity totals for a day (YYYY-MM-DD), for timesheets: active and idle seconds,
+// the number of idle episodes and the longest one
 #[tauri::command]
-async fn get_network_usage(limit: Option<u32>) -> Result<String, String> {
+async fn get_activity_summary(user_id: String, date: String) -> Result<String, CommandError> {
+    let date = chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| CommandError::InvalidInput(format!("Invalid date '{}': expected YYYY-MM-DD", date)))?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    match database::get_activity_summary(&user_id, &date) {
+        Ok(summary) => Ok(serde_json::to_string(&summary).map_err(|e| format!("Failed to serialize activity summary: {}", e))?),
+        Err(e) => Err(CommandError::DatabaseUnavailable(format!("Failed to get activity summary from database: {}", e))),
+    }
+}
+
+// Command to get the current user's network usage history from the local samples, newest first
+#[tauri::command]
+async fn get_network_usage(limit: Option<u32>) -> Result<String, CommandError> {
     // Get user ID before retrieving data
     let user_id_guard = USER_ID.lock().map_err(|e| e.to_string())?;
-    let user_id = user_id_guard.as_ref().ok_or("User ID not set")?.clone();
+    let user_id = user_id_guard
+        .as_ref()
+        .ok_or_else(|| CommandError::UserNotSet("User ID not set".to_string()))?
+        .clone();
     drop(user_id_guard); // Release the lock early
 
-    match database::get_network_usage(&user_id, limit) {
-        Ok(usage) => {
-            match serde_json::to_string(&usage) {
-                Ok(json) => Ok(json),
-                Err(e) => Err(format!("Failed to serialize network usage: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Failed to get network usage from database: {}", e)),
-    }
+    let usage = tokio::task::spawn_blocking(move || network_history::list(&network_history_path(), &user_id, limit))
+        .await
+        .map_err(|e| format!("Failed to read network usage: {}", e))??;
+    Ok(serde_json::to_string(&usage).map_err(|e| format!("Failed to serialize network usage: {}", e))?)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Log to the data directory from the start, the configured level is applied once the config is loaded
+    logging::init(&get_data_directory().join("logs"));
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .plugin({
-            let shortcut_builder = tauri_plugin_global_shortcut::Builder::new();
-            let shortcut_builder = shortcut_builder.with_shortcuts(["Ctrl+Shift+`"].iter().cloned()).expect("Failed to register global shortcut");
-            shortcut_builder
-                .with_handler(move |app, _shortcut, event| {
+        .plugin(
+            // Shortcuts themselves are registered in setup so they can be rebound at runtime
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(move |app, shortcut, event| {
                     if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
-                        // Open admin window when the global shortcut is pressed
-                        let app_handle = app.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let _ = create_admin_window_internal(&app_handle).await;
-                        });
+                        handle_global_shortcut(app, shortcut);
                     }
                 })
                 .build()
-        })
+        )
         .setup(|app| {
+            // Start the clock for the auto-resume startup delay
+            lazy_static::initialize(&APP_STARTED_AT);
+
+            // Keep a handle for events emitted from background code such as uploads
+            let _ = APP_HANDLE.set(app.handle().clone());
+
+            // Apply the stored configuration before anything starts capturing
+            load_runtime_config_file(app.handle());
+            let ignored_exclusions = load_excluded_windows_file();
+            if !ignored_exclusions.is_empty() {
+                warn!("Excluded windows entries not restored: {}", ignored_exclusions.join(", "));
+            }
+
+            // Recover from a previous run that exited while monitoring
+            check_interrupted_session(app.handle());
+
+            // Register the admin and panic stop shortcuts with the configured accelerators
+            if let Err(e) = register_global_shortcuts(app.handle()) {
+                error!("Failed to register global shortcuts: {}", e);
+            }
+
+            // Retry uploads that failed in a previous run or while offline
+            start_pending_upload_retry_task();
+
+            // Prune local captures older than the retention limit
+            start_retention_sweep_task();
+
             // Create the main window when the app starts
             create_main_window(app.handle())?;
 
@@ -2810,16 +9140,24 @@ pub fn run() {
                 let window_clone = window.clone();
                 window.on_window_event(move |event| {
                     if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                        // Prevent the window from closing
+                        // Prevent the window from closing, then hide it or quit depending on the close behavior
                         api.prevent_close();
-
-                        // Hide the window instead of closing it
-                        let _ = window_clone.hide();
+                        handle_main_window_close(&window_clone);
                     }
                 });
             }
 
+            // Verify database write access in the background so a missing GRANT surfaces at startup
+            let db_check_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                if let Err(e) = run_db_write_access_check(&db_check_handle) {
+                    warn!("Startup database write check skipped: {}", e);
+                }
+            });
+
             // Create the system tray
+            let status_item = tauri::menu::MenuItem::with_id(app, "status", "○ Idle", false, None::<&str>).unwrap();
+            let status_separator = tauri::menu::PredefinedMenuItem::separator(app).unwrap();
             let show_item = tauri::menu::MenuItem::with_id(app, "show", "Show", true, None::<&str>).unwrap();
             let hide_item = tauri::menu::MenuItem::with_id(app, "hide", "Hide", true, None::<&str>).unwrap();
             let start_monitoring_item = tauri::menu::MenuItem::with_id(app, "start_monitoring", "Start Monitoring", true, None::<&str>).unwrap();
@@ -2828,6 +9166,8 @@ pub fn run() {
             let quit_item = tauri::menu::MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).unwrap();
 
             let tray_menu = tauri::menu::MenuBuilder::new(app)
+                .item(&status_item)
+                .item(&status_separator)
                 .item(&show_item)
                 .item(&hide_item)
                 .item(&start_monitoring_item)
@@ -2858,13 +9198,13 @@ pub fn run() {
                         "start_monitoring" => {
                             // Emit an event to start monitoring from the frontend
                             if let Err(e) = app.emit("start-monitoring-request", ()) {
-                                eprintln!("Failed to emit start-monitoring-request: {}", e);
+                                error!("Failed to emit start-monitoring-request: {}", e);
                             }
                         }
                         "stop_monitoring" => {
                             // Emit an event to stop monitoring from the frontend
                             if let Err(e) = app.emit("stop-monitoring-request", ()) {
-                                eprintln!("Failed to emit stop-monitoring-request: {}", e);
+                                error!("Failed to emit stop-monitoring-request: {}", e);
                             }
                         }
                         "quit" => {
@@ -2925,6 +9265,10 @@ pub fn run() {
                 .build(app)
                 .unwrap();
 
+            // Processes may have been started (e.g. auto-resumed) before the tray existed
+            let _ = TRAY_STATUS_ITEM.set(status_item);
+            refresh_tray_status();
+
             Ok(())
         })
         .on_menu_event(|app, event| {
@@ -2960,6 +9304,7 @@ pub fn run() {
             stop_screenshotting,
             start_combined_recording,
             stop_combined_recording,
+            check_ffmpeg,
             stop_all_processes,
             get_process_status,
             update_user_activity,
@@ -2972,27 +9317,113 @@ pub fn run() {
             stop_idle_detection,
             add_excluded_window,
             remove_excluded_window,
+            reset_excluded_windows,
+            clear_excluded_windows,
             get_excluded_windows,
             create_admin_window,
             pause_combined_recording,
             resume_combined_recording,
+            split_recording_segment,
             get_screenshot_intervals,
             set_screenshot_intervals,
+            get_recording_snapshot_interval,
+            set_recording_snapshot_interval,
             get_network_stats,
             get_global_network_stats,
             update_network_usage,
+            get_per_process_network_stats,
             get_screenshots_by_session,
             get_all_screenshots,
+            get_screenshot,
             get_recordings,
+            get_recording_segments,
             get_user_activity,
+            purge_user_data,
+            get_activity_summary,
             get_network_usage,
             set_user_id,
+            switch_user,
             get_user_id,
             is_user_id_set,
             create_user,
             get_user,
             get_all_users,
-            user_exists
+            user_exists,
+            check_db_write_access,
+            set_adaptive_to_bandwidth,
+            set_manifest_signing_key,
+            set_manifest_public_key,
+            generate_session_manifest,
+            verify_session_manifest,
+            set_startup_delay,
+            set_capture_on_large_paste,
+            set_capture_on_session_events,
+            set_work_schedule,
+            clear_work_schedule,
+            capture_region,
+            set_default_capture_region,
+            clear_default_capture_region,
+            set_close_behavior,
+            list_cameras,
+            set_webcam_camera,
+            capture_webcam_snapshot,
+            set_max_upload_bytes,
+            get_next_snapshot_time,
+            set_ocr_enabled,
+            repair_recording_segments,
+            set_pause_during_camera_use,
+            set_skip_protected_content,
+            set_mask_style,
+            get_last_upload_time,
+            set_upload_retry_attempts,
+            set_upload_compression,
+            pause_all_processes,
+            resume_all_processes,
+            set_log_level,
+            delete_screenshot,
+            delete_recording,
+            set_idle_poll_interval,
+            set_strip_metadata,
+            get_interrupted_session,
+            get_process_status_json,
+            set_screenshot_max_dimension,
+            save_file_stream,
+            upload_recording,
+            set_max_concurrent_uploads,
+            set_blank_capture_threshold,
+            export_session,
+            set_proxy,
+            set_max_recording_duration,
+            cancel_ffmpeg_download,
+            set_min_free_disk_mb,
+            capture_window,
+            set_skip_duplicate_threshold,
+            set_watermark,
+            list_screens,
+            start_timelapse,
+            stop_timelapse,
+            preview_masking,
+            start_heartbeat,
+            set_auth_token,
+            stop_heartbeat,
+            set_idle_thresholds,
+            set_capture_mode,
+            set_screenshot_format,
+            set_recording_quality,
+            set_recording_output,
+            set_recording_framerate,
+            set_global_shortcut,
+            flush_pending_uploads,
+            pause_screenshotting,
+            resume_screenshotting,
+            set_retention_days,
+            get_disk_usage,
+            check_database_connection,
+            set_database_config,
+            capture_screenshot_now,
+            get_runtime_config,
+            export_config,
+            import_config
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -3022,15 +9453,162 @@ fn create_main_window(app_handle: &tauri::AppHandle) -> Result<(), Box<dyn std::
     .build()
     .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
 
-    // Add the same close prevention logic to this window
+    // Add the same close handling to this window
     let window_clone = main_window.clone();
     main_window.on_window_event(move |event| {
         if let tauri::WindowEvent::CloseRequested { api, .. } = event {
             api.prevent_close();
-            let _ = window_clone.hide();
+            handle_main_window_close(&window_clone);
         }
     });
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_usage_speeds_cover_the_current_interval() {
+        let start = std::time::Instant::now();
+        let mut usage = NetworkUsage {
+            total_bytes_downloaded: 0,
+            total_bytes_uploaded: 0,
+            last_bytes_downloaded: 0,
+            last_bytes_uploaded: 0,
+            last_updated: start,
+        };
+
+        // 200 KB down and 100 KB up over two seconds
+        usage.total_bytes_downloaded += 200 * 1024;
+        usage.total_bytes_uploaded += 100 * 1024;
+        let later = start + Duration::from_secs(2);
+        let (download_speed, upload_speed) = usage.speeds(later);
+        assert_eq!(download_speed / 1024.0, 100.0);
+        assert_eq!(upload_speed / 1024.0, 50.0);
+
+        // The next interval starts from the totals at the end of this one
+        usage.roll_interval(later);
+        assert_eq!(usage.last_bytes_downloaded, 200 * 1024);
+        assert_eq!(usage.last_updated, later);
+        assert_eq!(usage.speeds(later), (0.0, 0.0));
+    }
+
+    #[test]
+    fn network_usage_keeps_short_intervals_open() {
+        let start = std::time::Instant::now();
+        let mut usage = NetworkUsage {
+            total_bytes_downloaded: 4096,
+            total_bytes_uploaded: 0,
+            last_bytes_downloaded: 0,
+            last_bytes_uploaded: 0,
+            last_updated: start,
+        };
+
+        let soon = start + Duration::from_millis(500);
+        usage.roll_interval(soon);
+        assert_eq!(usage.last_updated, start);
+        assert_eq!(usage.speeds(soon).0 / 1024.0, 8.0);
+    }
+
+    #[test]
+    fn concat_list_entry_escapes_quotes() {
+        assert_eq!(
+            concat_list_entry("/home/o'brien/My Recordings/seg 1.mkv").unwrap(),
+            "file '/home/o'\\''brien/My Recordings/seg 1.mkv'\n"
+        );
+    }
+
+    #[test]
+    fn concat_list_entry_rejects_line_breaks() {
+        assert!(concat_list_entry("/tmp/seg\nfile '/etc/passwd'.mkv").is_err());
+        assert!(concat_list_entry("/tmp/seg\r.mkv").is_err());
+    }
+
+    #[test]
+    fn validate_session_id_accepts_only_uuids() {
+        assert!(validate_session_id("3f2b8c1e-9d4a-4f6b-8e2a-1c5d7e9f0a3b").is_ok());
+        assert!(validate_session_id("../x").is_err());
+        assert!(validate_session_id("").is_err());
+    }
+
+    #[test]
+    fn next_segment_index_skips_past_gaps() {
+        let session_id = "6f1c2a4e-8b3d-4c5e-9f70-1a2b3c4d5e6f";
+        let segments: VecDeque<String> = [0, 1, 4]
+            .iter()
+            .map(|index| format!("/data/recordings/recording_{}_seg_{}.mkv", session_id, index))
+            .collect();
+        assert_eq!(next_segment_index(&segments, session_id), 5);
+
+        // A re-encoded copy counts as its segment's index, and other sessions' files are ignored
+        let mut segments = segments;
+        segments.push_back(format!("/data/recordings/recording_{}_seg_7_compressed.mkv", session_id));
+        segments.push_back("/data/recordings/recording_other_seg_20.mkv".to_string());
+        assert_eq!(next_segment_index(&segments, session_id), 8);
+
+        assert_eq!(next_segment_index(&VecDeque::new(), session_id), 0);
+    }
+
+    #[test]
+    fn loopback_interfaces_are_matched_exactly() {
+        assert!(is_loopback_interface("lo"));
+        assert!(is_loopback_interface("lo0"));
+        assert!(is_loopback_interface("Loopback Pseudo-Interface 1"));
+        assert!(!is_loopback_interface("wlo1"));
+        assert!(!is_loopback_interface("eth0"));
+        assert!(!is_loopback_interface("vlan10"));
+    }
+
+    #[test]
+    fn capture_masked_region_crops_the_fake_screen() {
+        let source = screen_source::FakeScreenSource::new();
+        let region = screen_source::CaptureRegion { x: 130, y: 130, width: 200, height: 100 };
+        validate_capture_region(&source, &region).unwrap();
+
+        let img = capture_masked_region(&source, &region).unwrap();
+        assert_eq!(img.dimensions(), (200, 100));
+        assert_eq!(*img.get_pixel(0, 0), image::Rgba([17, 30, 128, 255]));
+        // x = 240 on the screen is one of the fake's grid lines
+        assert_eq!(*img.get_pixel(110, 0), image::Rgba([255, 255, 255, 255]));
+
+        let outside = screen_source::CaptureRegion { x: 1800, y: 0, width: 200, height: 100 };
+        assert!(matches!(validate_capture_region(&source, &outside), Err(CommandError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn mask_region_blacks_out_only_the_area() {
+        let source = screen_source::FakeScreenSource::new();
+        let region = screen_source::CaptureRegion { x: 0, y: 0, width: 400, height: 300 };
+        let mut img = capture_masked_region(&source, &region).unwrap();
+        let untouched = *img.get_pixel(300, 250);
+
+        mask_region(&mut img, 10, 20, 110, 220);
+        assert_eq!(*img.get_pixel(10, 20), image::Rgba([0, 0, 0, 255]));
+        assert_eq!(*img.get_pixel(109, 219), image::Rgba([0, 0, 0, 255]));
+        assert_eq!(*img.get_pixel(300, 250), untouched);
+    }
+
+    #[test]
+    fn capture_masked_screens_takes_the_given_monitor() {
+        let source = screen_source::FakeScreenSource::new();
+
+        let images = capture_masked_screens(&source, Some(0)).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].0, "_m0");
+        assert_eq!(images[0].1.dimensions(), (1920, 1080));
+        assert!(capture_masked_screens(&source, Some(1)).is_err());
+    }
+
+    #[test]
+    fn downscale_to_max_dimension_keeps_the_aspect_ratio() {
+        let source = screen_source::FakeScreenSource::new();
+        let region = screen_source::CaptureRegion { x: 0, y: 0, width: 1920, height: 1080 };
+        let img = capture_masked_region(&source, &region).unwrap();
+
+        assert_eq!(downscale_to_max_dimension(img.clone(), 960).dimensions(), (960, 540));
+        assert_eq!(downscale_to_max_dimension(img.clone(), 0).dimensions(), (1920, 1080));
+        assert_eq!(downscale_to_max_dimension(img, 4096).dimensions(), (1920, 1080));
+    }
+}
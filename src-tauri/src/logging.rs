@@ -0,0 +1,147 @@
+// Logging to stderr and to a size-rotated file in the data directory, so there is something to inspect
+// when a client stops uploading in the field. The app's level can be changed at runtime with set_level;
+// dependencies only report warnings unless RUST_LOG asks for more.
+
+use log::LevelFilter;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const LOG_FILE_NAME: &str = "remote-work.log";
+
+// The log file is rotated once it would grow past this size, keeping this many older files
+// (remote-work.log.1 is the most recent)
+const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const ROTATED_LOG_FILES: u32 = 3;
+
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+// Writes every record to stderr and appends it to the current log file
+struct RotatingFileWriter {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFileWriter {
+    fn new(path: PathBuf) -> Self {
+        let mut writer = RotatingFileWriter { path, file: None, size: 0 };
+        writer.open();
+        writer
+    }
+
+    fn open(&mut self) {
+        // The logger isn't running yet or is the one failing, so these go straight to stderr
+        if let Some(parent) = self.path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                eprintln!("Failed to create log directory {}: {}", parent.display(), e);
+            }
+        }
+
+        match OpenOptions::new().create(true).append(true).open(&self.path) {
+            Ok(file) => {
+                self.size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                self.file = Some(file);
+            }
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", self.path.display(), e);
+                self.file = None;
+            }
+        }
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        for index in (1..ROTATED_LOG_FILES).rev() {
+            let _ = std::fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+        }
+        let _ = std::fs::rename(&self.path, self.rotated_path(1));
+        self.open();
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = std::io::stderr().write_all(buf);
+
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_LOG_FILE_BYTES {
+            self.rotate();
+        }
+
+        // A full disk or a removed log directory must not break the app, stderr still has the record
+        if let Some(file) = self.file.as_mut() {
+            if file.write_all(buf).is_ok() {
+                self.size += buf.len() as u64;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _ = std::io::stderr().flush();
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+// Path of the current log file inside the given directory
+pub fn log_file_path(log_dir: &Path) -> PathBuf {
+    log_dir.join(LOG_FILE_NAME)
+}
+
+// Install the logger, writing to stderr and to the log file in log_dir. RUST_LOG filters such as
+// "mysql=debug" are applied on top of the defaults.
+pub fn init(log_dir: &Path) {
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(LevelFilter::Warn)
+        .filter_module(env!("CARGO_CRATE_NAME"), LevelFilter::Trace);
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+
+    let writer = RotatingFileWriter::new(log_file_path(log_dir));
+    builder
+        .format_timestamp_millis()
+        .write_style(env_logger::WriteStyle::Never)
+        .target(env_logger::Target::Pipe(Box::new(writer)));
+
+    if let Err(e) = builder.try_init() {
+        eprintln!("Failed to initialize logging: {}", e);
+        return;
+    }
+
+    // The filters above allow everything from the app; the global maximum is what set_level changes
+    log::set_max_level(DEFAULT_LEVEL);
+}
+
+// Parse a level name: "off", "error", "warn", "info", "debug" or "trace"
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.trim().parse::<LevelFilter>().map_err(|_| {
+        format!(
+            "Invalid log level '{}': expected \"off\", \"error\", \"warn\", \"info\", \"debug\" or \"trace\"",
+            level
+        )
+    })
+}
+
+// Change the most verbose level that is logged
+pub fn set_level(level: &str) -> Result<LevelFilter, String> {
+    let level = parse_level(level)?;
+    log::set_max_level(level);
+    Ok(level)
+}
+
+// Name of the current level, as accepted by set_level
+pub fn current_level() -> String {
+    log::max_level().to_string().to_lowercase()
+}
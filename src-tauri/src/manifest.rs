@@ -0,0 +1,224 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Data directory subfolders that hold captures belonging to a session
+const CAPTURE_DIRECTORIES: &[&str] = &["screenshots", "recordings"];
+
+// A single capture listed in a session manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String, // Relative to the data directory
+    pub sha256: String,
+    pub size: u64,
+    pub captured_at: u64, // Unix timestamp (seconds) of the file's last modification
+}
+
+// The signed part of a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestBody {
+    pub session_id: String,
+    pub generated_at: u64,
+    pub entries: Vec<ManifestEntry>,
+}
+
+// Manifest as written to disk: the body, its hash and an optional Ed25519 signature over the hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub body: ManifestBody,
+    pub manifest_hash: String,
+    pub signature: Option<String>,
+    pub public_key: Option<String>,
+}
+
+// Verification status of a single capture listed in a manifest
+#[derive(Debug, Serialize)]
+pub struct EntryVerification {
+    pub path: String,
+    pub status: String, // "ok", "modified", "missing" or "rejected" (path outside the data directory)
+}
+
+// Overall result of verifying a manifest
+#[derive(Debug, Serialize)]
+pub struct ManifestVerification {
+    pub session_id: String,
+    pub valid: bool,
+    pub hash_valid: bool,
+    pub signature: String, // "valid", "invalid", "unsigned" or "unverified" (no trusted key to check it with)
+    pub entries: Vec<EntryVerification>,
+}
+
+// Helper function to compute the SHA-256 of a file without loading it fully into memory
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Helper function to hash the manifest body in its serialized form
+fn hash_body(body: &ManifestBody) -> Result<[u8; 32], String> {
+    let bytes = serde_json::to_vec(body).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    Ok(Sha256::digest(&bytes).into())
+}
+
+// Helper function to parse a hex encoded 32-byte Ed25519 secret key
+pub fn parse_signing_key(secret_key_hex: &str) -> Result<SigningKey, String> {
+    let bytes = hex::decode(secret_key_hex.trim()).map_err(|e| format!("Signing key is not valid hex: {}", e))?;
+    let secret: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Signing key must be exactly 32 bytes".to_string())?;
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+// Helper function to parse a hex encoded 32-byte Ed25519 public key
+pub fn parse_verifying_key(public_key_hex: &str) -> Result<VerifyingKey, String> {
+    let bytes = hex::decode(public_key_hex.trim()).map_err(|e| format!("Public key is not valid hex: {}", e))?;
+    let key_bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Public key must be exactly 32 bytes".to_string())?;
+    VerifyingKey::from_bytes(&key_bytes).map_err(|e| format!("Invalid public key: {}", e))
+}
+
+// Helper function to check that a manifest entry path stays inside the data directory: relative, without ".."
+fn is_safe_entry_path(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Build a manifest of every capture of the session found in the data directory
+pub fn build_manifest(data_dir: &Path, session_id: &str, signing_key: Option<&SigningKey>) -> Result<SessionManifest, String> {
+    let mut entries = Vec::new();
+
+    for directory in CAPTURE_DIRECTORIES {
+        let dir_path = data_dir.join(directory);
+        let read_dir = match std::fs::read_dir(&dir_path) {
+            Ok(read_dir) => read_dir,
+            Err(_) => continue, // Directory not created yet, nothing captured there
+        };
+
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let filename = entry.file_name().to_string_lossy().to_string();
+            if !path.is_file() || !filename.contains(session_id) {
+                continue;
+            }
+
+            let metadata = entry.metadata().map_err(|e| format!("Failed to read metadata of {}: {}", filename, e))?;
+            let captured_at = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+
+            entries.push(ManifestEntry {
+                path: format!("{}/{}", directory, filename),
+                sha256: sha256_file(&path)?,
+                size: metadata.len(),
+                captured_at,
+            });
+        }
+    }
+
+    if entries.is_empty() {
+        return Err(format!("No local captures found for session {}", session_id));
+    }
+
+    // Keep a stable order so the same capture set always produces the same hash
+    entries.sort_by(|a, b| a.captured_at.cmp(&b.captured_at).then_with(|| a.path.cmp(&b.path)));
+
+    let body = ManifestBody {
+        session_id: session_id.to_string(),
+        generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        entries,
+    };
+
+    let digest = hash_body(&body)?;
+    let (signature, public_key) = match signing_key {
+        Some(key) => (
+            Some(hex::encode(key.sign(&digest).to_bytes())),
+            Some(hex::encode(key.verifying_key().to_bytes())),
+        ),
+        None => (None, None),
+    };
+
+    Ok(SessionManifest {
+        body,
+        manifest_hash: hex::encode(digest),
+        signature,
+        public_key,
+    })
+}
+
+// Check a manifest's hash, signature and that the listed files are unchanged on disk. The signature is checked
+// against trusted_key, never the public key stored in the manifest, since whoever altered the files could have
+// re-signed it with their own key. With a trusted key an unsigned manifest is invalid; without one the signature
+// can't be checked and only the hash and files count.
+pub fn verify_manifest(data_dir: &Path, manifest: &SessionManifest, trusted_key: Option<&VerifyingKey>) -> Result<ManifestVerification, String> {
+    let digest = hash_body(&manifest.body)?;
+    let hash_valid = hex::encode(digest) == manifest.manifest_hash;
+
+    let signature = match (trusted_key, &manifest.signature) {
+        (None, _) => "unverified",
+        (Some(_), None) => "unsigned",
+        (Some(trusted_key), Some(signature_hex)) => {
+            let signature_ok = (|| -> Option<()> {
+                let signature_bytes: [u8; 64] = hex::decode(signature_hex).ok()?.try_into().ok()?;
+                trusted_key.verify(&digest, &Signature::from_bytes(&signature_bytes)).ok()
+            })();
+            if signature_ok.is_some() { "valid" } else { "invalid" }
+        },
+    };
+
+    let entries: Vec<EntryVerification> = manifest.body.entries
+        .iter()
+        .map(|entry| {
+            // An edited manifest could point anywhere, e.g. "../../etc/passwd"; such entries are never read
+            let status = if !is_safe_entry_path(&entry.path) {
+                "rejected"
+            } else {
+                let path = data_dir.join(&entry.path);
+                if !path.exists() {
+                    "missing"
+                } else {
+                    match sha256_file(&path) {
+                        Ok(hash) if hash == entry.sha256 => "ok",
+                        _ => "modified",
+                    }
+                }
+            };
+            EntryVerification {
+                path: entry.path.clone(),
+                status: status.to_string(),
+            }
+        })
+        .collect();
+
+    let signature_accepted = match trusted_key {
+        Some(_) => signature == "valid",
+        None => true,
+    };
+    let valid = hash_valid && signature_accepted && entries.iter().all(|entry| entry.status == "ok");
+
+    Ok(ManifestVerification {
+        session_id: manifest.body.session_id.clone(),
+        valid,
+        hash_valid,
+        signature: signature.to_string(),
+        entries,
+    })
+}
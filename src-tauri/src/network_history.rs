@@ -0,0 +1,123 @@
+// Local history of network usage samples, one JSON object per line. The shared MySQL schema has no table for
+// them, so the history lives next to the other app data. The file is trimmed to the newest samples once it
+// grows past MAX_FILE_BYTES.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Size past which the oldest half of the samples is dropped
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+lazy_static::lazy_static! {
+    // Appends and trims rewrite the same file, so only one may run at a time
+    static ref HISTORY_FILE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// A single network usage sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkSample {
+    pub user_id: String,
+    pub recorded_at: String, // Local time, "YYYY-MM-DD HH:MM:SS"
+    pub timestamp: u64, // Unix timestamp (seconds)
+    pub download_bytes_per_sec: f64,
+    pub upload_bytes_per_sec: f64,
+    pub total_bytes_downloaded: u64,
+    pub total_bytes_uploaded: u64,
+    // Formatted for display, as shown by get_network_stats
+    pub download_speed: String,
+    pub upload_speed: String,
+    pub total_downloaded: String,
+    pub total_uploaded: String,
+}
+
+// Helper function to read every readable sample in the file, oldest first. A missing file holds no samples.
+fn read_all(path: &Path) -> Vec<NetworkSample> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<NetworkSample>(line) {
+            Ok(sample) => Some(sample),
+            Err(e) => {
+                // A line cut off by a crash mid-write is skipped rather than losing the whole history
+                warn!("Skipping unreadable network usage sample: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Helper function to replace the file with the given samples, via a temp file so a crash never truncates it
+fn write_all(path: &Path, samples: &[NetworkSample]) -> Result<(), String> {
+    let mut contents = String::new();
+    for sample in samples {
+        let line = serde_json::to_string(sample).map_err(|e| format!("Failed to serialize network usage sample: {}", e))?;
+        contents.push_str(&line);
+        contents.push('\n');
+    }
+
+    let temp_path = path.with_extension("jsonl.tmp");
+    std::fs::write(&temp_path, contents).map_err(|e| format!("Failed to write network usage history: {}", e))?;
+    std::fs::rename(&temp_path, path).map_err(|e| format!("Failed to write network usage history: {}", e))
+}
+
+// Append a sample to the history, trimming the oldest samples once the file is too large
+pub fn append(path: &Path, sample: &NetworkSample) -> Result<(), String> {
+    let _guard = HISTORY_FILE_LOCK.lock().map_err(|e| e.to_string())?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create network usage directory: {}", e))?;
+    }
+
+    let line = serde_json::to_string(sample).map_err(|e| format!("Failed to serialize network usage sample: {}", e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open network usage history: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write network usage sample: {}", e))?;
+    drop(file);
+
+    let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    if size > MAX_FILE_BYTES {
+        let samples = read_all(path);
+        write_all(path, &samples[samples.len() / 2..])?;
+    }
+
+    Ok(())
+}
+
+// The user's samples, newest first, at most limit of them
+pub fn list(path: &Path, user_id: &str, limit: Option<u32>) -> Result<Vec<NetworkSample>, String> {
+    let _guard = HISTORY_FILE_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let samples = read_all(path)
+        .into_iter()
+        .rev()
+        .filter(|sample| sample.user_id == user_id)
+        .take(limit.map(|limit| limit as usize).unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(samples)
+}
+
+// Remove every sample of the user, returns how many were removed
+pub fn remove_user(path: &Path, user_id: &str) -> Result<usize, String> {
+    let _guard = HISTORY_FILE_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let samples = read_all(path);
+    let total = samples.len();
+    let kept: Vec<NetworkSample> = samples.into_iter().filter(|sample| sample.user_id != user_id).collect();
+    let removed = total - kept.len();
+    if removed > 0 {
+        write_all(path, &kept)?;
+    }
+
+    Ok(removed)
+}
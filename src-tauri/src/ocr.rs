@@ -0,0 +1,96 @@
+// Text recognition on screenshots through the Tesseract command line tool, so screenshots can be searched by the
+// text that was on screen. The tesseract executable next to the app is used if bundled, otherwise the system one.
+// Languages are Tesseract's codes joined with '+', e.g. "eng+deu", and each needs its traineddata installed.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+// File name of the Tesseract binary bundled next to the app executable
+#[cfg(target_os = "windows")]
+const BUNDLED_TESSERACT_NAME: &str = "tesseract.exe";
+#[cfg(not(target_os = "windows"))]
+const BUNDLED_TESSERACT_NAME: &str = "tesseract";
+
+// Helper function to build a Tesseract command that doesn't open a console window
+fn tesseract_command(tesseract_cmd: &str) -> Command {
+    let mut command = Command::new(tesseract_cmd);
+    command.stdin(Stdio::null());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+    command
+}
+
+// Find a Tesseract executable: the bundled copy, then the system one
+pub fn find_tesseract() -> Option<String> {
+    let bundled_path = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(BUNDLED_TESSERACT_NAME)));
+    if let Some(bundled_path) = bundled_path.filter(|path| path.is_file()) {
+        return Some(bundled_path.to_string_lossy().to_string());
+    }
+
+    let mut command = tesseract_command("tesseract");
+    command.arg("--version").stdout(Stdio::null()).stderr(Stdio::null());
+    match command.status() {
+        Ok(status) if status.success() => Some("tesseract".to_string()),
+        _ => None,
+    }
+}
+
+// Check that a language list is made of Tesseract language codes, e.g. "eng" or "eng+chi_sim"
+pub fn validate_languages(languages: &str) -> Result<(), String> {
+    let is_valid = !languages.is_empty()
+        && languages
+            .split('+')
+            .all(|code| !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+
+    match is_valid {
+        true => Ok(()),
+        false => Err(format!(
+            "Invalid OCR languages '{}': expected Tesseract language codes joined with '+', e.g. \"eng+deu\"",
+            languages
+        )),
+    }
+}
+
+// The languages Tesseract has traineddata for
+pub fn installed_languages(tesseract_cmd: &str) -> Result<Vec<String>, String> {
+    let output = tesseract_command(tesseract_cmd)
+        .arg("--list-langs")
+        .output()
+        .map_err(|e| format!("Failed to run Tesseract: {}", e))?;
+
+    // The list goes to stdout in recent versions and to stderr in older ones, after a heading line
+    let listing = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    Ok(listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("List of available languages"))
+        .map(str::to_string)
+        .collect())
+}
+
+// Recognize the text in an image file. Returns the text with blank lines removed, empty if none was found.
+pub fn extract_text(tesseract_cmd: &str, image_path: &Path, languages: &str) -> Result<String, String> {
+    let output = tesseract_command(tesseract_cmd)
+        .arg(image_path)
+        .args(["stdout", "-l", languages])
+        .output()
+        .map_err(|e| format!("Failed to run Tesseract: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Tesseract failed on {}: {}",
+            image_path.display(),
+            stderr.lines().last().unwrap_or("unknown error")
+        ));
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = text.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect();
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,170 @@
+// Where screen captures come from. The real source is the connected displays; the fake one serves a fixed
+// in-memory image so the capture pipeline (masking, encoding, scaling, uploads) runs without a display, e.g. in CI.
+// The fake is used when built with the "test-capture" feature or when REMOTE_WORK_FAKE_SCREEN=1 is set.
+
+use log::info;
+use std::sync::Arc;
+
+// Position and size of a screen on the virtual desktop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+// A rectangle to capture instead of a whole screen, relative to the primary screen's origin
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CaptureRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CaptureRegion {
+    // Check that the region isn't empty and lies entirely within the screen
+    pub fn validate(&self, screen: &DisplayGeometry) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("Capture region {}x{} must not be empty", self.width, self.height));
+        }
+
+        let fits_horizontally = self.x >= 0 && self.x as u64 + self.width as u64 <= screen.width as u64;
+        let fits_vertically = self.y >= 0 && self.y as u64 + self.height as u64 <= screen.height as u64;
+        if !fits_horizontally || !fits_vertically {
+            return Err(format!(
+                "Capture region {}x{} at ({}, {}) is outside the {}x{} primary screen",
+                self.width, self.height, self.x, self.y, screen.width, screen.height
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// A screen that can be captured
+pub trait SourceScreen: Send + Sync {
+    fn display_info(&self) -> DisplayGeometry;
+
+    // Capture an area given in coordinates relative to the screen's origin
+    fn capture_area(&self, x: i32, y: i32, width: u32, height: u32) -> Result<image::RgbaImage, String>;
+}
+
+// Provider of the screens captures are taken from
+pub trait ScreenSource: Send + Sync {
+    fn screens(&self) -> Result<Vec<Box<dyn SourceScreen>>, String>;
+}
+
+// The connected displays, through the screenshots crate
+struct RealScreenSource;
+
+struct RealScreen(screenshots::Screen);
+
+impl SourceScreen for RealScreen {
+    fn display_info(&self) -> DisplayGeometry {
+        let info = &self.0.display_info;
+        DisplayGeometry {
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+            scale_factor: info.scale_factor,
+            is_primary: info.is_primary,
+        }
+    }
+
+    fn capture_area(&self, x: i32, y: i32, width: u32, height: u32) -> Result<image::RgbaImage, String> {
+        self.0.capture_area(x, y, width, height).map_err(|e| e.to_string())
+    }
+}
+
+impl ScreenSource for RealScreenSource {
+    fn screens(&self) -> Result<Vec<Box<dyn SourceScreen>>, String> {
+        let screens = screenshots::Screen::all().map_err(|e| e.to_string())?;
+        Ok(screens.into_iter().map(|screen| Box::new(RealScreen(screen)) as Box<dyn SourceScreen>).collect())
+    }
+}
+
+// Size of the fake source's single screen
+const FAKE_SCREEN_WIDTH: u32 = 1920;
+const FAKE_SCREEN_HEIGHT: u32 = 1080;
+
+// A single primary screen always showing the same image
+pub struct FakeScreenSource {
+    image: Arc<image::RgbaImage>,
+}
+
+struct FakeScreen {
+    image: Arc<image::RgbaImage>,
+}
+
+impl FakeScreenSource {
+    pub fn new() -> Self {
+        // A gradient with a grid on top, so the frame isn't mistaken for a blank capture
+        let image = image::RgbaImage::from_fn(FAKE_SCREEN_WIDTH, FAKE_SCREEN_HEIGHT, |x, y| {
+            if x % 120 == 0 || y % 120 == 0 {
+                image::Rgba([255, 255, 255, 255])
+            } else {
+                image::Rgba([(x * 255 / FAKE_SCREEN_WIDTH) as u8, (y * 255 / FAKE_SCREEN_HEIGHT) as u8, 128, 255])
+            }
+        });
+
+        FakeScreenSource { image: Arc::new(image) }
+    }
+}
+
+impl Default for FakeScreenSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceScreen for FakeScreen {
+    fn display_info(&self) -> DisplayGeometry {
+        DisplayGeometry {
+            x: 0,
+            y: 0,
+            width: self.image.width(),
+            height: self.image.height(),
+            scale_factor: 1.0,
+            is_primary: true,
+        }
+    }
+
+    fn capture_area(&self, x: i32, y: i32, width: u32, height: u32) -> Result<image::RgbaImage, String> {
+        if x < 0 || y < 0 || x as u32 + width > self.image.width() || y as u32 + height > self.image.height() {
+            return Err(format!("Area {}x{} at ({}, {}) is outside the fake screen", width, height, x, y));
+        }
+
+        Ok(image::imageops::crop_imm(self.image.as_ref(), x as u32, y as u32, width, height).to_image())
+    }
+}
+
+impl ScreenSource for FakeScreenSource {
+    fn screens(&self) -> Result<Vec<Box<dyn SourceScreen>>, String> {
+        Ok(vec![Box::new(FakeScreen { image: self.image.clone() })])
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SCREEN_SOURCE: Box<dyn ScreenSource> = match use_fake_screen() {
+        true => {
+            info!("Using the fake screen source, captures won't show the real screen");
+            Box::new(FakeScreenSource::new())
+        }
+        false => Box::new(RealScreenSource),
+    };
+}
+
+// Whether captures should come from the fake screen
+fn use_fake_screen() -> bool {
+    cfg!(feature = "test-capture") || std::env::var("REMOTE_WORK_FAKE_SCREEN").map(|value| value == "1").unwrap_or(false)
+}
+
+// The source captures are taken from, passed down to the capture helpers so they can be run against the fake
+pub fn current() -> &'static dyn ScreenSource {
+    SCREEN_SOURCE.as_ref()
+}
@@ -0,0 +1,293 @@
+// Notifications of the login session being locked, unlocked or logged in to, from the OS:
+// WTS session change messages on Windows, distributed notifications on macOS and logind over D-Bus on Linux.
+// The watcher runs on its own thread for the rest of the process once started.
+
+use log::{error, info};
+use std::sync::Arc;
+
+// A change of the login session the app runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Lock,
+    Unlock,
+    // Logged in to, or switched back to from another user's session
+    Login,
+}
+
+impl SessionEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionEvent::Lock => "lock",
+            SessionEvent::Unlock => "unlock",
+            SessionEvent::Login => "login",
+        }
+    }
+}
+
+type SessionEventHandler = Arc<dyn Fn(SessionEvent) + Send + Sync>;
+
+// Start watching the session, calling on_event (from the watcher thread) for every change.
+// Fails if the OS notifications can't be subscribed to.
+pub fn start(on_event: impl Fn(SessionEvent) + Send + Sync + 'static) -> Result<(), String> {
+    let handler: SessionEventHandler = Arc::new(on_event);
+    platform::start(handler)?;
+    info!("Watching session lock, unlock and login events");
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use once_cell::sync::OnceCell;
+    use winapi::shared::minwindef::{DWORD, LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::HWND;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::winuser::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassExW, TranslateMessage, HWND_MESSAGE, MSG,
+        WNDCLASSEXW,
+    };
+
+    const WM_WTSSESSION_CHANGE: UINT = 0x02B1;
+    const WTS_SESSION_LOGON: WPARAM = 0x5;
+    const WTS_SESSION_LOCK: WPARAM = 0x7;
+    const WTS_SESSION_UNLOCK: WPARAM = 0x8;
+    const NOTIFY_FOR_THIS_SESSION: DWORD = 0;
+
+    #[link(name = "wtsapi32")]
+    extern "system" {
+        fn WTSRegisterSessionNotification(hwnd: HWND, flags: DWORD) -> i32;
+    }
+
+    // The window procedure has no user data to carry the handler in
+    static HANDLER: OnceCell<SessionEventHandler> = OnceCell::new();
+
+    unsafe extern "system" fn window_proc(hwnd: HWND, message: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if message == WM_WTSSESSION_CHANGE {
+            let event = match wparam {
+                WTS_SESSION_LOCK => Some(SessionEvent::Lock),
+                WTS_SESSION_UNLOCK => Some(SessionEvent::Unlock),
+                WTS_SESSION_LOGON => Some(SessionEvent::Login),
+                _ => None,
+            };
+            if let (Some(event), Some(handler)) = (event, HANDLER.get()) {
+                handler(event);
+            }
+            return 0;
+        }
+
+        DefWindowProcW(hwnd, message, wparam, lparam)
+    }
+
+    // Session notifications are only sent to windows, so create a message-only one and pump its messages
+    unsafe fn create_notification_window() -> Result<HWND, String> {
+        let class_name: Vec<u16> = "RemoteWorkSessionEvents".encode_utf16().chain(std::iter::once(0)).collect();
+        let instance = GetModuleHandleW(std::ptr::null());
+
+        let mut window_class: WNDCLASSEXW = std::mem::zeroed();
+        window_class.cbSize = std::mem::size_of::<WNDCLASSEXW>() as UINT;
+        window_class.lpfnWndProc = Some(window_proc);
+        window_class.hInstance = instance;
+        window_class.lpszClassName = class_name.as_ptr();
+        if RegisterClassExW(&window_class) == 0 {
+            return Err("Failed to register the session notification window class".to_string());
+        }
+
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null_mut(),
+        );
+        if hwnd.is_null() {
+            return Err("Failed to create the session notification window".to_string());
+        }
+
+        if WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) == 0 {
+            return Err("Failed to register for session notifications".to_string());
+        }
+
+        Ok(hwnd)
+    }
+
+    pub fn start(handler: SessionEventHandler) -> Result<(), String> {
+        HANDLER.set(handler).map_err(|_| "Session events are already being watched".to_string())?;
+
+        // The window must be created on the thread that pumps its messages; wait for it to report back
+        let (ready_sender, ready_receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || unsafe {
+            if let Err(e) = create_notification_window() {
+                let _ = ready_sender.send(Err(e));
+                return;
+            }
+            let _ = ready_sender.send(Ok(()));
+
+            let mut message: MSG = std::mem::zeroed();
+            while GetMessageW(&mut message, std::ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+            error!("Session notification window stopped receiving messages");
+        });
+
+        ready_receiver
+            .recv()
+            .map_err(|_| "Session notification thread exited unexpectedly".to_string())?
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use once_cell::sync::OnceCell;
+    use std::ffi::{c_char, c_void, CString};
+
+    type CFStringRef = *const c_void;
+    type CFNotificationCallback = extern "C" fn(*const c_void, *const c_void, CFStringRef, *const c_void, *const c_void);
+
+    // kCFStringEncodingUTF8 and CFNotificationSuspensionBehaviorDeliverImmediately
+    const UTF8_ENCODING: u32 = 0x0800_0100;
+    const DELIVER_IMMEDIATELY: isize = 4;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFNotificationCenterGetDistributedCenter() -> *const c_void;
+        fn CFNotificationCenterAddObserver(
+            center: *const c_void,
+            observer: *const c_void,
+            callback: CFNotificationCallback,
+            name: CFStringRef,
+            object: *const c_void,
+            suspension_behavior: isize,
+        );
+        fn CFStringCreateWithCString(allocator: *const c_void, c_str: *const c_char, encoding: u32) -> CFStringRef;
+        fn CFRunLoopRun();
+    }
+
+    // The screen lock posts these; there is no distributed notification for logging in
+    const NOTIFICATIONS: [(&str, SessionEvent); 2] = [
+        ("com.apple.screenIsLocked", SessionEvent::Lock),
+        ("com.apple.screenIsUnlocked", SessionEvent::Unlock),
+    ];
+
+    static HANDLER: OnceCell<SessionEventHandler> = OnceCell::new();
+
+    // The observer pointer carries the index of the notification in NOTIFICATIONS
+    extern "C" fn notification_callback(
+        _center: *const c_void,
+        observer: *const c_void,
+        _name: CFStringRef,
+        _object: *const c_void,
+        _user_info: *const c_void,
+    ) {
+        if let (Some((_, event)), Some(handler)) = (NOTIFICATIONS.get(observer as usize), HANDLER.get()) {
+            handler(*event);
+        }
+    }
+
+    pub fn start(handler: SessionEventHandler) -> Result<(), String> {
+        HANDLER.set(handler).map_err(|_| "Session events are already being watched".to_string())?;
+
+        // Notifications are delivered on the run loop of the thread that subscribed
+        std::thread::spawn(|| unsafe {
+            let center = CFNotificationCenterGetDistributedCenter();
+            for (index, (name, _)) in NOTIFICATIONS.iter().enumerate() {
+                let name = CString::new(*name).expect("notification names contain no NUL bytes");
+                let cf_name = CFStringCreateWithCString(std::ptr::null(), name.as_ptr(), UTF8_ENCODING);
+                CFNotificationCenterAddObserver(
+                    center,
+                    index as *const c_void,
+                    notification_callback,
+                    cf_name,
+                    std::ptr::null(),
+                    DELIVER_IMMEDIATELY,
+                );
+            }
+
+            CFRunLoopRun();
+            error!("Session notification run loop stopped");
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    const LOGIND_SERVICE: &str = "org.freedesktop.login1";
+
+    // Helper function to find logind's object path of the session this process belongs to
+    fn own_session_path(connection: &Connection) -> Result<OwnedObjectPath, String> {
+        let manager = Proxy::new(connection, LOGIND_SERVICE, "/org/freedesktop/login1", "org.freedesktop.login1.Manager")
+            .map_err(|e| e.to_string())?;
+
+        // Processes started outside the session's scope (e.g. by a user service) aren't found by PID
+        match manager.call::<_, _, OwnedObjectPath>("GetSessionByPID", &(std::process::id(),)) {
+            Ok(path) => Ok(path),
+            Err(e) => match std::env::var("XDG_SESSION_ID") {
+                Ok(session_id) => manager
+                    .call("GetSession", &(session_id,))
+                    .map_err(|e| format!("Failed to find session {}: {}", session_id, e)),
+                Err(_) => Err(format!("Failed to find the login session: {}", e)),
+            },
+        }
+    }
+
+    pub fn start(handler: SessionEventHandler) -> Result<(), String> {
+        let connection = Connection::system().map_err(|e| format!("Failed to connect to the system bus: {}", e))?;
+        let session_path = own_session_path(&connection)?;
+        let session = Proxy::new(&connection, LOGIND_SERVICE, session_path, "org.freedesktop.login1.Session")
+            .map_err(|e| e.to_string())?;
+
+        // Lock and Unlock are signals on the session, sent for `loginctl lock-session` and by most desktops' lockers
+        let signals = session.receive_all_signals().map_err(|e| e.to_string())?;
+        let signal_handler = handler.clone();
+        std::thread::spawn(move || {
+            for message in signals {
+                let header = message.header();
+                let event = match header.member().map(|member| member.as_str()) {
+                    Some("Lock") => SessionEvent::Lock,
+                    Some("Unlock") => SessionEvent::Unlock,
+                    _ => continue,
+                };
+                signal_handler(event);
+            }
+            error!("Stopped receiving logind session signals");
+        });
+
+        // The session turning active again means the user logged back in or switched back to it
+        std::thread::spawn(move || {
+            let mut was_active = None;
+            for change in session.receive_property_changed::<bool>("Active") {
+                let Ok(active) = change.get() else { continue };
+                if active && was_active == Some(false) {
+                    handler(SessionEvent::Login);
+                }
+                was_active = Some(active);
+            }
+            error!("Stopped receiving logind session property changes");
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+mod platform {
+    use super::*;
+
+    pub fn start(_handler: SessionEventHandler) -> Result<(), String> {
+        Err("Session events are not supported on this platform".to_string())
+    }
+}
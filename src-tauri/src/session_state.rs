@@ -0,0 +1,71 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Name of the file inside the data directory that records what was running, to detect unclean shutdowns
+const STATE_FILE_NAME: &str = "monitoring_state.json";
+
+// Monitoring that was running when the state was last saved
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MonitoringState {
+    pub recording: bool,
+    pub screenshotting: bool,
+    pub idle_detection: bool,
+    pub recording_session_id: Option<String>,
+    pub screenshot_session_ids: Vec<String>,
+    pub updated_at: u64, // Unix timestamp (seconds)
+}
+
+impl MonitoringState {
+    // Whether anything was still running, i.e. the app didn't get to stop it before exiting
+    pub fn is_active(&self) -> bool {
+        self.recording || self.screenshotting || self.idle_detection
+    }
+}
+
+// Path of the monitoring state file
+pub fn state_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE_NAME)
+}
+
+// Read the saved monitoring state; None if there is none or it can't be parsed
+pub fn load(path: &Path) -> Option<MonitoringState> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            warn!("Ignoring unreadable monitoring state {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Save the monitoring state via a temp file so a crash never leaves a truncated one behind.
+// Nothing running removes the file, so its presence alone means monitoring was active.
+pub fn save(path: &Path, state: &MonitoringState) -> Result<(), String> {
+    if !state.is_active() {
+        return clear(path);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    let json = serde_json::to_string(state).map_err(|e| format!("Failed to serialize monitoring state: {}", e))?;
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, json).map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+    std::fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    Ok(())
+}
+
+// Remove the monitoring state file, e.g. after a clean stop
+pub fn clear(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
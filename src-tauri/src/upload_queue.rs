@@ -0,0 +1,98 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Sidecar describing a queued upload. The file data is stored next to it as <id>.data;
+// an entry only counts as queued once its sidecar has been written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub filename: String,
+    pub file_type: String,
+    pub user_id: String,
+    pub queued_at: u64, // Unix timestamp (seconds)
+    // Window focused when a screenshot was captured; missing from entries queued by older versions
+    #[serde(default)]
+    pub active_window: Option<String>,
+}
+
+fn data_path(queue_dir: &Path, id: &str) -> PathBuf {
+    queue_dir.join(format!("{}.data", id))
+}
+
+fn sidecar_path(queue_dir: &Path, id: &str) -> PathBuf {
+    queue_dir.join(format!("{}.json", id))
+}
+
+// Persist a failed upload so it can be retried later, returns the queue entry ID
+pub fn enqueue(queue_dir: &Path, file_data: &[u8], filename: &str, file_type: &str, user_id: &str, active_window: Option<&str>) -> Result<String, String> {
+    std::fs::create_dir_all(queue_dir)
+        .map_err(|e| format!("Failed to create pending uploads directory: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let upload = PendingUpload {
+        filename: filename.to_string(),
+        file_type: file_type.to_string(),
+        user_id: user_id.to_string(),
+        queued_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        active_window: active_window.map(str::to_string),
+    };
+
+    // Write the data first and the sidecar last, via a temp file, so a crash never leaves a half-queued entry
+    std::fs::write(data_path(queue_dir, &id), file_data)
+        .map_err(|e| format!("Failed to write pending upload data: {}", e))?;
+
+    let sidecar_json = serde_json::to_string(&upload)
+        .map_err(|e| format!("Failed to serialize pending upload: {}", e))?;
+    let temp_sidecar_path = queue_dir.join(format!("{}.json.tmp", id));
+    std::fs::write(&temp_sidecar_path, sidecar_json)
+        .map_err(|e| format!("Failed to write pending upload sidecar: {}", e))?;
+    std::fs::rename(&temp_sidecar_path, sidecar_path(queue_dir, &id))
+        .map_err(|e| format!("Failed to write pending upload sidecar: {}", e))?;
+
+    Ok(id)
+}
+
+// List queued uploads, oldest first
+pub fn list(queue_dir: &Path) -> Vec<(String, PendingUpload)> {
+    let read_dir = match std::fs::read_dir(queue_dir) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return Vec::new(), // Nothing has been queued yet
+    };
+
+    let mut uploads: Vec<(String, PendingUpload)> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                return None;
+            }
+
+            let id = path.file_stem()?.to_string_lossy().to_string();
+            let contents = std::fs::read_to_string(&path).ok()?;
+            match serde_json::from_str::<PendingUpload>(&contents) {
+                Ok(upload) => Some((id, upload)),
+                Err(e) => {
+                    warn!("Skipping unreadable pending upload {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    uploads.sort_by_key(|(_, upload)| upload.queued_at);
+    uploads
+}
+
+// Read the file data of a queued upload
+pub fn read_data(queue_dir: &Path, id: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(data_path(queue_dir, id)).map_err(|e| format!("Failed to read pending upload {}: {}", id, e))
+}
+
+// Remove an entry from the queue. The sidecar goes first so the entry is never retried after this.
+pub fn remove(queue_dir: &Path, id: &str) -> Result<(), String> {
+    std::fs::remove_file(sidecar_path(queue_dir, id))
+        .map_err(|e| format!("Failed to remove pending upload {}: {}", id, e))?;
+    let _ = std::fs::remove_file(data_path(queue_dir, id));
+    Ok(())
+}
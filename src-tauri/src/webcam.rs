@@ -0,0 +1,225 @@
+// Webcam snapshots through FFmpeg's camera input: dshow on Windows, avfoundation on macOS and v4l2 on Linux.
+// A camera that is held by another app can make FFmpeg wait forever, so every FFmpeg run has a timeout.
+
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+// How long FFmpeg gets to list the cameras
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+// How long FFmpeg gets to open the camera and grab a frame
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// A camera snapshots can be taken from
+#[derive(Debug, Clone, Serialize)]
+pub struct Camera {
+    pub id: String, // What FFmpeg opens: the device name on Windows, its index on macOS, its /dev path on Linux
+    pub name: String,
+}
+
+// Helper function to run FFmpeg, killing it once the timeout is up. Returns its exit status and stderr.
+fn run_with_timeout(mut command: Command, timeout: Duration) -> Result<(ExitStatus, String), String> {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW flag
+
+    let mut child = command.spawn().map_err(|e| format!("Failed to run FFmpeg: {}", e))?;
+
+    // Drain stderr on the side so FFmpeg never blocks on a full pipe
+    let stderr = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut output);
+        }
+        output
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(100)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "FFmpeg did not finish within {} seconds, the camera may be in use by another app",
+                    timeout.as_secs()
+                ));
+            }
+            Err(e) => return Err(format!("Failed to wait for FFmpeg: {}", e)),
+        }
+    };
+
+    Ok((status, stderr_reader.join().unwrap_or_default()))
+}
+
+// Helper function to strip the "[dshow @ 0000012345]" style prefix FFmpeg puts before device list lines
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn strip_log_prefix(line: &str) -> &str {
+    match (line.starts_with('['), line.find("] ")) {
+        (true, Some(end)) => line[end + 2..].trim(),
+        _ => line.trim(),
+    }
+}
+
+// The cameras connected to this machine, in the order FFmpeg reports them
+pub fn list(ffmpeg_cmd: &str) -> Result<Vec<Camera>, String> {
+    platform::list(ffmpeg_cmd)
+}
+
+// Grab a single frame from the camera into a JPEG file
+pub fn capture_snapshot(ffmpeg_cmd: &str, camera_id: &str, output_path: &Path) -> Result<(), String> {
+    let mut command = Command::new(ffmpeg_cmd);
+    command.args(["-hide_banner", "-loglevel", "error"]);
+    command.args(platform::input_args(camera_id));
+    command.args(["-frames:v", "1", "-q:v", "2", "-y"]).arg(output_path);
+
+    let (status, stderr) = run_with_timeout(command, SNAPSHOT_TIMEOUT)?;
+    if !status.success() || !output_path.is_file() {
+        return Err(format!(
+            "FFmpeg failed to capture from camera {}: {}",
+            camera_id,
+            stderr.lines().last().unwrap_or("unknown error")
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+
+    // Lists look like `"Integrated Camera" (video)` in recent FFmpeg versions, while older ones print the names
+    // under a "DirectShow video devices" heading
+    pub fn list(ffmpeg_cmd: &str) -> Result<Vec<Camera>, String> {
+        let mut command = Command::new(ffmpeg_cmd);
+        command.args(["-hide_banner", "-list_devices", "true", "-f", "dshow", "-i", "dummy"]);
+        // FFmpeg always fails here since "dummy" can't be opened; the list is all that's wanted
+        let (_, stderr) = run_with_timeout(command, LIST_TIMEOUT)?;
+
+        let mut cameras = Vec::new();
+        let mut in_video_section = false;
+        for line in stderr.lines().map(strip_log_prefix) {
+            if line.contains("DirectShow video devices") {
+                in_video_section = true;
+                continue;
+            }
+            if line.contains("DirectShow audio devices") {
+                in_video_section = false;
+                continue;
+            }
+
+            // Device names are quoted; "Alternative name" lines that follow them are not
+            let Some(rest) = line.strip_prefix('"') else { continue };
+            let Some((name, kind)) = rest.split_once('"') else { continue };
+            let is_video = match kind.trim() {
+                "" => in_video_section,
+                kind => kind.contains("video"),
+            };
+            if is_video && !cameras.iter().any(|camera: &Camera| camera.id == name) {
+                cameras.push(Camera { id: name.to_string(), name: name.to_string() });
+            }
+        }
+
+        Ok(cameras)
+    }
+
+    pub fn input_args(camera_id: &str) -> Vec<String> {
+        vec!["-f".to_string(), "dshow".to_string(), "-i".to_string(), format!("video={}", camera_id)]
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    // Lists look like `[0] FaceTime HD Camera` under an "AVFoundation video devices" heading. Screens are
+    // listed as video devices too, as "Capture screen N".
+    pub fn list(ffmpeg_cmd: &str) -> Result<Vec<Camera>, String> {
+        let mut command = Command::new(ffmpeg_cmd);
+        command.args(["-hide_banner", "-f", "avfoundation", "-list_devices", "true", "-i", ""]);
+        let (_, stderr) = run_with_timeout(command, LIST_TIMEOUT)?;
+
+        let mut cameras = Vec::new();
+        let mut in_video_section = false;
+        for line in stderr.lines().map(strip_log_prefix) {
+            if line.contains("AVFoundation video devices") {
+                in_video_section = true;
+                continue;
+            }
+            if line.contains("AVFoundation audio devices") {
+                in_video_section = false;
+                continue;
+            }
+            if !in_video_section {
+                continue;
+            }
+
+            let Some(rest) = line.strip_prefix('[') else { continue };
+            let Some((index, name)) = rest.split_once(']') else { continue };
+            let name = name.trim();
+            if index.parse::<u32>().is_ok() && !name.starts_with("Capture screen") {
+                cameras.push(Camera { id: index.to_string(), name: name.to_string() });
+            }
+        }
+
+        Ok(cameras)
+    }
+
+    // Most cameras don't support FFmpeg's default of 29.97 fps, and without ":none" a microphone is opened too
+    pub fn input_args(camera_id: &str) -> Vec<String> {
+        vec![
+            "-f".to_string(),
+            "avfoundation".to_string(),
+            "-framerate".to_string(),
+            "30".to_string(),
+            "-i".to_string(),
+            format!("{}:none", camera_id),
+        ]
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::*;
+
+    // Video4Linux devices are listed in sysfs. A camera usually has several /dev/video nodes, of which only the
+    // one with index 0 captures frames; the others carry metadata.
+    pub fn list(_ffmpeg_cmd: &str) -> Result<Vec<Camera>, String> {
+        let Ok(read_dir) = std::fs::read_dir("/sys/class/video4linux") else {
+            return Ok(Vec::new()); // No Video4Linux devices at all
+        };
+
+        let mut devices: Vec<(u32, Camera)> = read_dir
+            .flatten()
+            .filter_map(|entry| {
+                let node = entry.file_name().to_string_lossy().to_string();
+                let number = node.strip_prefix("video")?.parse::<u32>().ok()?;
+                let read_attribute = |attribute: &str| {
+                    std::fs::read_to_string(entry.path().join(attribute)).ok().map(|value| value.trim().to_string())
+                };
+                if read_attribute("index").is_some_and(|index| index != "0") {
+                    return None;
+                }
+
+                let name = read_attribute("name").unwrap_or_else(|| node.clone());
+                Some((number, Camera { id: format!("/dev/{}", node), name }))
+            })
+            .collect();
+
+        devices.sort_by_key(|(number, _)| *number);
+        Ok(devices.into_iter().map(|(_, camera)| camera).collect())
+    }
+
+    pub fn input_args(camera_id: &str) -> Vec<String> {
+        vec!["-f".to_string(), "v4l2".to_string(), "-i".to_string(), camera_id.to_string()]
+    }
+}
@@ -0,0 +1,98 @@
+// Work hours monitoring is limited to: a set of weekdays with a start and end time in a given time zone.
+// An end time before the start time means the shift runs past midnight into the next day.
+
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+// Work schedule as given to set_work_schedule and stored in the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkScheduleSettings {
+    pub days: Vec<String>, // Weekdays the shifts start on, e.g. "mon" or "monday"
+    pub start: String, // "HH:MM"
+    pub end: String, // "HH:MM"
+    pub timezone: String, // IANA name such as "Europe/Berlin"
+    #[serde(default)]
+    pub auto_stop: bool, // Stop all monitoring when the shift ends
+}
+
+// A validated work schedule
+#[derive(Debug, Clone)]
+pub struct WorkSchedule {
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    timezone: Tz,
+    settings: WorkScheduleSettings,
+}
+
+impl WorkSchedule {
+    pub fn parse(settings: &WorkScheduleSettings) -> Result<Self, String> {
+        if settings.days.is_empty() {
+            return Err("A work schedule needs at least one day".to_string());
+        }
+
+        let mut days = Vec::new();
+        for day in &settings.days {
+            let weekday = day
+                .trim()
+                .parse::<Weekday>()
+                .map_err(|_| format!("Invalid day '{}': expected a weekday such as \"mon\" or \"monday\"", day))?;
+            if !days.contains(&weekday) {
+                days.push(weekday);
+            }
+        }
+
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid time '{}': expected HH:MM", time))
+        };
+        let start = parse_time(&settings.start)?;
+        let end = parse_time(&settings.end)?;
+        if start == end {
+            return Err("Work hours must not start and end at the same time".to_string());
+        }
+
+        let timezone = settings
+            .timezone
+            .trim()
+            .parse::<Tz>()
+            .map_err(|_| format!("Unknown time zone '{}'", settings.timezone))?;
+
+        Ok(WorkSchedule { days, start, end, timezone, settings: settings.clone() })
+    }
+
+    pub fn settings(&self) -> &WorkScheduleSettings {
+        &self.settings
+    }
+
+    pub fn auto_stop(&self) -> bool {
+        self.settings.auto_stop
+    }
+
+    // Whether the moment falls within a shift
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.timezone);
+        let (day, time) = (local.weekday(), local.time());
+
+        if self.start < self.end {
+            self.days.contains(&day) && time >= self.start && time < self.end
+        } else {
+            // Overnight shifts: the evening part belongs to today's shift, the early hours to yesterday's
+            (self.days.contains(&day) && time >= self.start) || (self.days.contains(&day.pred()) && time < self.end)
+        }
+    }
+
+    // When the next shift starts after the moment, within the coming week
+    pub fn next_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.timezone).date_naive();
+
+        (0..=7)
+            .filter_map(|offset| today.checked_add_days(chrono::Days::new(offset)))
+            .filter(|date| self.days.contains(&date.weekday()))
+            // A start time skipped by a daylight saving change has no instant, so that shift is left out
+            .filter_map(|date| self.timezone.from_local_datetime(&date.and_time(self.start)).earliest())
+            .map(|start| start.with_timezone(&Utc))
+            .find(|start| *start > now)
+    }
+}
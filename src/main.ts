@@ -13,6 +13,88 @@ declare global {
   }
 }
 
+// Structured event payloads emitted by Rust (see src-tauri/src/events.rs)
+type ScreenshotEvent =
+  | { event: "screenshot_uploaded"; id: number | null; url: string }
+  | { event: "snapshot_uploaded"; id: number | null; url: string }
+  | { event: "timelapse_uploaded"; url: string }
+  | { event: "screenshot_skipped"; similarity: number };
+
+// Error every command rejects with (see src-tauri/src/errors.rs)
+type CommandError = {
+  code:
+    | "database_unavailable"
+    | "ffmpeg_missing"
+    | "already_running"
+    | "not_running"
+    | "invalid_input"
+    | "not_found"
+    | "user_not_set"
+    | "upload_failed"
+    | "unsupported"
+    | "internal";
+  message: string;
+};
+
+// Message to show for a rejected command; Tauri itself rejects with a plain string, e.g. for an unknown command
+function errorMessage(error: unknown): string {
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return (error as CommandError).message;
+  }
+  return String(error);
+}
+
+type ProgressEvent =
+  | { event: "next_snapshot"; seconds_remaining: number }
+  | { event: "startup_delay"; seconds_remaining: number }
+  | { event: "ffmpeg_missing" }
+  | { event: "ffmpeg_download_started"; total_bytes: number }
+  | { event: "ffmpeg_download_progress"; percent: number }
+  | { event: "ffmpeg_downloaded" };
+
+type ActivityEvent =
+  | { event: "idle_warning"; idle_seconds: number }
+  | { event: "user_idle"; idle_seconds: number }
+  | { event: "user_active"; idle_seconds: number }
+  | { event: "monitoring_stopped" }
+  | { event: "on_break" };
+
+function formatSeconds(seconds: number): string {
+  return `${Math.floor(seconds / 60)}m ${seconds % 60}s`;
+}
+
+function describeProgress(payload: ProgressEvent): string {
+  switch (payload.event) {
+    case "next_snapshot":
+      return `Next snapshot in: ${formatSeconds(payload.seconds_remaining)}`;
+    case "startup_delay":
+      return `Waiting ${payload.seconds_remaining} seconds for the desktop to settle...`;
+    case "ffmpeg_missing":
+      return "FFmpeg not found, downloading...";
+    case "ffmpeg_download_started":
+      return `Starting FFmpeg download (${(payload.total_bytes / (1024 * 1024)).toFixed(2)} MB)...`;
+    case "ffmpeg_download_progress":
+      return `Downloading FFmpeg: ${payload.percent.toFixed(1)}%...`;
+    case "ffmpeg_downloaded":
+      return "FFmpeg downloaded successfully!";
+  }
+}
+
+function describeActivity(payload: ActivityEvent): string {
+  switch (payload.event) {
+    case "idle_warning":
+      return `User has been idle for ${payload.idle_seconds} seconds`;
+    case "user_idle":
+      return `User has been idle for ${Math.floor(payload.idle_seconds / 60)} minutes`;
+    case "user_active":
+      return `User active, last activity ${payload.idle_seconds} seconds ago`;
+    case "monitoring_stopped":
+      return "All processes stopped - user considered active";
+    case "on_break":
+      return "On a break - idle time is not tracked";
+  }
+}
+
 // Check if user ID is set, if not show welcome UI
 async function checkUserId() {
   try {
@@ -34,6 +116,9 @@ async function checkUserId() {
 // Store original HTML content
 let originalHTML: string | null = null;
 
+// Set once the monitoring interrupted by the previous run has been resumed, so it is only resumed once
+let interruptedSessionHandled = false;
+
 // Function to display welcome UI
 function showWelcomeScreen() {
   // Store the original HTML on first visit (if not already stored)
@@ -177,12 +262,12 @@ function showWelcomeScreen() {
           }
         } catch (error) {
           console.error('Error checking user ID after setting:', error);
-          showStatusMessage(statusMessage, `Error: ${error}`, 'error');
+          showStatusMessage(statusMessage, `Error: ${errorMessage(error)}`, 'error');
         }
       }, 800); // Increased timeout to ensure state is updated
     } catch (error) {
       console.error('Error setting user ID:', error);
-      showStatusMessage(statusMessage, `Error: ${error}`, 'error');
+      showStatusMessage(statusMessage, `Error: ${errorMessage(error)}`, 'error');
     }
   });
 
@@ -231,14 +316,15 @@ function initializeMainAppComponents() {
   const networkUsage = new NetworkUsageComponent(true);
   networkUsage.start();
 
-  async function startCombinedRecording() {
+  // autoResumed makes the backend wait out the startup delay, for monitoring resumed at launch
+  async function startCombinedRecording(autoResumed = false) {
     if (recordBtn && stopBtn && screenshotStatus) {
       try {
         recordBtn.disabled = true;
         screenshotStatus.textContent = "Remote Worker: Starting...";
 
         // Call the Rust function to start combined recording
-        const result = await invoke("start_combined_recording");
+        const result = await invoke("start_combined_recording", { autoResumed });
         screenshotStatus.textContent = result as string;
 
         // Start idle detection
@@ -257,7 +343,7 @@ function initializeMainAppComponents() {
           stopBtn.disabled = false; // Ensure stop button is enabled
         }
       } catch (error) {
-        screenshotStatus.textContent = `Error: ${error}`;
+        screenshotStatus.textContent = `Error: ${errorMessage(error)}`;
         if (recordBtn) recordBtn.disabled = false;
         // Show the record button again if there's an error
         if (recordBtn) recordBtn.style.display = "block";
@@ -286,7 +372,7 @@ function initializeMainAppComponents() {
         }
 
       } catch (error) {
-        screenshotStatus.textContent = `Error: ${error}`;
+        screenshotStatus.textContent = `Error: ${errorMessage(error)}`;
       } finally {
         // Always reset the UI regardless of whether the Rust call succeeded
         // Show the record button and hide the stop button
@@ -300,18 +386,48 @@ function initializeMainAppComponents() {
     }
   }
 
+  // Resume the recording the previous run was making when it crashed or the machine rebooted
+  function resumeInterruptedSession(interrupted: { recording?: boolean } | null) {
+    if (!interrupted || interruptedSessionHandled) {
+      return;
+    }
+    interruptedSessionHandled = true;
+    if (interrupted.recording) {
+      startCombinedRecording(true);
+    }
+  }
+
   // Add event listeners for buttons
   if (recordBtn) {
-    recordBtn.onclick = startCombinedRecording;
+    recordBtn.onclick = () => startCombinedRecording();
   }
   if (stopBtn) {
     stopBtn.onclick = stopCombinedRecording;
   }
 
+  // The interrupted session is found during launch, possibly before this listener exists, so ask for it as well
+  listen<{ recording?: boolean }>("session-interrupted", (event) => resumeInterruptedSession(event.payload));
+  invoke<string>("get_interrupted_session")
+    .then((interrupted) => resumeInterruptedSession(JSON.parse(interrupted)))
+    .catch((error) => console.error("Error getting interrupted session:", errorMessage(error)));
+
   // Listen for screenshot taken event from Rust
-  listen("screenshot-taken", (event) => {
+  listen<ScreenshotEvent>("screenshot-taken", (event) => {
+    if (screenshotStatus && "url" in event.payload) {
+      screenshotStatus.textContent = `Screenshot taken: ${event.payload.url}`;
+    }
+  });
+
+  // Listen for screenshots skipped because nothing changed on screen
+  listen<ScreenshotEvent>("screenshot-skipped", (event) => {
+    if (screenshotStatus && event.payload.event === "screenshot_skipped") {
+      screenshotStatus.textContent = `Screenshot skipped: ${(event.payload.similarity * 100).toFixed(1)}% unchanged`;
+    }
+  });
+
+  listen<{ filename: string; deviation: number }>("capture-blank", (event) => {
     if (screenshotStatus) {
-      screenshotStatus.textContent = `Screenshot taken: ${event.payload}`;
+      screenshotStatus.textContent = `Blank screenshot skipped (brightness deviation ${event.payload.deviation.toFixed(2)})`;
     }
   });
 
@@ -340,9 +456,10 @@ function initializeMainAppComponents() {
     }
   });
 
-  listen("recording-progress", (event) => {
-    if (screenshotStatus) {
-      screenshotStatus.textContent = `Recording progress: ${event.payload}`;
+  listen<ProgressEvent>("recording-progress", (event) => {
+    // Countdown updates are shown on their own by the listener below
+    if (screenshotStatus && event.payload.event !== "next_snapshot") {
+      screenshotStatus.textContent = `Recording progress: ${describeProgress(event.payload)}`;
     }
   });
 
@@ -391,12 +508,45 @@ function initializeMainAppComponents() {
     }
   });
 
+  listen<{ next_start: string | null; auto_stop: boolean }>("outside-work-hours", (event) => {
+    if (screenshotStatus) {
+      const nextStart = event.payload.next_start ? new Date(event.payload.next_start).toLocaleString() : null;
+      screenshotStatus.textContent = event.payload.auto_stop
+        ? "Work hours ended, monitoring stopped"
+        : `Outside work hours, captures paused${nextStart ? ` until ${nextStart}` : ""}`;
+    }
+  });
+
+  listen<{ segment_index: number; segment_path: string }>("segment-split", (event) => {
+    if (screenshotStatus) {
+      screenshotStatus.textContent = `Recording split, now on segment ${event.payload.segment_index + 1}`;
+    }
+  });
+
+  listen<{ previous_count: number; count: number; resolution_changed: boolean }>("display-changed", (event) => {
+    if (screenshotStatus) {
+      const { previous_count, count, resolution_changed } = event.payload;
+      screenshotStatus.textContent = resolution_changed
+        ? "Screen resolution changed, capturing at the new size"
+        : `Monitors changed (${previous_count} → ${count}), capturing the current screens`;
+    }
+  });
+
   listen("recording-converted", (event) => {
     if (screenshotStatus) {
       screenshotStatus.textContent = `Video created: ${event.payload}`;
     }
   });
 
+  listen<{ exit_code: number | null; error_output: string[]; restarted: boolean }>("recording-crashed", (event) => {
+    if (screenshotStatus) {
+      const reason = event.payload.error_output[event.payload.error_output.length - 1] ?? `exit code ${event.payload.exit_code}`;
+      screenshotStatus.textContent = event.payload.restarted
+        ? `Recording restarted after FFmpeg crashed: ${reason}`
+        : `Recording stopped after FFmpeg crashed: ${reason}`;
+    }
+  });
+
   listen("recording-error", (event) => {
     if (screenshotStatus) {
       screenshotStatus.textContent = `Recording error: ${event.payload}`;
@@ -440,22 +590,20 @@ function initializeMainAppComponents() {
   });
 
   // Listen for progress updates from Rust (just for time display)
-  listen("recording-progress", (event) => {
-    if (typeof event.payload === 'string' && event.payload.includes("Next snapshot in:")) {
+  listen<ProgressEvent>("recording-progress", (event) => {
+    if (event.payload.event === "next_snapshot") {
       // Just update the status text with the remaining time
-      const payloadParts = event.payload.split('|');
-      const timePart = payloadParts[0];
       if (screenshotStatus) {
-        screenshotStatus.textContent = timePart;
+        screenshotStatus.textContent = describeProgress(event.payload);
       }
     }
   });
 
   // Listen for idle/active status updates from Rust
-  listen("user-idle", (event) => {
-    if (typeof event.payload === 'string') {
+  listen<ActivityEvent>("user-idle", (event) => {
+    if (event.payload) {
       if (screenshotStatus) {
-        screenshotStatus.textContent = `Idle: ${event.payload}`;
+        screenshotStatus.textContent = `Idle: ${describeActivity(event.payload)}`;
       }
       if (activityBadge) {
         activityBadge.style.backgroundColor = '#FFC107'; // Yellow color for idle
@@ -464,10 +612,10 @@ function initializeMainAppComponents() {
     }
   });
 
-  listen("user-active", (event) => {
-    if (typeof event.payload === 'string') {
+  listen<ActivityEvent>("user-active", (event) => {
+    if (event.payload) {
       if (screenshotStatus) {
-        screenshotStatus.textContent = `Active: ${event.payload}`;
+        screenshotStatus.textContent = `Active: ${describeActivity(event.payload)}`;
       }
       if (activityBadge) {
         activityBadge.style.backgroundColor = '#4CAF50'; // Green color for active
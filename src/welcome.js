@@ -45,7 +45,7 @@ document.addEventListener('DOMContentLoaded', () => {
             })
             .catch(error => {
                 console.error('Error setting user ID:', error);
-                showStatusMessage(`Error: ${error}`, 'error');
+                showStatusMessage(`Error: ${error.message ?? error}`, 'error');
             });
     });
 